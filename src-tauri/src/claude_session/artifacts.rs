@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::persistence::{self, ARCHIVE_DIR};
+use super::types::SessionInfo;
+
+/// Claude's output for the session's latest run, as shown in the output pane.
+pub const LOG_FILE: &str = "claude.log";
+/// The session's `summary_markdown`, written once it finishes.
+pub const REPORT_FILE: &str = "report.md";
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ArtifactCleanup {
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+/// Where session `id`'s log and report live: `<sessions dir>/<id>/`, beside
+/// its `<id>.json` record, so archiving moves them together.
+pub fn session_artifacts_dir_in(sessions_dir: &Path, id: &str) -> PathBuf {
+    sessions_dir.join(id)
+}
+
+/// Creates the session's log, replacing the one from any earlier run.
+pub fn create_log_in(sessions_dir: &Path, id: &str) -> std::io::Result<fs::File> {
+    let dir = session_artifacts_dir_in(sessions_dir, id);
+    fs::create_dir_all(&dir)?;
+    fs::File::create(dir.join(LOG_FILE))
+}
+
+pub fn write_report_in(sessions_dir: &Path, info: &SessionInfo) -> std::io::Result<()> {
+    let dir = session_artifacts_dir_in(sessions_dir, &info.id);
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join(REPORT_FILE),
+        format!("{}\n", info.summary_markdown()),
+    )
+}
+
+/// The per-session artifact dirs in `sessions_dir`, by session id.
+fn artifact_dirs(sessions_dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(sessions_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let id = path.file_name()?.to_str()?.to_string();
+            (id != ARCHIVE_DIR).then_some((id, path))
+        })
+        .collect()
+}
+
+fn remove_artifacts<F>(sessions_dir: &Path, should_remove: F) -> ArtifactCleanup
+where
+    F: Fn(&str) -> bool,
+{
+    let mut cleanup = ArtifactCleanup::default();
+
+    for (id, dir) in artifact_dirs(sessions_dir) {
+        if !should_remove(&id) {
+            continue;
+        }
+        let files: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default();
+        for path in files.iter().filter(|path| path.is_file()) {
+            let bytes = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    cleanup.removed += 1;
+                    cleanup.bytes += bytes;
+                }
+                Err(e) => eprintln!("Warning: Failed to remove {}: {}", path.display(), e),
+            }
+        }
+        if let Err(e) = fs::remove_dir(&dir) {
+            eprintln!("Warning: Failed to remove {}: {}", dir.display(), e);
+        }
+    }
+
+    cleanup
+}
+
+/// Removes logs and reports whose session is not in `known_ids`.
+pub fn cleanup_orphaned_artifacts_in(
+    sessions_dir: &Path,
+    known_ids: &HashSet<String>,
+) -> ArtifactCleanup {
+    remove_artifacts(sessions_dir, |id| !known_ids.contains(id))
+}
+
+pub fn delete_session_artifacts_in(sessions_dir: &Path, session_id: &str) -> ArtifactCleanup {
+    remove_artifacts(sessions_dir, |id| id == session_id)
+}
+
+pub fn delete_session_artifacts(session_id: &str) -> ArtifactCleanup {
+    match persistence::get_sessions_dir() {
+        Ok(dir) => delete_session_artifacts_in(&dir, session_id),
+        Err(_) => ArtifactCleanup::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_orphaned_artifacts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sessions_dir = temp_dir.path();
+        let live = session_artifacts_dir_in(sessions_dir, "live");
+        let gone = session_artifacts_dir_in(sessions_dir, "gone");
+        for dir in [&live, &gone, &sessions_dir.join(ARCHIVE_DIR)] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(live.join(LOG_FILE), "kept").unwrap();
+        fs::write(live.join(REPORT_FILE), "kept").unwrap();
+        fs::write(gone.join(LOG_FILE), "orphaned").unwrap();
+        fs::write(gone.join(REPORT_FILE), "x").unwrap();
+        fs::write(sessions_dir.join("live.json"), "{}").unwrap();
+
+        let known = HashSet::from(["live".to_string()]);
+        let cleanup = cleanup_orphaned_artifacts_in(sessions_dir, &known);
+
+        assert_eq!(
+            cleanup,
+            ArtifactCleanup {
+                removed: 2,
+                bytes: 9
+            }
+        );
+        assert!(live.join(LOG_FILE).exists());
+        assert!(live.join(REPORT_FILE).exists());
+        assert!(!gone.exists());
+        assert!(sessions_dir.join(ARCHIVE_DIR).exists());
+        assert!(sessions_dir.join("live.json").exists());
+
+        let cleanup = delete_session_artifacts_in(sessions_dir, "live");
+        assert_eq!(cleanup.removed, 2);
+        assert!(artifact_dirs(sessions_dir).is_empty());
+    }
+
+    #[test]
+    fn test_cleanup_without_artifact_dirs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cleanup = cleanup_orphaned_artifacts_in(temp_dir.path(), &HashSet::new());
+        assert_eq!(cleanup, ArtifactCleanup::default());
+    }
+}
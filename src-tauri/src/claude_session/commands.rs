@@ -1,72 +1,593 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
 use tauri::State;
 
-use super::manager::SessionManager;
-use super::orchestrator::{run_full_session, SessionConfig};
-use super::process::kill_process;
-use super::types::SessionInfo;
-use crate::git_ops::cleanup::cleanup_session;
+use super::artifacts::{self, ArtifactCleanup};
+use super::config::{
+    apply_commit_prefix, apply_editable_paths, apply_session_labels, apply_session_timeout,
+    effective_config, load_repo_allowed_commands, remember_base_branch, resolve_config,
+    save_repo_allowed_commands, EffectiveConfig,
+};
+use super::manager::{
+    self, filter_sessions_for_repo, recent_prs, Capacity, MergedSession, QueueStatus, RecentPr,
+    SessionManager, SessionMatch,
+};
+use super::orchestrator::{
+    compose_pr_payload, rerun_from_fresh_clone, run_full_session, PrPayload, SessionConfig,
+    SessionResult,
+};
+use super::persistence;
+use super::process::{
+    self, kill_process, run_allowed_command, CommandOutput, InstructionsLength,
+    ALLOWED_BASH_PATTERNS,
+};
+use super::project::{detect_project_types, ProjectDetection};
+use super::reconcile::{
+    list_live_checkouts_in, reap_stale_sessions_with, reconcile_sessions_in, LiveCheckout,
+    ReconcileSummary,
+};
+use super::types::{
+    now_ms, phase_durations, session_timeline, PhaseDuration, SessionInfo, SessionStatus,
+    TimelineEvent,
+};
+use crate::git_ops::branch::{self, generate_branch_name, DEFAULT_BRANCH_PREFIX};
+use crate::git_ops::bundle::create_bundle;
+use crate::git_ops::cleanup::{cleanup_session, cleanup_session_dir};
+use crate::git_ops::cli;
+use crate::git_ops::codeowners;
+use crate::git_ops::commit;
+use crate::git_ops::conflicts::{self, ConflictCheck};
+use crate::git_ops::clone::estimate_clone as estimate_clone_size;
+use crate::git_ops::patch::{
+    changed_paths, diff_checkout_branches, generate_branch_patch, generate_patch, has_branch,
+    write_patch, BranchDiff,
+};
+use crate::git_ops::pr::{self, BranchPermissions, BranchProtection, ForcePushCheck};
+use crate::git_ops::reset::{reset_remote_branch, ResetTarget};
+use crate::git_ops::size::DirStats;
+use crate::paths::first_unused_path;
+use crate::settings;
 
 pub struct AppState {
     pub session_manager: Arc<SessionManager>,
 }
 
+/// Saves the report of a session that has stopped running beside its record.
+fn write_report(session_manager: &SessionManager, session_id: &str) {
+    let (Some(dir), Ok(info)) = (
+        session_manager.queue_dir(),
+        session_manager.get_session_info(session_id),
+    ) else {
+        return;
+    };
+    if let Err(e) = artifacts::write_report_in(&dir, &info) {
+        eprintln!("Warning: Failed to write the report for {}: {}", session_id, e);
+    }
+}
+
+fn persist_queued_in(dir: &Path, session_manager: &SessionManager, config: &SessionConfig) {
+    let saved = session_manager
+        .get_session_info(&config.session_id)
+        .map_err(|e| e.to_string())
+        .and_then(|info| {
+            persistence::save_session_info_in(dir, &info).map_err(|e| e.to_string())?;
+            persistence::save_launch_config_in(dir, config).map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        eprintln!("Warning: Failed to persist queued session {}: {}", config.session_id, e);
+    }
+}
+
+/// Registers the sessions persisted in `dir` while queued, in the order they
+/// were queued, and returns their launch configs. Sessions whose spawn inputs
+/// were lost are registered as errored instead.
+fn requeue_persisted_in(dir: &Path, session_manager: &SessionManager) -> Vec<SessionConfig> {
+    let mut queued: Vec<SessionInfo> = persistence::load_all_in(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|info| info.status == SessionStatus::Queued)
+        .collect();
+    queued.sort_by_key(|info| (info.queued_at_ms, info.created_at));
+
+    let mut configs = Vec::new();
+    for info in queued {
+        let id = info.id.clone();
+        let Ok(work_dir) = session_manager.session_dir(&id) else {
+            continue;
+        };
+        let config = match persistence::load_launch_config_in(dir, &id) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Warning: Cannot resume queued session {}: {}", id, e);
+                if let Err(e) = session_manager.restore_persisted(info, work_dir) {
+                    eprintln!("Warning: Cannot restore session {}: {}", id, e);
+                }
+                continue;
+            }
+        };
+        match session_manager.restore_queued(info, work_dir, config.clone()) {
+            Ok(()) => configs.push(config),
+            Err(e) => eprintln!("Warning: Cannot resume queued session {}: {}", id, e),
+        }
+    }
+
+    configs
+}
+
+/// Registers the sessions persisted in `dir` that weren't queued, so history
+/// survives a restart. Returns how many were interrupted mid-run.
+fn restore_persisted_in(dir: &Path, session_manager: &SessionManager) -> usize {
+    let mut interrupted = 0;
+    for info in persistence::load_all_in(dir).unwrap_or_default() {
+        if info.status == SessionStatus::Queued {
+            continue;
+        }
+        let id = info.id.clone();
+        let Ok(work_dir) = session_manager.session_dir(&id) else {
+            continue;
+        };
+        match session_manager.restore_persisted(info, work_dir) {
+            Ok(true) => interrupted += 1,
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Warning: Cannot restore session {}: {}", id, e);
+                continue;
+            }
+        }
+        // Kept so the session can still be retried.
+        if let Ok(config) = persistence::load_launch_config_in(dir, &id) {
+            let _ = session_manager.set_launch_config(&id, config);
+        }
+    }
+    interrupted
+}
+
+/// Reloads the sessions from the app's last run, marking any that were
+/// running when it exited as errored.
+pub fn restore_persisted_sessions(session_manager: &SessionManager) -> usize {
+    match session_manager.queue_dir() {
+        Some(dir) => restore_persisted_in(&dir, session_manager),
+        None => 0,
+    }
+}
+
+/// Sessions saved on disk, newest first, whether or not they're loaded.
 #[tauri::command]
-pub async fn spawn_claude_session(
-    state: State<'_, AppState>,
-    git_directory: String,
-    instructions: String,
-    additional_instructions: Option<String>,
-    instructions_file_content: Option<String>,
-    base_branch: Option<String>,
+pub fn list_persisted_sessions() -> Result<Vec<SessionInfo>, String> {
+    let mut sessions = persistence::load_all().map_err(|e| e.to_string())?;
+    sessions.sort_by_key(|info| std::cmp::Reverse(info.created_at));
+    Ok(sessions)
+}
+
+/// Restarts the sessions that were still queued when the app last exited.
+/// Returns their ids in queue order.
+pub fn resume_persisted_queue(session_manager: &Arc<SessionManager>) -> Vec<String> {
+    let Some(dir) = session_manager.queue_dir() else {
+        return Vec::new();
+    };
+
+    requeue_persisted_in(&dir, session_manager)
+        .into_iter()
+        .map(|config| {
+            let session_id = config.session_id.clone();
+            launch_session(session_manager.clone(), config, false);
+            session_id
+        })
+        .collect()
+}
+
+fn launch_session(session_manager: Arc<SessionManager>, config: SessionConfig, fresh_clone: bool) {
+    std::thread::spawn(move || {
+        let session_id = config.session_id.clone();
+        let keep_failed_checkouts = config.effective.keep_failed_checkouts;
+
+        // The launch config stays beside the session record, so a restart can
+        // resume it while queued or retry it once it has failed.
+        if let Some(dir) = session_manager.queue_dir() {
+            if session_manager.queue_if_waiting(&session_id).unwrap_or(false) {
+                persist_queued_in(&dir, &session_manager, &config);
+            } else if let Err(e) = persistence::save_launch_config_in(&dir, &config) {
+                eprintln!("Warning: Failed to persist the launch of {}: {}", session_id, e);
+            }
+        }
+
+        let promoted = session_manager.wait_for_scheduler(&session_id).unwrap_or(false);
+
+        // Cancelled while queued; cancel_session already recorded the error.
+        if !promoted {
+            if fresh_clone {
+                let _ = session_manager.release_checkout(&session_id);
+            }
+            session_manager.persist(&session_id);
+            write_report(&session_manager, &session_id);
+            return;
+        }
+
+        let result = if fresh_clone {
+            rerun_from_fresh_clone(config, &session_manager)
+        } else {
+            run_full_session(config, &session_manager)
+        };
+
+        match result {
+            Ok(SessionResult {
+                pr_url: Some(pr_url),
+                ..
+            }) => {
+                let _ = session_manager.set_completed(&session_id, pr_url);
+            }
+            Ok(_) => {
+                let _ = session_manager.set_no_changes(&session_id);
+            }
+            Err(e) => {
+                let _ = session_manager.set_error(&session_id, e.to_string());
+                if !keep_failed_checkouts && !e.keeps_checkout() {
+                    if let Ok(work_dir) = session_manager.get_work_dir(&session_id) {
+                        let _ = cleanup_session_dir(&work_dir);
+                    }
+                }
+            }
+        }
+
+        session_manager.persist(&session_id);
+        write_report(&session_manager, &session_id);
+    });
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchSessionRequest {
+    pub git_directory: String,
+    pub instructions: String,
+    #[serde(default)]
+    pub additional_instructions: Option<String>,
+    #[serde(default)]
+    pub instructions_file_content: Option<String>,
+    #[serde(default)]
+    pub base_branch: Option<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Overrides the generated branch name.
+    #[serde(default)]
+    pub branch_name: Option<String>,
+    /// Prefixes the commit subject as `[ticket_id]`.
+    #[serde(default)]
+    pub ticket_id: Option<String>,
+    /// Added to the configured `pr_labels` for this session's PR.
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    /// Replaces the configured `editable_paths` for this session.
+    #[serde(default)]
+    pub editable_paths: Option<Vec<String>>,
+    /// Used verbatim as the commit message instead of the generated one.
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    /// Replaces the configured `max_changed_files`; 0 lifts the cap.
+    #[serde(default)]
+    pub max_changed_files: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchSessionResult {
+    pub batch_id: String,
+    pub git_directory: String,
+    pub session_id: Option<String>,
+    pub error: Option<String>,
+}
+
+fn start_session(
+    session_manager: &Arc<SessionManager>,
+    request: BatchSessionRequest,
+    batch_id: Option<&str>,
 ) -> Result<String, String> {
+    let BatchSessionRequest {
+        git_directory,
+        instructions,
+        additional_instructions,
+        instructions_file_content,
+        base_branch,
+        timeout_secs,
+        branch_name,
+        ticket_id,
+        labels,
+        editable_paths,
+        commit_message,
+        max_changed_files,
+    } = request;
+
+    cli::open_or_fallback(Path::new(&git_directory))
+        .map_err(|e| format!("Not a git repository: {} ({})", git_directory, e))?;
+
     let session_id = uuid::Uuid::new_v4().to_string();
-    let base_branch = base_branch.unwrap_or_else(|| "main".to_string());
+    let mut effective = effective_config(Path::new(&git_directory))?;
+    if let Some(base_branch) = base_branch {
+        effective.base_branch = base_branch;
+    }
+    apply_session_timeout(&mut effective, timeout_secs)?;
+    apply_commit_prefix(&mut effective, ticket_id)?;
+    apply_session_labels(&mut effective, labels)?;
+    apply_editable_paths(&mut effective, editable_paths)?;
+    if let Some(max) = max_changed_files {
+        effective.max_changed_files = Some(max).filter(|max| *max > 0);
+    }
+    let commit_message = commit_message
+        .map(|message| commit::validate_commit_message(&message))
+        .transpose()?;
+    let branch_name = branch_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    if let Some(name) = &branch_name {
+        if !git2::Branch::name_is_valid(name).unwrap_or(false) {
+            return Err(format!("Invalid branch name: {}", name));
+        }
+    }
+
+    if effective.strict_instructions {
+        let warnings = process::validate_instructions(&instructions);
+        if !warnings.is_empty() {
+            return Err(warnings.join("\n"));
+        }
+    }
+
+    let guidelines = process::session_guidelines(
+        Path::new(&git_directory),
+        effective.guidelines_file.as_deref(),
+    );
+    let length = InstructionsLength::measure(
+        &instructions,
+        additional_instructions.as_deref(),
+        instructions_file_content.as_deref(),
+        guidelines.as_deref(),
+        effective.max_instructions_len,
+    );
+    if let Some(error) = length.error() {
+        if effective.strict_instructions {
+            return Err(error);
+        }
+        eprintln!("Warning: {}", error);
+    }
+
+    let config = SessionConfig {
+        session_id: session_id.clone(),
+        git_directory,
+        user_instructions: instructions,
+        additional_instructions,
+        instructions_file_content,
+        branch_name,
+        commit_message,
+        effective,
+    };
+    register_and_launch(session_manager, config, batch_id)?;
+
+    Ok(session_id)
+}
 
-    let work_dir = crate::git_ops::get_session_dir(&session_id)
+/// Registers the session described by `config` and starts it.
+fn register_and_launch(
+    session_manager: &Arc<SessionManager>,
+    config: SessionConfig,
+    batch_id: Option<&str>,
+) -> Result<(), String> {
+    let session_id = config.session_id.clone();
+    let work_dir = session_manager
+        .session_dir(&session_id)
         .map_err(|e| e.to_string())?;
 
-    state
-        .session_manager
+    session_manager.set_session_limit(config.effective.max_concurrent_sessions);
+    session_manager
         .create_session(
             session_id.clone(),
-            git_directory.clone(),
-            instructions.clone(),
+            config.git_directory.clone(),
+            config.user_instructions.clone(),
             work_dir,
             String::new(),
         )
         .map_err(|e| e.to_string())?;
 
-    let session_manager = state.session_manager.clone();
-    let session_id_clone = session_id.clone();
+    if let Some(batch_id) = batch_id {
+        session_manager
+            .set_batch_id(&session_id, batch_id.to_string())
+            .map_err(|e| e.to_string())?;
+    }
+    session_manager
+        .set_base_branch(&session_id, config.effective.base_branch.clone())
+        .map_err(|e| e.to_string())?;
+    session_manager
+        .set_effective_config(&session_id, config.effective.clone())
+        .map_err(|e| e.to_string())?;
+    session_manager
+        .set_model(&session_id, config.effective.model.clone())
+        .map_err(|e| e.to_string())?;
 
-    std::thread::spawn(move || {
-        let config = SessionConfig {
-            session_id: session_id_clone.clone(),
+    session_manager.set_clone_limit(config.effective.max_concurrent_clones);
+    session_manager.set_min_spawn_interval(std::time::Duration::from_millis(
+        config.effective.min_spawn_interval_ms,
+    ));
+
+    session_manager
+        .set_launch_config(&session_id, config.clone())
+        .map_err(|e| e.to_string())?;
+
+    if config.effective.auto_delete_merged_branches
+        && session_manager.begin_sweep(&config.git_directory).unwrap_or(false)
+    {
+        let session_manager = session_manager.clone();
+        let repo_path = config.git_directory.clone();
+        let prefix = config.effective.branch_prefix.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = branch::sweep_merged_branches(Path::new(&repo_path), &prefix) {
+                eprintln!("Warning: Failed to sweep merged branches: {}", e);
+            }
+            let _ = session_manager.end_sweep(&repo_path);
+        });
+    }
+
+    launch_session(session_manager.clone(), config, false);
+
+    Ok(())
+}
+
+// Tauri maps each invoke argument to a parameter.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn spawn_claude_session(
+    state: State<'_, AppState>,
+    git_directory: String,
+    instructions: String,
+    additional_instructions: Option<String>,
+    instructions_file_content: Option<String>,
+    base_branch: Option<String>,
+    timeout_secs: Option<u64>,
+    branch_name: Option<String>,
+    ticket_id: Option<String>,
+    labels: Option<Vec<String>>,
+    editable_paths: Option<Vec<String>>,
+    commit_message: Option<String>,
+    max_changed_files: Option<usize>,
+) -> Result<String, String> {
+    start_session(
+        &state.session_manager,
+        BatchSessionRequest {
             git_directory,
-            user_instructions: instructions,
+            instructions,
             additional_instructions,
             instructions_file_content,
             base_branch,
-        };
+            timeout_secs,
+            branch_name,
+            ticket_id,
+            labels,
+            editable_paths,
+            commit_message,
+            max_changed_files,
+        },
+        None,
+    )
+}
 
-        match run_full_session(config) {
-            Ok(result) => {
-                let _ = session_manager.set_completed(&session_id_clone, result.pr_url);
-            }
-            Err(e) => {
-                let _ = session_manager.set_error(&session_id_clone, e.to_string());
-                let _ = cleanup_session(&session_id_clone);
+const RETRY_BRANCH_SEPARATOR: &str = "-retry-";
+const RETRY_SUFFIX_LEN: usize = 8;
+
+/// Starts one session per request. Each session waits for a clone slot like any
+/// other, and a request that fails is reported without stopping the rest. The
+/// sessions share a batch id so the failed ones can be retried together.
+fn spawn_batch(
+    session_manager: &Arc<SessionManager>,
+    requests: Vec<BatchSessionRequest>,
+) -> Vec<BatchSessionResult> {
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    requests
+        .into_iter()
+        .map(|request| {
+            let git_directory = request.git_directory.clone();
+            match start_session(session_manager, request, Some(&batch_id)) {
+                Ok(session_id) => BatchSessionResult {
+                    batch_id: batch_id.clone(),
+                    git_directory,
+                    session_id: Some(session_id),
+                    error: None,
+                },
+                Err(error) => BatchSessionResult {
+                    batch_id: batch_id.clone(),
+                    git_directory,
+                    session_id: None,
+                    error: Some(error),
+                },
             }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn spawn_batch_sessions(
+    state: State<'_, AppState>,
+    requests: Vec<BatchSessionRequest>,
+) -> Result<Vec<BatchSessionResult>, String> {
+    Ok(spawn_batch(&state.session_manager, requests))
+}
+
+/// `branch_name` with a suffix from the retry's session id, replacing the one
+/// an earlier retry added.
+fn retry_branch_name(branch_name: &str, session_id: &str) -> String {
+    let base = match branch_name.rsplit_once(RETRY_BRANCH_SEPARATOR) {
+        Some((base, suffix))
+            if suffix.len() == RETRY_SUFFIX_LEN
+                && suffix.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            base
         }
-    });
+        _ => branch_name,
+    };
+    let suffix: String = session_id.chars().take(RETRY_SUFFIX_LEN).collect();
+    format!("{}{}{}", base, RETRY_BRANCH_SEPARATOR, suffix)
+}
 
-    state
-        .session_manager
-        .set_working(&session_id, 0)
-        .map_err(|e| e.to_string())?;
+/// Starts a new session for each failed, not yet retried member of
+/// `batch_id`, with the inputs it was launched with. The new sessions join the
+/// same batch. Returns their ids.
+fn retry_failed_in(
+    session_manager: &Arc<SessionManager>,
+    batch_id: &str,
+) -> Result<Vec<String>, String> {
+    let mut failed: Vec<SessionInfo> = session_manager
+        .list_sessions()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|info| {
+            info.batch_id.as_deref() == Some(batch_id)
+                && info.status == SessionStatus::Error
+                && info.retried_as.is_none()
+        })
+        .collect();
+    failed.sort_by_key(|info| info.created_at);
 
-    Ok(session_id)
+    let mut retried = Vec::new();
+    for info in failed {
+        let config = match session_manager.get_launch_config(&info.id) {
+            Ok(Some(config)) => config,
+            _ => {
+                eprintln!(
+                    "Warning: Cannot retry session {}: no stored launch configuration",
+                    info.id
+                );
+                continue;
+            }
+        };
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        // The failed run may have pushed its branch already.
+        let branch_name = config
+            .branch_name
+            .as_deref()
+            .map(|name| retry_branch_name(name, &session_id));
+        register_and_launch(
+            session_manager,
+            SessionConfig {
+                session_id: session_id.clone(),
+                branch_name,
+                ..config
+            },
+            Some(batch_id),
+        )?;
+        session_manager
+            .set_retried_as(&info.id, session_id.clone())
+            .map_err(|e| e.to_string())?;
+        session_manager.persist(&info.id);
+        retried.push(session_id);
+    }
+
+    Ok(retried)
+}
+
+#[tauri::command]
+pub fn retry_failed_in_batch(
+    state: State<'_, AppState>,
+    batch_id: String,
+) -> Result<Vec<String>, String> {
+    retry_failed_in(&state.session_manager, &batch_id)
 }
 
 #[tauri::command]
@@ -81,23 +602,146 @@ pub fn get_session_status(
 }
 
 #[tauri::command]
-pub fn cancel_session(state: State<'_, AppState>, session_id: String) -> Result<(), String> {
-    let process_id = state
-        .session_manager
-        .get_process_id(&session_id)
+pub fn session_timing(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<PhaseDuration>, String> {
+    let info = get_session_status(state, session_id)?;
+    Ok(phase_durations(&info.phases, now_ms()))
+}
+
+/// Seconds from creation to completion; `None` while the session runs.
+#[tauri::command]
+pub fn session_duration(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<u64>, String> {
+    Ok(get_session_status(state, session_id)?.duration_secs())
+}
+
+/// Status changes, phases and output markers of the session in time order.
+#[tauri::command]
+pub fn get_session_timeline(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<TimelineEvent>, String> {
+    let info = get_session_status(state, session_id)?;
+    Ok(session_timeline(&info))
+}
+
+#[tauri::command]
+pub fn session_summary_markdown(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<String, String> {
+    get_session_status(state, session_id).map(|info| info.summary_markdown())
+}
+
+/// Claims the checkout before touching it, so two reclones (or a reclone and a
+/// run still using the checkout) can't race; the launched run inherits the
+/// claim.
+fn reclone(session_manager: &Arc<SessionManager>, session_id: &str) -> Result<(), String> {
+    let info = session_manager
+        .get_session_info(session_id)
+        .map_err(|e| e.to_string())?;
+
+    if info.status == SessionStatus::Completed {
+        return Err(format!("Session {} already completed", session_id));
+    }
+
+    let still_running = || {
+        format!(
+            "Session {} is still running; cancel it before recloning",
+            session_id
+        )
+    };
+    if info.status == SessionStatus::Queued {
+        return Err(still_running());
+    }
+    if !session_manager
+        .claim_checkout(session_id)
+        .map_err(|e| e.to_string())?
+    {
+        return Err(still_running());
+    }
+
+    let prepared = session_manager
+        .get_launch_config(session_id)
+        .map_err(|e| e.to_string())
+        .and_then(|config| {
+            config.ok_or_else(|| {
+                format!("Session {} has no stored launch configuration", session_id)
+            })
+        })
+        .and_then(|config| {
+            session_manager
+                .reset_for_rerun(session_id)
+                .map_err(|e| e.to_string())?;
+            Ok(config)
+        });
+    match prepared {
+        Ok(config) => {
+            launch_session(session_manager.clone(), config, true);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = session_manager.release_checkout(session_id);
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+pub fn reclone_session(state: State<'_, AppState>, session_id: String) -> Result<(), String> {
+    reclone(&state.session_manager, &session_id)
+}
+
+fn cancel(session_manager: &SessionManager, session_id: &str, force: bool) -> Result<(), String> {
+    session_manager
+        .ensure_cancellable(session_id, force)
+        .map_err(|e| e.to_string())?;
+
+    let process_id = session_manager
+        .get_process_id(session_id)
         .map_err(|e| e.to_string())?;
 
     if let Some(pid) = process_id {
         kill_process(pid).map_err(|e| e.to_string())?;
     }
 
-    cleanup_session(&session_id).map_err(|e| e.to_string())?;
+    let work_dir = session_manager
+        .get_work_dir(session_id)
+        .map_err(|e| e.to_string())?;
+    cleanup_session_dir(&work_dir).map_err(|e| e.to_string())?;
+
+    session_manager
+        .set_error(session_id, "Session cancelled by user".to_string())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Refuses to cancel a locked session unless `force` is set.
+#[tauri::command]
+pub fn cancel_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    cancel(&state.session_manager, &session_id, force.unwrap_or(false))
+}
 
+#[tauri::command]
+pub fn set_session_locked(
+    state: State<'_, AppState>,
+    session_id: String,
+    locked: bool,
+) -> Result<(), String> {
     state
         .session_manager
-        .set_error(&session_id, "Session cancelled by user".to_string())
+        .set_locked(&session_id, locked)
         .map_err(|e| e.to_string())?;
-
+    state.session_manager.persist(&session_id);
     Ok(())
 }
 
@@ -108,3 +752,1464 @@ pub fn list_claude_sessions(state: State<'_, AppState>) -> Result<Vec<SessionInf
         .list_sessions()
         .map_err(|e| e.to_string())
 }
+
+/// Moves a finished session's record into the archive and drops it from the
+/// active list. Its work directory, if any is left, is removed.
+#[tauri::command]
+pub fn archive_session(state: State<'_, AppState>, session_id: String) -> Result<(), String> {
+    if let Ok(info) = state.session_manager.get_session_info(&session_id) {
+        if matches!(
+            info.status,
+            SessionStatus::Queued | SessionStatus::Initializing | SessionStatus::Working
+        ) {
+            return Err(format!(
+                "Session {} is still running; cancel it before archiving",
+                session_id
+            ));
+        }
+        persistence::save_session_info(&info).map_err(|e| e.to_string())?;
+    }
+
+    persistence::archive_session_info(&session_id).map_err(|e| e.to_string())?;
+    let _ = state.session_manager.remove_session(&session_id);
+    if let Err(e) = cleanup_session(&session_id) {
+        eprintln!("Warning: Failed to clean up session {}: {}", session_id, e);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_archived_sessions() -> Result<Vec<SessionInfo>, String> {
+    persistence::load_archived().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn unarchive_session(session_id: String) -> Result<SessionInfo, String> {
+    persistence::unarchive_session_info(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_session_patch(
+    state: State<'_, AppState>,
+    session_id: String,
+    dest: String,
+) -> Result<(), String> {
+    let work_dir = state
+        .session_manager
+        .get_work_dir(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    if !work_dir.exists() {
+        return Err(format!(
+            "Work directory for session {} no longer exists. Enable keep_failed_checkouts to retain failed sessions.",
+            session_id
+        ));
+    }
+
+    let base_branch = state
+        .session_manager
+        .get_base_branch(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    write_patch(&work_dir, &base_branch, Path::new(&dest)).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// The checkout and branch holding a session's changes: its work dir, or
+/// the source repo once the work dir has been cleaned up.
+fn session_checkout(
+    session_manager: &SessionManager,
+    session_id: &str,
+) -> Result<(PathBuf, String), String> {
+    let info = session_manager
+        .get_session_info(session_id)
+        .map_err(|e| e.to_string())?;
+    let branch = session_manager
+        .get_branch_name(session_id)
+        .map_err(|e| e.to_string())?;
+    if branch.is_empty() {
+        return Err(format!("Session {} has no branch yet", session_id));
+    }
+
+    let work_dir = session_manager
+        .get_work_dir(session_id)
+        .map_err(|e| e.to_string())?;
+    if work_dir.exists() {
+        return Ok((work_dir, branch));
+    }
+    let source = PathBuf::from(&info.git_directory);
+    if has_branch(&source, &branch) {
+        return Ok((source, branch));
+    }
+    Err(format!(
+        "Work directory for session {} no longer exists and {} isn't in the source repo, \
+         so it can't be compared",
+        session_id, branch
+    ))
+}
+
+fn diff_sessions_in(
+    session_manager: &SessionManager,
+    session_a: &str,
+    session_b: &str,
+) -> Result<BranchDiff, String> {
+    let (dir_a, branch_a) = session_checkout(session_manager, session_a)?;
+    let (dir_b, branch_b) = session_checkout(session_manager, session_b)?;
+    diff_checkout_branches(&dir_a, &branch_a, &dir_b, &branch_b).map_err(|e| e.to_string())
+}
+
+/// What changes between session A's branch and session B's.
+#[tauri::command]
+pub async fn diff_sessions(
+    state: State<'_, AppState>,
+    session_a: String,
+    session_b: String,
+) -> Result<BranchDiff, String> {
+    let session_manager = state.session_manager.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        diff_sessions_in(&session_manager, &session_a, &session_b)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedPatch {
+    pub session_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedPatch {
+    pub session_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PatchExport {
+    pub written: Vec<ExportedPatch>,
+    pub skipped: Vec<SkippedPatch>,
+}
+
+/// The session's diff from its work dir, or from its branch in the source
+/// repo once the work dir has been cleaned up.
+fn session_patch(session_manager: &SessionManager, info: &SessionInfo) -> Result<Vec<u8>, String> {
+    let work_dir = session_manager.get_work_dir(&info.id).map_err(|e| e.to_string())?;
+    let base_branch = session_manager
+        .get_base_branch(&info.id)
+        .map_err(|e| e.to_string())?;
+    if work_dir.exists() {
+        return generate_patch(&work_dir, &base_branch).map_err(|e| e.to_string());
+    }
+
+    let branch = session_manager
+        .get_branch_name(&info.id)
+        .map_err(|e| e.to_string())?;
+    if branch.is_empty() {
+        return Err("Work directory no longer exists".to_string());
+    }
+    let source = Path::new(&info.git_directory);
+    if !has_branch(source, &branch) {
+        return Err(format!(
+            "Work directory no longer exists and {} isn't in the source repo",
+            branch
+        ));
+    }
+    generate_branch_patch(source, &base_branch, &branch).map_err(|e| e.to_string())
+}
+
+fn export_all_session_patches_in(
+    session_manager: &SessionManager,
+    dest_dir: &Path,
+) -> Result<PatchExport, String> {
+    fs::create_dir_all(dest_dir)
+        .map_err(|e| format!("Failed to create {}: {}", dest_dir.display(), e))?;
+
+    let mut sessions: Vec<SessionInfo> = session_manager
+        .list_sessions()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|info| info.status == SessionStatus::Completed)
+        .collect();
+    sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+    let mut export = PatchExport::default();
+    for info in sessions {
+        match session_patch(session_manager, &info) {
+            Ok(patch) => {
+                let path = first_unused_path(dest_dir, &info.id, ".patch");
+                fs::write(&path, patch)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                export.written.push(ExportedPatch {
+                    session_id: info.id,
+                    path: path.display().to_string(),
+                });
+            }
+            Err(reason) => export.skipped.push(SkippedPatch {
+                session_id: info.id,
+                reason,
+            }),
+        }
+    }
+    Ok(export)
+}
+
+/// Writes one `<session-id>.patch` per completed session into `dest_dir`.
+#[tauri::command]
+pub fn export_all_session_patches(
+    state: State<'_, AppState>,
+    dest_dir: String,
+) -> Result<PatchExport, String> {
+    export_all_session_patches_in(&state.session_manager, Path::new(&dest_dir))
+}
+
+/// Writes a git bundle of the session's branch to `dest` and returns its size.
+/// With `since_base`, the bundle holds only the commits since the base branch.
+#[tauri::command]
+pub fn export_session_bundle(
+    state: State<'_, AppState>,
+    session_id: String,
+    dest: String,
+    since_base: Option<bool>,
+) -> Result<u64, String> {
+    let work_dir = state
+        .session_manager
+        .get_work_dir(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    if !work_dir.exists() {
+        return Err(format!(
+            "Work directory for session {} no longer exists. Enable keep_failed_checkouts to retain failed sessions.",
+            session_id
+        ));
+    }
+
+    let branch_name = state
+        .session_manager
+        .get_branch_name(&session_id)
+        .map_err(|e| e.to_string())?;
+    let base_branch = state
+        .session_manager
+        .get_base_branch(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    create_bundle(
+        &work_dir,
+        &branch_name,
+        &base_branch,
+        since_base.unwrap_or(false),
+        Path::new(&dest),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn check_branch_permissions(
+    git_directory: String,
+    base_branch: String,
+) -> Result<BranchPermissions, String> {
+    pr::check_branch_permissions(Path::new(&git_directory), &base_branch).map_err(|e| e.to_string())
+}
+
+/// Required status checks, reviews and admin enforcement on `base_branch`
+/// (the configured base by default). An unprotected branch has none; one
+/// whose protection the token can't read is an error.
+#[tauri::command]
+pub async fn get_base_branch_requirements(
+    git_directory: String,
+    base_branch: Option<String>,
+) -> Result<BranchProtection, String> {
+    let base_branch = match base_branch {
+        Some(branch) => branch,
+        None => effective_config(Path::new(&git_directory))?.base_branch,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        pr::get_branch_protection(Path::new(&git_directory), &base_branch)
+            .and_then(|protection| pr::branch_requirements(&base_branch, protection))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Dry-runs merging the repo's HEAD into `base_branch` without touching the
+/// working tree.
+#[tauri::command]
+pub fn check_merge_conflicts(
+    git_directory: String,
+    base_branch: String,
+) -> Result<ConflictCheck, String> {
+    conflicts::check_merge_conflicts(Path::new(&git_directory), &base_branch)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn resume_queued_sessions(state: State<'_, AppState>) -> Vec<String> {
+    resume_persisted_queue(&state.session_manager)
+}
+
+#[tauri::command]
+pub fn validate_instructions(instructions: String) -> Vec<String> {
+    process::validate_instructions(&instructions)
+}
+
+/// Measures the composed instructions, guidelines included, against the
+/// repo's `max_instructions_len`, so the UI can flag them before spawning.
+#[tauri::command]
+pub fn check_instructions_length(
+    git_directory: String,
+    instructions: String,
+    additional_instructions: Option<String>,
+    instructions_file_content: Option<String>,
+) -> Result<InstructionsLength, String> {
+    let effective = effective_config(Path::new(&git_directory))?;
+    let guidelines = process::session_guidelines(
+        Path::new(&git_directory),
+        effective.guidelines_file.as_deref(),
+    );
+    Ok(InstructionsLength::measure(
+        &instructions,
+        additional_instructions.as_deref(),
+        instructions_file_content.as_deref(),
+        guidelines.as_deref(),
+        effective.max_instructions_len,
+    ))
+}
+
+#[tauri::command]
+pub fn preview_pr_title(instructions: String) -> String {
+    pr::suggest_pr_title(&instructions)
+}
+
+/// What the branch for a session on `description` would be called, using the
+/// repo's configured prefix when `git_directory` is given. The suffix is the
+/// current time, so the preview is representative rather than exact; pass the
+/// name as `branch_name` when spawning to pin it.
+#[tauri::command]
+pub fn preview_branch_name(
+    description: String,
+    git_directory: Option<String>,
+) -> Result<String, String> {
+    let prefix = match git_directory {
+        Some(git_directory) => effective_config(Path::new(&git_directory))?.branch_prefix,
+        None => DEFAULT_BRANCH_PREFIX.to_string(),
+    };
+    Ok(generate_branch_name(&description, &prefix))
+}
+
+/// The PR a session would open, from its stored launch config. Its checkout
+/// supplies the branch, changed paths and PR template once it exists; before
+/// that the source repo does, and no reviewers are suggested.
+fn preview_pr_payload_for(
+    session_manager: &SessionManager,
+    session_id: &str,
+) -> Result<PrPayload, String> {
+    let info = session_manager
+        .get_session_info(session_id)
+        .map_err(|e| e.to_string())?;
+    let config = session_manager
+        .get_launch_config(session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Session {} has no stored launch configuration", session_id))?;
+
+    let head = session_manager
+        .get_branch_name(session_id)
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| config.branch_name.clone())
+        .unwrap_or_else(|| {
+            generate_branch_name(&config.user_instructions, &config.effective.branch_prefix)
+        });
+
+    let checkout = session_manager
+        .get_work_dir(session_id)
+        .ok()
+        .filter(|dir| cli::open_or_fallback(dir).is_ok());
+    let (repo_dir, changed) = match checkout {
+        Some(dir) => {
+            let changed = changed_paths(&dir, &config.effective.base_branch).unwrap_or_default();
+            (dir, changed)
+        }
+        None => (Path::new(&config.git_directory).to_path_buf(), Vec::new()),
+    };
+
+    Ok(compose_pr_payload(&config, &repo_dir, &head, &changed, info.will_conflict))
+}
+
+/// Shows what would be sent to GitHub for a session's PR. Nothing is sent.
+#[tauri::command]
+pub fn preview_pr_payload(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<PrPayload, String> {
+    preview_pr_payload_for(&state.session_manager, &session_id)
+}
+
+/// A page of the GitHub repos the user can access, for picking one to clone.
+#[tauri::command]
+pub async fn list_user_repos(
+    page: Option<u32>,
+    per_page: Option<u32>,
+) -> Result<pr::UserRepoPage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        pr::list_user_repos(page.unwrap_or(1), per_page.unwrap_or(30)).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Reopens the session's closed PR and returns its new state.
+#[tauri::command]
+pub async fn reopen_pr(state: State<'_, AppState>, session_id: String) -> Result<String, String> {
+    let pr_url = known_sessions(&state.session_manager)?
+        .into_iter()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?
+        .pr_url
+        .ok_or_else(|| format!("Session {} has no PR", session_id))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        pr::reopen_pull_request(&pr_url).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Updates the title and/or body of the session's PR, e.g. after a refine
+/// made the original description stale. Returns the PR URL.
+#[tauri::command]
+pub async fn update_pr(
+    state: State<'_, AppState>,
+    session_id: String,
+    title: Option<String>,
+    body: Option<String>,
+) -> Result<String, String> {
+    let pr_url = known_sessions(&state.session_manager)?
+        .into_iter()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?
+        .pr_url
+        .ok_or_else(|| format!("Session {} has no PR", session_id))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        pr::update_pull_request(&pr_url, title.as_deref(), body.as_deref())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn reset_session_branch(
+    state: State<'_, AppState>,
+    session_id: String,
+    target: String,
+    confirm: bool,
+) -> Result<String, String> {
+    if !confirm {
+        return Err("Resetting a pushed branch requires confirmation".to_string());
+    }
+
+    let branch_name = state
+        .session_manager
+        .get_branch_name(&session_id)
+        .map_err(|e| e.to_string())?;
+    let info = state
+        .session_manager
+        .get_session_info(&session_id)
+        .map_err(|e| e.to_string())?;
+    let prefix = match &info.config {
+        Some(config) => config.branch_prefix.clone(),
+        None => settings::get_string("branch_prefix")
+            .unwrap_or_else(|| DEFAULT_BRANCH_PREFIX.to_string()),
+    };
+
+    if branch_name.is_empty() || !branch_name.starts_with(&prefix) {
+        return Err(format!(
+            "Refusing to reset branch '{}': it does not match the prefix '{}'",
+            branch_name, prefix
+        ));
+    }
+
+    let base_branch = state
+        .session_manager
+        .get_base_branch(&session_id)
+        .map_err(|e| e.to_string())?;
+    let work_dir = state
+        .session_manager
+        .get_work_dir(&session_id)
+        .map_err(|e| e.to_string())?;
+    let repo_path = if work_dir.exists() {
+        work_dir
+    } else {
+        Path::new(&info.git_directory).to_path_buf()
+    };
+
+    match pr::check_force_push(&repo_path, &branch_name) {
+        Ok(check) if !check.allowed => {
+            return Err(check.message.unwrap_or_default());
+        }
+        Ok(ForcePushCheck {
+            protection_known: false,
+            message: Some(message),
+            ..
+        }) => eprintln!("Warning: {}", message),
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: Could not check force-push protection: {}", e),
+    }
+
+    let new_head = reset_remote_branch(
+        &repo_path,
+        &branch_name,
+        &base_branch,
+        &ResetTarget::parse(&target),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(new_head.to_string())
+}
+
+/// Deletes the bot's branches on `origin` whose PRs have merged, returning
+/// the names removed.
+#[tauri::command]
+pub async fn sweep_merged_branches(git_directory: String) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo_path = Path::new(&git_directory);
+        let config = effective_config(repo_path)?;
+        branch::sweep_merged_branches(repo_path, &config.branch_prefix)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn check_force_push(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<ForcePushCheck, String> {
+    let branch_name = state
+        .session_manager
+        .get_branch_name(&session_id)
+        .map_err(|e| e.to_string())?;
+    let info = state
+        .session_manager
+        .get_session_info(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    pr::check_force_push(Path::new(&info.git_directory), &branch_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_sessions_for_repo(
+    state: State<'_, AppState>,
+    git_directory: String,
+) -> Result<Vec<SessionInfo>, String> {
+    let sessions = known_sessions(&state.session_manager)?;
+    Ok(filter_sessions_for_repo(sessions, &git_directory))
+}
+
+/// In-memory sessions plus persisted ones from earlier runs of the app.
+fn known_sessions(session_manager: &SessionManager) -> Result<Vec<SessionInfo>, String> {
+    let mut sessions = session_manager.list_sessions().map_err(|e| e.to_string())?;
+
+    let persisted = persistence::load_all().map_err(|e| e.to_string())?;
+    for info in persisted {
+        if !sessions.iter().any(|s| s.id == info.id) {
+            sessions.push(info);
+        }
+    }
+
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub fn search_sessions(
+    state: State<'_, AppState>,
+    query: String,
+) -> Result<Vec<SessionMatch>, String> {
+    let sessions = known_sessions(&state.session_manager)?;
+    Ok(manager::search_sessions(sessions, &query))
+}
+
+#[tauri::command]
+pub async fn run_command_in_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    command: String,
+) -> Result<CommandOutput, String> {
+    let work_dir = state
+        .session_manager
+        .get_work_dir(&session_id)
+        .map_err(|e| e.to_string())?;
+
+    if !work_dir.exists() {
+        return Err(format!(
+            "Work directory for session {} no longer exists",
+            session_id
+        ));
+    }
+
+    let allowed_commands = match state.session_manager.get_session_info(&session_id) {
+        Ok(SessionInfo {
+            config: Some(config),
+            ..
+        }) => config.allowed_commands,
+        _ => ALLOWED_BASH_PATTERNS.iter().map(|s| s.to_string()).collect(),
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        run_allowed_command(&work_dir, &command, &allowed_commands)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn dedupe_sessions(state: State<'_, AppState>) -> Result<Vec<MergedSession>, String> {
+    let merged = state
+        .session_manager
+        .dedupe_sessions()
+        .map_err(|e| e.to_string())?;
+
+    for merge in &merged {
+        if let Err(e) = persistence::delete_session_info(&merge.removed) {
+            eprintln!("Warning: Failed to remove persisted session {}: {}", merge.removed, e);
+        }
+        artifacts::delete_session_artifacts(&merge.removed);
+    }
+
+    Ok(merged)
+}
+
+#[tauri::command]
+pub async fn estimate_clone(git_directory: String) -> Result<DirStats, String> {
+    tauri::async_runtime::spawn_blocking(move || estimate_clone_size(Path::new(&git_directory)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Removes logs and reports left behind by sessions that are neither tracked,
+/// persisted nor archived.
+#[tauri::command]
+pub fn cleanup_orphaned_artifacts(state: State<'_, AppState>) -> Result<ArtifactCleanup, String> {
+    let mut known: HashSet<String> = known_sessions(&state.session_manager)?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    known.extend(
+        persistence::load_archived()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|s| s.id),
+    );
+
+    let sessions_dir = persistence::get_sessions_dir().map_err(|e| e.to_string())?;
+    Ok(artifacts::cleanup_orphaned_artifacts_in(&sessions_dir, &known))
+}
+
+#[tauri::command]
+pub fn get_repo_pr_template(git_directory: String) -> Result<String, String> {
+    let path = Path::new(&git_directory);
+    if !path.is_dir() {
+        return Err(format!("Directory not found: {}", git_directory));
+    }
+    pr::read_pr_template(path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_effective_session_config(git_directory: String) -> Result<EffectiveConfig, String> {
+    effective_config(Path::new(&git_directory))
+}
+
+fn repo_checkout(git_directory: &str) -> Result<&Path, String> {
+    let path = Path::new(git_directory);
+    if !path.is_dir() {
+        return Err(format!("Not a directory: {}", git_directory));
+    }
+    Ok(path)
+}
+
+/// The commands in the repo's `.dreamal/allowed-commands.json`; empty when
+/// the file doesn't exist.
+#[tauri::command]
+pub fn get_repo_allowed_commands(git_directory: String) -> Result<Vec<String>, String> {
+    Ok(load_repo_allowed_commands(repo_checkout(&git_directory)?)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn set_repo_allowed_commands(
+    git_directory: String,
+    commands: Vec<String>,
+) -> Result<(), String> {
+    save_repo_allowed_commands(repo_checkout(&git_directory)?, commands)
+}
+
+#[tauri::command]
+pub async fn list_recent_prs(
+    limit: Option<usize>,
+    include_state: Option<bool>,
+) -> Result<Vec<RecentPr>, String> {
+    let sessions = persistence::load_all().map_err(|e| e.to_string())?;
+    let mut prs = recent_prs(sessions, limit.unwrap_or(20));
+
+    if include_state.unwrap_or(false) {
+        prs = tauri::async_runtime::spawn_blocking(move || {
+            for pr in prs.iter_mut() {
+                pr.state = pr::get_pull_request_state(&pr.pr_url).ok();
+            }
+            prs
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(prs)
+}
+
+/// Remembers `branch` as the base for sessions on this repo that don't pass
+/// one. An empty branch clears it.
+#[tauri::command]
+pub fn set_repo_default_base(git_directory: String, branch: String) -> Result<(), String> {
+    let repo = git2::Repository::open(&git_directory)
+        .map_err(|e| format!("Not a git repository: {} ({})", git_directory, e.message()))?;
+    let branch = branch.trim();
+    if !branch.is_empty()
+        && repo.find_branch(branch, git2::BranchType::Local).is_err()
+        && repo
+            .find_branch(&format!("origin/{}", branch), git2::BranchType::Remote)
+            .is_err()
+    {
+        return Err(format!("Branch not found: {}", branch));
+    }
+
+    let mut global = settings::read_settings()?;
+    remember_base_branch(&mut global, Path::new(&git_directory), branch);
+    settings::write_settings(&global)
+}
+
+/// Sets the global `clone_depth` used for fetches while cloning; `None` or 0
+/// restores full history.
+#[tauri::command]
+pub fn set_clone_depth(depth: Option<u32>) -> Result<(), String> {
+    let mut global = settings::read_settings()?;
+
+    match depth.filter(|depth| *depth > 0) {
+        Some(depth) => {
+            global.insert("clone_depth".to_string(), serde_json::json!(depth));
+        }
+        None => {
+            global.remove("clone_depth");
+        }
+    }
+
+    settings::write_settings(&global)
+}
+
+/// Sets the global `max_memory_mb` cap on the memory Claude allocates; `None`
+/// or 0 removes it. Only enforced on Unix.
+#[tauri::command]
+pub fn set_max_memory_mb(max_memory_mb: Option<u64>) -> Result<(), String> {
+    let mut global = settings::read_settings()?;
+
+    match max_memory_mb.filter(|mb| *mb > 0) {
+        Some(mb) => {
+            global.insert("max_memory_mb".to_string(), serde_json::json!(mb));
+        }
+        None => {
+            global.remove("max_memory_mb");
+        }
+    }
+
+    settings::write_settings(&global)
+}
+
+/// Sets the global `max_changed_files` cap on how many files a session may
+/// change; `None` or 0 removes it.
+#[tauri::command]
+pub fn set_max_changed_files(max_changed_files: Option<usize>) -> Result<(), String> {
+    let mut global = settings::read_settings()?;
+
+    match max_changed_files.filter(|max| *max > 0) {
+        Some(max) => {
+            global.insert("max_changed_files".to_string(), serde_json::json!(max));
+        }
+        None => {
+            global.remove("max_changed_files");
+        }
+    }
+
+    settings::write_settings(&global)
+}
+
+/// Points the global `guidelines_file` setting at `path` after checking it
+/// has content. An empty path clears the setting.
+#[tauri::command]
+pub fn set_guidelines_file(path: String) -> Result<(), String> {
+    let mut global = settings::read_settings()?;
+    let path = path.trim();
+
+    if path.is_empty() {
+        global.remove("guidelines_file");
+    } else {
+        let path = Path::new(path)
+            .canonicalize()
+            .map_err(|e| format!("Guidelines file not found: {} ({})", path, e))?;
+        if process::read_guidelines_file(&path).is_none() {
+            return Err(format!(
+                "Guidelines file is empty or unreadable: {}",
+                path.display()
+            ));
+        }
+        global.insert(
+            "guidelines_file".to_string(),
+            serde_json::json!(path.to_string_lossy()),
+        );
+    }
+
+    settings::write_settings(&global)
+}
+
+/// Checks that pushing to the repo's `origin` would authenticate, without
+/// pushing anything.
+#[tauri::command]
+pub async fn test_push_credentials(git_directory: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        commit::test_push_credentials(Path::new(&git_directory)).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn detect_project_type(git_directory: String) -> Result<Vec<ProjectDetection>, String> {
+    let path = Path::new(&git_directory);
+    if !path.is_dir() {
+        return Err(format!("Directory not found: {}", git_directory));
+    }
+    Ok(detect_project_types(path))
+}
+
+#[tauri::command]
+pub fn pause_scheduler(state: State<'_, AppState>) -> Result<QueueStatus, String> {
+    state.session_manager.pause_scheduler();
+    state.session_manager.queue_status().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn resume_scheduler(state: State<'_, AppState>) -> Result<QueueStatus, String> {
+    state.session_manager.resume_scheduler();
+    state.session_manager.queue_status().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_queue_status(state: State<'_, AppState>) -> Result<QueueStatus, String> {
+    state.session_manager.queue_status().map_err(|e| e.to_string())
+}
+
+/// Running sessions against `max_concurrent_sessions`, so the UI can tell
+/// whether a new one would queue.
+#[tauri::command]
+pub fn get_capacity(state: State<'_, AppState>) -> Result<Capacity, String> {
+    if let Ok(global) = settings::read_settings() {
+        let limit = resolve_config(&global, &Default::default()).max_concurrent_sessions;
+        state.session_manager.set_session_limit(limit);
+    }
+    state.session_manager.capacity().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reconcile_sessions(
+    state: State<'_, AppState>,
+    fix: Option<bool>,
+) -> Result<ReconcileSummary, String> {
+    let checkouts_dir = crate::git_ops::get_temp_checkouts_dir().map_err(|e| e.to_string())?;
+    let persisted = persistence::load_all().map_err(|e| e.to_string())?;
+
+    let summary = reconcile_sessions_in(
+        &checkouts_dir,
+        &state.session_manager,
+        &persisted,
+        fix.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())?;
+
+    for session_id in &summary.marked_error {
+        state.session_manager.persist(session_id);
+    }
+
+    Ok(summary)
+}
+
+/// How often the background reaper looks for sessions whose Claude died.
+const REAP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+fn reap_stale(session_manager: &SessionManager) -> Result<Vec<String>, String> {
+    let reaped = reap_stale_sessions_with(session_manager, process::process_exists)
+        .map_err(|e| e.to_string())?;
+
+    for session_id in &reaped {
+        let cleaned = session_manager
+            .get_work_dir(session_id)
+            .map_err(|e| e.to_string())
+            .and_then(|work_dir| cleanup_session_dir(&work_dir).map_err(|e| e.to_string()));
+        if let Err(e) = cleaned {
+            eprintln!("Warning: Failed to clean up {}: {}", session_id, e);
+        }
+        session_manager.persist(session_id);
+    }
+
+    Ok(reaped)
+}
+
+/// Checks every `REAP_INTERVAL` for `Working` sessions whose process is gone.
+pub fn spawn_stale_session_reaper(session_manager: Arc<SessionManager>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(REAP_INTERVAL);
+        if let Err(e) = reap_stale(&session_manager) {
+            eprintln!("Warning: Failed to reap stale sessions: {}", e);
+        }
+    });
+}
+
+/// Marks `Working` sessions whose Claude process has exited as errored and
+/// cleans up their checkouts. Returns the reaped session ids.
+#[tauri::command]
+pub fn reap_stale_sessions(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    reap_stale(&state.session_manager)
+}
+
+#[tauri::command]
+pub fn list_live_checkouts(state: State<'_, AppState>) -> Result<Vec<LiveCheckout>, String> {
+    let checkouts_dir = crate::git_ops::get_temp_checkouts_dir().map_err(|e| e.to_string())?;
+    let persisted = persistence::load_all().map_err(|e| e.to_string())?;
+
+    list_live_checkouts_in(&checkouts_dir, &state.session_manager, &persisted)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn suggest_reviewers(
+    git_directory: String,
+    changed_paths: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let rules = codeowners::load_codeowners(Path::new(&git_directory)).map_err(|e| e.to_string())?;
+    Ok(codeowners::suggest_reviewers(&rules, &changed_paths))
+}
+
+#[tauri::command]
+pub fn peek_session_output(
+    state: State<'_, AppState>,
+    session_id: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let buffer = state
+        .session_manager
+        .output_buffer(&session_id)
+        .map_err(|e| e.to_string())?;
+    Ok(buffer.last(lines.unwrap_or(50)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, request, setup_test_repo, test_manager};
+
+    fn completed_session_with_changes(manager: &SessionManager, id: &str) -> tempfile::TempDir {
+        let (work_dir, repo_path) = setup_test_repo();
+        fs::write(repo_path.join("test.txt"), format!("{}\n", id)).unwrap();
+
+        manager
+            .create_session(
+                id.to_string(),
+                "/path/to/repo".to_string(),
+                "Edit notes".to_string(),
+                repo_path,
+                String::new(),
+            )
+            .unwrap();
+        manager.set_completed(id, String::new()).unwrap();
+        work_dir
+    }
+
+    #[test]
+    fn test_write_report_beside_session_record() {
+        let queue_dir = tempfile::tempdir().unwrap();
+        let session_manager = SessionManager::new();
+        session_manager.set_queue_dir(queue_dir.path().to_path_buf());
+        session_manager
+            .create_session(
+                "reported".to_string(),
+                "/path/to/repo".to_string(),
+                "Fix the login form".to_string(),
+                PathBuf::from("/tmp/work"),
+                String::new(),
+            )
+            .unwrap();
+        session_manager
+            .set_completed("reported", "https://github.com/o/r/pull/7".to_string())
+            .unwrap();
+
+        write_report(&session_manager, "reported");
+
+        let report = fs::read_to_string(
+            artifacts::session_artifacts_dir_in(queue_dir.path(), "reported")
+                .join(artifacts::REPORT_FILE),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            "**Completed**: Fix the login form\nPR: https://github.com/o/r/pull/7\n"
+        );
+    }
+
+    #[test]
+    fn test_export_without_base_branch_skips_session() {
+        let manager = SessionManager::new();
+        let (_source_dir, source) = setup_test_repo();
+        let repo = git2::Repository::open(&source).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("claude/pushed", &head, false).unwrap();
+        manager
+            .create_session(
+                "pushed".to_string(),
+                source.to_string_lossy().to_string(),
+                "Edit notes".to_string(),
+                source.join("cleaned-up"),
+                "claude/pushed".to_string(),
+            )
+            .unwrap();
+        manager
+            .set_base_branch("pushed", "release".to_string())
+            .unwrap();
+        manager.set_completed("pushed", String::new()).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        let export = export_all_session_patches_in(&manager, dest.path()).unwrap();
+
+        assert!(export.written.is_empty());
+        assert_eq!(export.skipped.len(), 1);
+        assert!(
+            export.skipped[0].reason.contains("Base branch release not found"),
+            "{}",
+            export.skipped[0].reason
+        );
+    }
+
+    #[test]
+    fn test_export_all_session_patches() {
+        let manager = SessionManager::new();
+        let _first = completed_session_with_changes(&manager, "first");
+        let _second = completed_session_with_changes(&manager, "second");
+        manager
+            .create_session(
+                "gone".to_string(),
+                "/path/to/repo".to_string(),
+                "Edit notes".to_string(),
+                PathBuf::from("/nonexistent/dreamal-session"),
+                String::new(),
+            )
+            .unwrap();
+        manager.set_completed("gone", String::new()).unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let dest_dir = dest.path().join("patches");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::write(dest_dir.join("first.patch"), "older export").unwrap();
+
+        let export = export_all_session_patches_in(&manager, &dest_dir).unwrap();
+
+        let mut written: Vec<&str> = export.written.iter().map(|p| p.path.as_str()).collect();
+        written.sort();
+        assert_eq!(
+            written,
+            vec![
+                dest_dir.join("first-1.patch").display().to_string(),
+                dest_dir.join("second.patch").display().to_string(),
+            ]
+        );
+        let first = fs::read_to_string(dest_dir.join("first-1.patch")).unwrap();
+        let second = fs::read_to_string(dest_dir.join("second.patch")).unwrap();
+        assert!(first.contains("+first"));
+        assert!(second.contains("+second"));
+        assert_eq!(export.skipped.len(), 1);
+        assert_eq!(export.skipped[0].session_id, "gone");
+    }
+
+    #[test]
+    fn test_spawn_batch_reports_invalid_repo() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        // Keep the sessions queued so the test never clones or runs Claude.
+        session_manager.pause_scheduler();
+
+        let first = init_repo();
+        let second = init_repo();
+        let not_a_repo = tempfile::tempdir().unwrap();
+
+        let results = spawn_batch(
+            &session_manager,
+            vec![
+                request(first.path()),
+                request(not_a_repo.path()),
+                request(second.path()),
+            ],
+        );
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].session_id.is_some());
+        assert!(results[2].session_id.is_some());
+        assert!(results[1].session_id.is_none());
+        assert!(results[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Not a git repository"));
+
+        for result in [&results[0], &results[2]] {
+            let info = session_manager
+                .get_session_info(result.session_id.as_deref().unwrap())
+                .unwrap();
+            assert_eq!(info.git_directory, result.git_directory);
+        }
+        assert_eq!(session_manager.list_sessions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_retry_failed_in_batch_skips_completed() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        session_manager.pause_scheduler();
+
+        let repo = init_repo();
+        let requests = ["Fix parser", "Fix lexer", "Fix docs"].map(|instructions| {
+            let mut request = request(repo.path());
+            request.instructions = instructions.to_string();
+            request
+        });
+        let results = spawn_batch(&session_manager, requests.to_vec());
+        let ids: Vec<String> = results
+            .iter()
+            .map(|result| result.session_id.clone().unwrap())
+            .collect();
+        let batch_id = results[0].batch_id.clone();
+
+        session_manager
+            .set_completed(&ids[0], "https://github.com/o/r/pull/1".to_string())
+            .unwrap();
+        session_manager
+            .set_error(&ids[1], "Network hiccup".to_string())
+            .unwrap();
+        session_manager
+            .set_error(&ids[2], "Network hiccup".to_string())
+            .unwrap();
+        // Failed sessions outside the batch are left alone.
+        let other = start_session(&session_manager, request(repo.path()), None).unwrap();
+        session_manager
+            .set_error(&other, "Network hiccup".to_string())
+            .unwrap();
+
+        let retried = retry_failed_in(&session_manager, &batch_id).unwrap();
+
+        assert_eq!(retried.len(), 2);
+        for original in &ids[1..] {
+            let failed = session_manager.get_session_info(original).unwrap();
+            let retry = failed.retried_as.unwrap();
+            assert!(retried.contains(&retry));
+            let info = session_manager.get_session_info(&retry).unwrap();
+            assert_eq!(info.batch_id.as_deref(), Some(batch_id.as_str()));
+            assert_eq!(info.instructions, failed.instructions);
+        }
+        let completed = session_manager.get_session_info(&ids[0]).unwrap();
+        assert_eq!(completed.status, SessionStatus::Completed);
+        assert!(completed.retried_as.is_none());
+        assert!(session_manager
+            .get_session_info(&other)
+            .unwrap()
+            .retried_as
+            .is_none());
+
+        // Sessions already retried aren't retried again.
+        assert!(retry_failed_in(&session_manager, &batch_id)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_retry_after_restart_uses_fresh_branch() {
+        let (dreamal_dir, session_manager) = test_manager();
+        let queue_dir = session_manager.queue_dir().unwrap();
+        session_manager.pause_scheduler();
+
+        let repo = init_repo();
+        let mut pinned = request(repo.path());
+        pinned.branch_name = Some("fix/lexer".to_string());
+        let results = spawn_batch(&session_manager, vec![pinned]);
+        let failed = results[0].session_id.clone().unwrap();
+        while persistence::load_launch_config_in(&queue_dir, &failed).is_err() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        session_manager
+            .set_error(&failed, "Push rejected".to_string())
+            .unwrap();
+
+        let restarted = Arc::new(SessionManager::new());
+        restarted.set_queue_dir(queue_dir.clone());
+        restarted.set_checkouts_dir(dreamal_dir.path().join("temp-checkouts"));
+        restore_persisted_in(&queue_dir, &restarted);
+
+        let retried = retry_failed_in(&restarted, &results[0].batch_id).unwrap();
+        assert_eq!(retried.len(), 1);
+        let branch = restarted
+            .get_launch_config(&retried[0])
+            .unwrap()
+            .unwrap()
+            .branch_name
+            .unwrap();
+        assert_eq!(branch, format!("fix/lexer-retry-{}", &retried[0][..8]));
+        let saved = persistence::load_session_info_in(&queue_dir, &failed).unwrap();
+        assert_eq!(saved.retried_as.as_deref(), Some(retried[0].as_str()));
+    }
+
+    #[test]
+    fn test_retry_branch_name_replaces_earlier_suffix() {
+        assert_eq!(
+            retry_branch_name("fix/lexer", "0123abcd-ffff"),
+            "fix/lexer-retry-0123abcd"
+        );
+        assert_eq!(
+            retry_branch_name("fix/lexer-retry-0123abcd", "89efcdab-0000"),
+            "fix/lexer-retry-89efcdab"
+        );
+        assert_eq!(
+            retry_branch_name("fix/lexer-retry-today", "89efcdab-0000"),
+            "fix/lexer-retry-today-retry-89efcdab"
+        );
+    }
+
+    #[test]
+    fn test_preview_pr_payload_reflects_config() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        session_manager.pause_scheduler();
+        let repo = init_repo();
+
+        let mut pinned = request(repo.path());
+        pinned.base_branch = Some("develop".to_string());
+        pinned.branch_name = Some("feature/dark-mode".to_string());
+        pinned.labels = Some(vec!["frontend".to_string()]);
+        pinned.ticket_id = Some("UI-42".to_string());
+        let session_id = start_session(&session_manager, pinned, None).unwrap();
+
+        let payload = preview_pr_payload_for(&session_manager, &session_id).unwrap();
+        assert_eq!(payload.head, "feature/dark-mode");
+        assert_eq!(payload.base, "develop");
+        assert_eq!(payload.labels, vec!["frontend"]);
+        assert_eq!(payload.title, "Apply the fix");
+        assert!(payload.body.contains("Apply the fix"));
+        assert!(!payload.draft);
+        assert!(payload.reviewers.is_empty() && payload.assignees.is_empty());
+
+        let generated = start_session(&session_manager, request(repo.path()), None).unwrap();
+        let payload = preview_pr_payload_for(&session_manager, &generated).unwrap();
+        assert!(payload.head.starts_with(DEFAULT_BRANCH_PREFIX));
+        assert_eq!(payload.base, "main");
+    }
+
+    #[test]
+    fn test_cancel_locked_session_requires_force() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        session_manager
+            .create_session(
+                session_id.clone(),
+                "/repo".to_string(),
+                "Long migration".to_string(),
+                session_manager.session_dir(&session_id).unwrap(),
+                String::new(),
+            )
+            .unwrap();
+        session_manager.set_locked(&session_id, true).unwrap();
+
+        let refused = cancel(&session_manager, &session_id, false).unwrap_err();
+        assert!(refused.contains("locked"));
+        assert_eq!(
+            session_manager.get_session_info(&session_id).unwrap().status,
+            SessionStatus::Initializing
+        );
+
+        cancel(&session_manager, &session_id, true).unwrap();
+        let info = session_manager.get_session_info(&session_id).unwrap();
+        assert_eq!(info.status, SessionStatus::Error);
+        assert!(info.locked);
+    }
+
+    #[test]
+    fn test_reclone_refuses_claimed_checkout() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        session_manager
+            .create_session(
+                session_id.clone(),
+                "/repo".to_string(),
+                "Add dark mode".to_string(),
+                session_manager.session_dir(&session_id).unwrap(),
+                String::new(),
+            )
+            .unwrap();
+        session_manager
+            .set_error(&session_id, "Claude failed".to_string())
+            .unwrap();
+
+        // A run still holding the checkout.
+        assert!(session_manager.claim_checkout(&session_id).unwrap());
+        let refused = reclone(&session_manager, &session_id).unwrap_err();
+        assert!(refused.contains("still running"));
+        assert_eq!(
+            session_manager.get_session_info(&session_id).unwrap().status,
+            SessionStatus::Error
+        );
+        session_manager.release_checkout(&session_id).unwrap();
+
+        // A reclone that can't launch gives the claim back.
+        let failed = reclone(&session_manager, &session_id).unwrap_err();
+        assert!(failed.contains("no stored launch configuration"));
+        assert!(!session_manager.is_checkout_claimed(&session_id).unwrap());
+    }
+
+    #[test]
+    fn test_queued_session_resumes_after_restart() {
+        let (_dreamal_dir, session_manager) = test_manager();
+        let queue_dir = session_manager.queue_dir().unwrap();
+        session_manager.pause_scheduler();
+
+        let repo = init_repo();
+        let mut ids = Vec::new();
+        for instructions in ["First task", "Second task"] {
+            let mut request = request(repo.path());
+            request.instructions = instructions.to_string();
+            let session_id = start_session(&session_manager, request, None).unwrap();
+            while persistence::load_launch_config_in(&queue_dir, &session_id).is_err() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            ids.push(session_id);
+            // Queue order is kept to the millisecond.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        // One whose launch inputs were lost can't be resumed.
+        let mut lost = request(repo.path());
+        lost.instructions = "Lost task".to_string();
+        let lost_id = start_session(&session_manager, lost, None).unwrap();
+        while persistence::load_launch_config_in(&queue_dir, &lost_id).is_err() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        fs::remove_file(queue_dir.join(format!("{}.launch", lost_id))).unwrap();
+
+        // A fresh manager stands in for the restarted app.
+        let restarted = SessionManager::new();
+        let configs = requeue_persisted_in(&queue_dir, &restarted);
+        let lost = restarted.get_session_info(&lost_id).unwrap();
+        assert_eq!(lost.status, SessionStatus::Error);
+        assert!(lost.error_message.unwrap().contains("launch settings were lost"));
+
+        let requeued: Vec<_> = configs.iter().map(|c| c.session_id.clone()).collect();
+        assert_eq!(requeued, ids);
+        assert_eq!(configs[1].user_instructions, "Second task");
+        for id in &ids {
+            let info = restarted.get_session_info(id).unwrap();
+            assert_eq!(info.status, SessionStatus::Queued);
+            assert!(info.resumed_from_queue);
+        }
+
+        assert!(restarted.wait_for_scheduler(&ids[0]).unwrap());
+        assert_eq!(
+            restarted.get_session_info(&ids[0]).unwrap().status,
+            SessionStatus::Initializing
+        );
+    }
+
+    #[test]
+    fn test_history_restored_after_restart() {
+        let queue_dir = tempfile::tempdir().unwrap();
+        let session_manager = SessionManager::new();
+        session_manager.set_queue_dir(queue_dir.path().to_path_buf());
+        for id in ["done", "running"] {
+            session_manager
+                .create_session(
+                    id.to_string(),
+                    "/repo".to_string(),
+                    "Task".to_string(),
+                    queue_dir.path().join(id),
+                    format!("claude/{}", id),
+                )
+                .unwrap();
+            session_manager.set_working(id, 1).unwrap();
+        }
+        session_manager
+            .set_completed("done", "https://github.com/o/r/pull/1".to_string())
+            .unwrap();
+
+        let restarted = SessionManager::new();
+        restarted.set_queue_dir(queue_dir.path().to_path_buf());
+        assert_eq!(restore_persisted_in(queue_dir.path(), &restarted), 1);
+        assert_eq!(restore_persisted_in(queue_dir.path(), &restarted), 0);
+
+        let done = restarted.get_session_info("done").unwrap();
+        assert_eq!(done.status, SessionStatus::Completed);
+        assert_eq!(done.pr_url.as_deref(), Some("https://github.com/o/r/pull/1"));
+        let running = restarted.get_session_info("running").unwrap();
+        assert_eq!(running.status, SessionStatus::Error);
+        assert!(running.error_message.unwrap().contains("exited"));
+
+        let saved = persistence::load_all_in(queue_dir.path()).unwrap();
+        let saved_running = saved.iter().find(|info| info.id == "running").unwrap();
+        assert_eq!(saved_running.status, SessionStatus::Error);
+    }
+
+    #[test]
+    fn test_diff_sessions_falls_back_to_source_repo() {
+        let source = init_repo();
+        let repo = git2::Repository::open(source.path()).unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        let mut parent = None;
+        for (branch, content) in [("claude/kept", "kept\n"), ("claude/gone", "gone\n")] {
+            let blob = repo.blob(content.as_bytes()).unwrap();
+            let mut tree = repo.treebuilder(None).unwrap();
+            tree.insert("notes.txt", blob, 0o100644).unwrap();
+            let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = parent.iter().cloned().collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            let commit = repo.commit(None, &sig, &sig, branch, &tree, &parents).unwrap();
+            let commit = repo.find_commit(commit).unwrap();
+            repo.branch(branch, &commit, false).unwrap();
+            parent = Some(commit);
+        }
+
+        let session_manager = SessionManager::new();
+        let cleaned_up = source.path().join("cleaned-up");
+        for (id, work_dir) in [
+            ("kept", source.path().to_path_buf()),
+            ("gone", cleaned_up.clone()),
+            ("unpushed", cleaned_up.clone()),
+        ] {
+            session_manager
+                .create_session(
+                    id.to_string(),
+                    source.path().to_string_lossy().to_string(),
+                    "Task".to_string(),
+                    work_dir,
+                    format!("claude/{}", id),
+                )
+                .unwrap();
+        }
+
+        let diff = diff_sessions_in(&session_manager, "kept", "gone").unwrap();
+        assert!(diff.patch.contains("-kept"));
+        assert!(diff.patch.contains("+gone"));
+        let error = diff_sessions_in(&session_manager, "kept", "unpushed").unwrap_err();
+        assert!(error.contains("claude/unpushed isn't in the source repo"));
+        assert!(diff_sessions_in(&session_manager, "kept", "missing").is_err());
+    }
+}
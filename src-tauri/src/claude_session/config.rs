@@ -0,0 +1,1058 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::manager::{DEFAULT_MAX_CONCURRENT_CLONES, DEFAULT_MAX_CONCURRENT_SESSIONS};
+use super::process::{ALLOWED_BASH_PATTERNS, DEFAULT_ALLOWED_TOOLS, DEFAULT_MAX_INSTRUCTIONS_LEN};
+use super::project::{detect_project_types, ProjectDetection};
+use crate::git_ops::branch::DEFAULT_BRANCH_PREFIX;
+use crate::settings;
+
+pub const REPO_CONFIG_PATH: &str = ".dreamal/config.json";
+/// Bash commands a repo allows on top of its `allowed_commands`, once the user
+/// trusts such files. Read from the user's repo when the session starts, never
+/// from the checkout.
+pub const REPO_ALLOWED_COMMANDS_PATH: &str = ".dreamal/allowed-commands.json";
+/// Global setting that lets `REPO_ALLOWED_COMMANDS_PATH` widen the allowlist.
+pub const TRUST_REPO_ALLOWED_COMMANDS_KEY: &str = "trust_repo_allowed_commands";
+
+pub const MIN_TIMEOUT_SECS: u64 = 30;
+pub const MAX_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// Global settings key mapping canonicalized repo paths to the base branch
+/// remembered for each.
+pub const REPO_DEFAULT_BASES_KEY: &str = "repo_default_bases";
+
+type Settings = HashMap<String, serde_json::Value>;
+
+/// Keys a repo's `.dreamal/config.json` can't set. A cloned repo is not trusted
+/// to loosen them, and there is no tighter value to merge towards.
+const GLOBAL_ONLY_KEYS: &[&str] = &[
+    "keep_failed_checkouts",
+    "model",
+    "auto_delete_merged_branches",
+    "branch_prefix",
+    // The build command runs unsandboxed, before Claude's allowlist applies.
+    "baseline_build",
+    "baseline_build_command",
+];
+
+/// The settings that governed a session after merging repo config, global settings
+/// and built-in defaults, in that order of precedence. Settings that bound what
+/// Claude may do are the exception: a repo can only tighten those, never loosen
+/// them. Fields missing from records persisted by older versions deserialize to
+/// their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffectiveConfig {
+    pub base_branch: String,
+    pub branch_prefix: String,
+    pub allowed_commands: Vec<String>,
+    pub allowed_tools: Vec<String>,
+    pub max_concurrent_clones: usize,
+    pub max_concurrent_sessions: usize,
+    pub clone_submodules: bool,
+    pub keep_failed_checkouts: bool,
+    pub strict_instructions: bool,
+    pub bot_author_name: Option<String>,
+    pub bot_author_email: Option<String>,
+    pub request_codeowner_reviewers: bool,
+    /// Run after clone, before Claude, when `baseline_build` is enabled. Only
+    /// the global settings can enable it or pick the command.
+    pub baseline_build_command: Option<String>,
+    /// How long Claude may run before it is killed; `None` means no limit.
+    pub timeout_secs: Option<u64>,
+    /// Markdown file replacing the default guidelines; relative paths are
+    /// resolved against the repo. Only the global settings may name a file
+    /// outside the repo. Read fresh for every session.
+    pub guidelines_file: Option<String>,
+    /// Prepended to the commit subject, e.g. a `[JIRA-123]` ticket id.
+    pub commit_message_prefix: Option<String>,
+    /// Whether `commit_message_prefix` also starts the PR title.
+    pub prefix_pr_title: bool,
+    /// History depth for fetches made while cloning. The checkout itself is a
+    /// copy of the local repo, so this only shortens submodule fetches.
+    pub clone_depth: Option<u32>,
+    /// Longest composed instructions, in bytes, a session may start with.
+    /// Longer ones are refused under `strict_instructions`, otherwise warned.
+    pub max_instructions_len: usize,
+    /// Labels added to the session's PR once it is open.
+    pub pr_labels: Vec<String>,
+    /// Cap on the memory Claude and its tools allocate, in MB. Unix only.
+    pub max_memory_mb: Option<u64>,
+    /// Paths, relative to the repo root, Claude's changes must stay within.
+    /// Empty allows the whole checkout.
+    pub editable_paths: Vec<String>,
+    /// Most files a session may change before its commit is refused. A repo
+    /// may lower the global cap but not raise it.
+    pub max_changed_files: Option<usize>,
+    /// Least time between two Claude process spawns, in ms; 0 disables it.
+    pub min_spawn_interval_ms: u64,
+    /// Passed to Claude as `--model`; unset uses the CLI's default.
+    pub model: Option<String>,
+    /// Whether merged `branch_prefix` branches are deleted from `origin`.
+    pub auto_delete_merged_branches: bool,
+}
+
+fn validate_timeout_secs(secs: u64) -> Result<u64, String> {
+    if !(MIN_TIMEOUT_SECS..=MAX_TIMEOUT_SECS).contains(&secs) {
+        return Err(format!(
+            "Timeout must be between {} and {} seconds, got {}",
+            MIN_TIMEOUT_SECS, MAX_TIMEOUT_SECS, secs
+        ));
+    }
+    Ok(secs)
+}
+
+/// Checks a value for the global `session_timeout_secs` setting. Null or 0
+/// turns the default timeout off.
+pub fn validate_timeout_setting(value: &serde_json::Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(0) => Ok(()),
+        Some(secs) => validate_timeout_secs(secs).map(|_| ()),
+        None if value.is_null() => Ok(()),
+        None => Err("Timeout must be a whole number of seconds".to_string()),
+    }
+}
+
+/// Applies a timeout chosen at spawn, which takes precedence over the
+/// configured default for that session only.
+pub fn apply_session_timeout(
+    config: &mut EffectiveConfig,
+    timeout_secs: Option<u64>,
+) -> Result<(), String> {
+    if let Some(secs) = timeout_secs {
+        config.timeout_secs = Some(validate_timeout_secs(secs)?);
+    }
+    Ok(())
+}
+
+/// A session's `ticket_id` becomes a `[ticket]` prefix, replacing the
+/// configured one. Either way the prefix must fit on the subject line.
+pub fn apply_commit_prefix(
+    config: &mut EffectiveConfig,
+    ticket_id: Option<String>,
+) -> Result<(), String> {
+    if let Some(ticket_id) = ticket_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        config.commit_message_prefix = Some(format!("[{}]", ticket_id));
+    }
+    if let Some(prefix) = &config.commit_message_prefix {
+        if prefix.contains(['\n', '\r']) {
+            return Err("The commit message prefix must be a single line".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Adds a session's `labels` to the configured `pr_labels`, dropping
+/// duplicates. Every label must be non-empty.
+pub fn apply_session_labels(
+    config: &mut EffectiveConfig,
+    labels: Option<Vec<String>>,
+) -> Result<(), String> {
+    let mut merged: Vec<String> = Vec::new();
+    for label in config.pr_labels.iter().chain(labels.iter().flatten()) {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err("PR labels must not be empty".to_string());
+        }
+        if !merged.iter().any(|l| l == label) {
+            merged.push(label.to_string());
+        }
+    }
+    config.pr_labels = merged;
+    Ok(())
+}
+
+/// `path` relative to the repo root with `/` separators and no leading `./`
+/// or trailing `/`. Absolute paths and `..` are refused.
+fn normalize_editable_path(path: &str) -> Result<String, String> {
+    let slashed = path.trim().replace('\\', "/");
+    let mut components = Vec::new();
+    for component in slashed.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return Err(format!("Editable path must stay in the repo: {}", path)),
+            name => components.push(name),
+        }
+    }
+    if slashed.starts_with('/') || slashed.contains(':') {
+        return Err(format!("Editable path must be relative: {}", path));
+    }
+    if components.is_empty() {
+        return Err("Editable paths must not be empty".to_string());
+    }
+    Ok(components.join("/"))
+}
+
+/// Replaces the configured `editable_paths` with a session's own, if given,
+/// and normalizes them.
+pub fn apply_editable_paths(
+    config: &mut EffectiveConfig,
+    editable_paths: Option<Vec<String>>,
+) -> Result<(), String> {
+    let paths = editable_paths.unwrap_or_else(|| config.editable_paths.clone());
+    config.editable_paths = paths
+        .iter()
+        .map(|path| normalize_editable_path(path))
+        .collect::<Result<_, _>>()?;
+    Ok(())
+}
+
+impl Default for EffectiveConfig {
+    fn default() -> Self {
+        resolve_config(&HashMap::new(), &HashMap::new())
+    }
+}
+
+pub fn load_repo_config(repo_path: &Path) -> Result<Settings, String> {
+    let path = repo_path.join(REPO_CONFIG_PATH);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", REPO_CONFIG_PATH, e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", REPO_CONFIG_PATH, e))
+}
+
+/// An entry matching any command, such as `*` or `:*`, which would remove
+/// the allowlist altogether.
+fn is_wildcard_only(command: &str) -> bool {
+    command
+        .chars()
+        .all(|c| c == '*' || c == ':' || c.is_whitespace())
+}
+
+fn validate_allowed_commands(value: serde_json::Value) -> Result<Vec<String>, String> {
+    let invalid = || {
+        format!(
+            "{} must be a JSON array of non-empty strings that are not only wildcards",
+            REPO_ALLOWED_COMMANDS_PATH
+        )
+    };
+    let serde_json::Value::Array(items) = value else {
+        return Err(invalid());
+    };
+    items
+        .into_iter()
+        .map(|item| match item {
+            serde_json::Value::String(command) if !is_wildcard_only(&command) => {
+                Ok(command.trim().to_string())
+            }
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+/// The repo's allowed-commands file, or `None` when it has none.
+pub fn load_repo_allowed_commands(repo_path: &Path) -> Result<Option<Vec<String>>, String> {
+    let path = repo_path.join(REPO_ALLOWED_COMMANDS_PATH);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", REPO_ALLOWED_COMMANDS_PATH, e))?;
+    let value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", REPO_ALLOWED_COMMANDS_PATH, e))?;
+    validate_allowed_commands(value).map(Some)
+}
+
+pub fn save_repo_allowed_commands(repo_path: &Path, commands: Vec<String>) -> Result<(), String> {
+    let commands = validate_allowed_commands(serde_json::json!(commands))?;
+    let path = repo_path.join(REPO_ALLOWED_COMMANDS_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(&commands).map_err(|e| e.to_string())?;
+    fs::write(&path, content + "\n")
+        .map_err(|e| format!("Failed to write {}: {}", REPO_ALLOWED_COMMANDS_PATH, e))
+}
+
+/// `patterns` followed by the repo's `.dreamal/allowed-commands.json`
+/// entries, without duplicates. A missing or invalid file adds nothing.
+fn with_repo_allowed_commands(repo_path: &Path, patterns: &[String]) -> Vec<String> {
+    let repo_commands = load_repo_allowed_commands(repo_path)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let mut merged: Vec<String> = Vec::new();
+    for pattern in patterns.iter().cloned().chain(repo_commands) {
+        if !merged.contains(&pattern) {
+            merged.push(pattern);
+        }
+    }
+    merged
+}
+
+/// Adds the repo's allowed-commands file to `config` once the user has set
+/// `trust_repo_allowed_commands` globally. The file is committed with the
+/// repo, and a cloned repo may otherwise only narrow what Claude can run.
+pub fn apply_repo_allowed_commands(
+    config: &mut EffectiveConfig,
+    global: &Settings,
+    repo_path: &Path,
+) {
+    if bool_or_false(TRUST_REPO_ALLOWED_COMMANDS_KEY, &HashMap::new(), global) {
+        config.allowed_commands = with_repo_allowed_commands(repo_path, &config.allowed_commands);
+    }
+}
+
+fn lookup<'a>(
+    key: &str,
+    repo: &'a Settings,
+    global: &'a Settings,
+) -> Option<&'a serde_json::Value> {
+    repo.get(key).or_else(|| global.get(key))
+}
+
+fn string_or(key: &str, repo: &Settings, global: &Settings, default: &str) -> String {
+    lookup(key, repo, global)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(default)
+        .to_string()
+}
+
+fn list_or(key: &str, repo: &Settings, global: &Settings, default: &[&str]) -> Vec<String> {
+    lookup(key, repo, global)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_else(|| default.iter().map(|s| s.to_string()).collect())
+}
+
+fn optional_string(key: &str, repo: &Settings, global: &Settings) -> Option<String> {
+    lookup(key, repo, global)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn bool_or_false(key: &str, repo: &Settings, global: &Settings) -> bool {
+    lookup(key, repo, global)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// True when either side turns the flag on, so a repo can enable a check but
+/// not disable one the user enabled.
+fn bool_either(key: &str, repo: &Settings, global: &Settings) -> bool {
+    [repo, global]
+        .iter()
+        .any(|settings| settings.get(key).and_then(|v| v.as_bool()) == Some(true))
+}
+
+/// `allowed` narrowed to the entries the repo's list for `key` also has. A
+/// repo can drop entries this way but never add them.
+fn narrow_to_repo(key: &str, allowed: Vec<String>, repo: &Settings) -> Vec<String> {
+    if repo.get(key).and_then(|v| v.as_array()).is_none() {
+        return allowed;
+    }
+    let requested = list_or(key, repo, &HashMap::new(), &[]);
+    allowed
+        .into_iter()
+        .filter(|entry| requested.contains(entry))
+        .collect()
+}
+
+fn narrowed_list(key: &str, repo: &Settings, global: &Settings, default: &[&str]) -> Vec<String> {
+    narrow_to_repo(key, list_or(key, &HashMap::new(), global, default), repo)
+}
+
+/// The smaller of the positive values either side sets for `key`, so a repo
+/// can lower a limit but not raise it.
+fn narrowed_limit(key: &str, repo: &Settings, global: &Settings) -> Option<u64> {
+    [repo, global]
+        .iter()
+        .filter_map(|settings| settings.get(key).and_then(|v| v.as_u64()))
+        .filter(|limit| *limit > 0)
+        .min()
+}
+
+/// The repo's `editable_paths` that fall inside the global ones, or the global
+/// ones if none do. Without global paths the whole checkout is editable, so
+/// the repo's apply as given.
+fn narrowed_editable_paths(repo: &Settings, global: &Settings) -> Vec<String> {
+    let allowed: Vec<String> = list_or("editable_paths", &HashMap::new(), global, &[]);
+    let requested = list_or("editable_paths", repo, &HashMap::new(), &[]);
+    if allowed.is_empty() {
+        return requested;
+    }
+    let allowed_normalized: Vec<String> = allowed
+        .iter()
+        .filter_map(|path| normalize_editable_path(path).ok())
+        .collect();
+    let within: Vec<String> = requested
+        .into_iter()
+        .filter(|path| {
+            normalize_editable_path(path).is_ok_and(|path| {
+                allowed_normalized
+                    .iter()
+                    .any(|root| path == *root || path.starts_with(&format!("{}/", root)))
+            })
+        })
+        .collect();
+    if within.is_empty() {
+        allowed
+    } else {
+        within
+    }
+}
+
+/// The repo's `guidelines_file` when it is a plain relative path, otherwise
+/// the global one, so repo config can't point Claude at files outside the repo.
+fn guidelines_file(repo: &Settings, global: &Settings) -> Option<String> {
+    let requested = optional_string("guidelines_file", repo, &HashMap::new());
+    if let Some(file) = &requested {
+        let inside_repo = Path::new(file)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if inside_repo {
+            return requested;
+        }
+        eprintln!(
+            "Warning: Ignoring repo guidelines_file {}; it must be a path inside the repo",
+            file
+        );
+    }
+    optional_string("guidelines_file", &HashMap::new(), global)
+}
+
+/// `repo` without the keys only global settings may set.
+fn repo_overridable(repo: &Settings) -> Settings {
+    repo.iter()
+        .filter(|(key, _)| !GLOBAL_ONLY_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+pub fn resolve_config(global: &Settings, repo: &Settings) -> EffectiveConfig {
+    let repo = &repo_overridable(repo);
+    // Without a global limit, the default is the one a repo may only lower.
+    let max_instructions_len = narrowed_limit("max_instructions_len", &HashMap::new(), global)
+        .unwrap_or(DEFAULT_MAX_INSTRUCTIONS_LEN as u64);
+    EffectiveConfig {
+        base_branch: string_or("base_branch", repo, global, "main"),
+        branch_prefix: string_or("branch_prefix", repo, global, DEFAULT_BRANCH_PREFIX),
+        allowed_commands: narrowed_list("allowed_commands", repo, global, ALLOWED_BASH_PATTERNS),
+        allowed_tools: narrowed_list("allowed_tools", repo, global, DEFAULT_ALLOWED_TOOLS),
+        // Clones share one pool across repos, so only the global setting applies.
+        max_concurrent_clones: global
+            .get("max_concurrent_clones")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_CLONES),
+        max_concurrent_sessions: global
+            .get("max_concurrent_sessions")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_SESSIONS),
+        clone_submodules: bool_or_false("clone_submodules", repo, global),
+        keep_failed_checkouts: bool_or_false("keep_failed_checkouts", repo, global),
+        strict_instructions: bool_either("strict_instructions", repo, global),
+        bot_author_name: optional_string("bot_author_name", repo, global),
+        bot_author_email: optional_string("bot_author_email", repo, global),
+        request_codeowner_reviewers: bool_or_false("request_codeowner_reviewers", repo, global),
+        baseline_build_command: if bool_or_false("baseline_build", repo, global) {
+            optional_string("baseline_build_command", repo, global)
+        } else {
+            None
+        },
+        timeout_secs: narrowed_limit("session_timeout_secs", repo, global),
+        guidelines_file: guidelines_file(repo, global),
+        commit_message_prefix: optional_string("commit_message_prefix", repo, global),
+        prefix_pr_title: bool_or_false("prefix_pr_title", repo, global),
+        clone_depth: lookup("clone_depth", repo, global)
+            .and_then(|v| v.as_u64())
+            .filter(|depth| *depth > 0)
+            .and_then(|depth| u32::try_from(depth).ok()),
+        max_instructions_len: narrowed_limit("max_instructions_len", repo, global)
+            .map_or(max_instructions_len, |len| len.min(max_instructions_len))
+            as usize,
+        pr_labels: list_or("pr_labels", repo, global, &[]),
+        max_memory_mb: narrowed_limit("max_memory_mb", repo, global),
+        editable_paths: narrowed_editable_paths(repo, global),
+        max_changed_files: narrowed_limit("max_changed_files", repo, global)
+            .map(|max| max as usize),
+        // Spawns share one API quota across repos, so only the global setting applies.
+        min_spawn_interval_ms: global
+            .get("min_spawn_interval_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        model: optional_string("model", repo, global),
+        auto_delete_merged_branches: bool_or_false("auto_delete_merged_branches", repo, global),
+    }
+}
+
+/// Fills defaults that depend on the repo's detected project types: test
+/// commands join the default allowlist unless `allowed_commands` is set
+/// globally, and an enabled baseline build with no explicit command uses the
+/// first detected build command.
+pub fn apply_project_defaults(
+    config: &mut EffectiveConfig,
+    global: &Settings,
+    repo: &Settings,
+    detections: &[ProjectDetection],
+) {
+    if global.get("allowed_commands").is_none() {
+        let mut allowed = list_or(
+            "allowed_commands",
+            &HashMap::new(),
+            global,
+            ALLOWED_BASH_PATTERNS,
+        );
+        for detection in detections {
+            if !allowed.contains(&detection.test_command) {
+                allowed.push(detection.test_command.clone());
+            }
+        }
+        config.allowed_commands = narrow_to_repo("allowed_commands", allowed, repo);
+    }
+
+    let baseline_build = bool_or_false("baseline_build", &HashMap::new(), global);
+    if baseline_build && config.baseline_build_command.is_none() {
+        config.baseline_build_command = detections.iter().find_map(|d| d.build_command.clone());
+    }
+}
+
+fn repo_key(repo_path: &Path) -> String {
+    repo_path
+        .canonicalize()
+        .unwrap_or_else(|_| repo_path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn remembered_base_branch(global: &Settings, repo_path: &Path) -> Option<String> {
+    global
+        .get(REPO_DEFAULT_BASES_KEY)?
+        .get(repo_key(repo_path))?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Records `branch` as the default base for `repo_path`; an empty branch
+/// forgets it.
+pub fn remember_base_branch(global: &mut Settings, repo_path: &Path, branch: &str) {
+    let bases = global
+        .entry(REPO_DEFAULT_BASES_KEY.to_string())
+        .or_insert_with(|| serde_json::json!({}));
+    if !bases.is_object() {
+        *bases = serde_json::json!({});
+    }
+    let Some(bases) = bases.as_object_mut() else {
+        return;
+    };
+
+    let branch = branch.trim();
+    if branch.is_empty() {
+        bases.remove(&repo_key(repo_path));
+    } else {
+        bases.insert(repo_key(repo_path), serde_json::json!(branch));
+    }
+}
+
+/// An explicit base branch wins, then the one remembered for the repo, then the
+/// configured default.
+pub fn apply_base_branch(
+    config: &mut EffectiveConfig,
+    global: &Settings,
+    repo_path: &Path,
+    explicit: Option<String>,
+) {
+    if let Some(branch) = explicit.or_else(|| remembered_base_branch(global, repo_path)) {
+        config.base_branch = branch;
+    }
+}
+
+pub fn effective_config(repo_path: &Path) -> Result<EffectiveConfig, String> {
+    let global = settings::read_settings()?;
+    let repo = load_repo_config(repo_path)?;
+    let mut config = resolve_config(&global, &repo);
+    apply_base_branch(&mut config, &global, repo_path, None);
+    apply_project_defaults(
+        &mut config,
+        &global,
+        &repo,
+        &detect_project_types(repo_path),
+    );
+    apply_repo_allowed_commands(&mut config, &global, repo_path);
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_config_defaults() {
+        let config = resolve_config(&HashMap::new(), &HashMap::new());
+
+        assert_eq!(config.base_branch, "main");
+        assert_eq!(config.branch_prefix, DEFAULT_BRANCH_PREFIX);
+        assert_eq!(config.allowed_commands.len(), ALLOWED_BASH_PATTERNS.len());
+        assert_eq!(config.allowed_tools, vec!["Edit", "Write", "Read"]);
+        assert_eq!(config.max_concurrent_clones, DEFAULT_MAX_CONCURRENT_CLONES);
+        assert_eq!(
+            config.max_concurrent_sessions,
+            DEFAULT_MAX_CONCURRENT_SESSIONS
+        );
+        assert!(!config.clone_submodules);
+        assert!(config.bot_author_name.is_none());
+        assert!(config.clone_depth.is_none());
+    }
+
+    #[test]
+    fn test_repo_config_overrides_global() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".dreamal")).unwrap();
+        fs::write(
+            temp_dir.path().join(REPO_CONFIG_PATH),
+            r#"{"base_branch": "develop", "allowed_commands": ["cargo test"], "max_concurrent_clones": 9, "min_spawn_interval_ms": 9}"#,
+        )
+        .unwrap();
+
+        let mut global = HashMap::new();
+        global.insert("base_branch".to_string(), serde_json::json!("trunk"));
+        global.insert("branch_prefix".to_string(), serde_json::json!("bot/"));
+        global.insert("max_concurrent_clones".to_string(), serde_json::json!(4));
+        global.insert("max_concurrent_sessions".to_string(), serde_json::json!(5));
+        global.insert("min_spawn_interval_ms".to_string(), serde_json::json!(500));
+
+        let repo = load_repo_config(temp_dir.path()).unwrap();
+        let config = resolve_config(&global, &repo);
+
+        assert_eq!(config.base_branch, "develop");
+        assert_eq!(config.branch_prefix, "bot/");
+        assert_eq!(config.allowed_commands, vec!["cargo test"]);
+        assert_eq!(config.max_concurrent_clones, 4);
+        assert_eq!(config.max_concurrent_sessions, 5);
+        assert_eq!(config.min_spawn_interval_ms, 500);
+    }
+
+    #[test]
+    fn test_deserialize_config_missing_fields() {
+        let config: EffectiveConfig =
+            serde_json::from_str(r#"{"base_branch": "develop", "clone_submodules": true}"#)
+                .unwrap();
+
+        assert_eq!(config.base_branch, "develop");
+        assert!(config.clone_submodules);
+        assert!(!config.request_codeowner_reviewers);
+        assert_eq!(config.branch_prefix, DEFAULT_BRANCH_PREFIX);
+    }
+
+    #[test]
+    fn test_project_defaults_seed_allowed_commands() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("go.mod"), "module example").unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]").unwrap();
+        let detections = detect_project_types(temp_dir.path());
+
+        let empty = HashMap::new();
+        let mut config = resolve_config(&empty, &empty);
+        apply_project_defaults(&mut config, &empty, &empty, &detections);
+
+        assert!(config
+            .allowed_commands
+            .contains(&"go test ./...".to_string()));
+        let cargo_entries = config
+            .allowed_commands
+            .iter()
+            .filter(|c| *c == "cargo test")
+            .count();
+        assert_eq!(cargo_entries, 1);
+
+        let mut repo = HashMap::new();
+        repo.insert(
+            "allowed_commands".to_string(),
+            serde_json::json!(["go test ./...", "make"]),
+        );
+        let mut config = resolve_config(&empty, &repo);
+        apply_project_defaults(&mut config, &empty, &repo, &detections);
+        assert_eq!(config.allowed_commands, vec!["go test ./..."]);
+    }
+
+    #[test]
+    fn test_repo_config_cannot_loosen_safety_settings() {
+        let mut global = HashMap::new();
+        global.insert(
+            "allowed_commands".to_string(),
+            serde_json::json!(["cargo test", "npm test"]),
+        );
+        global.insert("allowed_tools".to_string(), serde_json::json!(["Read"]));
+        global.insert("strict_instructions".to_string(), serde_json::json!(true));
+
+        let mut repo = HashMap::new();
+        repo.insert(
+            "allowed_commands".to_string(),
+            serde_json::json!(["cargo test", "curl", "rm -rf"]),
+        );
+        repo.insert(
+            "allowed_tools".to_string(),
+            serde_json::json!(["Read", "Write", "Bash"]),
+        );
+        repo.insert("strict_instructions".to_string(), serde_json::json!(false));
+        repo.insert("keep_failed_checkouts".to_string(), serde_json::json!(true));
+        repo.insert(
+            "auto_delete_merged_branches".to_string(),
+            serde_json::json!(true),
+        );
+        // The sweep deletes every merged branch under the prefix.
+        repo.insert("branch_prefix".to_string(), serde_json::json!("release/"));
+
+        let config = resolve_config(&global, &repo);
+        assert_eq!(config.allowed_commands, vec!["cargo test"]);
+        assert_eq!(config.allowed_tools, vec!["Read"]);
+        assert!(config.strict_instructions);
+        assert!(!config.keep_failed_checkouts);
+        assert!(!config.auto_delete_merged_branches);
+        assert_eq!(config.branch_prefix, DEFAULT_BRANCH_PREFIX);
+
+        // Without global lists, the defaults bound what a repo may ask for.
+        let config = resolve_config(&HashMap::new(), &repo);
+        assert_eq!(config.allowed_commands, vec!["cargo test"]);
+        assert_eq!(config.allowed_tools, vec!["Write", "Read"]);
+
+        let mut narrowing = HashMap::new();
+        narrowing.insert("strict_instructions".to_string(), serde_json::json!(true));
+        assert!(resolve_config(&HashMap::new(), &narrowing).strict_instructions);
+    }
+
+    #[test]
+    fn test_repo_config_can_only_lower_limits() {
+        let mut global = HashMap::new();
+        global.insert("max_memory_mb".to_string(), serde_json::json!(2048));
+        global.insert("session_timeout_secs".to_string(), serde_json::json!(600));
+        global.insert("editable_paths".to_string(), serde_json::json!(["src"]));
+        global.insert("model".to_string(), serde_json::json!("sonnet"));
+        global.insert("max_changed_files".to_string(), serde_json::json!(20));
+
+        let mut repo = HashMap::new();
+        repo.insert("max_memory_mb".to_string(), serde_json::json!(8192));
+        repo.insert("max_changed_files".to_string(), serde_json::json!(500));
+        repo.insert("session_timeout_secs".to_string(), serde_json::json!(0));
+        repo.insert(
+            "max_instructions_len".to_string(),
+            serde_json::json!(DEFAULT_MAX_INSTRUCTIONS_LEN * 10),
+        );
+        repo.insert(
+            "editable_paths".to_string(),
+            serde_json::json!(["src/ui", "../outside", "docs"]),
+        );
+        repo.insert("model".to_string(), serde_json::json!("opus"));
+
+        let config = resolve_config(&global, &repo);
+        assert_eq!(config.max_memory_mb, Some(2048));
+        assert_eq!(config.timeout_secs, Some(600));
+        assert_eq!(config.max_instructions_len, DEFAULT_MAX_INSTRUCTIONS_LEN);
+        assert_eq!(config.editable_paths, vec!["src/ui"]);
+        assert_eq!(config.model.as_deref(), Some("sonnet"));
+        assert_eq!(config.max_changed_files, Some(20));
+
+        repo.insert("max_memory_mb".to_string(), serde_json::json!(512));
+        repo.insert("max_changed_files".to_string(), serde_json::json!(5));
+        repo.insert("session_timeout_secs".to_string(), serde_json::json!(120));
+        repo.insert("max_instructions_len".to_string(), serde_json::json!(100));
+        repo.insert("editable_paths".to_string(), serde_json::json!(["docs"]));
+        let config = resolve_config(&global, &repo);
+        assert_eq!(config.max_memory_mb, Some(512));
+        assert_eq!(config.timeout_secs, Some(120));
+        assert_eq!(config.max_instructions_len, 100);
+        assert_eq!(config.max_changed_files, Some(5));
+        assert_eq!(config.editable_paths, vec!["src"]);
+
+        // With no global paths the whole checkout was editable already.
+        assert_eq!(
+            resolve_config(&HashMap::new(), &repo).editable_paths,
+            vec!["docs"]
+        );
+    }
+
+    #[test]
+    fn test_repo_guidelines_file_stays_inside_repo() {
+        let mut global = HashMap::new();
+        global.insert(
+            "guidelines_file".to_string(),
+            serde_json::json!("/home/me/guidelines.md"),
+        );
+        let mut repo = HashMap::new();
+        for escaping in ["/etc/passwd", "../other/guidelines.md", "docs/../../x.md"] {
+            repo.insert("guidelines_file".to_string(), serde_json::json!(escaping));
+            assert_eq!(
+                resolve_config(&global, &repo).guidelines_file.as_deref(),
+                Some("/home/me/guidelines.md")
+            );
+        }
+
+        repo.insert(
+            "guidelines_file".to_string(),
+            serde_json::json!("docs/bot.md"),
+        );
+        assert_eq!(
+            resolve_config(&global, &repo).guidelines_file.as_deref(),
+            Some("docs/bot.md")
+        );
+    }
+
+    #[test]
+    fn test_project_defaults_baseline_build_command() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]").unwrap();
+        let detections = detect_project_types(temp_dir.path());
+        let empty = HashMap::new();
+
+        let mut config = resolve_config(&empty, &empty);
+        apply_project_defaults(&mut config, &empty, &empty, &detections);
+        assert!(config.baseline_build_command.is_none());
+
+        let mut repo = HashMap::new();
+        repo.insert("baseline_build".to_string(), serde_json::json!(true));
+        repo.insert(
+            "baseline_build_command".to_string(),
+            serde_json::json!("sh -c ./steal-credentials"),
+        );
+        let mut config = resolve_config(&empty, &repo);
+        apply_project_defaults(&mut config, &empty, &repo, &detections);
+        assert!(config.baseline_build_command.is_none());
+
+        let mut global = HashMap::new();
+        global.insert("baseline_build".to_string(), serde_json::json!(true));
+        let mut config = resolve_config(&global, &repo);
+        apply_project_defaults(&mut config, &global, &repo, &detections);
+        assert_eq!(
+            config.baseline_build_command.as_deref(),
+            Some("cargo build")
+        );
+
+        global.insert(
+            "baseline_build_command".to_string(),
+            serde_json::json!("make"),
+        );
+        let mut config = resolve_config(&global, &repo);
+        apply_project_defaults(&mut config, &global, &repo, &detections);
+        assert_eq!(config.baseline_build_command.as_deref(), Some("make"));
+    }
+
+    #[test]
+    fn test_timeout_secs() {
+        let mut global = HashMap::new();
+        assert!(resolve_config(&global, &HashMap::new())
+            .timeout_secs
+            .is_none());
+
+        global.insert("session_timeout_secs".to_string(), serde_json::json!(3600));
+        assert_eq!(
+            resolve_config(&global, &HashMap::new()).timeout_secs,
+            Some(3600)
+        );
+
+        let mut config = resolve_config(&global, &HashMap::new());
+        apply_session_timeout(&mut config, None).unwrap();
+        assert_eq!(config.timeout_secs, Some(3600));
+        apply_session_timeout(&mut config, Some(MIN_TIMEOUT_SECS)).unwrap();
+        assert_eq!(config.timeout_secs, Some(MIN_TIMEOUT_SECS));
+
+        assert!(apply_session_timeout(&mut config, Some(0)).is_err());
+        assert!(validate_timeout_setting(&serde_json::json!(3600)).is_ok());
+        assert!(validate_timeout_setting(&serde_json::json!(0)).is_ok());
+        assert!(validate_timeout_setting(&serde_json::Value::Null).is_ok());
+        assert!(validate_timeout_setting(&serde_json::json!(5)).is_err());
+        assert!(validate_timeout_setting(&serde_json::json!("60")).is_err());
+        assert!(apply_session_timeout(&mut config, Some(MAX_TIMEOUT_SECS + 1)).is_err());
+        assert_eq!(config.timeout_secs, Some(MIN_TIMEOUT_SECS));
+    }
+
+    #[test]
+    fn test_load_repo_config_missing_and_invalid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(load_repo_config(temp_dir.path()).unwrap().is_empty());
+
+        fs::create_dir_all(temp_dir.path().join(".dreamal")).unwrap();
+        fs::write(temp_dir.path().join(REPO_CONFIG_PATH), "not json").unwrap();
+        assert!(load_repo_config(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_repo_allowed_commands_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        assert_eq!(load_repo_allowed_commands(repo_path).unwrap(), None);
+
+        save_repo_allowed_commands(
+            repo_path,
+            vec!["make check".to_string(), " cargo test ".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            load_repo_allowed_commands(repo_path).unwrap(),
+            Some(vec!["make check".to_string(), "cargo test".to_string()])
+        );
+
+        let defaults = resolve_config(&HashMap::new(), &HashMap::new()).allowed_commands;
+        let merged = with_repo_allowed_commands(repo_path, &defaults);
+        assert_eq!(merged.len(), defaults.len() + 1);
+        assert_eq!(merged.last().map(String::as_str), Some("make check"));
+        assert_eq!(merged.iter().filter(|c| *c == "cargo test").count(), 1);
+
+        for wildcard in ["  ", "*", " :* ", "* *"] {
+            assert!(save_repo_allowed_commands(repo_path, vec![wildcard.to_string()]).is_err());
+        }
+        for invalid in [r#"{"commands": []}"#, r#"["ok", 3]"#, "not json"] {
+            fs::write(repo_path.join(REPO_ALLOWED_COMMANDS_PATH), invalid).unwrap();
+            assert!(
+                load_repo_allowed_commands(repo_path).is_err(),
+                "{}",
+                invalid
+            );
+            assert_eq!(with_repo_allowed_commands(repo_path, &defaults), defaults);
+        }
+    }
+
+    #[test]
+    fn test_repo_allowed_commands_need_global_trust() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        save_repo_allowed_commands(repo_path, vec!["curl".to_string()]).unwrap();
+
+        let mut global = HashMap::new();
+        let mut config = resolve_config(&global, &HashMap::new());
+        let defaults = config.allowed_commands.clone();
+        apply_repo_allowed_commands(&mut config, &global, repo_path);
+        assert_eq!(config.allowed_commands, defaults);
+
+        global.insert(
+            TRUST_REPO_ALLOWED_COMMANDS_KEY.to_string(),
+            serde_json::json!(true),
+        );
+        apply_repo_allowed_commands(&mut config, &global, repo_path);
+        assert_eq!(
+            config.allowed_commands.last().map(String::as_str),
+            Some("curl")
+        );
+    }
+
+    #[test]
+    fn test_remembered_base_branch() {
+        let repo_dir = tempfile::tempdir().unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let mut global = HashMap::new();
+        global.insert("base_branch".to_string(), serde_json::json!("trunk"));
+
+        // Stored under the canonical path, so an equivalent spelling finds it.
+        remember_base_branch(&mut global, &repo_dir.path().join("."), "develop");
+        assert_eq!(
+            remembered_base_branch(&global, repo_dir.path()).as_deref(),
+            Some("develop")
+        );
+
+        let mut config = resolve_config(&global, &HashMap::new());
+        apply_base_branch(&mut config, &global, repo_dir.path(), None);
+        assert_eq!(config.base_branch, "develop");
+
+        apply_base_branch(
+            &mut config,
+            &global,
+            repo_dir.path(),
+            Some("hotfix".to_string()),
+        );
+        assert_eq!(config.base_branch, "hotfix");
+
+        let mut config = resolve_config(&global, &HashMap::new());
+        apply_base_branch(&mut config, &global, other_dir.path(), None);
+        assert_eq!(config.base_branch, "trunk");
+
+        remember_base_branch(&mut global, repo_dir.path(), "");
+        assert!(remembered_base_branch(&global, repo_dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_commit_prefix() {
+        let mut global = HashMap::new();
+        global.insert(
+            "commit_message_prefix".to_string(),
+            serde_json::json!(" [OPS-7] "),
+        );
+
+        let mut config = resolve_config(&global, &HashMap::new());
+        apply_commit_prefix(&mut config, None).unwrap();
+        assert_eq!(config.commit_message_prefix.as_deref(), Some("[OPS-7]"));
+        assert!(!config.prefix_pr_title);
+
+        apply_commit_prefix(&mut config, Some("JIRA-123".to_string())).unwrap();
+        assert_eq!(config.commit_message_prefix.as_deref(), Some("[JIRA-123]"));
+
+        assert!(apply_commit_prefix(&mut config, Some("JIRA-1\nextra".to_string())).is_err());
+
+        global.insert(
+            "commit_message_prefix".to_string(),
+            serde_json::json!("fix:\nbody"),
+        );
+        let mut config = resolve_config(&global, &HashMap::new());
+        assert!(apply_commit_prefix(&mut config, None).is_err());
+    }
+
+    #[test]
+    fn test_session_editable_paths_replace_setting() {
+        let mut global = HashMap::new();
+        global.insert("editable_paths".to_string(), serde_json::json!(["./src/"]));
+        let mut config = resolve_config(&global, &HashMap::new());
+
+        apply_editable_paths(&mut config, None).unwrap();
+        assert_eq!(config.editable_paths, vec!["src"]);
+
+        apply_editable_paths(
+            &mut config,
+            Some(vec!["docs\\guide".to_string(), "README.md".to_string()]),
+        )
+        .unwrap();
+        assert_eq!(config.editable_paths, vec!["docs/guide", "README.md"]);
+
+        for bad in ["../outside", "/etc", "C:/repo", " "] {
+            assert!(
+                apply_editable_paths(&mut config, Some(vec![bad.to_string()])).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_session_labels_merge_with_setting() {
+        let mut global = HashMap::new();
+        global.insert(
+            "pr_labels".to_string(),
+            serde_json::json!(["automated", "needs-review"]),
+        );
+        let mut config = resolve_config(&global, &HashMap::new());
+
+        apply_session_labels(
+            &mut config,
+            Some(vec![" frontend ".to_string(), "automated".to_string()]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.pr_labels,
+            vec!["automated", "needs-review", "frontend"]
+        );
+
+        assert!(apply_session_labels(&mut config, Some(vec!["  ".to_string()])).is_err());
+        assert!(resolve_config(&HashMap::new(), &HashMap::new())
+            .pr_labels
+            .is_empty());
+    }
+}
@@ -1,14 +1,28 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
 
-use super::types::{Session, SessionInfo, SessionStatus};
+use crate::git_ops::{self, conflicts::ConflictCheck, GitOpsError};
+
+use super::config::EffectiveConfig;
+use super::orchestrator::SessionConfig;
+use super::persistence;
+use super::process::OutputBuffer;
+use super::scheduler::Scheduler;
+use super::semaphore::Semaphore;
+use super::types::{
+    now_ms, DiffStat, OutputMarkerKind, Session, SessionInfo, SessionPhase, SessionStatus,
+};
+
+pub const DEFAULT_MAX_CONCURRENT_CLONES: usize = 2;
+pub const DEFAULT_MAX_CONCURRENT_SESSIONS: usize = 3;
 
 #[derive(Debug)]
 pub enum SessionError {
     NotFound(String),
     AlreadyExists(String),
     LockError,
+    Locked(String),
 }
 
 impl std::fmt::Display for SessionError {
@@ -17,13 +31,243 @@ impl std::fmt::Display for SessionError {
             SessionError::NotFound(id) => write!(f, "Session not found: {}", id),
             SessionError::AlreadyExists(id) => write!(f, "Session already exists: {}", id),
             SessionError::LockError => write!(f, "Failed to acquire session lock"),
+            SessionError::Locked(id) => write!(
+                f,
+                "Session {} is locked; unlock it or force the cancel",
+                id
+            ),
+        }
+    }
+}
+
+fn is_active(session: &Session) -> bool {
+    matches!(
+        session.info.status,
+        SessionStatus::Initializing | SessionStatus::Working
+    )
+}
+
+fn active_count(sessions: &HashMap<String, Session>) -> usize {
+    sessions.values().filter(|s| is_active(s)).count()
+}
+
+/// Queued sessions leave the queue in this order; ones not yet queued go last.
+fn queue_key(info: &SessionInfo) -> (u64, u64) {
+    (info.queued_at_ms.unwrap_or(u64::MAX), info.created_at)
+}
+
+/// Each queued session's place in the queue, from 1.
+fn queue_positions(sessions: &HashMap<String, Session>) -> HashMap<&str, usize> {
+    let mut queued: Vec<&SessionInfo> = sessions
+        .values()
+        .map(|s| &s.info)
+        .filter(|info| info.status == SessionStatus::Queued)
+        .collect();
+    queued.sort_by_key(|info| queue_key(info));
+    queued
+        .iter()
+        .enumerate()
+        .map(|(i, info)| (info.id.as_str(), i + 1))
+        .collect()
+}
+
+/// A queue time after every session already queued, so sessions queued in
+/// the same millisecond still leave in the order they joined.
+fn next_queued_at_ms(sessions: &HashMap<String, Session>) -> u64 {
+    let last = sessions
+        .values()
+        .filter(|s| s.info.status == SessionStatus::Queued)
+        .filter_map(|s| s.info.queued_at_ms)
+        .max();
+    last.map_or(now_ms(), |last| now_ms().max(last + 1))
+}
+
+pub fn normalize_repo_path(path: &str) -> PathBuf {
+    let path = Path::new(path.trim());
+
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
         }
     }
+    normalized
+}
+
+pub fn filter_sessions_for_repo(sessions: Vec<SessionInfo>, git_directory: &str) -> Vec<SessionInfo> {
+    let target = normalize_repo_path(git_directory);
+
+    let mut matching: Vec<SessionInfo> = sessions
+        .into_iter()
+        .filter(|s| normalize_repo_path(&s.git_directory) == target)
+        .collect();
+
+    matching.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+    matching
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecentPr {
+    pub session_id: String,
+    pub pr_url: String,
+    pub git_directory: String,
+    pub instructions: String,
+    pub completed_at_ms: u64,
+    pub state: Option<String>,
+}
+
+pub fn recent_prs(sessions: Vec<SessionInfo>, limit: usize) -> Vec<RecentPr> {
+    let mut prs: Vec<RecentPr> = sessions
+        .into_iter()
+        .filter(|s| s.status == SessionStatus::Completed)
+        .filter_map(|s| {
+            let completed_at_ms = s.finished_at_ms();
+            s.pr_url.map(|pr_url| RecentPr {
+                session_id: s.id,
+                pr_url,
+                git_directory: s.git_directory,
+                instructions: s.instructions,
+                completed_at_ms,
+                state: None,
+            })
+        })
+        .collect();
+
+    prs.sort_by_key(|pr| std::cmp::Reverse(pr.completed_at_ms));
+    prs.truncate(limit);
+    prs
+}
+
+/// Characters of context kept on either side of a search match.
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionMatch {
+    pub session: SessionInfo,
+    pub snippet: String,
+}
+
+/// Byte range in `text` of the first case-insensitive match. Lowercasing can
+/// change a character's byte length (or turn it into several characters), so
+/// the search runs over lowercased characters that each remember the byte
+/// range of the character they came from.
+fn find_case_insensitive(text: &str, query: &str) -> Option<(usize, usize)> {
+    let lowered: Vec<(char, usize, usize)> = text
+        .char_indices()
+        .flat_map(|(i, c)| c.to_lowercase().map(move |l| (l, i, i + c.len_utf8())))
+        .collect();
+    let needle: Vec<char> = query.to_lowercase().chars().collect();
+    if needle.is_empty() {
+        return Some((0, 0));
+    }
+
+    let found = lowered
+        .windows(needle.len())
+        .find(|window| window.iter().map(|(c, _, _)| *c).eq(needle.iter().copied()))?;
+    Some((found[0].1, found[needle.len() - 1].2))
 }
 
+fn match_snippet(text: &str, start: usize, end: usize) -> String {
+    let before: String = text[..start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[end..].chars().take(SNIPPET_CONTEXT).collect();
+
+    let mut snippet = format!("{}{}{}", before, &text[start..end], after);
+    if before.len() < start {
+        snippet.insert(0, '…');
+    }
+    if end + after.len() < text.len() {
+        snippet.push('…');
+    }
+
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn search_sessions(sessions: Vec<SessionInfo>, query: &str) -> Vec<SessionMatch> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<SessionMatch> = sessions
+        .into_iter()
+        .filter_map(|session| {
+            let (start, end) = find_case_insensitive(&session.instructions, query)?;
+            let snippet = match_snippet(&session.instructions, start, end);
+            Some(SessionMatch { session, snippet })
+        })
+        .collect();
+
+    matches.sort_by_key(|m| std::cmp::Reverse(m.session.created_at));
+    matches
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct QueueStatus {
+    pub paused: bool,
+    pub queued: usize,
+    pub running: usize,
+}
+
+/// How many sessions are starting or working, against the limit.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Capacity {
+    pub active: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MergedSession {
+    pub kept: String,
+    pub removed: String,
+}
+
+const INTERRUPTED_MESSAGE: &str = "Dreamal exited while this session was running";
+const LOST_LAUNCH_MESSAGE: &str = "This session was queued, but its launch settings were lost";
+
+/// Emitted with the session's `SessionInfo` whenever its status changes
+/// after it starts.
+pub const SESSION_STATUS_EVENT: &str = "session-status-changed";
+
+/// Receives events for the frontend, e.g. a Tauri app handle's `emit`.
+pub type EventSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
 #[derive(Clone)]
 pub struct SessionManager {
     sessions: Arc<Mutex<HashMap<String, Session>>>,
+    clone_slots: Arc<Semaphore>,
+    max_concurrent: Arc<Mutex<usize>>,
+    /// Signalled whenever a session changes status, so queued ones can check
+    /// whether it's their turn.
+    slot_freed: Arc<Condvar>,
+    claimed_checkouts: Arc<Mutex<HashSet<String>>>,
+    /// Repos with a merged-branch sweep in flight, so launches don't start
+    /// another.
+    sweeping_repos: Arc<Mutex<HashSet<PathBuf>>>,
+    scheduler: Arc<Scheduler>,
+    event_sink: Arc<Mutex<Option<EventSink>>>,
+    /// Where sessions are persisted so they survive a restart. Unset, they
+    /// live only in memory.
+    queue_dir: Arc<Mutex<Option<PathBuf>>>,
+    /// Where sessions are checked out. Unset, they go in `~/.dreamal`'s
+    /// temp-checkouts dir.
+    checkouts_dir: Arc<Mutex<Option<PathBuf>>>,
+    /// Held while a session file is written, so writes can't land out of order.
+    persisting: Arc<Mutex<()>>,
 }
 
 impl Default for SessionManager {
@@ -36,9 +280,337 @@ impl SessionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            clone_slots: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_CLONES)),
+            max_concurrent: Arc::new(Mutex::new(DEFAULT_MAX_CONCURRENT_SESSIONS)),
+            slot_freed: Arc::new(Condvar::new()),
+            claimed_checkouts: Arc::new(Mutex::new(HashSet::new())),
+            sweeping_repos: Arc::new(Mutex::new(HashSet::new())),
+            scheduler: Arc::new(Scheduler::new()),
+            event_sink: Arc::new(Mutex::new(None)),
+            queue_dir: Arc::new(Mutex::new(None)),
+            checkouts_dir: Arc::new(Mutex::new(None)),
+            persisting: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn set_event_sink(&self, sink: EventSink) {
+        if let Ok(mut event_sink) = self.event_sink.lock() {
+            *event_sink = Some(sink);
         }
     }
 
+    /// Also restores a scheduler pause saved in `dir` by an earlier run.
+    pub fn set_queue_dir(&self, dir: PathBuf) {
+        if persistence::load_scheduler_paused_in(&dir) {
+            self.scheduler.pause();
+        }
+        if let Ok(mut queue_dir) = self.queue_dir.lock() {
+            *queue_dir = Some(dir);
+        }
+    }
+
+    pub fn queue_dir(&self) -> Option<PathBuf> {
+        self.queue_dir.lock().ok().and_then(|dir| dir.clone())
+    }
+
+    /// Lets tests keep checkouts out of `~/.dreamal`.
+    #[cfg(test)]
+    pub fn set_checkouts_dir(&self, dir: PathBuf) {
+        if let Ok(mut checkouts_dir) = self.checkouts_dir.lock() {
+            *checkouts_dir = Some(dir);
+        }
+    }
+
+    /// Where a new session `id` is checked out.
+    pub fn session_dir(&self, id: &str) -> Result<PathBuf, GitOpsError> {
+        match self.checkouts_dir.lock().ok().and_then(|dir| dir.clone()) {
+            Some(dir) => Ok(git_ops::session_dir_in(&dir, id)),
+            None => git_ops::get_session_dir(id),
+        }
+    }
+
+    /// Sends `payload` to the event sink, if one is set; otherwise does nothing.
+    pub fn emit(&self, event: &str, payload: serde_json::Value) {
+        let sink = self.event_sink.lock().ok().and_then(|sink| sink.clone());
+        if let Some(sink) = sink {
+            sink(event, payload);
+        }
+    }
+
+    /// Announces a status change and saves the session to the queue dir, if
+    /// one is set.
+    ///
+    /// Must be called without the sessions lock held, as the sink may call
+    /// back into the manager.
+    fn status_changed(&self, info: SessionInfo) {
+        self.slot_freed.notify_all();
+        self.persist(&info.id);
+        let info = SessionInfo {
+            queue_position: self.queue_position(&info.id),
+            ..info
+        };
+        match serde_json::to_value(info) {
+            Ok(payload) => self.emit(SESSION_STATUS_EVENT, payload),
+            Err(e) => eprintln!("Warning: Failed to serialize session status: {}", e),
+        }
+    }
+
+    /// Saves the session's current state to the queue dir, if one is set.
+    /// The state is read after taking the write lock, so when two changes
+    /// race, the one written last is the newer one.
+    ///
+    /// Must be called without the sessions lock held.
+    pub fn persist(&self, id: &str) {
+        let Some(dir) = self.queue_dir() else {
+            return;
+        };
+        let _writing = self.persisting.lock().unwrap_or_else(|e| e.into_inner());
+        let info = match self.sessions.lock() {
+            Ok(sessions) => match sessions.get(id) {
+                Some(session) => session.info.clone(),
+                None => return,
+            },
+            Err(_) => return,
+        };
+        if let Err(e) = persistence::save_session_info_in(&dir, &info) {
+            eprintln!("Warning: Failed to persist session {}: {}", info.id, e);
+        }
+    }
+
+    pub fn claim_checkout(&self, id: &str) -> Result<bool, SessionError> {
+        let mut claimed = self
+            .claimed_checkouts
+            .lock()
+            .map_err(|_| SessionError::LockError)?;
+
+        Ok(claimed.insert(id.to_string()))
+    }
+
+    pub fn release_checkout(&self, id: &str) -> Result<(), SessionError> {
+        let mut claimed = self
+            .claimed_checkouts
+            .lock()
+            .map_err(|_| SessionError::LockError)?;
+
+        claimed.remove(id);
+        Ok(())
+    }
+
+    /// Marks a merged-branch sweep of `git_directory` as started. False if
+    /// one is already running.
+    pub fn begin_sweep(&self, git_directory: &str) -> Result<bool, SessionError> {
+        let mut sweeping = self
+            .sweeping_repos
+            .lock()
+            .map_err(|_| SessionError::LockError)?;
+
+        Ok(sweeping.insert(normalize_repo_path(git_directory)))
+    }
+
+    pub fn end_sweep(&self, git_directory: &str) -> Result<(), SessionError> {
+        let mut sweeping = self
+            .sweeping_repos
+            .lock()
+            .map_err(|_| SessionError::LockError)?;
+
+        sweeping.remove(&normalize_repo_path(git_directory));
+        Ok(())
+    }
+
+    pub fn is_checkout_claimed(&self, id: &str) -> Result<bool, SessionError> {
+        let claimed = self
+            .claimed_checkouts
+            .lock()
+            .map_err(|_| SessionError::LockError)?;
+
+        Ok(claimed.contains(id))
+    }
+
+    pub fn clone_slots(&self) -> &Semaphore {
+        &self.clone_slots
+    }
+
+    pub fn set_clone_limit(&self, limit: usize) {
+        self.clone_slots.set_limit(limit);
+    }
+
+    pub fn set_session_limit(&self, limit: usize) {
+        if let Ok(mut max) = self.max_concurrent.lock() {
+            *max = limit.max(1);
+        }
+        self.wake_waiters();
+    }
+
+    /// Wakes `wait_for_scheduler` after a change made outside the sessions
+    /// lock. Taking the lock first means a waiter is either still to check
+    /// its turn, and sees the change, or already waiting, and is woken.
+    fn wake_waiters(&self) {
+        let _sessions = self.sessions.lock();
+        self.slot_freed.notify_all();
+    }
+
+    fn session_limit(&self) -> usize {
+        self.max_concurrent
+            .lock()
+            .map(|max| *max)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_SESSIONS)
+    }
+
+    /// Whether `id` may start now: the scheduler is running, fewer than
+    /// `max_concurrent` other sessions are active and none was queued before it.
+    fn has_turn(&self, sessions: &HashMap<String, Session>, id: &str) -> bool {
+        let Some(session) = sessions.get(id) else {
+            return false;
+        };
+        let key = queue_key(&session.info);
+        let others = || sessions.values().filter(|s| s.info.id != id);
+
+        !self.scheduler.is_paused()
+            && others().filter(|s| is_active(s)).count() < self.session_limit()
+            && !others().any(|s| {
+                s.info.status == SessionStatus::Queued && queue_key(&s.info) < key
+            })
+    }
+
+    pub fn capacity(&self) -> Result<Capacity, SessionError> {
+        let max = *self.max_concurrent.lock().map_err(|_| SessionError::LockError)?;
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+        Ok(Capacity {
+            active: active_count(&sessions),
+            max,
+        })
+    }
+
+    pub fn set_min_spawn_interval(&self, interval: std::time::Duration) {
+        self.scheduler.set_min_spawn_interval(interval);
+    }
+
+    /// Blocks until the next Claude spawn is allowed by `min_spawn_interval_ms`.
+    pub fn wait_for_spawn_slot(&self) {
+        self.scheduler.wait_for_spawn_slot();
+    }
+
+    pub fn pause_scheduler(&self) {
+        self.scheduler.pause();
+        self.persist_paused(true);
+    }
+
+    pub fn resume_scheduler(&self) {
+        self.scheduler.resume();
+        self.persist_paused(false);
+        self.wake_waiters();
+    }
+
+    fn persist_paused(&self, paused: bool) {
+        if let Some(dir) = self.queue_dir() {
+            if let Err(e) = persistence::save_scheduler_paused_in(&dir, paused) {
+                eprintln!("Warning: Failed to persist scheduler pause: {}", e);
+            }
+        }
+    }
+
+    /// Moves a new session to `Queued` if it can't start yet, because the
+    /// scheduler is paused or the session limit is reached. Returns whether it
+    /// did.
+    pub fn queue_if_waiting(&self, id: &str) -> Result<bool, SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+            if self.has_turn(&sessions, id) {
+                return Ok(false);
+            }
+
+            let queued_at_ms = next_queued_at_ms(&sessions);
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            if session.info.status != SessionStatus::Initializing {
+                return Ok(false);
+            }
+            session.set_status(SessionStatus::Queued);
+            session.info.queued_at_ms = Some(queued_at_ms);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(true)
+    }
+
+    /// Holds the session in `Queued` until it has a turn, first in first out.
+    /// Returns false if the session was cancelled or removed while it waited.
+    pub fn wait_for_scheduler(&self, id: &str) -> Result<bool, SessionError> {
+        self.queue_if_waiting(id)?;
+
+        'gate: loop {
+            self.scheduler.wait_until_resumed();
+
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+            loop {
+                let waiting = sessions.get(id).is_some_and(|s| {
+                    matches!(
+                        s.info.status,
+                        SessionStatus::Queued | SessionStatus::Initializing
+                    )
+                });
+                if !waiting || self.has_turn(&sessions, id) {
+                    break 'gate;
+                }
+                if self.scheduler.is_paused() {
+                    continue 'gate;
+                }
+                sessions = self
+                    .slot_freed
+                    .wait(sessions)
+                    .map_err(|_| SessionError::LockError)?;
+            }
+        }
+
+        match self.set_status_if(id, SessionStatus::Queued, SessionStatus::Initializing) {
+            Ok(_) => Ok(self.get_session_info(id)?.status == SessionStatus::Initializing),
+            Err(SessionError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn set_status_if(
+        &self,
+        id: &str,
+        from: SessionStatus,
+        to: SessionStatus,
+    ) -> Result<bool, SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            if session.info.status != from {
+                return Ok(false);
+            }
+            session.set_status(to);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(true)
+    }
+
+    pub fn queue_status(&self) -> Result<QueueStatus, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let count = |status: SessionStatus| {
+            sessions
+                .values()
+                .filter(|s| s.info.status == status)
+                .count()
+        };
+
+        Ok(QueueStatus {
+            paused: self.scheduler.is_paused(),
+            queued: count(SessionStatus::Queued),
+            running: count(SessionStatus::Initializing) + count(SessionStatus::Working),
+        })
+    }
+
     pub fn create_session(
         &self,
         id: String,
@@ -55,95 +627,578 @@ impl SessionManager {
 
         let session = Session::new(id.clone(), git_directory, instructions, work_dir, branch_name);
         let info = session.info.clone();
-        sessions.insert(id, session);
+        sessions.insert(id.clone(), session);
+        drop(sessions);
 
+        self.persist(&id);
         Ok(info)
     }
 
+    /// Re-registers a session that was still queued when the app last exited,
+    /// keeping its id and queue position. It waits in `Queued` for the scheduler.
+    pub fn restore_queued(
+        &self,
+        info: SessionInfo,
+        work_dir: PathBuf,
+        config: SessionConfig,
+    ) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        if sessions.contains_key(&info.id) {
+            return Err(SessionError::AlreadyExists(info.id));
+        }
+
+        let mut session = Session::new(
+            info.id.clone(),
+            info.git_directory.clone(),
+            info.instructions.clone(),
+            work_dir,
+            String::new(),
+        );
+        session.info = info;
+        session.set_status(SessionStatus::Queued);
+        session.info.resumed_from_queue = true;
+        session.base_branch = config.effective.base_branch.clone();
+        session.launch_config = Some(config);
+        sessions.insert(session.info.id.clone(), session);
+
+        Ok(())
+    }
+
+    /// Registers a session saved by an earlier run. One that was still
+    /// starting or working then has lost its process, and one still queued
+    /// only gets here when its launch inputs were lost, so either is marked
+    /// errored; returns whether that happened.
+    pub fn restore_persisted(
+        &self,
+        info: SessionInfo,
+        work_dir: PathBuf,
+    ) -> Result<bool, SessionError> {
+        let interrupted = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            if sessions.contains_key(&info.id) {
+                return Err(SessionError::AlreadyExists(info.id));
+            }
+
+            let mut session = Session::new(
+                info.id.clone(),
+                info.git_directory.clone(),
+                info.instructions.clone(),
+                work_dir,
+                String::new(),
+            );
+            if let Some(config) = &info.config {
+                session.base_branch = config.base_branch.clone();
+            }
+            session.info = info;
+            let error = match session.info.status {
+                SessionStatus::Initializing | SessionStatus::Working => Some(INTERRUPTED_MESSAGE),
+                SessionStatus::Queued => Some(LOST_LAUNCH_MESSAGE),
+                _ => None,
+            };
+            if let Some(error) = error {
+                // The run ended when the app did, not now; its last phase is
+                // the latest sign of life on record.
+                let last_seen_ms = session
+                    .info
+                    .phases
+                    .last()
+                    .map_or(session.info.created_at * 1000, |t| t.at_ms);
+                session.set_error_at(error.to_string(), last_seen_ms);
+            }
+            let id = session.info.id.clone();
+            sessions.insert(id.clone(), session);
+            error.is_some().then_some(id)
+        };
+
+        match interrupted {
+            Some(id) => {
+                self.status_changed(self.get_session_info(&id)?);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// The session, with its place in the queue if it is queued.
     pub fn get_session_info(&self, id: &str) -> Result<SessionInfo, SessionError> {
         let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
-        sessions
+        let session = sessions
             .get(id)
-            .map(|s| s.info.clone())
-            .ok_or_else(|| SessionError::NotFound(id.to_string()))
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+        Ok(SessionInfo {
+            queue_position: queue_positions(&sessions).get(id).copied(),
+            ..session.info.clone()
+        })
+    }
+
+    fn queue_position(&self, id: &str) -> Option<usize> {
+        let sessions = self.sessions.lock().ok()?;
+        queue_positions(&sessions).get(id).copied()
+    }
+
+    pub fn set_working(&self, id: &str, process_id: u32) -> Result<(), SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            session.set_working(process_id);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(())
+    }
+
+    /// Forgets the session's Claude process once it has exited, so nothing
+    /// later signals or reaps a pid that may have been reused.
+    pub fn clear_process_id(&self, id: &str) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.process_id = None;
+        Ok(())
+    }
+
+    pub fn enter_phase(&self, id: &str, phase: SessionPhase) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.enter_phase(phase);
+        Ok(())
+    }
+
+    pub fn set_completed(&self, id: &str, pr_url: String) -> Result<(), SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            session.set_completed(pr_url);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(())
+    }
+
+    pub fn set_no_changes(&self, id: &str) -> Result<(), SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            session.set_no_changes();
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(())
+    }
+
+    pub fn set_error(&self, id: &str, message: String) -> Result<(), SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            session.set_error(message);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(())
+    }
+
+    pub fn get_process_id(&self, id: &str) -> Result<Option<u32>, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        Ok(session.process_id)
+    }
+
+    pub fn get_work_dir(&self, id: &str) -> Result<PathBuf, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        Ok(session.work_dir.clone())
+    }
+
+    pub fn get_branch_name(&self, id: &str) -> Result<String, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        Ok(session.branch_name.clone())
+    }
+
+    pub fn set_branch_name(&self, id: &str, branch_name: String) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.branch_name = branch_name;
+        Ok(())
+    }
+
+    pub fn set_base_branch(&self, id: &str, base_branch: String) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.base_branch = base_branch;
+        Ok(())
+    }
+
+    pub fn set_effective_config(
+        &self,
+        id: &str,
+        config: EffectiveConfig,
+    ) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.config = Some(config);
+        Ok(())
+    }
+
+    pub fn set_baseline_build_ok(&self, id: &str, ok: bool) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.baseline_build_ok = Some(ok);
+        Ok(())
+    }
+
+    pub fn set_merge_conflicts(&self, id: &str, check: &ConflictCheck) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.will_conflict = check.will_conflict;
+        session.info.conflicting_paths = check.conflicting_paths.clone();
+        Ok(())
+    }
+
+    pub fn set_test_runs(&self, id: &str, commands: Option<Vec<String>>) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.tests_run_by_claude = commands.as_ref().map(|c| !c.is_empty());
+        session.info.claude_test_commands = commands.unwrap_or_default();
+        Ok(())
+    }
+
+    pub fn set_model(&self, id: &str, model: Option<String>) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.model = model;
+        Ok(())
+    }
+
+    pub fn set_diffstat(&self, id: &str, diffstat: DiffStat) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.info.diffstat = Some(diffstat);
+        Ok(())
+    }
+
+    pub fn add_output_marker(
+        &self,
+        id: &str,
+        kind: OutputMarkerKind,
+        detail: String,
+    ) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.add_marker(kind, detail);
+        Ok(())
     }
 
-    pub fn set_working(&self, id: &str, process_id: u32) -> Result<(), SessionError> {
+    pub fn set_locked(&self, id: &str, locked: bool) -> Result<(), SessionError> {
         let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get_mut(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        session.set_working(process_id);
+        session.info.locked = locked;
         Ok(())
     }
 
-    pub fn set_completed(&self, id: &str, pr_url: String) -> Result<(), SessionError> {
+    pub fn set_batch_id(&self, id: &str, batch_id: String) -> Result<(), SessionError> {
         let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get_mut(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        session.set_completed(pr_url);
+        session.info.batch_id = Some(batch_id);
         Ok(())
     }
 
-    pub fn set_error(&self, id: &str, message: String) -> Result<(), SessionError> {
+    pub fn set_retried_as(&self, id: &str, retry_id: String) -> Result<(), SessionError> {
         let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get_mut(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        session.set_error(message);
+        session.info.retried_as = Some(retry_id);
         Ok(())
     }
 
-    pub fn get_process_id(&self, id: &str) -> Result<Option<u32>, SessionError> {
+    /// Fails with `Locked` if the session is locked and `force` isn't set.
+    pub fn ensure_cancellable(&self, id: &str, force: bool) -> Result<(), SessionError> {
         let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        Ok(session.process_id)
+        if session.info.locked && !force {
+            return Err(SessionError::Locked(id.to_string()));
+        }
+        Ok(())
     }
 
-    pub fn get_work_dir(&self, id: &str) -> Result<PathBuf, SessionError> {
+    pub fn output_buffer(&self, id: &str) -> Result<Arc<OutputBuffer>, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        sessions
+            .get(id)
+            .map(|s| s.output.clone())
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))
+    }
+
+    pub fn set_launch_config(&self, id: &str, config: SessionConfig) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+        session.launch_config = Some(config);
+        Ok(())
+    }
+
+    pub fn get_launch_config(&self, id: &str) -> Result<Option<SessionConfig>, SessionError> {
         let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        Ok(session.work_dir.clone())
+        Ok(session.launch_config.clone())
     }
 
-    pub fn get_branch_name(&self, id: &str) -> Result<String, SessionError> {
+    pub fn reset_for_rerun(&self, id: &str) -> Result<(), SessionError> {
+        {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            session.reset_for_rerun();
+        }
+        self.persist(id);
+        Ok(())
+    }
+
+    pub fn get_base_branch(&self, id: &str) -> Result<String, SessionError> {
         let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
         let session = sessions
             .get(id)
             .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
 
-        Ok(session.branch_name.clone())
+        Ok(session.base_branch.clone())
     }
 
     pub fn remove_session(&self, id: &str) -> Result<Session, SessionError> {
         let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
-        sessions
+        let session = sessions
             .remove(id)
-            .ok_or_else(|| SessionError::NotFound(id.to_string()))
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+        self.slot_freed.notify_all();
+        Ok(session)
+    }
+
+    pub fn clear_sessions(&self) -> Result<Vec<Session>, SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let cleared = sessions.drain().map(|(_, session)| session).collect();
+        self.slot_freed.notify_all();
+        Ok(cleared)
     }
 
+    /// Every session, with queued ones numbered by their place in the queue.
     pub fn list_sessions(&self) -> Result<Vec<SessionInfo>, SessionError> {
         let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
 
-        Ok(sessions.values().map(|s| s.info.clone()).collect())
+        let positions = queue_positions(&sessions);
+        Ok(sessions
+            .values()
+            .map(|s| SessionInfo {
+                queue_position: positions.get(s.info.id.as_str()).copied(),
+                ..s.info.clone()
+            })
+            .collect())
+    }
+
+    /// Merges sessions for the same repo and branch into the one furthest
+    /// along. A duplicate that is still queued or running is left until it
+    /// stops, so its Claude process is never orphaned.
+    pub fn dedupe_sessions(&self) -> Result<Vec<MergedSession>, SessionError> {
+        let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        let mut best: HashMap<(PathBuf, String), String> = HashMap::new();
+        let mut merged = Vec::new();
+
+        let mut ids: Vec<String> = sessions.keys().cloned().collect();
+        ids.sort();
+
+        for id in ids {
+            let session = &sessions[&id];
+            if session.branch_name.is_empty() {
+                continue;
+            }
+
+            let key = (
+                normalize_repo_path(&session.info.git_directory),
+                session.branch_name.clone(),
+            );
+            let rank = (session.info.status.progress_rank(), session.info.created_at);
+
+            match best.get(&key) {
+                None => {
+                    best.insert(key, id);
+                }
+                Some(existing_id) => {
+                    let existing = &sessions[existing_id];
+                    let existing_rank =
+                        (existing.info.status.progress_rank(), existing.info.created_at);
+
+                    let (kept, removed) = if rank > existing_rank {
+                        (id, existing_id.clone())
+                    } else {
+                        (existing_id.clone(), id)
+                    };
+                    let still_running = matches!(
+                        sessions[&removed].info.status,
+                        SessionStatus::Queued | SessionStatus::Initializing | SessionStatus::Working
+                    );
+                    if !still_running {
+                        merged.push(MergedSession {
+                            kept: kept.clone(),
+                            removed,
+                        });
+                    }
+                    best.insert(key, kept);
+                }
+            }
+        }
+
+        for merge in &merged {
+            sessions.remove(&merge.removed);
+            eprintln!(
+                "Merged duplicate session {} into {}",
+                merge.removed, merge.kept
+            );
+        }
+
+        Ok(merged)
+    }
+
+    /// `(session id, pid)` for every `Working` session still running Claude.
+    /// Later phases keep the status but no longer have a process to watch.
+    pub fn working_processes(&self) -> Result<Vec<(String, u32)>, SessionError> {
+        let sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+        Ok(sessions
+            .values()
+            .filter(|s| s.info.status == SessionStatus::Working)
+            .filter(|s| s.info.current_phase() == Some(SessionPhase::Run))
+            .filter_map(|s| Some((s.info.id.clone(), s.process_id?)))
+            .collect())
+    }
+
+    /// Marks the session errored if it is still `Working` on `process_id`, so
+    /// a session that moved on meanwhile is left alone. Returns whether it did.
+    pub fn fail_if_running(
+        &self,
+        id: &str,
+        process_id: u32,
+        message: String,
+    ) -> Result<bool, SessionError> {
+        let info = {
+            let mut sessions = self.sessions.lock().map_err(|_| SessionError::LockError)?;
+
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+
+            let running = session.info.status == SessionStatus::Working
+                && session.info.current_phase() == Some(SessionPhase::Run)
+                && session.process_id == Some(process_id);
+            if !running {
+                return Ok(false);
+            }
+            session.set_error(message);
+            session.info.clone()
+        };
+        self.status_changed(info);
+        Ok(true)
     }
 
     pub fn get_active_sessions(&self) -> Result<Vec<SessionInfo>, SessionError> {
@@ -152,7 +1207,8 @@ impl SessionManager {
         Ok(sessions
             .values()
             .filter(|s| {
-                s.info.status == SessionStatus::Initializing
+                s.info.status == SessionStatus::Queued
+                    || s.info.status == SessionStatus::Initializing
                     || s.info.status == SessionStatus::Working
             })
             .map(|s| s.info.clone())
@@ -163,6 +1219,7 @@ impl SessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude_session::types::PhaseTransition;
 
     #[test]
     fn test_create_session() {
@@ -207,6 +1264,81 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_sessions_over_limit_queue_in_order() {
+        let manager = SessionManager::new();
+        manager.set_session_limit(1);
+        let queued_events: Arc<Mutex<Vec<(String, serde_json::Value)>>> = Arc::default();
+        let sink_events = queued_events.clone();
+        manager.set_event_sink(Arc::new(move |_, payload| {
+            if payload["status"] == "queued" {
+                let id = payload["id"].as_str().unwrap().to_string();
+                sink_events.lock().unwrap().push((id, payload["queue_position"].clone()));
+            }
+        }));
+        let create = |id: &str| {
+            manager
+                .create_session(
+                    id.to_string(),
+                    "/path/to/repo".to_string(),
+                    "Add feature".to_string(),
+                    PathBuf::from(format!("/tmp/session-{}", id)),
+                    String::new(),
+                )
+                .unwrap();
+        };
+        let status = |id: &str| manager.get_session_info(id).unwrap().status;
+
+        create("one");
+        assert!(manager.wait_for_scheduler("one").unwrap());
+        manager.set_working("one", 1).unwrap();
+
+        let mut waiters = Vec::new();
+        for id in ["two", "three"] {
+            create(id);
+            let waiter = manager.clone();
+            waiters.push(std::thread::spawn(move || waiter.wait_for_scheduler(id).unwrap()));
+            // Each session joins the queue before the next is created.
+            while status(id) != SessionStatus::Queued {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+        }
+        assert_eq!(manager.capacity().unwrap(), Capacity { active: 1, max: 1 });
+        let position = |id: &str| {
+            manager
+                .list_sessions()
+                .unwrap()
+                .into_iter()
+                .find(|info| info.id == id)
+                .unwrap()
+                .queue_position
+        };
+        assert_eq!(position("one"), None);
+        assert_eq!(position("two"), Some(1));
+        assert_eq!(position("three"), Some(2));
+        assert_eq!(manager.get_session_info("three").unwrap().queue_position, Some(2));
+        assert_eq!(
+            *queued_events.lock().unwrap(),
+            vec![
+                ("two".to_string(), serde_json::json!(1)),
+                ("three".to_string(), serde_json::json!(2)),
+            ]
+        );
+
+        manager
+            .set_completed("one", "https://github.com/o/r/pull/1".to_string())
+            .unwrap();
+        let mut waiters = waiters.into_iter();
+        assert!(waiters.next().unwrap().join().unwrap());
+        assert_eq!(status("two"), SessionStatus::Initializing);
+        assert_eq!(position("three"), Some(1));
+
+        manager
+            .set_error("three", "Session cancelled by user".to_string())
+            .unwrap();
+        assert!(!waiters.next().unwrap().join().unwrap());
+    }
+
     #[test]
     fn test_get_session_info() {
         let manager = SessionManager::new();
@@ -283,6 +1415,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_set_no_changes() {
+        let manager = SessionManager::new();
+
+        manager
+            .create_session(
+                "test-1".to_string(),
+                "/path/to/repo".to_string(),
+                "Add feature".to_string(),
+                PathBuf::from("/tmp/session-test-1"),
+                "claude/feature-123".to_string(),
+            )
+            .unwrap();
+
+        manager.set_no_changes("test-1").unwrap();
+
+        let info = manager.get_session_info("test-1").unwrap();
+        assert_eq!(info.status, SessionStatus::NoChanges);
+        assert_eq!(info.pr_url, None);
+        assert!(info.summary_markdown().starts_with("**No changes**"));
+    }
+
     #[test]
     fn test_set_error() {
         let manager = SessionManager::new();
@@ -304,6 +1458,89 @@ mod tests {
         let info = manager.get_session_info("test-1").unwrap();
         assert_eq!(info.status, SessionStatus::Error);
         assert_eq!(info.error_message, Some("Something failed".to_string()));
+        assert!(info.completed_at.unwrap() >= info.created_at);
+        assert!(info.duration_secs().is_some());
+    }
+
+    #[test]
+    fn test_interrupted_session_finishes_at_its_last_phase() {
+        let manager = SessionManager::new();
+        manager
+            .create_session(
+                "test-1".to_string(),
+                "/path/to/repo".to_string(),
+                "Add feature".to_string(),
+                PathBuf::from("/tmp/session-test-1"),
+                "claude/feature-123".to_string(),
+            )
+            .unwrap();
+        manager.set_working("test-1", 1).unwrap();
+
+        let mut info = manager.get_session_info("test-1").unwrap();
+        info.created_at = 1_000;
+        info.phases = vec![PhaseTransition {
+            phase: SessionPhase::Run,
+            at_ms: 1_060_500,
+        }];
+
+        let restarted = SessionManager::new();
+        assert!(restarted
+            .restore_persisted(info, PathBuf::from("/tmp/session-test-1"))
+            .unwrap());
+
+        let info = restarted.get_session_info("test-1").unwrap();
+        assert_eq!(info.status, SessionStatus::Error);
+        assert_eq!(info.completed_at, Some(1_060));
+        assert_eq!(info.duration_secs(), Some(60));
+    }
+
+    #[test]
+    fn test_status_changes_emit_events() {
+        let manager = SessionManager::new();
+        let events: Arc<Mutex<Vec<(String, serde_json::Value)>>> = Arc::default();
+        let sink_events = events.clone();
+        let sink_manager = manager.clone();
+        manager.set_event_sink(Arc::new(move |event, payload| {
+            // The sink can read the manager without deadlocking.
+            sink_manager.get_session_info("test-1").unwrap();
+            sink_events
+                .lock()
+                .unwrap()
+                .push((event.to_string(), payload));
+        }));
+
+        manager
+            .create_session(
+                "test-1".to_string(),
+                "/path/to/repo".to_string(),
+                "Add feature".to_string(),
+                PathBuf::from("/tmp/session-test-1"),
+                "claude/feature-123".to_string(),
+            )
+            .unwrap();
+        manager.set_working("test-1", 12345).unwrap();
+        manager
+            .set_error("test-1", "Something failed".to_string())
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let statuses: Vec<&serde_json::Value> = events
+            .iter()
+            .map(|(event, payload)| {
+                assert_eq!(event, SESSION_STATUS_EVENT);
+                assert_eq!(payload["id"], "test-1");
+                &payload["status"]
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                &serde_json::to_value(SessionStatus::Working).unwrap(),
+                &serde_json::to_value(SessionStatus::Error).unwrap()
+            ]
+        );
+        assert!(manager.set_completed("missing", String::new()).is_err());
+        assert_eq!(events.len(), 2);
     }
 
     #[test]
@@ -355,6 +1592,395 @@ mod tests {
         assert_eq!(sessions.len(), 2);
     }
 
+    #[test]
+    fn test_list_sessions_for_repo_normalizes_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_dir = temp_dir.path().join("repo");
+        let other_dir = temp_dir.path().join("other");
+        std::fs::create_dir_all(repo_dir.join("src")).unwrap();
+        std::fs::create_dir_all(&other_dir).unwrap();
+
+        let repo = repo_dir.to_str().unwrap().to_string();
+        let spellings = [
+            repo.clone(),
+            format!("{}/", repo),
+            format!("{}/./", repo),
+            format!("{}/src/..", repo),
+        ];
+
+        let manager = SessionManager::new();
+        for (i, spelling) in spellings.iter().enumerate() {
+            manager
+                .create_session(
+                    format!("repo-{}", i),
+                    spelling.clone(),
+                    "Add feature".to_string(),
+                    PathBuf::from(format!("/tmp/session-repo-{}", i)),
+                    String::new(),
+                )
+                .unwrap();
+        }
+        manager
+            .create_session(
+                "other".to_string(),
+                other_dir.to_str().unwrap().to_string(),
+                "Fix bug".to_string(),
+                PathBuf::from("/tmp/session-other"),
+                String::new(),
+            )
+            .unwrap();
+
+        let sessions =
+            filter_sessions_for_repo(manager.list_sessions().unwrap(), &format!("{}/", repo));
+
+        assert_eq!(sessions.len(), spellings.len());
+        assert!(sessions.iter().all(|s| s.id.starts_with("repo-")));
+    }
+
+    #[test]
+    fn test_filter_sessions_for_repo_sorts_newest_first() {
+        let manager = SessionManager::new();
+        for id in ["older", "newer"] {
+            manager
+                .create_session(
+                    id.to_string(),
+                    "/path/to/repo".to_string(),
+                    "Add feature".to_string(),
+                    PathBuf::from(format!("/tmp/session-{}", id)),
+                    String::new(),
+                )
+                .unwrap();
+        }
+
+        let mut sessions = manager.list_sessions().unwrap();
+        for s in sessions.iter_mut() {
+            s.created_at = if s.id == "newer" { 200 } else { 100 };
+        }
+
+        let sorted = filter_sessions_for_repo(sessions, "/path/to/repo/");
+
+        assert_eq!(sorted[0].id, "newer");
+        assert_eq!(sorted[1].id, "older");
+    }
+
+    #[test]
+    fn test_recent_prs_sorted_by_completion() {
+        let manager = SessionManager::new();
+        for id in ["first", "second", "third", "failed"] {
+            manager
+                .create_session(
+                    id.to_string(),
+                    format!("/path/to/{}", id),
+                    "Add feature".to_string(),
+                    PathBuf::from(format!("/tmp/session-{}", id)),
+                    String::new(),
+                )
+                .unwrap();
+        }
+        let finish_order = ["third", "first", "second"];
+        for id in finish_order {
+            manager
+                .set_completed(id, format!("https://github.com/owner/{}/pull/1", id))
+                .unwrap();
+        }
+        manager.set_error("failed", "boom".to_string()).unwrap();
+
+        let mut sessions = manager.list_sessions().unwrap();
+        for s in sessions.iter_mut() {
+            let Some(rank) = finish_order.iter().position(|id| *id == s.id) else {
+                continue;
+            };
+            for t in s.phases.iter_mut().filter(|t| t.phase == SessionPhase::Finished) {
+                t.at_ms = 1_000 + rank as u64;
+            }
+        }
+
+        let prs = recent_prs(sessions.clone(), 10);
+        let ids: Vec<_> = prs.iter().map(|pr| pr.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["second", "first", "third"]);
+        assert_eq!(prs[0].pr_url, "https://github.com/owner/second/pull/1");
+        assert_eq!(prs[0].git_directory, "/path/to/second");
+
+        let prs = recent_prs(sessions, 1);
+        assert_eq!(prs.len(), 1);
+        assert_eq!(prs[0].session_id, "second");
+    }
+
+    #[test]
+    fn test_search_sessions_by_instructions() {
+        let manager = SessionManager::new();
+        let sessions = [
+            ("auth-old", "Fix the Auth bug in login", 100),
+            ("unrelated", "Update the README", 200),
+            ("auth-new", "Investigate why AUTH tokens expire early", 300),
+        ];
+        for (id, instructions, _) in sessions {
+            manager
+                .create_session(
+                    id.to_string(),
+                    "/repo".to_string(),
+                    instructions.to_string(),
+                    PathBuf::from(format!("/tmp/session-{}", id)),
+                    String::new(),
+                )
+                .unwrap();
+        }
+        let infos: Vec<SessionInfo> = manager
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|mut info| {
+                info.created_at = sessions.iter().find(|s| s.0 == info.id).unwrap().2;
+                info
+            })
+            .collect();
+
+        let matches = search_sessions(infos.clone(), "auth");
+        let ids: Vec<_> = matches.iter().map(|m| m.session.id.as_str()).collect();
+        assert_eq!(ids, vec!["auth-new", "auth-old"]);
+        assert_eq!(matches[1].snippet, "Fix the Auth bug in login");
+
+        assert!(search_sessions(infos.clone(), "deploy").is_empty());
+        assert!(search_sessions(infos, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_find_case_insensitive_when_lowercasing_changes_length() {
+        // İ lowercases to two characters, ẞ to a shorter ß.
+        let text = "İstanbul: fix the STRAẞE sign";
+        let (start, end) = find_case_insensitive(text, "straße").unwrap();
+        assert_eq!(&text[start..end], "STRAẞE");
+        let (start, end) = find_case_insensitive(text, "FIX").unwrap();
+        assert_eq!(&text[start..end], "fix");
+        let (start, end) = find_case_insensitive(text, "i̇stanbul").unwrap();
+        assert_eq!(&text[start..end], "İstanbul");
+        assert!(find_case_insensitive(text, "strasse").is_none());
+    }
+
+    #[test]
+    fn test_match_snippet_truncates_long_text() {
+        let text = format!("{}needle\n{}", "a".repeat(60), "b".repeat(60));
+        let (start, end) = find_case_insensitive(&text, "NEEDLE").unwrap();
+
+        let snippet = match_snippet(&text, start, end);
+        assert_eq!(
+            snippet,
+            format!("…{}needle {}…", "a".repeat(40), "b".repeat(39))
+        );
+    }
+
+    #[test]
+    fn test_claim_checkout() {
+        let manager = SessionManager::new();
+
+        assert!(manager.claim_checkout("test-1").unwrap());
+        assert!(!manager.claim_checkout("test-1").unwrap());
+
+        manager.release_checkout("test-1").unwrap();
+        assert!(manager.claim_checkout("test-1").unwrap());
+    }
+
+    #[test]
+    fn test_one_sweep_per_repo() {
+        let manager = SessionManager::new();
+
+        assert!(manager.begin_sweep("/repo").unwrap());
+        assert!(!manager.begin_sweep("/repo/.").unwrap());
+        assert!(manager.begin_sweep("/other").unwrap());
+
+        manager.end_sweep("/repo").unwrap();
+        assert!(manager.begin_sweep("/repo").unwrap());
+    }
+
+    #[test]
+    fn test_dedupe_sessions_keeps_furthest_along() {
+        let manager = SessionManager::new();
+
+        for id in ["restored", "recreated", "unrelated"] {
+            manager
+                .create_session(
+                    id.to_string(),
+                    "/path/to/repo".to_string(),
+                    "Add feature".to_string(),
+                    PathBuf::from(format!("/tmp/session-{}", id)),
+                    String::new(),
+                )
+                .unwrap();
+        }
+        manager
+            .set_branch_name("restored", "claude/add-feature-1".to_string())
+            .unwrap();
+        manager
+            .set_branch_name("recreated", "claude/add-feature-1".to_string())
+            .unwrap();
+        manager
+            .set_branch_name("unrelated", "claude/other-2".to_string())
+            .unwrap();
+
+        manager
+            .set_completed("restored", "https://github.com/owner/repo/pull/1".to_string())
+            .unwrap();
+        manager.set_working("recreated", 4242).unwrap();
+
+        // Dropping it now would leave its Claude running untracked.
+        assert!(manager.dedupe_sessions().unwrap().is_empty());
+        assert!(manager.get_session_info("recreated").is_ok());
+
+        manager
+            .set_error("recreated", "Claude failed".to_string())
+            .unwrap();
+        let merged = manager.dedupe_sessions().unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].kept, "restored");
+        assert_eq!(merged[0].removed, "recreated");
+
+        let sessions = manager.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        let kept = manager.get_session_info("restored").unwrap();
+        assert_eq!(kept.status, SessionStatus::Completed);
+        assert!(manager.get_session_info("recreated").is_err());
+    }
+
+    #[test]
+    fn test_paused_scheduler_queues_until_resumed() {
+        let manager = SessionManager::new();
+        manager.pause_scheduler();
+
+        for id in ["queued-1", "queued-2"] {
+            manager
+                .create_session(
+                    id.to_string(),
+                    "/repo".to_string(),
+                    "instructions".to_string(),
+                    PathBuf::from("/tmp/work"),
+                    "branch".to_string(),
+                )
+                .unwrap();
+        }
+
+        let waiters: Vec<_> = ["queued-1", "queued-2"]
+            .into_iter()
+            .map(|id| {
+                let manager = manager.clone();
+                std::thread::spawn(move || manager.wait_for_scheduler(id).unwrap())
+            })
+            .collect();
+
+        while manager.queue_status().unwrap().queued < 2 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(waiters.iter().all(|w| !w.is_finished()));
+        assert_eq!(
+            manager.queue_status().unwrap(),
+            QueueStatus {
+                paused: true,
+                queued: 2,
+                running: 0,
+            }
+        );
+
+        manager
+            .set_error("queued-2", "Session cancelled by user".to_string())
+            .unwrap();
+        manager.resume_scheduler();
+
+        let started: Vec<bool> = waiters.into_iter().map(|w| w.join().unwrap()).collect();
+        assert_eq!(started, vec![true, false]);
+        assert_eq!(
+            manager.get_session_info("queued-1").unwrap().status,
+            SessionStatus::Initializing
+        );
+        assert!(!manager.queue_status().unwrap().paused);
+    }
+
+    #[test]
+    fn test_sessions_persisted_from_creation_through_rerun() {
+        let queue_dir = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new();
+        manager.set_queue_dir(queue_dir.path().to_path_buf());
+        let saved = |id: &str| persistence::load_session_info_in(queue_dir.path(), id).unwrap();
+
+        manager
+            .create_session(
+                "persisted".to_string(),
+                "/repo".to_string(),
+                "instructions".to_string(),
+                PathBuf::from("/tmp/work"),
+                "branch".to_string(),
+            )
+            .unwrap();
+        assert_eq!(saved("persisted").status, SessionStatus::Initializing);
+
+        manager
+            .set_error("persisted", "Claude failed".to_string())
+            .unwrap();
+        manager.reset_for_rerun("persisted").unwrap();
+        let rerun = saved("persisted");
+        assert_eq!(rerun.status, SessionStatus::Initializing);
+        assert!(rerun.error_message.is_none());
+    }
+
+    #[test]
+    fn test_racing_status_changes_persist_the_latest() {
+        let queue_dir = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new();
+        manager.set_queue_dir(queue_dir.path().to_path_buf());
+
+        for round in 0..20 {
+            let id = format!("race-{}", round);
+            manager
+                .create_session(
+                    id.clone(),
+                    "/repo".to_string(),
+                    "instructions".to_string(),
+                    PathBuf::from("/tmp/work"),
+                    "branch".to_string(),
+                )
+                .unwrap();
+            let writers: Vec<_> = (0..4)
+                .map(|n| {
+                    let manager = manager.clone();
+                    let id = id.clone();
+                    std::thread::spawn(move || {
+                        if n % 2 == 0 {
+                            manager.set_error(&id, format!("failed {}", n)).unwrap();
+                        } else {
+                            manager.set_completed(&id, format!("https://pr/{}", n)).unwrap();
+                        }
+                    })
+                })
+                .collect();
+            for writer in writers {
+                writer.join().unwrap();
+            }
+
+            let saved = persistence::load_session_info_in(queue_dir.path(), &id).unwrap();
+            let current = manager.get_session_info(&id).unwrap();
+            assert_eq!(saved.status, current.status);
+            assert_eq!(saved.error_message, current.error_message);
+            assert_eq!(saved.pr_url, current.pr_url);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_pause_survives_restart() {
+        let queue_dir = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new();
+        manager.set_queue_dir(queue_dir.path().to_path_buf());
+        manager.pause_scheduler();
+
+        let restarted = SessionManager::new();
+        restarted.set_queue_dir(queue_dir.path().to_path_buf());
+        assert!(restarted.queue_status().unwrap().paused);
+
+        restarted.resume_scheduler();
+        let restarted_again = SessionManager::new();
+        restarted_again.set_queue_dir(queue_dir.path().to_path_buf());
+        assert!(!restarted_again.queue_status().unwrap().paused);
+    }
+
     #[test]
     fn test_get_active_sessions() {
         let manager = SessionManager::new();
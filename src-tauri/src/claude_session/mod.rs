@@ -1,7 +1,14 @@
+pub mod artifacts;
 pub mod commands;
+pub mod config;
 pub mod manager;
 pub mod orchestrator;
+pub mod persistence;
 pub mod process;
+pub mod project;
+pub mod reconcile;
+pub mod scheduler;
+pub mod semaphore;
 pub mod types;
 
 pub use commands::AppState;
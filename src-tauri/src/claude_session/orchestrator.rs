@@ -1,20 +1,57 @@
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 
 use crate::git_ops::{
     branch::{create_feature_branch, generate_branch_name},
-    cleanup::cleanup_session_dir,
-    clone::clone_to_temp,
-    commit::commit_and_push,
-    pr::create_pull_request,
+    cleanup::{cleanup_session, cleanup_session_dir},
+    clone::{clone_to_dir_reclaiming, update_submodules, CloneProgress},
+    codeowners::{load_codeowners, suggest_reviewers},
+    commit::{
+        create_commit, has_changes, out_of_scope_paths, prefixed_message, push_to_remote,
+        snapshot_worktree, stage_all_changes, stage_changes_since, staged_change_count,
+        CommitIdentity, WorkTreeSnapshot,
+    },
+    conflicts::check_merge_conflicts,
+    patch::head_commit_stats,
+    pr::{
+        add_labels, compose_pr_body, create_merge_request, create_pull_request, get_remote_host,
+        read_pr_template, request_reviewers, suggest_pr_title, RemoteHost,
+    },
+    reset::fetch_remote_branch,
+    GitOpsError,
+};
+
+use super::artifacts::create_log_in;
+use super::config::EffectiveConfig;
+use super::manager::SessionManager;
+use super::process::{
+    compose_instructions, detect_test_runs, reported_model, run_claude_and_wait,
+    run_command_with_timeout, session_guidelines, stream_json_display_lines, InstructionsLength,
+    ProcessError, ProcessLimits,
 };
+use super::semaphore::Semaphore;
+use super::types::{DiffStat, OutputMarkerKind, SessionPhase};
 
-use super::process::{compose_instructions, run_claude_and_wait, ProcessError};
+pub const CLONE_PROGRESS_EVENT: &str = "clone-progress";
+/// Emitted for each line of Claude's output, as `stream_json_display_lines`
+/// renders it, while a session runs.
+pub const SESSION_OUTPUT_EVENT: &str = "session-output";
 
 #[derive(Debug)]
 pub enum OrchestratorError {
     GitError(String),
     ProcessError(ProcessError),
     IoError(std::io::Error),
+    /// Claude changed files outside `editable_paths`.
+    OutOfScopeEdits(Vec<String>),
+    /// Claude changed more files than `max_changed_files` allows.
+    TooManyChangedFiles { changed: usize, limit: usize },
+    /// The composed instructions exceed `max_instructions_len` under
+    /// `strict_instructions`.
+    InstructionsTooLong(String),
 }
 
 impl std::fmt::Display for OrchestratorError {
@@ -23,7 +60,35 @@ impl std::fmt::Display for OrchestratorError {
             OrchestratorError::GitError(msg) => write!(f, "Git error: {}", msg),
             OrchestratorError::ProcessError(e) => write!(f, "Process error: {}", e),
             OrchestratorError::IoError(e) => write!(f, "IO error: {}", e),
+            OrchestratorError::OutOfScopeEdits(paths) => write!(
+                f,
+                "Claude changed files outside editable_paths, so nothing was committed: {}",
+                paths.join(", ")
+            ),
+            OrchestratorError::TooManyChangedFiles { changed, limit } => write!(
+                f,
+                "Claude changed {} files, exceeding the limit of {}; nothing was committed",
+                changed, limit
+            ),
+            OrchestratorError::InstructionsTooLong(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl OrchestratorError {
+    /// Whether the checkout is worth keeping for inspection even when failed
+    /// checkouts are normally removed.
+    pub fn keeps_checkout(&self) -> bool {
+        matches!(self, OrchestratorError::TooManyChangedFiles { .. })
+    }
+}
+
+fn check_changed_file_limit(changed: usize, limit: Option<usize>) -> Result<(), OrchestratorError> {
+    match limit {
+        Some(limit) if changed > limit => {
+            Err(OrchestratorError::TooManyChangedFiles { changed, limit })
         }
+        _ => Ok(()),
     }
 }
 
@@ -45,54 +110,477 @@ impl From<std::io::Error> for OrchestratorError {
     }
 }
 
+/// Everything the orchestrator sends to GitHub for a session's PR.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrPayload {
+    pub title: String,
+    pub body: String,
+    pub head: String,
+    pub base: String,
+    pub draft: bool,
+    pub reviewers: Vec<String>,
+    /// Nothing assigns PRs yet, so this is always empty.
+    pub assignees: Vec<String>,
+    pub labels: Vec<String>,
+}
+
+/// The PR for `config` from branch `head`. The template and CODEOWNERS are
+/// read from `repo_dir`; `changed_paths` pick the reviewers.
+pub fn compose_pr_payload(
+    config: &SessionConfig,
+    repo_dir: &Path,
+    head: &str,
+    changed_paths: &[String],
+    will_conflict: bool,
+) -> PrPayload {
+    let mut title = suggest_pr_title(&config.user_instructions);
+    if config.effective.prefix_pr_title {
+        title = prefixed_message(config.effective.commit_message_prefix.as_deref(), &title);
+    }
+    let template = read_pr_template(repo_dir).unwrap_or_default();
+    let reviewers = if config.effective.request_codeowner_reviewers {
+        load_codeowners(repo_dir)
+            .map(|rules| suggest_reviewers(&rules, changed_paths))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    PrPayload {
+        title,
+        body: compose_pr_body(&template, &config.user_instructions),
+        head: head.to_string(),
+        base: config.effective.base_branch.clone(),
+        // Opened as a draft so a conflicting PR isn't mistaken for one ready to merge.
+        draft: will_conflict,
+        reviewers,
+        assignees: Vec::new(),
+        labels: config.effective.pr_labels.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
     pub session_id: String,
     pub git_directory: String,
     pub user_instructions: String,
     pub additional_instructions: Option<String>,
     pub instructions_file_content: Option<String>,
-    pub base_branch: String,
+    /// Used instead of a generated branch name when set.
+    pub branch_name: Option<String>,
+    /// Used verbatim instead of the generated commit message when set.
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    pub effective: EffectiveConfig,
+}
+
+/// The session's own commit message if it was given one, otherwise one
+/// derived from the instructions.
+pub fn session_commit_message(config: &SessionConfig) -> String {
+    match &config.commit_message {
+        Some(message) => message.clone(),
+        None => prefixed_message(
+            config.effective.commit_message_prefix.as_deref(),
+            &format!("feat: {}", config.user_instructions),
+        ),
+    }
 }
 
+#[derive(Debug)]
 pub struct SessionResult {
-    pub pr_url: String,
+    /// `None` when Claude left the checkout unchanged and no PR was opened.
+    pub pr_url: Option<String>,
     pub branch_name: String,
 }
 
-pub fn run_full_session(config: SessionConfig) -> Result<SessionResult, OrchestratorError> {
+fn clone_with_limit<F>(clone_slots: &Semaphore, clone: F) -> Result<PathBuf, GitOpsError>
+where
+    F: FnOnce() -> Result<PathBuf, GitOpsError>,
+{
+    let _permit = clone_slots.acquire();
+    clone()
+}
+
+struct CheckoutClaim<'a> {
+    session_manager: &'a SessionManager,
+    session_id: &'a str,
+    already_claimed: bool,
+}
+
+impl<'a> CheckoutClaim<'a> {
+    /// Takes over a claim the caller already made with `claim_checkout`.
+    fn adopt(session_manager: &'a SessionManager, session_id: &'a str) -> Self {
+        Self {
+            session_manager,
+            session_id,
+            already_claimed: false,
+        }
+    }
+
+    fn acquire(session_manager: &'a SessionManager, session_id: &'a str) -> Self {
+        let already_claimed = !session_manager.claim_checkout(session_id).unwrap_or(false);
+        Self {
+            session_manager,
+            session_id,
+            already_claimed,
+        }
+    }
+}
+
+impl Drop for CheckoutClaim<'_> {
+    fn drop(&mut self) {
+        if !self.already_claimed {
+            let _ = self.session_manager.release_checkout(self.session_id);
+        }
+    }
+}
+
+fn prepare_checkout(
+    config: &SessionConfig,
+    session_manager: &SessionManager,
+    claim: &CheckoutClaim,
+    fresh: bool,
+) -> Result<(PathBuf, String), OrchestratorError> {
     let source_path = Path::new(&config.git_directory);
+    let session_dir = session_manager
+        .get_work_dir(&config.session_id)
+        .or_else(|_| session_manager.session_dir(&config.session_id))?;
+
+    if fresh {
+        cleanup_session_dir(&session_dir)?;
+    }
 
-    let work_dir = clone_to_temp(source_path, &config.session_id)?;
+    let report_progress = |progress: CloneProgress| {
+        session_manager.emit(
+            CLONE_PROGRESS_EVENT,
+            serde_json::json!({
+                "session_id": config.session_id,
+                "unit": progress.unit,
+                "done": progress.done,
+                "total": progress.total,
+            }),
+        );
+    };
 
-    let branch_name = generate_branch_name(&config.user_instructions);
+    let work_dir = clone_with_limit(session_manager.clone_slots(), || {
+        let work_dir = clone_to_dir_reclaiming(
+            source_path,
+            &session_dir,
+            claim.already_claimed,
+            Some(&report_progress),
+        )?;
+        if config.effective.clone_submodules {
+            update_submodules(
+                &work_dir,
+                Some(&report_progress),
+                config.effective.clone_depth,
+            )?;
+        }
+        Ok(work_dir)
+    })?;
+
+    let branch_name = config.branch_name.clone().unwrap_or_else(|| {
+        generate_branch_name(&config.user_instructions, &config.effective.branch_prefix)
+    });
     create_feature_branch(&work_dir, &branch_name)?;
+    let _ = session_manager.set_branch_name(&config.session_id, branch_name.clone());
+
+    Ok((work_dir, branch_name))
+}
+
+/// How long the baseline build may run before it is killed and counted as
+/// broken.
+const BASELINE_BUILD_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Builds the fresh checkout so a later failure can be attributed to Claude or
+/// to a baseline that was already broken. A build still running after
+/// `timeout` is killed, along with anything it spawned, and counts as broken.
+/// Returns `None` if the command could not be started.
+pub fn run_baseline_build(work_dir: &Path, command: &str, timeout: Duration) -> Option<bool> {
+    match run_command_with_timeout(work_dir, command, timeout) {
+        Ok(output) => Some(output.exit_code == Some(0)),
+        Err(ProcessError::Timeout(_)) => {
+            eprintln!(
+                "Warning: Baseline build `{}` timed out after {}s",
+                command,
+                timeout.as_secs()
+            );
+            Some(false)
+        }
+        Err(e) => {
+            eprintln!("Warning: Baseline build `{}` did not run: {}", command, e);
+            None
+        }
+    }
+}
+
+/// Dry-runs the merge of the committed branch into its freshly fetched base
+/// and records the outcome on the session. Returns whether it would conflict.
+fn record_merge_conflicts(
+    config: &SessionConfig,
+    session_manager: &SessionManager,
+    work_dir: &Path,
+) -> bool {
+    let base_branch = &config.effective.base_branch;
+    if let Err(e) = fetch_remote_branch(work_dir, base_branch) {
+        eprintln!("Warning: Failed to fetch {}: {}", base_branch, e);
+    }
+
+    match check_merge_conflicts(work_dir, base_branch) {
+        Ok(check) => {
+            let _ = session_manager.set_merge_conflicts(&config.session_id, &check);
+            check.will_conflict
+        }
+        Err(e) => {
+            eprintln!("Warning: Conflict check against {} failed: {}", base_branch, e);
+            false
+        }
+    }
+}
+
+/// Records the size of the commit just made on the session.
+fn record_diffstat(session_id: &str, session_manager: &SessionManager, work_dir: &Path) {
+    match head_commit_stats(work_dir) {
+        Ok(files) => {
+            let diffstat = DiffStat {
+                files: files.len(),
+                insertions: files.iter().map(|file| file.insertions).sum(),
+                deletions: files.iter().map(|file| file.deletions).sum(),
+            };
+            let _ = session_manager.set_diffstat(session_id, diffstat);
+        }
+        Err(e) => eprintln!("Warning: Failed to count the committed changes: {}", e),
+    }
+}
+
+fn record_baseline_build(
+    config: &SessionConfig,
+    session_manager: &SessionManager,
+    work_dir: &Path,
+) {
+    let Some(command) = &config.effective.baseline_build_command else {
+        return;
+    };
+    if let Some(ok) = run_baseline_build(work_dir, command, BASELINE_BUILD_TIMEOUT) {
+        let _ = session_manager.set_baseline_build_ok(&config.session_id, ok);
+    }
+}
+
+pub fn run_full_session(
+    config: SessionConfig,
+    session_manager: &SessionManager,
+) -> Result<SessionResult, OrchestratorError> {
+    run_session(config, session_manager, false)
+}
+
+/// Discards whatever is in the session's work dir and runs the session again
+/// from a fresh clone. The caller must already hold the checkout claim; it is
+/// released when the run ends.
+pub fn rerun_from_fresh_clone(
+    config: SessionConfig,
+    session_manager: &SessionManager,
+) -> Result<SessionResult, OrchestratorError> {
+    run_session(config, session_manager, true)
+}
+
+fn run_session(
+    config: SessionConfig,
+    session_manager: &SessionManager,
+    fresh_checkout: bool,
+) -> Result<SessionResult, OrchestratorError> {
+    let enter_phase = |phase| {
+        let _ = session_manager.enter_phase(&config.session_id, phase);
+    };
+
+    enter_phase(SessionPhase::Clone);
+    let claim = if fresh_checkout {
+        CheckoutClaim::adopt(session_manager, &config.session_id)
+    } else {
+        CheckoutClaim::acquire(session_manager, &config.session_id)
+    };
+    let (work_dir, branch_name) =
+        prepare_checkout(&config, session_manager, &claim, fresh_checkout)?;
+    record_baseline_build(&config, session_manager, &work_dir);
+    // Taken after the baseline build so its artifacts aren't attributed to
+    // Claude.
+    let pre_run = snapshot_worktree(&work_dir)
+        .map_err(|e| eprintln!("Warning: Failed to snapshot {}: {}", work_dir.display(), e))
+        .ok();
 
+    enter_phase(SessionPhase::Run);
+    let output = session_manager.output_buffer(&config.session_id).ok();
+    // Read fresh, so the length check at spawn may have seen an older file.
+    let guidelines = session_guidelines(
+        Path::new(&config.git_directory),
+        config.effective.guidelines_file.as_deref(),
+    );
+    let length = InstructionsLength::measure(
+        &config.user_instructions,
+        config.additional_instructions.as_deref(),
+        config.instructions_file_content.as_deref(),
+        guidelines.as_deref(),
+        config.effective.max_instructions_len,
+    );
+    if let Some(error) = length.error() {
+        if config.effective.strict_instructions {
+            return Err(OrchestratorError::InstructionsTooLong(error));
+        }
+        eprintln!("Warning: {}", error);
+    }
     let instructions = compose_instructions(
         &config.user_instructions,
         config.additional_instructions.as_deref(),
         config.instructions_file_content.as_deref(),
+        guidelines.as_deref(),
     );
 
-    run_claude_and_wait(&work_dir, &instructions)?;
+    // Sessions are only kept on disk once the queue dir is set.
+    let mut log =
+        session_manager
+            .queue_dir()
+            .and_then(|dir| match create_log_in(&dir, &config.session_id) {
+                Ok(log) => Some(log),
+                Err(e) => {
+                    eprintln!("Warning: Failed to create the session log: {}", e);
+                    None
+                }
+            });
+    session_manager.wait_for_spawn_slot();
+    let result = run_claude_and_wait(
+        &work_dir,
+        &instructions,
+        &config.effective.allowed_tools,
+        &config.effective.allowed_commands,
+        config.effective.model.as_deref(),
+        ProcessLimits {
+            timeout: config.effective.timeout_secs.map(Duration::from_secs),
+            max_memory_mb: config.effective.max_memory_mb,
+        },
+        |pid| {
+            let _ = session_manager.set_working(&config.session_id, pid);
+        },
+        |line| {
+            for command in detect_test_runs(line, &config.effective.allowed_commands)
+                .unwrap_or_default()
+            {
+                let _ = session_manager.add_output_marker(
+                    &config.session_id,
+                    OutputMarkerKind::TestRunStarted,
+                    command,
+                );
+            }
+            for line in stream_json_display_lines(line) {
+                if let Some(output) = output.as_deref() {
+                    output.push_line(&line);
+                }
+                if let Some(log) = log.as_mut() {
+                    let _ = writeln!(log, "{}", line);
+                }
+                session_manager.emit(
+                    SESSION_OUTPUT_EVENT,
+                    serde_json::json!({ "session_id": config.session_id, "line": line }),
+                );
+            }
+        },
+    );
+    let _ = session_manager.clear_process_id(&config.session_id);
+    let result = result?;
+    if let Some(model) = reported_model(&result.stdout) {
+        let _ = session_manager.set_model(&config.session_id, Some(model));
+    }
+    let test_runs = detect_test_runs(&result.stdout, &config.effective.allowed_commands);
+    let _ = session_manager.set_test_runs(&config.session_id, test_runs);
 
-    let commit_message = format!("feat: {}", config.user_instructions);
-    commit_and_push(&work_dir, &commit_message)?;
+    let commit_message = session_commit_message(&config);
+    enter_phase(SessionPhase::Commit);
+    let editable_paths = &config.effective.editable_paths;
+    let changed_paths = match &pre_run {
+        Some(snapshot) => stage_changes_since(&work_dir, snapshot)?,
+        // Without a snapshot, everything dirty is Claude's to check.
+        None if !editable_paths.is_empty() => {
+            stage_changes_since(&work_dir, &WorkTreeSnapshot::default())?
+        }
+        None => {
+            stage_all_changes(&work_dir)?;
+            Vec::new()
+        }
+    };
+    let out_of_scope = out_of_scope_paths(&changed_paths, editable_paths);
+    if !out_of_scope.is_empty() {
+        return Err(OrchestratorError::OutOfScopeEdits(out_of_scope));
+    }
+    if let Some(limit) = config.effective.max_changed_files {
+        check_changed_file_limit(staged_change_count(&work_dir)?, Some(limit))?;
+    }
+    if !has_changes(&work_dir)? {
+        // Nothing to commit, so no branch is pushed and no PR is opened.
+        cleanup_session_dir(&work_dir)?;
+        return Ok(SessionResult {
+            pr_url: None,
+            branch_name,
+        });
+    }
+    let identity = CommitIdentity {
+        name: config.effective.bot_author_name.clone(),
+        email: config.effective.bot_author_email.clone(),
+    };
+    create_commit(&work_dir, &commit_message, &identity)?;
+    record_diffstat(&config.session_id, session_manager, &work_dir);
+    let will_conflict = record_merge_conflicts(&config, session_manager, &work_dir);
 
-    let pr_title = config.user_instructions.clone();
-    let pr_body = format!(
-        "## Summary\n\nThis PR was generated by Claude via the Dreamal `/claude` macro.\n\n## Instructions\n\n{}\n",
-        config.user_instructions
-    );
+    enter_phase(SessionPhase::Push);
+    push_to_remote(&work_dir, &branch_name)?;
+
+    enter_phase(SessionPhase::PullRequest);
+
+    let changed: Vec<String> = changed_paths
+        .iter()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .collect();
+    let payload = compose_pr_payload(&config, &work_dir, &branch_name, &changed, will_conflict);
+    let pr_url = match get_remote_host(&work_dir)? {
+        RemoteHost::GitLab => create_merge_request(
+            &work_dir,
+            &payload.title,
+            &payload.body,
+            &payload.head,
+            &payload.base,
+            payload.draft,
+            &payload.labels,
+        )?,
+        RemoteHost::GitHub => {
+            let pr_url = create_pull_request(
+                &work_dir,
+                &payload.title,
+                &payload.body,
+                &payload.head,
+                &payload.base,
+                payload.draft,
+            )?;
 
-    let pr_url = create_pull_request(&work_dir, &pr_title, &pr_body, &branch_name, &config.base_branch)?;
+            if let Err(e) = add_labels(&pr_url, &payload.labels) {
+                eprintln!("Warning: Failed to label {}: {}", pr_url, e);
+            }
+
+            if config.effective.request_codeowner_reviewers {
+                // The PR exists at this point; a failed request shouldn't fail the session.
+                if let Err(e) = request_reviewers(&pr_url, &payload.reviewers) {
+                    eprintln!("Warning: Failed to request reviewers on {}: {}", pr_url, e);
+                }
+            }
+            pr_url
+        }
+    };
 
     cleanup_session_dir(&work_dir)?;
 
-    Ok(SessionResult { pr_url, branch_name })
+    Ok(SessionResult {
+        pr_url: Some(pr_url),
+        branch_name,
+    })
 }
 
 pub fn cleanup_failed_session(session_id: &str) -> Result<(), OrchestratorError> {
-    use crate::git_ops::cleanup::cleanup_session;
     cleanup_session(session_id)?;
     Ok(())
 }
@@ -100,30 +588,427 @@ pub fn cleanup_failed_session(session_id: &str) -> Result<(), OrchestratorError>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude_session::config::resolve_config;
+    use crate::claude_session::types::SessionStatus;
+    use crate::test_support::{setup_test_repo, test_manager};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_changed_file_limit() {
+        assert!(check_changed_file_limit(4, Some(5)).is_ok());
+        assert!(check_changed_file_limit(5, Some(5)).is_ok());
+        assert!(check_changed_file_limit(500, None).is_ok());
+
+        let error = check_changed_file_limit(6, Some(5)).unwrap_err();
+        assert!(error.keeps_checkout());
+        assert!(error
+            .to_string()
+            .contains("Claude changed 6 files, exceeding the limit of 5"));
+    }
+
+    #[test]
+    fn test_clone_with_limit_bounds_concurrent_clones() {
+        let manager = SessionManager::new();
+        manager.set_clone_limit(1);
+
+        let current = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let manager = &manager;
+                let current = current.clone();
+                let peak = peak.clone();
+                scope.spawn(move || {
+                    clone_with_limit(manager.clone_slots(), || {
+                        let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        current.fetch_sub(1, Ordering::SeqCst);
+                        Ok(PathBuf::from(format!("/tmp/session-{}", i)))
+                    })
+                    .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
 
     #[test]
     fn test_session_config_creation() {
-        let config = SessionConfig {
-            session_id: "test-123".to_string(),
-            git_directory: "/path/to/repo".to_string(),
-            user_instructions: "Add dark mode".to_string(),
-            additional_instructions: Some("Use CSS variables".to_string()),
-            instructions_file_content: None,
-            base_branch: "main".to_string(),
-        };
+        let (_dreamal_dir, manager) = test_manager();
+        let effective = resolve_config(&HashMap::new(), &HashMap::new());
+        let repo = Path::new("/path/to/repo");
+        let config = session_for(&manager, repo, "Add dark mode", effective);
 
-        assert_eq!(config.session_id, "test-123");
+        assert!(config.session_id.starts_with("test-"));
+        assert_eq!(config.git_directory, "/path/to/repo");
         assert_eq!(config.user_instructions, "Add dark mode");
+        assert!(manager.get_session_info(&config.session_id).is_ok());
     }
 
     #[test]
     fn test_session_result_creation() {
         let result = SessionResult {
-            pr_url: "https://github.com/owner/repo/pull/1".to_string(),
+            pr_url: Some("https://github.com/owner/repo/pull/1".to_string()),
             branch_name: "claude/add-dark-mode-123".to_string(),
         };
 
-        assert!(result.pr_url.contains("github.com"));
+        assert!(result.pr_url.unwrap().contains("github.com"));
         assert!(result.branch_name.starts_with("claude/"));
     }
+
+    #[test]
+    fn test_baseline_build_records_broken_repo() {
+        let (source, _) = setup_test_repo();
+        std::fs::write(source.path().join("Cargo.toml"), "[package\nname = ").unwrap();
+
+        let (_dreamal_dir, manager) = test_manager();
+        let mut effective = resolve_config(&HashMap::new(), &HashMap::new());
+        effective.baseline_build_command = Some("cargo build --offline".to_string());
+        let config = session_for(&manager, source.path(), "Fix the build", effective);
+
+        record_baseline_build(&config, &manager, source.path());
+        let info = manager.get_session_info(&config.session_id).unwrap();
+        assert_eq!(info.baseline_build_ok, Some(false));
+
+        let timeout = Duration::from_secs(30);
+        assert_eq!(run_baseline_build(source.path(), "git status", timeout), Some(true));
+        assert_eq!(
+            run_baseline_build(source.path(), "dreamal-no-such-build-tool", timeout),
+            None
+        );
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            run_baseline_build(source.path(), "sleep 30", Duration::from_millis(200)),
+            Some(false)
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_prepare_fresh_checkout_replaces_corrupted_work_dir() {
+        let (source, _) = setup_test_repo();
+        let (_dreamal_dir, manager) = test_manager();
+        let effective = resolve_config(&HashMap::new(), &HashMap::new());
+        let config = session_for(&manager, source.path(), "Add dark mode", effective);
+        let session_id = config.session_id.clone();
+        let work_dir = manager.session_dir(&session_id).unwrap();
+        manager.set_error(&session_id, "interrupted copy".to_string()).unwrap();
+
+        std::fs::create_dir_all(work_dir.join(".git")).unwrap();
+        std::fs::write(work_dir.join("partial.bin"), "garbage").unwrap();
+
+        manager.reset_for_rerun(&session_id).unwrap();
+        let claim = CheckoutClaim::acquire(&manager, &session_id);
+        let (checkout, branch_name) = prepare_checkout(&config, &manager, &claim, true).unwrap();
+
+        let repo = git2::Repository::open(&checkout).unwrap();
+        assert_eq!(repo.head().unwrap().shorthand(), Some(branch_name.as_str()));
+        assert!(checkout.join("test.txt").exists());
+        assert!(!checkout.join("partial.bin").exists());
+
+        let info = manager.get_session_info(&session_id).unwrap();
+        assert_eq!(info.status, SessionStatus::Initializing);
+        assert_eq!(manager.get_branch_name(&session_id).unwrap(), branch_name);
+
+        drop(claim);
+    }
+
+    /// Points this thread's sessions at a stand-in for the `claude` CLI that
+    /// runs the user instructions, up to the first `## ` heading, as a shell
+    /// script in the checkout. It refuses to run unless asked for stream-json
+    /// output.
+    #[cfg(unix)]
+    fn use_fake_claude() -> tempfile::TempDir {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("claude");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             case \" $* \" in *\" --output-format stream-json \"*) ;; *) exit 64 ;; esac\n\
+             for last; do :; done\n\
+             printf '%s\\n' \"$last\" | sed '/^## /,$d' | sh\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        crate::claude_session::process::FAKE_CLAUDE.with(|fake| *fake.borrow_mut() = Some(script));
+        dir
+    }
+
+    /// A source repo whose `origin` is a local bare repo with its `main`.
+    #[cfg(unix)]
+    fn setup_source_with_origin() -> (tempfile::TempDir, tempfile::TempDir) {
+        let origin = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(origin.path()).unwrap();
+        let (source, _) = setup_test_repo();
+        let repo = git2::Repository::open(source.path()).unwrap();
+        repo.remote("origin", &origin.path().to_string_lossy())
+            .unwrap();
+        let status = std::process::Command::new("git")
+            .current_dir(source.path())
+            .args(["push", "--quiet", "origin", "HEAD:refs/heads/main"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        (source, origin)
+    }
+
+    fn session_for(
+        manager: &SessionManager,
+        source: &Path,
+        instructions: &str,
+        effective: EffectiveConfig,
+    ) -> SessionConfig {
+        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        let work_dir = manager.session_dir(&session_id).unwrap();
+        manager
+            .create_session(
+                session_id.clone(),
+                source.to_string_lossy().to_string(),
+                instructions.to_string(),
+                work_dir,
+                String::new(),
+            )
+            .unwrap();
+        SessionConfig {
+            session_id,
+            git_directory: source.to_string_lossy().to_string(),
+            user_instructions: instructions.to_string(),
+            additional_instructions: None,
+            instructions_file_content: None,
+            branch_name: None,
+            commit_message: None,
+            effective,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_session_marks_test_runs_from_stream_json() {
+        let _claude = use_fake_claude();
+        let (source, _) = setup_test_repo();
+        let (_dreamal_dir, manager) = test_manager();
+        let config = session_for(
+            &manager,
+            source.path(),
+            r#"printf '%s\n' '{"type":"system","subtype":"init","model":"claude-sonnet-4-5"}'
+printf '%s\n' '{"type":"assistant","message":{"content":[{"type":"text","text":"Checking"},{"type":"tool_use","name":"Bash","input":{"command":"cargo test --lib"}}]}}'
+printf '%s\n' '{"type":"result","subtype":"success","is_error":false,"result":"Nothing to change"}'"#,
+            resolve_config(&HashMap::new(), &HashMap::new()),
+        );
+        let session_id = config.session_id.clone();
+
+        let result = run_full_session(config, &manager).unwrap();
+        assert!(result.pr_url.is_none());
+
+        let info = manager.get_session_info(&session_id).unwrap();
+        assert_eq!(info.tests_run_by_claude, Some(true));
+        assert_eq!(info.claude_test_commands, vec!["cargo test --lib"]);
+        let timeline = crate::claude_session::types::session_timeline(&info);
+        assert!(timeline.iter().any(|event| matches!(
+            event,
+            crate::claude_session::types::TimelineEvent::Marker {
+                kind: OutputMarkerKind::TestRunStarted,
+                detail,
+                ..
+            } if detail == "cargo test --lib"
+        )));
+        let output = manager.output_buffer(&session_id).unwrap();
+        assert_eq!(output.last(10), vec!["Checking", "$ cargo test --lib"]);
+        let log = crate::claude_session::artifacts::session_artifacts_dir_in(
+            &manager.queue_dir().unwrap(),
+            &session_id,
+        )
+        .join(crate::claude_session::artifacts::LOG_FILE);
+        assert_eq!(
+            std::fs::read_to_string(log).unwrap(),
+            "Checking\n$ cargo test --lib\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_session_records_reported_model() {
+        let _claude = use_fake_claude();
+        let (source, _) = setup_test_repo();
+        let (_dreamal_dir, manager) = test_manager();
+        let mut effective = resolve_config(&HashMap::new(), &HashMap::new());
+        effective.model = Some("sonnet".to_string());
+        let config = session_for(
+            &manager,
+            source.path(),
+            r#"printf '%s\n' '{"type":"system","subtype":"init","model":"claude-sonnet-4-5"}'"#,
+            effective,
+        );
+        let session_id = config.session_id.clone();
+        // As recorded at spawn, until Claude names the model it ran.
+        manager
+            .set_model(&session_id, config.effective.model.clone())
+            .unwrap();
+
+        run_full_session(config, &manager).unwrap();
+
+        let info = manager.get_session_info(&session_id).unwrap();
+        assert_eq!(info.model.as_deref(), Some("claude-sonnet-4-5"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_commit_message_is_committed_verbatim() {
+        let _claude = use_fake_claude();
+        let (source, origin) = setup_source_with_origin();
+        let (_dreamal_dir, manager) = test_manager();
+        let mut effective = resolve_config(&HashMap::new(), &HashMap::new());
+        effective.commit_message_prefix = Some("[JIRA-1]".to_string());
+        let mut config = session_for(&manager, source.path(), "echo dark > test.txt", effective);
+        assert_eq!(
+            session_commit_message(&config),
+            "[JIRA-1] feat: echo dark > test.txt"
+        );
+        config.commit_message = Some("Switch the theme to dark\n\nUsers asked for it.".to_string());
+        let session_id = config.session_id.clone();
+
+        // The local origin is neither GitHub nor GitLab, so no PR is opened.
+        run_full_session(config, &manager).unwrap_err();
+
+        let branch = manager.get_branch_name(&session_id).unwrap();
+        let origin = git2::Repository::open_bare(origin.path()).unwrap();
+        let pushed = origin
+            .find_branch(&branch, git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(
+            pushed.message(),
+            Some("Switch the theme to dark\n\nUsers asked for it.")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_session_commits_up_to_max_changed_files() {
+        let _claude = use_fake_claude();
+        let (source, origin) = setup_source_with_origin();
+        let (_dreamal_dir, manager) = test_manager();
+        let mut effective = resolve_config(&HashMap::new(), &HashMap::new());
+        effective.max_changed_files = Some(2);
+        let config = session_for(
+            &manager,
+            source.path(),
+            "echo one > one.txt\necho two > two.txt",
+            effective,
+        );
+        let session_id = config.session_id.clone();
+
+        // The local origin is neither GitHub nor GitLab, so no PR is opened.
+        let error = run_full_session(config, &manager).unwrap_err();
+        assert!(!error.keeps_checkout(), "{}", error);
+
+        let branch = manager.get_branch_name(&session_id).unwrap();
+        let origin = git2::Repository::open_bare(origin.path()).unwrap();
+        let pushed = origin
+            .find_branch(&branch, git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        assert!(pushed.tree().unwrap().get_path(Path::new("two.txt")).is_ok());
+        assert_eq!(
+            manager.get_session_info(&session_id).unwrap().diffstat,
+            Some(DiffStat {
+                files: 2,
+                insertions: 2,
+                deletions: 0
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_session_refuses_over_max_changed_files() {
+        let _claude = use_fake_claude();
+        let (source, origin) = setup_source_with_origin();
+        let (_dreamal_dir, manager) = test_manager();
+        let mut effective = resolve_config(&HashMap::new(), &HashMap::new());
+        effective.max_changed_files = Some(2);
+        let config = session_for(
+            &manager,
+            source.path(),
+            "echo one > one.txt\necho two > two.txt\necho three > three.txt",
+            effective,
+        );
+        let session_id = config.session_id.clone();
+
+        let error = run_full_session(config, &manager).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                OrchestratorError::TooManyChangedFiles {
+                    changed: 3,
+                    limit: 2
+                }
+            ),
+            "{}",
+            error
+        );
+        assert!(error.keeps_checkout());
+
+        let work_dir = manager.get_work_dir(&session_id).unwrap();
+        assert!(work_dir.join("three.txt").exists());
+        let checkout = git2::Repository::open(&work_dir).unwrap();
+        let head = checkout.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_count(), 0);
+        let origin = git2::Repository::open_bare(origin.path()).unwrap();
+        let branch = manager.get_branch_name(&session_id).unwrap();
+        assert!(origin.find_branch(&branch, git2::BranchType::Local).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_session_in_checkout_libgit2_cannot_open() {
+        let _claude = use_fake_claude();
+        let (source, origin) = setup_source_with_origin();
+        let (_dreamal_dir, manager) = test_manager();
+        // git reads a checkout with an extension libgit2 doesn't know, so every
+        // step after Claude runs has to take the git CLI path.
+        let config = session_for(
+            &manager,
+            source.path(),
+            "git config core.repositoryformatversion 1\n\
+             git config extensions.preciousObjects true\n\
+             echo dark > theme.txt",
+            resolve_config(&HashMap::new(), &HashMap::new()),
+        );
+        let session_id = config.session_id.clone();
+
+        // The local origin is neither GitHub nor GitLab, so no PR is opened.
+        let error = run_full_session(config, &manager).unwrap_err();
+        assert!(error.to_string().contains("remote"), "{}", error);
+
+        let work_dir = manager.get_work_dir(&session_id).unwrap();
+        assert!(crate::git_ops::cli::open_or_fallback(&work_dir)
+            .unwrap()
+            .is_none());
+        let branch = manager.get_branch_name(&session_id).unwrap();
+        let origin = git2::Repository::open_bare(origin.path()).unwrap();
+        let pushed = origin
+            .find_branch(&branch, git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        let theme = pushed.tree().unwrap().get_path(Path::new("theme.txt")).unwrap();
+        let blob = origin.find_blob(theme.id()).unwrap();
+        assert_eq!(blob.content(), b"dark\n");
+        assert_eq!(pushed.tree().unwrap().len(), 2);
+    }
 }
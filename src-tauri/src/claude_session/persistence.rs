@@ -0,0 +1,363 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::artifacts::session_artifacts_dir_in;
+use super::orchestrator::SessionConfig;
+use super::types::SessionInfo;
+
+#[derive(Debug)]
+pub enum PersistenceError {
+    HomeNotFound,
+    IoError(std::io::Error),
+    ParseError(String),
+    NotFound(String),
+}
+
+impl std::fmt::Display for PersistenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistenceError::HomeNotFound => write!(f, "Could not determine home directory"),
+            PersistenceError::IoError(e) => write!(f, "IO error: {}", e),
+            PersistenceError::ParseError(msg) => write!(f, "Failed to parse session: {}", msg),
+            PersistenceError::NotFound(id) => write!(f, "No saved session: {}", id),
+        }
+    }
+}
+
+impl From<std::io::Error> for PersistenceError {
+    fn from(e: std::io::Error) -> Self {
+        PersistenceError::IoError(e)
+    }
+}
+
+/// Subdirectory of the sessions dir holding archived sessions. `load_all_in`
+/// only reads files, so archived sessions stay out of the normal list.
+pub const ARCHIVE_DIR: &str = "archive";
+
+pub fn get_sessions_dir() -> Result<PathBuf, PersistenceError> {
+    crate::git_ops::get_dreamal_dir()
+        .map(|dir| dir.join("sessions"))
+        .map_err(|_| PersistenceError::HomeNotFound)
+}
+
+pub fn save_session_info_in(dir: &Path, info: &SessionInfo) -> Result<(), PersistenceError> {
+    fs::create_dir_all(dir)?;
+
+    let content = serde_json::to_string_pretty(info)
+        .map_err(|e| PersistenceError::ParseError(e.to_string()))?;
+
+    let path = dir.join(format!("{}.json", info.id));
+    let tmp_path = dir.join(format!("{}.json.tmp", info.id));
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, &path)?;
+
+    Ok(())
+}
+
+pub fn load_session_info_in(dir: &Path, id: &str) -> Result<SessionInfo, PersistenceError> {
+    let content = fs::read_to_string(dir.join(format!("{}.json", id)))?;
+    serde_json::from_str(&content).map_err(|e| PersistenceError::ParseError(e.to_string()))
+}
+
+pub fn load_all_in(dir: &Path) -> Result<Vec<SessionInfo>, PersistenceError> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut sessions = Vec::new();
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+
+        let parsed = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SessionInfo>(&content).ok());
+
+        match parsed {
+            Some(info) => sessions.push(info),
+            None => eprintln!(
+                "Warning: Skipping unreadable session file {}",
+                path.display()
+            ),
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Removes session `id`'s record and its saved launch config.
+pub fn delete_session_info_in(dir: &Path, id: &str) -> Result<(), PersistenceError> {
+    for path in [
+        dir.join(format!("{}.json", id)),
+        launch_config_path(dir, id),
+    ] {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Files belonging to session `id`: its JSON record, any other `<id>.*` file
+/// saved beside it, and its artifacts dir.
+fn session_files(dir: &Path, id: &str) -> Result<Vec<PathBuf>, PersistenceError> {
+    let prefix = format!("{}.", id);
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let owned = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&prefix));
+        if owned && path.is_file() {
+            files.push(path);
+        }
+    }
+    let artifacts = session_artifacts_dir_in(dir, id);
+    if artifacts.is_dir() {
+        files.push(artifacts);
+    }
+
+    Ok(files)
+}
+
+fn move_session_files(from: &Path, to: &Path, id: &str) -> Result<(), PersistenceError> {
+    if !from.join(format!("{}.json", id)).is_file() {
+        return Err(PersistenceError::NotFound(id.to_string()));
+    }
+
+    fs::create_dir_all(to)?;
+    for path in session_files(from, id)? {
+        if let Some(name) = path.file_name() {
+            fs::rename(&path, to.join(name))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn archive_session_info_in(dir: &Path, id: &str) -> Result<(), PersistenceError> {
+    move_session_files(dir, &dir.join(ARCHIVE_DIR), id)
+}
+
+pub fn unarchive_session_info_in(dir: &Path, id: &str) -> Result<SessionInfo, PersistenceError> {
+    move_session_files(&dir.join(ARCHIVE_DIR), dir, id)?;
+    load_session_info_in(dir, id)
+}
+
+pub fn load_archived_in(dir: &Path) -> Result<Vec<SessionInfo>, PersistenceError> {
+    load_all_in(&dir.join(ARCHIVE_DIR))
+}
+
+/// Spawn inputs of a session, saved as `<id>.launch` so after a restart it
+/// can be started again if it was queued, or retried if it failed. The
+/// extension keeps it out of `load_all_in`.
+fn launch_config_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.launch", id))
+}
+
+pub fn save_launch_config_in(dir: &Path, config: &SessionConfig) -> Result<(), PersistenceError> {
+    fs::create_dir_all(dir)?;
+
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| PersistenceError::ParseError(e.to_string()))?;
+    fs::write(launch_config_path(dir, &config.session_id), content)?;
+
+    Ok(())
+}
+
+pub fn load_launch_config_in(dir: &Path, id: &str) -> Result<SessionConfig, PersistenceError> {
+    let path = launch_config_path(dir, id);
+    if !path.is_file() {
+        return Err(PersistenceError::NotFound(id.to_string()));
+    }
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| PersistenceError::ParseError(e.to_string()))
+}
+
+/// Present while the scheduler is paused, so a pause survives a restart.
+const SCHEDULER_PAUSED_FILE: &str = "scheduler.paused";
+
+pub fn save_scheduler_paused_in(dir: &Path, paused: bool) -> Result<(), PersistenceError> {
+    let path = dir.join(SCHEDULER_PAUSED_FILE);
+    if paused {
+        fs::create_dir_all(dir)?;
+        fs::write(path, "")?;
+    } else if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+pub fn load_scheduler_paused_in(dir: &Path) -> bool {
+    dir.join(SCHEDULER_PAUSED_FILE).is_file()
+}
+
+pub fn save_session_info(info: &SessionInfo) -> Result<(), PersistenceError> {
+    save_session_info_in(&get_sessions_dir()?, info)
+}
+
+pub fn load_all() -> Result<Vec<SessionInfo>, PersistenceError> {
+    load_all_in(&get_sessions_dir()?)
+}
+
+pub fn delete_session_info(id: &str) -> Result<(), PersistenceError> {
+    delete_session_info_in(&get_sessions_dir()?, id)
+}
+
+pub fn archive_session_info(id: &str) -> Result<(), PersistenceError> {
+    archive_session_info_in(&get_sessions_dir()?, id)
+}
+
+pub fn unarchive_session_info(id: &str) -> Result<SessionInfo, PersistenceError> {
+    unarchive_session_info_in(&get_sessions_dir()?, id)
+}
+
+pub fn load_archived() -> Result<Vec<SessionInfo>, PersistenceError> {
+    load_archived_in(&get_sessions_dir()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude_session::types::{PhaseTransition, SessionPhase, SessionStatus};
+
+    fn sample_info(id: &str) -> SessionInfo {
+        SessionInfo {
+            id: id.to_string(),
+            status: SessionStatus::Completed,
+            pr_url: Some("https://github.com/owner/repo/pull/1".to_string()),
+            error_message: None,
+            git_directory: "/path/to/repo".to_string(),
+            instructions: "Add feature".to_string(),
+            created_at: 1700000000,
+            completed_at: Some(1700000090),
+            config: None,
+            phases: Vec::new(),
+            baseline_build_ok: None,
+            will_conflict: false,
+            conflicting_paths: Vec::new(),
+            locked: false,
+            tests_run_by_claude: None,
+            claude_test_commands: Vec::new(),
+            queued_at_ms: None,
+            queue_position: None,
+            resumed_from_queue: false,
+            batch_id: None,
+            retried_as: None,
+            status_changes: Vec::new(),
+            markers: Vec::new(),
+            model: None,
+            diffstat: None,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_session_info() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let mut info = sample_info("abc");
+        info.phases.push(PhaseTransition {
+            phase: SessionPhase::Finished,
+            at_ms: 1700000090000,
+        });
+        save_session_info_in(temp_dir.path(), &info).unwrap();
+        let loaded = load_session_info_in(temp_dir.path(), "abc").unwrap();
+
+        assert_eq!(loaded.id, "abc");
+        assert_eq!(loaded.status, SessionStatus::Completed);
+        assert_eq!(loaded.pr_url, sample_info("abc").pr_url);
+        assert_eq!(loaded.completed_at, Some(1700000090));
+        assert_eq!(loaded.duration_secs(), Some(90));
+        assert!(!temp_dir.path().join("abc.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_load_all_skips_invalid_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        save_session_info_in(temp_dir.path(), &sample_info("one")).unwrap();
+        save_session_info_in(temp_dir.path(), &sample_info("two")).unwrap();
+        fs::write(temp_dir.path().join("broken.json"), "{not json").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let mut ids: Vec<String> = load_all_in(temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        ids.sort();
+
+        assert_eq!(ids, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_delete_session_info() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        save_session_info_in(temp_dir.path(), &sample_info("gone")).unwrap();
+        fs::write(launch_config_path(temp_dir.path(), "gone"), "{}").unwrap();
+        delete_session_info_in(temp_dir.path(), "gone").unwrap();
+        delete_session_info_in(temp_dir.path(), "never-existed").unwrap();
+
+        assert!(load_session_info_in(temp_dir.path(), "gone").is_err());
+        assert!(!launch_config_path(temp_dir.path(), "gone").exists());
+    }
+
+    #[test]
+    fn test_load_all_missing_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sessions = load_all_in(&temp_dir.path().join("missing")).unwrap();
+        assert!(sessions.is_empty());
+    }
+
+    #[test]
+    fn test_archive_and_unarchive_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+
+        save_session_info_in(dir, &sample_info("old")).unwrap();
+        save_session_info_in(dir, &sample_info("kept")).unwrap();
+        let artifacts = session_artifacts_dir_in(dir, "old");
+        fs::create_dir_all(&artifacts).unwrap();
+        fs::write(artifacts.join("claude.log"), "claude output").unwrap();
+
+        archive_session_info_in(dir, "old").unwrap();
+
+        let active: Vec<String> = load_all_in(dir)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(active, vec!["kept"]);
+        let archived = load_archived_in(dir).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, "old");
+        assert!(dir.join("archive/old/claude.log").exists());
+        assert!(!artifacts.exists());
+
+        let restored = unarchive_session_info_in(dir, "old").unwrap();
+        assert_eq!(restored.pr_url, sample_info("old").pr_url);
+        assert!(load_archived_in(dir).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(artifacts.join("claude.log")).unwrap(),
+            "claude output"
+        );
+        assert_eq!(load_all_in(dir).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_archive_missing_session() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            archive_session_info_in(temp_dir.path(), "missing"),
+            Err(PersistenceError::NotFound(_))
+        ));
+        assert!(unarchive_session_info_in(temp_dir.path(), "missing").is_err());
+    }
+}
@@ -1,12 +1,27 @@
+use std::collections::VecDeque;
+use std::fs;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
 
 #[derive(Debug)]
 pub enum ProcessError {
     SpawnFailed(String),
     IoError(std::io::Error),
     ProcessFailed { exit_code: Option<i32>, stderr: String },
+    CommandNotAllowed(String),
+    Timeout(Duration),
+    /// Claude rejected the API key or login.
+    AuthFailed,
+    /// The account has no credits left.
+    CreditsExhausted,
+    /// Claude or a tool it ran hit the `max_memory_mb` cap, in MB.
+    MemoryLimitExceeded(u64),
 }
 
 impl std::fmt::Display for ProcessError {
@@ -17,6 +32,27 @@ impl std::fmt::Display for ProcessError {
             ProcessError::ProcessFailed { exit_code, stderr } => {
                 write!(f, "Claude process failed (exit code: {:?}): {}", exit_code, stderr)
             }
+            ProcessError::CommandNotAllowed(cmd) => {
+                write!(f, "Command is not in the allowed commands list: {}", cmd)
+            }
+            ProcessError::Timeout(timeout) => write!(
+                f,
+                "Claude did not finish within {} seconds and was stopped",
+                timeout.as_secs()
+            ),
+            ProcessError::AuthFailed => write!(
+                f,
+                "Claude authentication failed — check your API key (ANTHROPIC_API_KEY) or run `claude login`"
+            ),
+            ProcessError::CreditsExhausted => write!(
+                f,
+                "Claude has no credits left — add credits or check your plan's usage limits"
+            ),
+            ProcessError::MemoryLimitExceeded(mb) => write!(
+                f,
+                "Claude ran out of memory under the {} MB limit and was stopped — raise max_memory_mb if the task needs more",
+                mb
+            ),
         }
     }
 }
@@ -27,8 +63,10 @@ impl From<std::io::Error> for ProcessError {
     }
 }
 
-// TODO: Make this configurable via .dreamal/allowed-commands.json in the target repo
-const ALLOWED_BASH_PATTERNS: &[&str] = &[
+/// Bash commands allowed when no `allowed_commands` are configured. A repo's
+/// `.dreamal/allowed-commands.json` adds to whichever list applies when the
+/// user trusts it.
+pub const ALLOWED_BASH_PATTERNS: &[&str] = &[
     "npm run test",
     "npm run test:*",
     "npm test",
@@ -38,11 +76,211 @@ const ALLOWED_BASH_PATTERNS: &[&str] = &[
     "jest",
 ];
 
-// TODO: Make the system prompt suffix configurable via settings
+pub const DEFAULT_ALLOWED_TOOLS: &[&str] = &["Edit", "Write", "Read"];
+
+pub fn is_command_allowed<S: AsRef<str>>(command: &str, patterns: &[S]) -> bool {
+    let command = command.split_whitespace().collect::<Vec<_>>().join(" ");
+    if command.is_empty() {
+        return false;
+    }
+
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.as_ref();
+        match pattern.strip_suffix(":*") {
+            Some(prefix) => {
+                command == prefix
+                    || command
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with([' ', ':']))
+            }
+            None => command == pattern,
+        }
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub fn run_allowed_command<S: AsRef<str>>(
+    work_dir: &Path,
+    command: &str,
+    patterns: &[S],
+) -> Result<CommandOutput, ProcessError> {
+    if !is_command_allowed(command, patterns) {
+        return Err(ProcessError::CommandNotAllowed(command.to_string()));
+    }
+
+    run_command(work_dir, command)
+}
+
+/// Runs `command` split on whitespace, without a shell. Callers are responsible
+/// for deciding the command is safe to run.
+pub fn run_command(work_dir: &Path, command: &str) -> Result<CommandOutput, ProcessError> {
+    let mut parts = command.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| ProcessError::SpawnFailed("Empty command".to_string()))?;
+
+    let output = Command::new(program)
+        .args(parts)
+        .current_dir(work_dir)
+        .output()
+        .map_err(|e| ProcessError::SpawnFailed(format!("Failed to run {}: {}", program, e)))?;
+
+    Ok(CommandOutput {
+        exit_code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    })
+}
+
+/// Like `run_command`, but kills the command and everything it spawned if it
+/// is still running after `timeout`.
+pub fn run_command_with_timeout(
+    work_dir: &Path,
+    command: &str,
+    timeout: Duration,
+) -> Result<CommandOutput, ProcessError> {
+    let mut parts = command.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| ProcessError::SpawnFailed("Empty command".to_string()))?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(parts)
+        .current_dir(work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    isolate_process_group(&mut cmd);
+    let child = cmd
+        .spawn()
+        .map_err(|e| ProcessError::SpawnFailed(format!("Failed to run {}: {}", program, e)))?;
+
+    let result = wait_for_process_with_timeout(child, Some(timeout), |_| {})?;
+    Ok(CommandOutput {
+        exit_code: result.exit_status.code(),
+        stdout: result.stdout,
+        stderr: result.stderr,
+    })
+}
+
+const DISALLOWED_OPERATIONS: &[(&str, &str)] = &[
+    ("git push", "pushes to a remote"),
+    ("git commit", "creates commits"),
+    ("git reset", "rewrites branch history"),
+    ("git rebase", "rewrites branch history"),
+    ("git merge", "merges branches"),
+    ("git checkout", "switches branches"),
+    ("git switch", "switches branches"),
+    ("git branch -d", "deletes branches"),
+    ("git clean", "deletes untracked files"),
+    ("rm -rf", "force-deletes files"),
+];
+
+/// Flags instructions that explicitly ask for operations that conflict with the
+/// controlled commit/push flow.
+pub fn validate_instructions(instructions: &str) -> Vec<String> {
+    let normalized = instructions
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    DISALLOWED_OPERATIONS
+        .iter()
+        .filter(|(operation, _)| {
+            normalized.match_indices(operation).any(|(idx, _)| {
+                let before = normalized[..idx].chars().next_back();
+                let after = normalized[idx + operation.len()..].chars().next();
+                !before.is_some_and(|c| c.is_alphanumeric())
+                    && !after.is_some_and(|c| c.is_alphanumeric())
+            })
+        })
+        .map(|(operation, effect)| {
+            format!(
+                "Instructions mention `{}`, which {}; Dreamal handles git itself",
+                operation, effect
+            )
+        })
+        .collect()
+}
+
+pub const DEFAULT_GUIDELINES: &str = "\
+- Make the requested changes to the codebase
+- Run tests to verify your changes work correctly
+- Do NOT perform any git operations (no git add, commit, push, branch, etc.)
+- When you have completed all changes and tests pass, simply stop working
+";
+
+/// Reads a `guidelines_file` for one session. A missing or blank file logs a
+/// warning and returns `None` so the default guidelines are used.
+pub fn read_guidelines_file(path: &Path) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(content) if !content.trim().is_empty() => Some(content),
+        Ok(_) => {
+            eprintln!(
+                "Warning: Guidelines file {} is empty; using the default guidelines",
+                path.display()
+            );
+            None
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to read guidelines file {}: {}; using the default guidelines",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Where a session's `guidelines_file` lives. Relative paths must resolve,
+/// symlinks included, to a file inside `repo_root`; absolute ones can only
+/// come from the global settings and are used as given.
+pub fn guidelines_path(repo_root: &Path, file: &str) -> Option<PathBuf> {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        return Some(path.to_path_buf());
+    }
+
+    // A missing file is reported when it is read.
+    let Ok(resolved) = repo_root.join(path).canonicalize() else {
+        return Some(repo_root.join(path));
+    };
+    let root = repo_root
+        .canonicalize()
+        .unwrap_or_else(|_| repo_root.to_path_buf());
+    if resolved.starts_with(&root) {
+        Some(resolved)
+    } else {
+        eprintln!(
+            "Warning: Guidelines file {} resolves outside the repo; using the default guidelines",
+            file
+        );
+        None
+    }
+}
+
+/// The custom guidelines a session in `repo_root` runs with, or `None` for the
+/// defaults.
+pub fn session_guidelines(repo_root: &Path, guidelines_file: Option<&str>) -> Option<String> {
+    guidelines_file
+        .and_then(|file| guidelines_path(repo_root, file))
+        .and_then(|path| read_guidelines_file(&path))
+}
+
+/// `guidelines` replaces the default "Important Guidelines" list when given.
 pub fn compose_instructions(
     user_instructions: &str,
     additional_instructions: Option<&str>,
     instructions_file_content: Option<&str>,
+    guidelines: Option<&str>,
 ) -> String {
     let mut full_instructions = String::new();
 
@@ -63,28 +301,235 @@ pub fn compose_instructions(
     }
 
     full_instructions.push_str("\n\n## Important Guidelines\n");
-    full_instructions.push_str("- Make the requested changes to the codebase\n");
-    full_instructions.push_str("- Run tests to verify your changes work correctly\n");
-    full_instructions.push_str("- Do NOT perform any git operations (no git add, commit, push, branch, etc.)\n");
-    full_instructions.push_str("- When you have completed all changes and tests pass, simply stop working\n");
+    match guidelines.filter(|g| !g.trim().is_empty()) {
+        Some(guidelines) => {
+            full_instructions.push_str(guidelines.trim_end());
+            full_instructions.push('\n');
+        }
+        None => full_instructions.push_str(DEFAULT_GUIDELINES),
+    }
 
     full_instructions
 }
 
-pub fn build_claude_command(work_dir: &Path, instructions: &str) -> Command {
-    let mut cmd = Command::new("claude");
+/// Composed instructions are passed to Claude as one argument, which Linux
+/// caps at 128 KiB; this leaves room for the guidelines and some margin.
+pub const DEFAULT_MAX_INSTRUCTIONS_LEN: usize = 100_000;
+
+/// Byte lengths of the composed instructions, exactly as Claude receives them,
+/// and the parts they came from. `guidelines` is 0 when the default
+/// guidelines are used.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstructionsLength {
+    pub total: usize,
+    pub max: usize,
+    pub user: usize,
+    pub additional: usize,
+    pub file: usize,
+    pub guidelines: usize,
+}
+
+impl InstructionsLength {
+    pub fn measure(
+        user_instructions: &str,
+        additional_instructions: Option<&str>,
+        instructions_file_content: Option<&str>,
+        guidelines: Option<&str>,
+        max: usize,
+    ) -> Self {
+        let composed = compose_instructions(
+            user_instructions,
+            additional_instructions,
+            instructions_file_content,
+            guidelines,
+        );
+        InstructionsLength {
+            total: composed.len(),
+            max,
+            user: user_instructions.len(),
+            additional: additional_instructions.map_or(0, str::len),
+            file: instructions_file_content.map_or(0, str::len),
+            guidelines: guidelines.map_or(0, str::len),
+        }
+    }
+
+    /// Describes the overflow and names the largest part, or `None` when the
+    /// composed instructions fit.
+    pub fn error(&self) -> Option<String> {
+        if self.total <= self.max {
+            return None;
+        }
+
+        let (part, len) = [
+            ("instructions", self.user),
+            ("additional instructions", self.additional),
+            ("instructions file", self.file),
+            ("guidelines file", self.guidelines),
+        ]
+        .into_iter()
+        .max_by_key(|(_, len)| *len)
+        .unwrap_or_default();
+
+        Some(format!(
+            "Instructions are too long: {} bytes composed, limit is {}. The {} are the largest part at {} bytes.",
+            self.total, self.max, part, len
+        ))
+    }
+}
+
+/// Bash commands from the tool-use events in Claude's `--output-format
+/// stream-json` output. Lines that aren't JSON events are skipped; `None`
+/// means the output held no events at all, e.g. plain `--print` text.
+pub fn bash_commands_from_stream_json(output: &str) -> Option<Vec<String>> {
+    let mut saw_event = false;
+    let mut commands = Vec::new();
+
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        saw_event = true;
 
-    let bash_tools: Vec<String> = ALLOWED_BASH_PATTERNS
+        let Some(blocks) = event["message"]["content"].as_array() else {
+            continue;
+        };
+        for block in blocks {
+            if block["type"] == "tool_use" && block["name"] == "Bash" {
+                if let Some(command) = block["input"]["command"].as_str() {
+                    commands.push(command.to_string());
+                }
+            }
+        }
+    }
+
+    saw_event.then_some(commands)
+}
+
+/// What to show for one line of Claude's stream-json output: the text of an
+/// assistant message, a line per tool call, or the error a failed run ended
+/// with. Lines that aren't JSON events are shown as they are.
+pub fn stream_json_display_lines(line: &str) -> Vec<String> {
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+        return vec![line.to_string()];
+    };
+
+    match event["type"].as_str() {
+        Some("assistant") => event["message"]["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|block| match block["type"].as_str() {
+                Some("text") => block["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                Some("tool_use") => {
+                    let name = block["name"].as_str().unwrap_or("tool");
+                    match block["input"]["command"].as_str() {
+                        Some(command) if name == "Bash" => vec![format!("$ {}", command)],
+                        _ => vec![format!("[{}]", name)],
+                    }
+                }
+                _ => Vec::new(),
+            })
+            .collect(),
+        Some("result") if event["is_error"].as_bool() == Some(true) => event["result"]
+            .as_str()
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether any step of a shell command line (split on `&&`, `||` and `;`)
+/// starts with one of the test command patterns.
+fn runs_test_command<S: AsRef<str>>(command: &str, test_patterns: &[S]) -> bool {
+    command
+        .split(['&', '|', ';'])
+        .map(|step| step.split_whitespace().collect::<Vec<_>>().join(" "))
+        .any(|step| {
+            test_patterns.iter().any(|pattern| {
+                let pattern = pattern.as_ref().trim_end_matches(":*");
+                step == pattern || step.starts_with(&format!("{} ", pattern))
+            })
+        })
+}
+
+/// The test commands Claude ran, or `None` if its output can't tell.
+pub fn detect_test_runs<S: AsRef<str>>(output: &str, test_patterns: &[S]) -> Option<Vec<String>> {
+    let commands = bash_commands_from_stream_json(output)?;
+    Some(
+        commands
+            .into_iter()
+            .filter(|command| runs_test_command(command, test_patterns))
+            .collect(),
+    )
+}
+
+/// The model Claude reported using in `--output-format stream-json` output,
+/// taken from the first event that names one.
+pub fn reported_model(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let event = serde_json::from_str::<serde_json::Value>(line.trim()).ok()?;
+        event["model"]
+            .as_str()
+            .or_else(|| event["message"]["model"].as_str())
+            .filter(|model| !model.is_empty())
+            .map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Run in place of the `claude` CLI by sessions on this test thread.
+    pub static FAKE_CLAUDE: std::cell::RefCell<Option<std::path::PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+fn claude_program() -> std::ffi::OsString {
+    #[cfg(test)]
+    if let Some(program) = FAKE_CLAUDE.with(|fake| fake.borrow().clone()) {
+        return program.into_os_string();
+    }
+    "claude".into()
+}
+
+/// `model`, when set, is passed as `--model`; otherwise the CLI's default is used.
+pub fn build_claude_command<S: AsRef<str>>(
+    work_dir: &Path,
+    instructions: &str,
+    tools: &[S],
+    bash_patterns: &[S],
+    model: Option<&str>,
+) -> Command {
+    let mut cmd = Command::new(claude_program());
+
+    let allowed_tools = tools
         .iter()
-        .map(|pattern| format!("Bash({})", pattern))
-        .collect();
-    let allowed_tools = format!("Edit,Write,Read,{}", bash_tools.join(","));
+        .map(|tool| tool.as_ref().to_string())
+        .chain(
+            bash_patterns
+                .iter()
+                .map(|pattern| format!("Bash({})", pattern.as_ref())),
+        )
+        .collect::<Vec<_>>()
+        .join(",");
 
+    // Tool-use events are how `detect_test_runs` and `reported_model` see
+    // what Claude did; the CLI only streams them with `--verbose`.
     cmd.current_dir(work_dir)
         .arg("--print")
+        .args(["--output-format", "stream-json", "--verbose"])
         .arg("--allowedTools")
-        .arg(&allowed_tools)
-        .arg("--")
+        .arg(&allowed_tools);
+    if let Some(model) = model {
+        cmd.arg("--model").arg(model);
+    }
+    cmd.arg("--")
         .arg(instructions)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
@@ -92,35 +537,176 @@ pub fn build_claude_command(work_dir: &Path, instructions: &str) -> Command {
     cmd
 }
 
-pub fn spawn_claude_process(work_dir: &Path, instructions: &str) -> Result<Child, ProcessError> {
-    let mut cmd = build_claude_command(work_dir, instructions);
+/// Resource limits for a Claude run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessLimits {
+    pub timeout: Option<Duration>,
+    pub max_memory_mb: Option<u64>,
+}
+
+pub fn spawn_claude_process<S: AsRef<str>>(
+    work_dir: &Path,
+    instructions: &str,
+    tools: &[S],
+    bash_patterns: &[S],
+    model: Option<&str>,
+    max_memory_mb: Option<u64>,
+) -> Result<Child, ProcessError> {
+    let mut cmd = build_claude_command(work_dir, instructions, tools, bash_patterns, model);
+    isolate_process_group(&mut cmd);
+    limit_memory(&mut cmd, max_memory_mb);
 
     cmd.spawn().map_err(|e| {
         ProcessError::SpawnFailed(format!("Failed to spawn claude process: {}", e))
     })
 }
 
+/// Runs the child in its own process group so `kill_process` can take down
+/// anything it spawns along with it.
+pub fn isolate_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Caps the child's data segment (`RLIMIT_DATA`), which on Linux counts the
+/// heap and other private writable memory, at `max_memory_mb`; the limit is
+/// inherited by everything it spawns. Unlike an address-space cap it ignores
+/// address space that runtimes like Node's reserve without using, so it
+/// bounds what the process actually allocates. Allocations past the cap fail.
+/// This is a best-effort guard and only applies on Unix; elsewhere the limit
+/// is ignored.
+pub fn limit_memory(cmd: &mut Command, max_memory_mb: Option<u64>) {
+    #[cfg(unix)]
+    if let Some(mb) = max_memory_mb {
+        use std::os::unix::process::CommandExt;
+
+        let bytes = mb.saturating_mul(1024 * 1024) as libc::rlim_t;
+        // SAFETY: setrlimit is async-signal-safe, and nothing else runs in
+        // the forked child before exec.
+        unsafe {
+            cmd.pre_exec(move || {
+                let limit = libc::rlimit {
+                    rlim_cur: bytes,
+                    rlim_max: bytes,
+                };
+                if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = (cmd, max_memory_mb);
+}
+
+const OUT_OF_MEMORY_ERRORS: &[&str] = &[
+    "out of memory",
+    "cannot allocate memory",
+    "cannot allocate",
+    "bad_alloc",
+    "allocation failed",
+    "memoryerror",
+];
+
+/// Whether a failed run under a memory cap hit the cap: an allocation failed
+/// and said so on stderr. A crash alone isn't enough, since it can't be told
+/// apart from any other crash.
+pub fn memory_limit_error(
+    result: &ProcessResult,
+    max_memory_mb: Option<u64>,
+) -> Option<ProcessError> {
+    let mb = max_memory_mb?;
+    if result.exit_status.success() {
+        return None;
+    }
+
+    let stderr = result.stderr.to_lowercase();
+    if OUT_OF_MEMORY_ERRORS.iter().any(|m| stderr.contains(m)) {
+        Some(ProcessError::MemoryLimitExceeded(mb))
+    } else {
+        None
+    }
+}
+
 pub fn kill_process(process_id: u32) -> Result<(), ProcessError> {
+    // `kill -- -0` would signal our own process group.
+    if process_id == 0 {
+        return Ok(());
+    }
+
     #[cfg(unix)]
     {
-        use std::process::Command;
-        Command::new("kill")
-            .arg("-9")
-            .arg(process_id.to_string())
+        let group = Command::new("kill")
+            .args(["-9", "--", &format!("-{}", process_id)])
             .output()?;
+
+        // Not a group leader (e.g. spawned before process groups were used).
+        if !group.status.success() {
+            Command::new("kill")
+                .arg("-9")
+                .arg(process_id.to_string())
+                .output()?;
+        }
     }
 
     #[cfg(windows)]
     {
-        use std::process::Command;
         Command::new("taskkill")
-            .args(["/F", "/PID", &process_id.to_string()])
+            .args(["/F", "/T", "/PID", &process_id.to_string()])
             .output()?;
     }
 
     Ok(())
 }
 
+/// Whether a process with this pid is still alive.
+pub fn process_exists(process_id: u32) -> bool {
+    #[cfg(unix)]
+    {
+        match Command::new("kill")
+            .args(["-0", &process_id.to_string()])
+            .output()
+        {
+            // EPERM means it exists but belongs to someone else.
+            Ok(output) => {
+                output.status.success()
+                    || String::from_utf8_lossy(&output.stderr)
+                        .to_lowercase()
+                        .contains("not permitted")
+            }
+            Err(_) => true,
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        match Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", process_id), "/NH"])
+            .output()
+        {
+            Ok(output) => String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|field| field == process_id.to_string()),
+            Err(_) => true,
+        }
+    }
+
+    // Without a way to check, never report a process as gone.
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = process_id;
+        true
+    }
+}
+
 #[derive(Debug)]
 pub struct ProcessResult {
     pub exit_status: ExitStatus,
@@ -128,23 +714,102 @@ pub struct ProcessResult {
     pub stderr: String,
 }
 
-pub fn wait_for_process(mut child: Child) -> Result<ProcessResult, ProcessError> {
+/// Lines of Claude output kept per session for `peek_session_output`.
+pub const OUTPUT_BUFFER_LINES: usize = 500;
+
+/// Bounded buffer of the most recent stdout lines of a running process.
+#[derive(Debug)]
+pub struct OutputBuffer {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl Default for OutputBuffer {
+    fn default() -> Self {
+        Self::new(OUTPUT_BUFFER_LINES)
+    }
+}
+
+impl OutputBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn lock_lines(&self) -> std::sync::MutexGuard<'_, VecDeque<String>> {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn push_line(&self, line: &str) {
+        let mut lines = self.lock_lines();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn last(&self, count: usize) -> Vec<String> {
+        let lines = self.lock_lines();
+        let skip = lines.len().saturating_sub(count);
+        lines.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lock_lines().clear();
+    }
+}
+
+/// Reads `reader` line by line until EOF or a read error, passing each line
+/// to `on_line`. Invalid UTF-8 is replaced rather than ending the stream, so
+/// one stray byte in build output doesn't drop everything after it.
+fn read_lines_lossy(reader: impl std::io::Read, mut on_line: impl FnMut(&str)) -> String {
+    let mut reader = BufReader::new(reader);
+    let mut lines = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if buf.ends_with(b"\n") {
+                    buf.pop();
+                    if buf.ends_with(b"\r") {
+                        buf.pop();
+                    }
+                }
+                let line = String::from_utf8_lossy(&buf).into_owned();
+                on_line(&line);
+                lines.push(line);
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// Reads the process to completion, passing each stdout line to `on_line` as
+/// it arrives. Stderr is drained on its own thread meanwhile, so a child that
+/// fills the stderr pipe can't stall on it while stdout is being read.
+pub fn wait_for_process_streaming(
+    mut child: Child,
+    mut on_line: impl FnMut(&str),
+) -> Result<ProcessResult, ProcessError> {
     let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
+    let stderr_reader = child.stderr.take().map(|stderr| {
+        std::thread::spawn(move || read_lines_lossy(stderr, |_| {}))
+    });
 
     let stdout_content = if let Some(stdout) = stdout {
-        let reader = BufReader::new(stdout);
-        reader.lines().filter_map(|l| l.ok()).collect::<Vec<_>>().join("\n")
+        read_lines_lossy(stdout, &mut on_line)
     } else {
         String::new()
     };
 
-    let stderr_content = if let Some(stderr) = stderr {
-        let reader = BufReader::new(stderr);
-        reader.lines().filter_map(|l| l.ok()).collect::<Vec<_>>().join("\n")
-    } else {
-        String::new()
-    };
+    let stderr_content = stderr_reader
+        .map(|reader| reader.join().unwrap_or_default())
+        .unwrap_or_default();
 
     let exit_status = child.wait()?;
 
@@ -155,18 +820,137 @@ pub fn wait_for_process(mut child: Child) -> Result<ProcessResult, ProcessError>
     })
 }
 
-pub fn run_claude_and_wait(
+/// Waits like `wait_for_process_streaming`, killing the process (and its group) if it is
+/// still running after `timeout`. A process that exits cleanly before the kill lands, e.g.
+/// while its output is still being drained, keeps its result rather than timing out.
+pub fn wait_for_process_with_timeout(
+    child: Child,
+    timeout: Option<Duration>,
+    on_line: impl FnMut(&str),
+) -> Result<ProcessResult, ProcessError> {
+    let Some(timeout) = timeout else {
+        return wait_for_process_streaming(child, on_line);
+    };
+
+    let process_id = child.id();
+    let finished = Arc::new((Mutex::new(false), Condvar::new()));
+    let watcher = {
+        let finished = finished.clone();
+        std::thread::spawn(move || {
+            let (done, signal) = &*finished;
+            let done = done.lock().unwrap_or_else(|e| e.into_inner());
+            let (done, _) = signal
+                .wait_timeout_while(done, timeout, |done| !*done)
+                .unwrap_or_else(|e| e.into_inner());
+            if *done {
+                return false;
+            }
+            let _ = kill_process(process_id);
+            true
+        })
+    };
+
+    let result = wait_for_process_streaming(child, on_line);
+
+    let (done, signal) = &*finished;
+    *done.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    signal.notify_all();
+
+    let exited_cleanly = matches!(&result, Ok(r) if r.exit_status.success());
+    if watcher.join().unwrap_or(false) && !exited_cleanly {
+        return Err(ProcessError::Timeout(timeout));
+    }
+    result
+}
+
+const CLAUDE_AUTH_ERRORS: &[&str] = &[
+    "invalid api key",
+    "invalid x-api-key",
+    "authentication_error",
+    "api error: 401",
+    "please run /login",
+    "not logged in",
+];
+
+const CLAUDE_CREDIT_ERRORS: &[&str] = &[
+    "credit balance is too low",
+    "insufficient credits",
+    "insufficient_quota",
+];
+
+/// Maps a failed Claude run to an actionable error when its stderr shows an
+/// auth or billing problem; anything else stays a `ProcessFailed`. Stdout is
+/// left out, as Claude's own messages may merely mention such problems.
+pub fn classify_claude_failure(exit_code: Option<i32>, stderr: &str) -> ProcessError {
+    let output = stderr.to_lowercase();
+
+    if CLAUDE_CREDIT_ERRORS.iter().any(|m| output.contains(m)) {
+        ProcessError::CreditsExhausted
+    } else if CLAUDE_AUTH_ERRORS.iter().any(|m| output.contains(m)) {
+        ProcessError::AuthFailed
+    } else {
+        ProcessError::ProcessFailed {
+            exit_code,
+            stderr: stderr.to_string(),
+        }
+    }
+}
+
+/// Spawns Claude and waits for it within `limits`, passing each stdout line
+/// to `on_line` as it is read. The full output is still returned.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_claude_process_streaming<S: AsRef<str>>(
+    work_dir: &Path,
+    instructions: &str,
+    tools: &[S],
+    bash_patterns: &[S],
+    model: Option<&str>,
+    limits: ProcessLimits,
+    on_spawn: impl FnOnce(u32),
+    on_line: impl FnMut(&str),
+) -> Result<ProcessResult, ProcessError> {
+    let child = spawn_claude_process(
+        work_dir,
+        instructions,
+        tools,
+        bash_patterns,
+        model,
+        limits.max_memory_mb,
+    )?;
+    on_spawn(child.id());
+    wait_for_process_with_timeout(child, limits.timeout, on_line)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_claude_and_wait<S: AsRef<str>>(
     work_dir: &Path,
     instructions: &str,
+    tools: &[S],
+    bash_patterns: &[S],
+    model: Option<&str>,
+    limits: ProcessLimits,
+    on_spawn: impl FnOnce(u32),
+    on_line: impl FnMut(&str),
 ) -> Result<ProcessResult, ProcessError> {
-    let child = spawn_claude_process(work_dir, instructions)?;
-    let result = wait_for_process(child)?;
+    let result = spawn_claude_process_streaming(
+        work_dir,
+        instructions,
+        tools,
+        bash_patterns,
+        model,
+        limits,
+        on_spawn,
+        on_line,
+    )?;
 
+    if let Some(error) = memory_limit_error(&result, limits.max_memory_mb) {
+        return Err(error);
+    }
     if !result.exit_status.success() {
-        return Err(ProcessError::ProcessFailed {
-            exit_code: result.exit_status.code(),
-            stderr: result.stderr.clone(),
-        });
+        return Err(classify_claude_failure(
+            result.exit_status.code(),
+            &result.stderr,
+        ));
     }
 
     Ok(result)
@@ -178,7 +962,7 @@ mod tests {
 
     #[test]
     fn test_compose_instructions_basic() {
-        let instructions = compose_instructions("Add dark mode", None, None);
+        let instructions = compose_instructions("Add dark mode", None, None, None);
 
         assert!(instructions.contains("Add dark mode"));
         assert!(instructions.contains("Do NOT perform any git operations"));
@@ -190,6 +974,7 @@ mod tests {
             "Add dark mode",
             Some("Use CSS variables for theming"),
             None,
+            None,
         );
 
         assert!(instructions.contains("Add dark mode"));
@@ -203,6 +988,7 @@ mod tests {
             "Add dark mode",
             None,
             Some("# Detailed Requirements\n- Support system preference"),
+            None,
         );
 
         assert!(instructions.contains("Add dark mode"));
@@ -216,6 +1002,7 @@ mod tests {
             "Add dark mode",
             Some("Additional context here"),
             Some("File content here"),
+            None,
         );
 
         assert!(instructions.contains("Add dark mode"));
@@ -223,19 +1010,302 @@ mod tests {
         assert!(instructions.contains("File content here"));
     }
 
+    #[test]
+    fn test_guidelines_file_replaces_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("bot.md");
+        fs::write(&path, "- Follow the team style guide\n- Never touch migrations\n\n").unwrap();
+
+        let guidelines = read_guidelines_file(&path);
+        let instructions = compose_instructions("Add dark mode", None, None, guidelines.as_deref());
+
+        assert!(instructions.ends_with(
+            "## Important Guidelines\n- Follow the team style guide\n- Never touch migrations\n"
+        ));
+        assert!(!instructions.contains("Do NOT perform any git operations"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_guidelines_path_rejects_symlinks_out_of_repo() {
+        let repo = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret.md"), "secret").unwrap();
+        fs::write(repo.path().join("bot.md"), "- Be brief\n").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.md"), repo.path().join("link.md"))
+            .unwrap();
+
+        assert!(guidelines_path(repo.path(), "link.md").is_none());
+        let inside = guidelines_path(repo.path(), "bot.md").unwrap();
+        assert_eq!(read_guidelines_file(&inside).as_deref(), Some("- Be brief\n"));
+        assert_eq!(
+            guidelines_path(repo.path(), "missing.md"),
+            Some(repo.path().join("missing.md"))
+        );
+    }
+
+    #[test]
+    fn test_guidelines_file_missing_or_empty_falls_back() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let empty = temp_dir.path().join("empty.md");
+        fs::write(&empty, "  \n").unwrap();
+
+        assert!(read_guidelines_file(&temp_dir.path().join("missing.md")).is_none());
+        assert!(read_guidelines_file(&empty).is_none());
+
+        let instructions = compose_instructions("Add dark mode", None, None, None);
+        assert!(instructions.ends_with(DEFAULT_GUIDELINES));
+    }
+
+    #[test]
+    fn test_classify_claude_auth_failure() {
+        let error = classify_claude_failure(Some(1), "Invalid API key · Please run /login");
+        assert!(matches!(error, ProcessError::AuthFailed));
+        assert!(error.to_string().contains("check your API key"));
+
+        let stderr = r#"API Error: 401 {"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        assert!(matches!(
+            classify_claude_failure(Some(1), stderr),
+            ProcessError::AuthFailed
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failure_not_classified_from_claude_output() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("claude");
+        fs::write(
+            &script,
+            "#!/bin/sh\n\
+             echo 'The login test expects \"Invalid API key\" once the credit balance is too low'\n\
+             echo 'Error: tests failed' >&2\n\
+             exit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        FAKE_CLAUDE.with(|fake| *fake.borrow_mut() = Some(script));
+
+        let error = run_claude_and_wait(
+            dir.path(),
+            "Fix the login test",
+            &["Bash"],
+            &[] as &[&str],
+            None,
+            ProcessLimits::default(),
+            |_| {},
+            |_| {},
+        )
+        .unwrap_err();
+        FAKE_CLAUDE.with(|fake| *fake.borrow_mut() = None);
+
+        match error {
+            ProcessError::ProcessFailed { exit_code, stderr } => {
+                assert_eq!(exit_code, Some(1));
+                assert_eq!(stderr.trim(), "Error: tests failed");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_classify_claude_credit_failure() {
+        let error = classify_claude_failure(Some(1), "Credit balance is too low");
+        assert!(matches!(error, ProcessError::CreditsExhausted));
+
+        let error = classify_claude_failure(Some(2), "Error: ENOENT: no such file");
+        match error {
+            ProcessError::ProcessFailed { exit_code, stderr } => {
+                assert_eq!(exit_code, Some(2));
+                assert_eq!(stderr, "Error: ENOENT: no such file");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_instructions_length_under_limit() {
+        let length = InstructionsLength::measure(
+            "Add dark mode",
+            Some("Use CSS variables"),
+            None,
+            None,
+            10_000,
+        );
+
+        assert_eq!(length.user, 13);
+        assert_eq!(length.additional, 17);
+        assert_eq!(length.file, 0);
+        assert!(length.total > length.user + length.additional);
+        assert!(length.error().is_none());
+    }
+
+    #[test]
+    fn test_instructions_length_over_limit_names_largest_part() {
+        let file = "x".repeat(5_000);
+        let length = InstructionsLength::measure("Add dark mode", None, Some(&file), None, 4_000);
+
+        let error = length.error().unwrap();
+        assert!(error.contains("limit is 4000"));
+        assert!(error.contains("instructions file are the largest part at 5000 bytes"));
+    }
+
+    #[test]
+    fn test_instructions_length_counts_guidelines_file() {
+        let guidelines = "- Follow the style guide\n".repeat(200);
+        let length =
+            InstructionsLength::measure("Add dark mode", None, None, Some(&guidelines), 4_000);
+
+        assert_eq!(length.guidelines, guidelines.len());
+        assert_eq!(
+            length.total,
+            compose_instructions("Add dark mode", None, None, Some(&guidelines)).len()
+        );
+        assert!(length
+            .error()
+            .unwrap()
+            .contains("guidelines file are the largest part"));
+    }
+
+    const STREAM_WITH_TESTS: &str = r#"{"type":"system","subtype":"init"}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Running the tests"},{"type":"tool_use","name":"Bash","input":{"command":"cd web && npm test -- --watch=false"}}]}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"ls src"}},{"type":"tool_use","name":"Edit","input":{"file_path":"a.rs"}}]}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo test --lib"}}]}}
+{"type":"result","subtype":"success"}"#;
+
+    #[test]
+    fn test_detect_test_runs_finds_test_commands() {
+        let runs = detect_test_runs(STREAM_WITH_TESTS, ALLOWED_BASH_PATTERNS).unwrap();
+        assert_eq!(runs, vec!["cd web && npm test -- --watch=false", "cargo test --lib"]);
+    }
+
+    #[test]
+    fn test_stream_json_display_lines() {
+        let assistant = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Running the tests.\nThen fixing."},{"type":"tool_use","name":"Bash","input":{"command":"cargo test"}},{"type":"tool_use","name":"Edit","input":{"file_path":"src/lib.rs"}}]}}"#;
+        assert_eq!(
+            stream_json_display_lines(assistant),
+            vec!["Running the tests.", "Then fixing.", "$ cargo test", "[Edit]"]
+        );
+
+        let init = r#"{"type":"system","subtype":"init","model":"claude-sonnet-4-5"}"#;
+        assert!(stream_json_display_lines(init).is_empty());
+        let success = r#"{"type":"result","subtype":"success","is_error":false,"result":"Done"}"#;
+        assert!(stream_json_display_lines(success).is_empty());
+        let failure = r#"{"type":"result","is_error":true,"result":"API Error: 529 Overloaded"}"#;
+        assert_eq!(
+            stream_json_display_lines(failure),
+            vec!["API Error: 529 Overloaded"]
+        );
+        assert_eq!(stream_json_display_lines("plain text"), vec!["plain text"]);
+    }
+
+    #[test]
+    fn test_detect_test_runs_without_test_commands() {
+        let output = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo build"}},{"type":"tool_use","name":"Bash","input":{"command":"echo cargo test"}}]}}"#;
+        assert_eq!(detect_test_runs(output, ALLOWED_BASH_PATTERNS), Some(Vec::new()));
+
+        // Plain --print text carries no tool-use events.
+        assert_eq!(detect_test_runs("I ran cargo test and it passed.", ALLOWED_BASH_PATTERNS), None);
+    }
+
+    #[test]
+    fn test_validate_instructions_flags_git_operations() {
+        let warnings = validate_instructions("Fix the bug, then GIT   PUSH --force and rm -rf build");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("git push"));
+        assert!(warnings[1].contains("rm -rf"));
+
+        let warnings = validate_instructions("Run `git branch -D old` when done");
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_instructions_clean() {
+        assert!(validate_instructions("Add dark mode to the settings page").is_empty());
+        assert!(validate_instructions("Document the git pushdown optimizer").is_empty());
+        assert!(validate_instructions("").is_empty());
+    }
+
     #[test]
     fn test_build_claude_command() {
         let work_dir = std::path::PathBuf::from("/tmp/test");
-        let cmd = build_claude_command(&work_dir, "Test instructions");
+        let cmd = build_claude_command(
+            &work_dir,
+            "Test instructions",
+            DEFAULT_ALLOWED_TOOLS,
+            ALLOWED_BASH_PATTERNS,
+            None,
+        );
 
         let program = cmd.get_program();
         assert_eq!(program, "claude");
 
         let args: Vec<_> = cmd.get_args().collect();
         assert!(args.contains(&std::ffi::OsStr::new("--print")));
+        let format_at = args.iter().position(|a| *a == "--output-format").unwrap();
+        assert_eq!(args[format_at + 1], "stream-json");
+        assert!(args.contains(&std::ffi::OsStr::new("--verbose")));
         assert!(args.contains(&std::ffi::OsStr::new("--allowedTools")));
         assert!(args.contains(&std::ffi::OsStr::new("--")));
         assert!(args.contains(&std::ffi::OsStr::new("Test instructions")));
+        assert!(args.contains(&std::ffi::OsStr::new(
+            "Edit,Write,Read,Bash(npm run test),Bash(npm run test:*),Bash(npm test),Bash(cargo test),Bash(go test),Bash(pytest),Bash(jest)"
+        )));
+    }
+
+    #[test]
+    fn test_build_claude_command_with_model() {
+        let cmd = build_claude_command(
+            Path::new("/tmp/test"),
+            "Test instructions",
+            DEFAULT_ALLOWED_TOOLS,
+            ALLOWED_BASH_PATTERNS,
+            Some("sonnet"),
+        );
+
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect();
+        let model_at = args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(args[model_at + 1], "sonnet");
+        assert!(model_at < args.iter().position(|a| a == "--").unwrap());
+    }
+
+    #[test]
+    fn test_reported_model() {
+        let output = concat!(
+            "plain text\n",
+            r#"{"type":"system","subtype":"init","model":"claude-sonnet-4-5"}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"model":"other","content":[]}}"#,
+        );
+        assert_eq!(reported_model(output).as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(
+            reported_model(r#"{"type":"assistant","message":{"model":"m1"}}"#).as_deref(),
+            Some("m1")
+        );
+        assert_eq!(reported_model("Done, all tests pass."), None);
+    }
+
+    #[test]
+    fn test_build_claude_command_ignores_checkout_allowed_commands() {
+        // Claude can write to its checkout, so a file there must not widen
+        // the allowlist it runs with.
+        let temp_dir = tempfile::tempdir().unwrap();
+        let commands_file = temp_dir.path().join(".dreamal/allowed-commands.json");
+        fs::create_dir_all(commands_file.parent().unwrap()).unwrap();
+        fs::write(&commands_file, r#"["curl"]"#).unwrap();
+
+        let cmd = build_claude_command(
+            temp_dir.path(),
+            "Test instructions",
+            DEFAULT_ALLOWED_TOOLS,
+            &["cargo test"],
+            None,
+        );
+        let args: Vec<_> = cmd.get_args().collect();
+        let at = args.iter().position(|a| *a == "--allowedTools").unwrap();
+        assert_eq!(args[at + 1], "Edit,Write,Read,Bash(cargo test)");
     }
 
     #[test]
@@ -245,6 +1315,38 @@ mod tests {
         assert!(!ALLOWED_BASH_PATTERNS.contains(&"rm -rf /"));
     }
 
+    #[test]
+    fn test_is_command_allowed() {
+        assert!(is_command_allowed("npm test", ALLOWED_BASH_PATTERNS));
+        assert!(is_command_allowed("  cargo   test ", ALLOWED_BASH_PATTERNS));
+        assert!(is_command_allowed("npm run test:unit", ALLOWED_BASH_PATTERNS));
+        assert!(is_command_allowed("npm run test -- --watch", ALLOWED_BASH_PATTERNS));
+        assert!(!is_command_allowed("npm run testing", ALLOWED_BASH_PATTERNS));
+        assert!(!is_command_allowed("rm -rf /", ALLOWED_BASH_PATTERNS));
+        assert!(!is_command_allowed("", ALLOWED_BASH_PATTERNS));
+    }
+
+    #[test]
+    fn test_run_allowed_command_succeeds() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let output = run_allowed_command(temp_dir.path(), "echo hello", &["echo hello"]).unwrap();
+
+        assert_eq!(output.exit_code, Some(0));
+        assert!(output.stdout.contains("hello"));
+    }
+
+    #[test]
+    fn test_run_allowed_command_rejects_disallowed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("keep.txt"), "data").unwrap();
+
+        let result = run_allowed_command(temp_dir.path(), "rm keep.txt", ALLOWED_BASH_PATTERNS);
+
+        assert!(matches!(result, Err(ProcessError::CommandNotAllowed(_))));
+        assert!(temp_dir.path().join("keep.txt").exists());
+    }
+
     #[test]
     fn test_wait_for_process_success() {
         let child = Command::new("echo")
@@ -254,11 +1356,29 @@ mod tests {
             .spawn()
             .unwrap();
 
-        let result = wait_for_process(child).unwrap();
+        let result = wait_for_process_streaming(child, |_| {}).unwrap();
         assert!(result.exit_status.success());
         assert!(result.stdout.contains("hello"));
     }
 
+    #[test]
+    fn test_wait_for_process_keeps_output_after_invalid_utf8() {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg("printf 'before\\n\\377 bad\\nafter\\n'; printf 'err \\377\\nlast\\n' >&2")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut streamed = Vec::new();
+        let result = wait_for_process_streaming(child, |line| streamed.push(line.to_string()))
+            .unwrap();
+        assert_eq!(streamed, vec!["before", "\u{FFFD} bad", "after"]);
+        assert_eq!(result.stdout, "before\n\u{FFFD} bad\nafter");
+        assert_eq!(result.stderr, "err \u{FFFD}\nlast");
+    }
+
     #[test]
     fn test_wait_for_process_failure() {
         let child = Command::new("sh")
@@ -269,10 +1389,110 @@ mod tests {
             .spawn()
             .unwrap();
 
-        let result = wait_for_process(child).unwrap();
+        let result = wait_for_process_streaming(child, |_| {}).unwrap();
         assert!(!result.exit_status.success());
     }
 
+    #[test]
+    fn test_wait_for_process_with_timeout_kills_slow_process() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30").stdout(Stdio::piped()).stderr(Stdio::piped());
+        isolate_process_group(&mut cmd);
+        let child = cmd.spawn().unwrap();
+
+        let started = std::time::Instant::now();
+        let result = wait_for_process_with_timeout(child, Some(Duration::from_millis(200)), |_| {});
+
+        assert!(matches!(result, Err(ProcessError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_wait_for_process_with_timeout_keeps_clean_exit() {
+        // The shell exits at once, but the background sleep holds stdout open
+        // until the deadline kills the group.
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & exit 0"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        isolate_process_group(&mut cmd);
+        let child = cmd.spawn().unwrap();
+
+        let result =
+            wait_for_process_with_timeout(child, Some(Duration::from_millis(200)), |_| {}).unwrap();
+        assert!(result.exit_status.success());
+    }
+
+    #[test]
+    fn test_wait_for_process_with_timeout_allows_fast_process() {
+        let child = Command::new("echo")
+            .arg("hello")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let result =
+            wait_for_process_with_timeout(child, Some(Duration::from_secs(30)), |_| {}).unwrap();
+        assert!(result.stdout.contains("hello"));
+    }
+
+    #[test]
+    fn test_output_buffer_keeps_last_lines() {
+        let buffer = OutputBuffer::new(100);
+        for i in 0..10_000 {
+            buffer.push_line(&format!("line {}", i));
+        }
+
+        let all = buffer.last(usize::MAX);
+        assert_eq!(all.len(), 100);
+        assert_eq!(all[0], "line 9900");
+        assert_eq!(buffer.last(2), vec!["line 9998", "line 9999"]);
+
+        buffer.clear();
+        assert!(buffer.last(5).is_empty());
+    }
+
+    #[test]
+    fn test_wait_for_process_fills_output_buffer() {
+        let child = Command::new("sh")
+            .args(["-c", "for i in $(seq 1 2000); do echo out $i; done"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let buffer = OutputBuffer::new(10);
+
+        let result =
+            wait_for_process_with_timeout(child, None, |line| buffer.push_line(line)).unwrap();
+        assert!(result.stdout.ends_with("out 2000"));
+        assert_eq!(buffer.last(3), vec!["out 1998", "out 1999", "out 2000"]);
+        assert_eq!(buffer.last(50).len(), 10);
+    }
+
+    #[test]
+    fn test_wait_for_process_streaming_with_chatty_stderr() {
+        // Far more stderr than a pipe buffer holds, written before any stdout.
+        let child = Command::new("sh")
+            .args(["-c", "head -c 300000 /dev/zero | tr '\\0' x >&2; echo one; echo two"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut lines = Vec::new();
+
+        let result = wait_for_process_with_timeout(
+            child,
+            Some(Duration::from_secs(30)),
+            |line| lines.push(line.to_string()),
+        )
+        .unwrap();
+
+        assert_eq!(lines, vec!["one", "two"]);
+        assert_eq!(result.stdout, "one\ntwo");
+        assert_eq!(result.stderr.len(), 300000);
+    }
+
     #[test]
     fn test_process_result_struct() {
         let result = ProcessResult {
@@ -284,4 +1504,109 @@ mod tests {
         assert!(result.exit_status.success());
         assert_eq!(result.stdout, "output");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_limit_stops_runaway_child() {
+        // Builds a ~100 MB string under a 50 MB cap.
+        let mut cmd = Command::new("bash");
+        cmd.args(["-c", "x=$(head -c 100000000 /dev/zero | tr '\\0' a); echo done"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        limit_memory(&mut cmd, Some(50));
+
+        let result = wait_for_process_streaming(cmd.spawn().unwrap(), |_| {}).unwrap();
+
+        assert!(!result.stdout.contains("done"));
+        assert!(matches!(
+            memory_limit_error(&result, Some(50)),
+            Some(ProcessError::MemoryLimitExceeded(50))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crash_under_memory_limit_is_not_reported_as_oom() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "kill -SEGV $$"]).stdout(Stdio::piped()).stderr(Stdio::piped());
+        limit_memory(&mut cmd, Some(512));
+
+        let result = wait_for_process_streaming(cmd.spawn().unwrap(), |_| {}).unwrap();
+
+        assert!(!result.exit_status.success());
+        assert!(memory_limit_error(&result, Some(512)).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_limit_leaves_reserved_address_space_alone() {
+        // Reserving far more address space than the cap, without touching it,
+        // is what runtimes like Node do at startup.
+        let mut cmd = Command::new("python3");
+        cmd.args([
+            "-c",
+            "import mmap; m = mmap.mmap(-1, 1 << 30, flags=mmap.MAP_PRIVATE | mmap.MAP_ANONYMOUS, \
+             prot=0); print('reserved')",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+        limit_memory(&mut cmd, Some(256));
+
+        let Ok(child) = cmd.spawn() else {
+            return;
+        };
+        let result = wait_for_process_streaming(child, |_| {}).unwrap();
+        assert_eq!(result.stdout.trim(), "reserved");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_limit_allows_small_child() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo fits"]).stdout(Stdio::piped()).stderr(Stdio::piped());
+        limit_memory(&mut cmd, Some(512));
+
+        let result = wait_for_process_streaming(cmd.spawn().unwrap(), |_| {}).unwrap();
+
+        assert!(result.exit_status.success());
+        assert_eq!(result.stdout.trim(), "fits");
+        assert!(memory_limit_error(&result, Some(512)).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_running(pid: &str) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat
+                .rsplit(')')
+                .next()
+                .is_some_and(|rest| rest.trim_start().starts_with('Z')),
+            Err(_) => false,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kill_process_terminates_children() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped());
+        isolate_process_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+
+        let mut grandchild_pid = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut grandchild_pid)
+            .unwrap();
+        let grandchild_pid = grandchild_pid.trim().to_string();
+        assert!(is_running(&grandchild_pid));
+
+        kill_process(child.id()).unwrap();
+        child.wait().unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while is_running(&grandchild_pid) && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert!(!is_running(&grandchild_pid));
+    }
 }
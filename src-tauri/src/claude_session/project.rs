@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectDetection {
+    pub ecosystem: String,
+    pub marker: String,
+    pub test_command: String,
+    pub build_command: Option<String>,
+}
+
+/// Marker file, ecosystem, and the test and build commands suggested for it.
+/// Checked in order; each ecosystem is reported at most once.
+const PROJECT_MARKERS: &[(&str, &str, &str, Option<&str>)] = &[
+    (
+        "package.json",
+        "node",
+        "npm test",
+        Some("npm run build --if-present"),
+    ),
+    ("Cargo.toml", "rust", "cargo test", Some("cargo build")),
+    ("go.mod", "go", "go test ./...", Some("go build ./...")),
+    ("pyproject.toml", "python", "pytest", None),
+    ("setup.py", "python", "pytest", None),
+    ("requirements.txt", "python", "pytest", None),
+    ("pom.xml", "java", "mvn test", Some("mvn compile")),
+    (
+        "build.gradle",
+        "java",
+        "gradle test",
+        Some("gradle assemble"),
+    ),
+    (
+        "build.gradle.kts",
+        "java",
+        "gradle test",
+        Some("gradle assemble"),
+    ),
+];
+
+fn node_commands(repo_path: &Path) -> (&'static str, &'static str) {
+    if repo_path.join("pnpm-lock.yaml").exists() {
+        ("pnpm test", "pnpm run --if-present build")
+    } else if repo_path.join("yarn.lock").exists() {
+        ("yarn test", "yarn build")
+    } else {
+        ("npm test", "npm run build --if-present")
+    }
+}
+
+pub fn detect_project_types(repo_path: &Path) -> Vec<ProjectDetection> {
+    let mut detections: Vec<ProjectDetection> = Vec::new();
+
+    for (marker, ecosystem, test_command, build_command) in PROJECT_MARKERS {
+        if !repo_path.join(marker).is_file() || detections.iter().any(|d| d.ecosystem == *ecosystem)
+        {
+            continue;
+        }
+
+        let (test_command, build_command) = if *ecosystem == "node" {
+            let (test, build) = node_commands(repo_path);
+            (test, Some(build))
+        } else {
+            (*test_command, *build_command)
+        };
+
+        detections.push(ProjectDetection {
+            ecosystem: ecosystem.to_string(),
+            marker: marker.to_string(),
+            test_command: test_command.to_string(),
+            build_command: build_command.map(str::to_string),
+        });
+    }
+
+    detections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn fixture(files: &[&str]) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for file in files {
+            fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+        temp_dir
+    }
+
+    fn ecosystems(repo: &tempfile::TempDir) -> Vec<(String, String)> {
+        detect_project_types(repo.path())
+            .into_iter()
+            .map(|d| (d.ecosystem, d.test_command))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_node() {
+        let repo = fixture(&["package.json"]);
+        assert_eq!(ecosystems(&repo), vec![("node".into(), "npm test".into())]);
+
+        let repo = fixture(&["package.json", "yarn.lock"]);
+        assert_eq!(ecosystems(&repo), vec![("node".into(), "yarn test".into())]);
+    }
+
+    #[test]
+    fn test_detect_rust() {
+        let repo = fixture(&["Cargo.toml"]);
+        assert_eq!(
+            ecosystems(&repo),
+            vec![("rust".into(), "cargo test".into())]
+        );
+        assert_eq!(
+            detect_project_types(repo.path())[0]
+                .build_command
+                .as_deref(),
+            Some("cargo build")
+        );
+    }
+
+    #[test]
+    fn test_detect_go() {
+        let repo = fixture(&["go.mod"]);
+        assert_eq!(
+            ecosystems(&repo),
+            vec![("go".into(), "go test ./...".into())]
+        );
+    }
+
+    #[test]
+    fn test_detect_python_once() {
+        let repo = fixture(&["pyproject.toml", "requirements.txt"]);
+        let detections = detect_project_types(repo.path());
+
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].ecosystem, "python");
+        assert_eq!(detections[0].marker, "pyproject.toml");
+        assert_eq!(detections[0].test_command, "pytest");
+        assert!(detections[0].build_command.is_none());
+    }
+
+    #[test]
+    fn test_detect_polyglot_and_empty() {
+        let repo = fixture(&["package.json", "Cargo.toml"]);
+        let found: Vec<_> = ecosystems(&repo).into_iter().map(|(e, _)| e).collect();
+        assert_eq!(found, vec!["node", "rust"]);
+
+        let repo = fixture(&[]);
+        assert!(detect_project_types(repo.path()).is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::manager::{SessionError, SessionManager};
+use super::types::{SessionInfo, SessionStatus};
+use crate::git_ops::size::dir_stats;
+
+pub const MISSING_WORK_DIR_MESSAGE: &str = "Work directory is missing; the session was interrupted";
+pub const STALE_PROCESS_MESSAGE: &str = "Claude process exited unexpectedly";
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReconcileSummary {
+    /// `session-*` directories with no in-memory or persisted record.
+    pub orphan_dirs: Vec<String>,
+    pub removed_dirs: Vec<String>,
+    /// Sessions whose work directory no longer exists.
+    pub missing_work_dirs: Vec<String>,
+    /// Active sessions marked as errored because nothing owns their checkout.
+    pub marked_error: Vec<String>,
+}
+
+fn session_dirs(checkouts_dir: &Path) -> Vec<(String, std::path::PathBuf)> {
+    let Ok(entries) = fs::read_dir(checkouts_dir) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let id = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("session-")?
+                .to_string();
+            Some((id, path))
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Compares session records against the work directories in `checkouts_dir`.
+/// With `fix`, orphan directories are removed and stale active sessions are
+/// marked as errored; otherwise the discrepancies are only reported.
+pub fn reconcile_sessions_in(
+    checkouts_dir: &Path,
+    session_manager: &SessionManager,
+    persisted: &[SessionInfo],
+    fix: bool,
+) -> Result<ReconcileSummary, SessionError> {
+    let in_memory = session_manager.list_sessions()?;
+    let known: HashSet<&str> = in_memory
+        .iter()
+        .chain(persisted)
+        .map(|s| s.id.as_str())
+        .collect();
+
+    let mut summary = ReconcileSummary::default();
+
+    for (id, path) in session_dirs(checkouts_dir) {
+        if known.contains(id.as_str()) {
+            continue;
+        }
+        summary.orphan_dirs.push(path.display().to_string());
+        if fix {
+            match fs::remove_dir_all(&path) {
+                Ok(()) => summary.removed_dirs.push(path.display().to_string()),
+                Err(e) => eprintln!("Warning: Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for info in in_memory.iter().chain(persisted) {
+        if !seen.insert(info.id.as_str()) {
+            continue;
+        }
+        if checkouts_dir.join(format!("session-{}", info.id)).exists() {
+            continue;
+        }
+        // Queued sessions have not cloned yet.
+        if info.status == SessionStatus::Queued {
+            continue;
+        }
+
+        summary.missing_work_dirs.push(info.id.clone());
+
+        let active = matches!(
+            info.status,
+            SessionStatus::Initializing | SessionStatus::Working
+        );
+        let tracked = in_memory.iter().any(|s| s.id == info.id);
+        if fix && active && tracked && !session_manager.is_checkout_claimed(&info.id)? {
+            session_manager.set_error(&info.id, MISSING_WORK_DIR_MESSAGE.to_string())?;
+            summary.marked_error.push(info.id.clone());
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Marks `Working` sessions whose Claude process is gone while they are still
+/// in the Run phase as errored and returns their ids. `is_alive` is
+/// `process::process_exists` outside tests.
+pub fn reap_stale_sessions_with<F>(
+    session_manager: &SessionManager,
+    is_alive: F,
+) -> Result<Vec<String>, SessionError>
+where
+    F: Fn(u32) -> bool,
+{
+    let mut reaped = Vec::new();
+
+    for (id, pid) in session_manager.working_processes()? {
+        if is_alive(pid) {
+            continue;
+        }
+        if session_manager.fail_if_running(&id, pid, STALE_PROCESS_MESSAGE.to_string())? {
+            reaped.push(id);
+        }
+    }
+
+    reaped.sort();
+    Ok(reaped)
+}
+
+#[derive(Debug, Serialize)]
+pub struct LiveCheckout {
+    pub session_id: String,
+    pub path: String,
+    /// Whether an in-memory or persisted session record owns this directory.
+    pub known: bool,
+    pub status: Option<SessionStatus>,
+    pub bytes: u64,
+    pub modified_ms: Option<u64>,
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let elapsed = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(elapsed.as_millis() as u64)
+}
+
+/// Lists the `session-*` directories in `checkouts_dir` with their size and
+/// the status of the session that owns each, if any. Nothing is changed.
+pub fn list_live_checkouts_in(
+    checkouts_dir: &Path,
+    session_manager: &SessionManager,
+    persisted: &[SessionInfo],
+) -> Result<Vec<LiveCheckout>, SessionError> {
+    let in_memory = session_manager.list_sessions()?;
+
+    Ok(session_dirs(checkouts_dir)
+        .into_iter()
+        .map(|(id, path)| {
+            // In-memory records are newer than what was last persisted.
+            let status = in_memory
+                .iter()
+                .chain(persisted)
+                .find(|s| s.id == id)
+                .map(|s| s.status);
+            LiveCheckout {
+                known: status.is_some(),
+                status,
+                bytes: dir_stats(&path, false).map(|s| s.bytes).unwrap_or(0),
+                modified_ms: modified_ms(&path),
+                path: path.display().to_string(),
+                session_id: id,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude_session::types::SessionPhase;
+    use std::path::PathBuf;
+
+    fn add_session(manager: &SessionManager, id: &str) {
+        manager
+            .create_session(
+                id.to_string(),
+                "/repo".to_string(),
+                "instructions".to_string(),
+                PathBuf::from(format!("/tmp/session-{}", id)),
+                String::new(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reconcile_reports_without_fixing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("session-orphan")).unwrap();
+
+        let manager = SessionManager::new();
+        add_session(&manager, "vanished");
+        manager.set_working("vanished", 1234).unwrap();
+
+        let summary = reconcile_sessions_in(temp_dir.path(), &manager, &[], false).unwrap();
+
+        assert_eq!(summary.orphan_dirs.len(), 1);
+        assert!(summary.removed_dirs.is_empty());
+        assert_eq!(summary.missing_work_dirs, vec!["vanished"]);
+        assert!(summary.marked_error.is_empty());
+        assert!(temp_dir.path().join("session-orphan").exists());
+        assert_eq!(
+            manager.get_session_info("vanished").unwrap().status,
+            SessionStatus::Working
+        );
+    }
+
+    #[test]
+    fn test_reconcile_fixes_drift() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let checkouts = temp_dir.path();
+        for dir in [
+            "session-orphan",
+            "session-live",
+            "session-persisted",
+            "other",
+        ] {
+            fs::create_dir_all(checkouts.join(dir)).unwrap();
+        }
+
+        let manager = SessionManager::new();
+        for id in ["live", "vanished", "cloning", "queued"] {
+            add_session(&manager, id);
+        }
+        manager.set_working("vanished", 1234).unwrap();
+        manager.claim_checkout("cloning").unwrap();
+        manager.pause_scheduler();
+        let waiter = {
+            let manager = manager.clone();
+            std::thread::spawn(move || manager.wait_for_scheduler("queued").unwrap())
+        };
+        while manager.queue_status().unwrap().queued == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let persisted_manager = SessionManager::new();
+        for id in ["persisted", "finished"] {
+            add_session(&persisted_manager, id);
+            persisted_manager
+                .set_completed(id, "https://github.com/o/r/pull/1".to_string())
+                .unwrap();
+        }
+        let persisted = persisted_manager.list_sessions().unwrap();
+
+        let mut summary = reconcile_sessions_in(checkouts, &manager, &persisted, true).unwrap();
+        summary.missing_work_dirs.sort();
+
+        assert_eq!(
+            summary.removed_dirs,
+            vec![checkouts.join("session-orphan").display().to_string()]
+        );
+        assert!(!checkouts.join("session-orphan").exists());
+        assert!(checkouts.join("session-live").exists());
+        assert!(checkouts.join("other").exists());
+
+        assert_eq!(
+            summary.missing_work_dirs,
+            vec!["cloning", "finished", "vanished"]
+        );
+        assert_eq!(summary.marked_error, vec!["vanished"]);
+
+        let vanished = manager.get_session_info("vanished").unwrap();
+        assert_eq!(vanished.status, SessionStatus::Error);
+        assert_eq!(
+            vanished.error_message.as_deref(),
+            Some(MISSING_WORK_DIR_MESSAGE)
+        );
+        assert_eq!(
+            manager.get_session_info("cloning").unwrap().status,
+            SessionStatus::Initializing
+        );
+
+        manager.resume_scheduler();
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn test_list_live_checkouts_classifies_dirs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let checkouts = temp_dir.path();
+        for dir in [
+            "session-live",
+            "session-persisted",
+            "session-orphan",
+            "other",
+        ] {
+            fs::create_dir_all(checkouts.join(dir)).unwrap();
+        }
+        fs::write(checkouts.join("session-live/file.txt"), "12345").unwrap();
+
+        let manager = SessionManager::new();
+        add_session(&manager, "live");
+        manager.set_working("live", 1234).unwrap();
+
+        let persisted_manager = SessionManager::new();
+        add_session(&persisted_manager, "persisted");
+        persisted_manager
+            .set_completed("persisted", "https://github.com/o/r/pull/1".to_string())
+            .unwrap();
+        let persisted = persisted_manager.list_sessions().unwrap();
+
+        let checkouts = list_live_checkouts_in(checkouts, &manager, &persisted).unwrap();
+        let summary: Vec<_> = checkouts
+            .iter()
+            .map(|c| (c.session_id.as_str(), c.known, c.status, c.bytes))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("live", true, Some(SessionStatus::Working), 5),
+                ("orphan", false, None, 0),
+                ("persisted", true, Some(SessionStatus::Completed), 0),
+            ]
+        );
+        assert!(checkouts.iter().all(|c| c.modified_ms.is_some()));
+    }
+
+    #[test]
+    fn test_reap_stale_sessions() {
+        let mut exited = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = exited.id();
+        exited.wait().unwrap();
+
+        let manager = SessionManager::new();
+        for id in ["dead", "alive", "starting", "committing"] {
+            add_session(&manager, id);
+        }
+        for (id, pid) in [
+            ("dead", dead_pid),
+            ("alive", std::process::id()),
+            ("committing", dead_pid),
+        ] {
+            manager.set_working(id, pid).unwrap();
+            manager.enter_phase(id, SessionPhase::Run).unwrap();
+        }
+        // Claude has exited and the session moved on to committing its work.
+        manager.clear_process_id("committing").unwrap();
+        manager
+            .enter_phase("committing", SessionPhase::Commit)
+            .unwrap();
+
+        let reaped =
+            reap_stale_sessions_with(&manager, crate::claude_session::process::process_exists)
+                .unwrap();
+
+        assert_eq!(reaped, vec!["dead"]);
+        let dead = manager.get_session_info("dead").unwrap();
+        assert_eq!(dead.status, SessionStatus::Error);
+        assert_eq!(dead.error_message.as_deref(), Some(STALE_PROCESS_MESSAGE));
+        assert_eq!(
+            manager.get_session_info("alive").unwrap().status,
+            SessionStatus::Working
+        );
+        assert_eq!(
+            manager.get_session_info("starting").unwrap().status,
+            SessionStatus::Initializing
+        );
+        assert_eq!(
+            manager.get_session_info("committing").unwrap().status,
+            SessionStatus::Working
+        );
+
+        // A stale pid alone doesn't make a session past its Run phase dead.
+        manager.set_working("committing", dead_pid).unwrap();
+        assert!(reap_stale_sessions_with(&manager, |pid| pid != dead_pid)
+            .unwrap()
+            .is_empty());
+    }
+}
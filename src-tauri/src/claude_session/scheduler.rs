@@ -0,0 +1,134 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Gate that session threads pass through before starting work. While paused,
+/// new sessions wait here; sessions already past the gate are unaffected.
+/// It also spaces out Claude spawns by a minimum interval.
+pub struct Scheduler {
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    min_spawn_interval: Mutex<Duration>,
+    last_spawn: Mutex<Option<Instant>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            paused: Mutex::new(false),
+            resumed: Condvar::new(),
+            min_spawn_interval: Mutex::new(Duration::ZERO),
+            last_spawn: Mutex::new(None),
+        }
+    }
+
+    fn lock_paused(&self) -> MutexGuard<'_, bool> {
+        self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn pause(&self) {
+        *self.lock_paused() = true;
+    }
+
+    pub fn resume(&self) {
+        *self.lock_paused() = false;
+        self.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.lock_paused()
+    }
+
+    pub fn wait_until_resumed(&self) {
+        let mut paused = self.lock_paused();
+        while *paused {
+            paused = self.resumed.wait(paused).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    pub fn set_min_spawn_interval(&self, interval: Duration) {
+        *self
+            .min_spawn_interval
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = interval;
+    }
+
+    /// Blocks until at least the minimum interval has passed since the last
+    /// spawn, then records this one. Waiters go one at a time, so each is
+    /// spaced from the one before it.
+    pub fn wait_for_spawn_slot(&self) {
+        let interval = *self
+            .min_spawn_interval
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut last_spawn = self.last_spawn.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(last) = *last_spawn {
+            let ready_at = last + interval;
+            let now = Instant::now();
+            if ready_at > now {
+                std::thread::sleep(ready_at - now);
+            }
+        }
+        *last_spawn = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wait_blocks_until_resumed() {
+        let scheduler = Arc::new(Scheduler::new());
+        scheduler.wait_until_resumed();
+
+        scheduler.pause();
+        let waiter = {
+            let scheduler = scheduler.clone();
+            std::thread::spawn(move || scheduler.wait_until_resumed())
+        };
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+
+        scheduler.resume();
+        waiter.join().unwrap();
+        assert!(!scheduler.is_paused());
+    }
+
+    #[test]
+    fn test_spawns_spaced_by_min_interval() {
+        let scheduler = Arc::new(Scheduler::new());
+        let interval = Duration::from_millis(80);
+        scheduler.set_min_spawn_interval(interval);
+
+        let spawns: Vec<Instant> = (0..2)
+            .map(|_| {
+                let scheduler = scheduler.clone();
+                std::thread::spawn(move || {
+                    scheduler.wait_for_spawn_slot();
+                    Instant::now()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|spawn| spawn.join().unwrap())
+            .collect();
+
+        let gap = spawns[0].max(spawns[1]) - spawns[0].min(spawns[1]);
+        assert!(gap >= interval, "{:?}", gap);
+
+        scheduler.set_min_spawn_interval(Duration::ZERO);
+        let start = Instant::now();
+        scheduler.wait_for_spawn_slot();
+        assert!(start.elapsed() < interval);
+    }
+}
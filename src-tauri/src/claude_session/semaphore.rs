@@ -0,0 +1,107 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+struct SemaphoreState {
+    limit: usize,
+    in_use: usize,
+}
+
+pub struct Semaphore {
+    state: Mutex<SemaphoreState>,
+    released: Condvar,
+}
+
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            state: Mutex::new(SemaphoreState {
+                limit: limit.max(1),
+                in_use: 0,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, SemaphoreState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        self.lock_state().limit = limit.max(1);
+        self.released.notify_all();
+    }
+
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut state = self.lock_state();
+        while state.in_use >= state.limit {
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.in_use += 1;
+
+        SemaphorePermit { semaphore: self }
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.lock_state().in_use -= 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_semaphore_limits_concurrent_permits() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let current = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let semaphore = semaphore.clone();
+                let current = current.clone();
+                let peak = peak.clone();
+                std::thread::spawn(move || {
+                    let _permit = semaphore.acquire();
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    current.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_semaphore_zero_limit_allows_one() {
+        let semaphore = Semaphore::new(0);
+
+        let _permit = semaphore.acquire();
+        assert_eq!(semaphore.lock_state().in_use, 1);
+    }
+
+    #[test]
+    fn test_semaphore_set_limit() {
+        let semaphore = Semaphore::new(1);
+        semaphore.set_limit(2);
+
+        let _first = semaphore.acquire();
+        let _second = semaphore.acquire();
+        assert_eq!(semaphore.lock_state().in_use, 2);
+    }
+}
@@ -1,15 +1,153 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::config::EffectiveConfig;
+use super::orchestrator::SessionConfig;
+use super::process::OutputBuffer;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
+    Queued,
     Initializing,
     Working,
     Completed,
+    /// Claude finished without changing anything, so no PR was opened.
+    NoChanges,
     Error,
 }
 
+impl SessionStatus {
+    pub fn progress_rank(self) -> u8 {
+        match self {
+            SessionStatus::Error => 0,
+            SessionStatus::Queued => 1,
+            SessionStatus::Initializing => 2,
+            SessionStatus::Working => 3,
+            SessionStatus::Completed | SessionStatus::NoChanges => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionPhase {
+    Clone,
+    Run,
+    Commit,
+    Push,
+    PullRequest,
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTransition {
+    pub phase: SessionPhase,
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusTransition {
+    pub status: SessionStatus,
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMarkerKind {
+    TestRunStarted,
+    PrOpened,
+}
+
+/// A notable point in a session's output: the test command Claude ran, or
+/// the URL of the PR that was opened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputMarker {
+    pub kind: OutputMarkerKind,
+    pub detail: String,
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimelineEvent {
+    Status {
+        status: SessionStatus,
+        at_ms: u64,
+    },
+    Phase {
+        phase: SessionPhase,
+        at_ms: u64,
+    },
+    Marker {
+        kind: OutputMarkerKind,
+        detail: String,
+        at_ms: u64,
+    },
+}
+
+impl TimelineEvent {
+    pub fn at_ms(&self) -> u64 {
+        match self {
+            TimelineEvent::Status { at_ms, .. }
+            | TimelineEvent::Phase { at_ms, .. }
+            | TimelineEvent::Marker { at_ms, .. } => *at_ms,
+        }
+    }
+}
+
+/// Status changes, phase transitions and output markers merged in time order.
+/// Events at the same millisecond keep that order: status, phase, marker.
+pub fn session_timeline(info: &SessionInfo) -> Vec<TimelineEvent> {
+    let statuses = info.status_changes.iter().map(|t| TimelineEvent::Status {
+        status: t.status,
+        at_ms: t.at_ms,
+    });
+    let phases = info.phases.iter().map(|t| TimelineEvent::Phase {
+        phase: t.phase,
+        at_ms: t.at_ms,
+    });
+    let markers = info.markers.iter().map(|m| TimelineEvent::Marker {
+        kind: m.kind,
+        detail: m.detail.clone(),
+        at_ms: m.at_ms,
+    });
+
+    let mut timeline: Vec<TimelineEvent> = statuses.chain(phases).chain(markers).collect();
+    timeline.sort_by_key(TimelineEvent::at_ms);
+    timeline
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhaseDuration {
+    pub phase: SessionPhase,
+    pub duration_ms: u64,
+}
+
+/// Time spent in each phase; a phase that hasn't been left yet runs until `now_ms`.
+pub fn phase_durations(transitions: &[PhaseTransition], now_ms: u64) -> Vec<PhaseDuration> {
+    transitions
+        .iter()
+        .enumerate()
+        .filter(|(_, transition)| transition.phase != SessionPhase::Finished)
+        .map(|(i, transition)| {
+            let end_ms = transitions.get(i + 1).map_or(now_ms, |next| next.at_ms);
+            PhaseDuration {
+                phase: transition.phase,
+                duration_ms: end_ms.saturating_sub(transition.at_ms),
+            }
+        })
+        .collect()
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
     pub id: String,
@@ -19,6 +157,155 @@ pub struct SessionInfo {
     pub git_directory: String,
     pub instructions: String,
     pub created_at: u64,
+    /// When the session completed, failed or found nothing to change, in
+    /// seconds like `created_at`; mirrors its `Finished` phase. `None` while it
+    /// is still running.
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    #[serde(default)]
+    pub config: Option<EffectiveConfig>,
+    #[serde(default)]
+    pub phases: Vec<PhaseTransition>,
+    /// Whether the checkout built before Claude ran; `None` if not checked.
+    #[serde(default)]
+    pub baseline_build_ok: Option<bool>,
+    /// Whether merging the branch into its base would conflict, as of the
+    /// dry run after committing.
+    #[serde(default)]
+    pub will_conflict: bool,
+    #[serde(default)]
+    pub conflicting_paths: Vec<String>,
+    /// A locked session can only be cancelled with `force`.
+    #[serde(default)]
+    pub locked: bool,
+    /// Whether Claude ran a test command itself; `None` when its output
+    /// doesn't show tool use.
+    #[serde(default)]
+    pub tests_run_by_claude: Option<bool>,
+    #[serde(default)]
+    pub claude_test_commands: Vec<String>,
+    /// When the session joined the queue.
+    #[serde(default)]
+    pub queued_at_ms: Option<u64>,
+    /// Place in the queue, from 1, while `Queued`. Filled in when read from
+    /// the manager, not persisted.
+    #[serde(default)]
+    pub queue_position: Option<usize>,
+    /// Re-enqueued from persistence after the app restarted.
+    #[serde(default)]
+    pub resumed_from_queue: bool,
+    /// Shared by the sessions started by one `spawn_batch_sessions` call.
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    /// The session started to retry this one, once it has been retried.
+    #[serde(default)]
+    pub retried_as: Option<String>,
+    #[serde(default)]
+    pub status_changes: Vec<StatusTransition>,
+    #[serde(default)]
+    pub markers: Vec<OutputMarker>,
+    /// The model Claude reported using, or the configured one until it does.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Size of the session's commit, once it has committed.
+    #[serde(default)]
+    pub diffstat: Option<DiffStat>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffStat {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl DiffStat {
+    /// Like the last line of `git diff --stat`.
+    pub fn summary(&self) -> String {
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        format!(
+            "{} changed, {}(+), {}(-)",
+            plural(self.files, "file", "files"),
+            plural(self.insertions, "insertion", "insertions"),
+            plural(self.deletions, "deletion", "deletions")
+        )
+    }
+}
+
+impl SessionInfo {
+    /// How long the session ran, from creation to its `Finished` phase.
+    pub fn duration_secs(&self) -> Option<u64> {
+        let finished = self
+            .phases
+            .iter()
+            .rev()
+            .find(|t| t.phase == SessionPhase::Finished)?;
+        Some((finished.at_ms / 1000).saturating_sub(self.created_at))
+    }
+
+    /// The phase the session last entered, if any.
+    pub fn current_phase(&self) -> Option<SessionPhase> {
+        self.phases.last().map(|t| t.phase)
+    }
+
+    /// When the session reached a terminal state, falling back to its creation time
+    /// for records that predate phase tracking.
+    pub fn finished_at_ms(&self) -> u64 {
+        self.phases
+            .iter()
+            .rev()
+            .find(|t| t.phase == SessionPhase::Finished)
+            .map_or(self.created_at * 1000, |t| t.at_ms)
+    }
+
+    /// A few lines of markdown suitable for pasting into an issue or chat thread.
+    pub fn summary_markdown(&self) -> String {
+        let status = match self.status {
+            SessionStatus::Queued => "Queued",
+            SessionStatus::Initializing => "Initializing",
+            SessionStatus::Working => "In progress",
+            SessionStatus::Completed => "Completed",
+            SessionStatus::NoChanges => "No changes",
+            SessionStatus::Error => "Failed",
+        };
+        let task = self
+            .instructions
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty());
+
+        let mut lines = vec![match task {
+            Some(task) => format!("**{}**: {}", status, task),
+            None => format!("**{}**", status),
+        }];
+        if let Some(pr_url) = &self.pr_url {
+            lines.push(format!("PR: {}", pr_url));
+        }
+        if let Some(diffstat) = &self.diffstat {
+            lines.push(format!("Diffstat: {}", diffstat.summary()));
+        }
+        if self.baseline_build_ok == Some(false) {
+            lines.push("Note: the repo already failed to build before Claude ran".to_string());
+        }
+        if let Some(config) = self.config.as_ref().filter(|c| !c.pr_labels.is_empty()) {
+            lines.push(format!("Labels: {}", config.pr_labels.join(", ")));
+        }
+        if self.will_conflict {
+            lines.push(format!(
+                "Conflicts with the base branch in: {}",
+                self.conflicting_paths.join(", ")
+            ));
+        }
+        if let Some(error) = &self.error_message {
+            lines.push(format!(
+                "Error: {}",
+                error.lines().next().unwrap_or_default()
+            ));
+        }
+
+        lines.join("\n")
+    }
 }
 
 #[derive(Debug)]
@@ -26,7 +313,10 @@ pub struct Session {
     pub info: SessionInfo,
     pub work_dir: PathBuf,
     pub branch_name: String,
+    pub base_branch: String,
     pub process_id: Option<u32>,
+    pub launch_config: Option<SessionConfig>,
+    pub output: Arc<OutputBuffer>,
 }
 
 impl Session {
@@ -51,30 +341,122 @@ impl Session {
                 git_directory,
                 instructions,
                 created_at,
+                completed_at: None,
+                config: None,
+                phases: Vec::new(),
+                baseline_build_ok: None,
+                will_conflict: false,
+                conflicting_paths: Vec::new(),
+                locked: false,
+                tests_run_by_claude: None,
+                claude_test_commands: Vec::new(),
+                queued_at_ms: None,
+                queue_position: None,
+                resumed_from_queue: false,
+                batch_id: None,
+                retried_as: None,
+                status_changes: vec![StatusTransition {
+                    status: SessionStatus::Initializing,
+                    at_ms: now_ms(),
+                }],
+                markers: Vec::new(),
+                model: None,
+                diffstat: None,
             },
             work_dir,
             branch_name,
+            base_branch: "main".to_string(),
             process_id: None,
+            launch_config: None,
+            output: Arc::new(OutputBuffer::default()),
         }
     }
 
     pub fn set_status(&mut self, status: SessionStatus) {
         self.info.status = status;
+        self.info.status_changes.push(StatusTransition {
+            status,
+            at_ms: now_ms(),
+        });
+    }
+
+    pub fn add_marker(&mut self, kind: OutputMarkerKind, detail: String) {
+        self.info.markers.push(OutputMarker {
+            kind,
+            detail,
+            at_ms: now_ms(),
+        });
+    }
+
+    pub fn reset_for_rerun(&mut self) {
+        self.info.status_changes.clear();
+        self.info.markers.clear();
+        self.set_status(SessionStatus::Initializing);
+        self.info.pr_url = None;
+        self.info.error_message = None;
+        self.info.completed_at = None;
+        self.info.phases.clear();
+        self.info.baseline_build_ok = None;
+        self.info.will_conflict = false;
+        self.info.conflicting_paths.clear();
+        self.info.tests_run_by_claude = None;
+        self.info.claude_test_commands.clear();
+        self.info.diffstat = None;
+        self.info.model = self.info.config.as_ref().and_then(|c| c.model.clone());
+        self.output.clear();
+        self.branch_name.clear();
+        self.process_id = None;
     }
 
     pub fn set_working(&mut self, process_id: u32) {
-        self.info.status = SessionStatus::Working;
+        self.set_status(SessionStatus::Working);
         self.process_id = Some(process_id);
     }
 
+    pub fn enter_phase(&mut self, phase: SessionPhase) {
+        self.info.phases.push(PhaseTransition {
+            phase,
+            at_ms: now_ms(),
+        });
+    }
+
+    fn finish_at(&mut self, at_ms: u64) {
+        self.info.phases.push(PhaseTransition {
+            phase: SessionPhase::Finished,
+            at_ms,
+        });
+        self.info.completed_at = Some(at_ms / 1000);
+    }
+
+    fn finish(&mut self) {
+        self.finish_at(now_ms());
+    }
+
     pub fn set_completed(&mut self, pr_url: String) {
-        self.info.status = SessionStatus::Completed;
+        self.finish();
+        self.set_status(SessionStatus::Completed);
+        if !pr_url.is_empty() {
+            self.add_marker(OutputMarkerKind::PrOpened, pr_url.clone());
+        }
         self.info.pr_url = Some(pr_url);
         self.process_id = None;
     }
 
+    pub fn set_no_changes(&mut self) {
+        self.finish();
+        self.set_status(SessionStatus::NoChanges);
+        self.process_id = None;
+    }
+
     pub fn set_error(&mut self, message: String) {
-        self.info.status = SessionStatus::Error;
+        self.set_error_at(message, now_ms());
+    }
+
+    /// Fails the session as of `at_ms`, for a run whose end is only noticed
+    /// later, such as one cut short by the app exiting.
+    pub fn set_error_at(&mut self, message: String, at_ms: u64) {
+        self.finish_at(at_ms);
+        self.set_status(SessionStatus::Error);
         self.info.error_message = Some(message);
         self.process_id = None;
     }
@@ -83,6 +465,8 @@ impl Session {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude_session::config::resolve_config;
+    use std::collections::HashMap;
 
     #[test]
     fn test_session_new() {
@@ -98,6 +482,8 @@ mod tests {
         assert_eq!(session.info.status, SessionStatus::Initializing);
         assert!(session.info.pr_url.is_none());
         assert!(session.info.error_message.is_none());
+        assert!(session.info.completed_at.is_none());
+        assert!(session.info.duration_secs().is_none());
     }
 
     #[test]
@@ -153,4 +539,260 @@ mod tests {
             Some("Something went wrong".to_string())
         );
     }
+
+    #[test]
+    fn test_summary_markdown_completed() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            "Add feature X\nwith details".to_string(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+        session.set_completed("https://github.com/owner/repo/pull/123".to_string());
+
+        assert_eq!(
+            session.info.summary_markdown(),
+            "**Completed**: Add feature X\nPR: https://github.com/owner/repo/pull/123"
+        );
+
+        session.info.diffstat = Some(DiffStat {
+            files: 3,
+            insertions: 12,
+            deletions: 4,
+        });
+        assert_eq!(
+            session.info.summary_markdown(),
+            "**Completed**: Add feature X\nPR: https://github.com/owner/repo/pull/123\n\
+             Diffstat: 3 files changed, 12 insertions(+), 4 deletions(-)"
+        );
+    }
+
+    #[test]
+    fn test_diffstat_summary() {
+        let diffstat = |files, insertions, deletions| DiffStat {
+            files,
+            insertions,
+            deletions,
+        };
+        assert_eq!(
+            diffstat(1, 1, 0).summary(),
+            "1 file changed, 1 insertion(+), 0 deletions(-)"
+        );
+        assert_eq!(
+            diffstat(2, 0, 1).summary(),
+            "2 files changed, 0 insertions(+), 1 deletion(-)"
+        );
+    }
+
+    #[test]
+    fn test_summary_markdown_omits_missing_fields() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            String::new(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+        assert_eq!(session.info.summary_markdown(), "**Initializing**");
+
+        session.set_error("Clone failed\nstack trace".to_string());
+        assert_eq!(
+            session.info.summary_markdown(),
+            "**Failed**\nError: Clone failed"
+        );
+    }
+
+    #[test]
+    fn test_session_reset_for_rerun() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            "Add feature X".to_string(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+        let mut config = resolve_config(&HashMap::new(), &HashMap::new());
+        config.model = Some("sonnet".to_string());
+        session.info.config = Some(config);
+        session.info.model = Some("claude-sonnet-4-5".to_string());
+        session.info.diffstat = Some(DiffStat {
+            files: 1,
+            insertions: 2,
+            deletions: 0,
+        });
+        session.enter_phase(SessionPhase::Clone);
+        session.set_error("Clone failed".to_string());
+
+        session.reset_for_rerun();
+
+        assert_eq!(session.info.status, SessionStatus::Initializing);
+        assert!(session.info.error_message.is_none());
+        assert!(session.info.phases.is_empty());
+        assert!(session.info.diffstat.is_none());
+        assert_eq!(session.info.model.as_deref(), Some("sonnet"));
+        assert!(!session.info.summary_markdown().contains("Diffstat"));
+        assert!(session.branch_name.is_empty());
+        assert_eq!(session.info.instructions, "Add feature X");
+    }
+
+    #[test]
+    fn test_phase_durations() {
+        let transition = |phase, at_ms| PhaseTransition { phase, at_ms };
+        let transitions = vec![
+            transition(SessionPhase::Clone, 1_000),
+            transition(SessionPhase::Run, 4_000),
+            transition(SessionPhase::Commit, 64_000),
+            transition(SessionPhase::Push, 64_500),
+            transition(SessionPhase::PullRequest, 66_500),
+            transition(SessionPhase::Finished, 67_000),
+        ];
+
+        let durations: Vec<_> = phase_durations(&transitions, 100_000)
+            .into_iter()
+            .map(|d| (d.phase, d.duration_ms))
+            .collect();
+
+        assert_eq!(
+            durations,
+            vec![
+                (SessionPhase::Clone, 3_000),
+                (SessionPhase::Run, 60_000),
+                (SessionPhase::Commit, 500),
+                (SessionPhase::Push, 2_000),
+                (SessionPhase::PullRequest, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_phase_durations_in_progress() {
+        let transitions = vec![
+            PhaseTransition {
+                phase: SessionPhase::Clone,
+                at_ms: 1_000,
+            },
+            PhaseTransition {
+                phase: SessionPhase::Run,
+                at_ms: 2_000,
+            },
+        ];
+
+        let durations = phase_durations(&transitions, 5_000);
+
+        assert_eq!(durations.len(), 2);
+        assert_eq!(durations[1].phase, SessionPhase::Run);
+        assert_eq!(durations[1].duration_ms, 3_000);
+    }
+
+    #[test]
+    fn test_set_completed_records_finished_phase() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            "Add feature X".to_string(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+
+        session.enter_phase(SessionPhase::Clone);
+        session.set_completed("https://github.com/owner/repo/pull/123".to_string());
+
+        let phases: Vec<_> = session.info.phases.iter().map(|t| t.phase).collect();
+        assert_eq!(phases, vec![SessionPhase::Clone, SessionPhase::Finished]);
+    }
+
+    #[test]
+    fn test_session_timeline_is_chronological() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            "Add feature X".to_string(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+        let status = |status, at_ms| StatusTransition { status, at_ms };
+        let phase = |phase, at_ms| PhaseTransition { phase, at_ms };
+        let marker = |kind, detail: &str, at_ms| OutputMarker {
+            kind,
+            detail: detail.to_string(),
+            at_ms,
+        };
+        session.info.status_changes = vec![
+            status(SessionStatus::Initializing, 100),
+            status(SessionStatus::Working, 300),
+            status(SessionStatus::Completed, 900),
+        ];
+        session.info.phases = vec![
+            phase(SessionPhase::Clone, 100),
+            phase(SessionPhase::Run, 250),
+            phase(SessionPhase::PullRequest, 700),
+            phase(SessionPhase::Finished, 900),
+        ];
+        session.info.markers = vec![
+            marker(OutputMarkerKind::TestRunStarted, "cargo test", 400),
+            marker(
+                OutputMarkerKind::PrOpened,
+                "https://github.com/o/r/pull/1",
+                900,
+            ),
+        ];
+
+        let timeline = session_timeline(&session.info);
+
+        let times: Vec<u64> = timeline.iter().map(TimelineEvent::at_ms).collect();
+        assert_eq!(times, [100, 100, 250, 300, 400, 700, 900, 900, 900]);
+        assert_eq!(
+            timeline[0],
+            TimelineEvent::Status {
+                status: SessionStatus::Initializing,
+                at_ms: 100
+            }
+        );
+        assert_eq!(
+            timeline[4],
+            TimelineEvent::Marker {
+                kind: OutputMarkerKind::TestRunStarted,
+                detail: "cargo test".to_string(),
+                at_ms: 400
+            }
+        );
+        assert!(matches!(
+            timeline[8],
+            TimelineEvent::Marker {
+                kind: OutputMarkerKind::PrOpened,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_status_changes_are_recorded() {
+        let mut session = Session::new(
+            "test-id".to_string(),
+            "/path/to/repo".to_string(),
+            "Add feature X".to_string(),
+            PathBuf::from("/tmp/session-test"),
+            "claude/feature-123".to_string(),
+        );
+        session.set_working(42);
+        session.set_completed("https://github.com/o/r/pull/1".to_string());
+
+        let statuses: Vec<_> = session
+            .info
+            .status_changes
+            .iter()
+            .map(|t| t.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                SessionStatus::Initializing,
+                SessionStatus::Working,
+                SessionStatus::Completed
+            ]
+        );
+        assert_eq!(session.info.markers.len(), 1);
+        assert_eq!(session.info.markers[0].kind, OutputMarkerKind::PrOpened);
+    }
 }
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The entry with line endings unified, runs of whitespace within a line
+/// collapsed to one space and blank lines at either end dropped, so copies
+/// that differ only in those hash alike.
+fn normalize(content: &str) -> String {
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    let start = lines.iter().position(|line| !line.is_empty());
+    let end = lines.iter().rposition(|line| !line.is_empty());
+    match (start, end) {
+        (Some(start), Some(end)) => lines[start..=end].join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Groups of entries whose normalized content is identical, each in
+/// `list_entries` order and ordered by their first path. Blank entries and
+/// ones that can't be read as text are left out.
+pub fn find_duplicate_entries_in(journal_dir: &Path) -> Result<Vec<Vec<String>>, String> {
+    let mut by_hash: HashMap<git2::Oid, Vec<String>> = HashMap::new();
+    for entry in crate::list_entries_in(journal_dir)? {
+        let Ok(content) = fs::read_to_string(&entry) else {
+            continue;
+        };
+        let normalized = normalize(&content);
+        if normalized.is_empty() {
+            continue;
+        }
+        let hash = git2::Oid::hash_object(git2::ObjectType::Blob, normalized.as_bytes())
+            .map_err(|e| format!("Failed to hash {}: {}", entry, e.message()))?;
+        by_hash.entry(hash).or_default().push(entry);
+    }
+
+    let mut groups: Vec<Vec<String>> = by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect();
+    groups.sort();
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_entries_groups_matching_content() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        let month_dir = journal_dir.join("2024/04");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        fs::write(month_dir.join("entry-1.md"), "# Sunday\nWalked  the dog.\n").unwrap();
+        fs::write(
+            month_dir.join("entry-1 (conflicted copy).md"),
+            "\r\n# Sunday \r\nWalked the\tdog.\r\n\r\n",
+        )
+        .unwrap();
+        fs::write(month_dir.join("entry-2.md"), "# Monday\nWalked the dog.\n").unwrap();
+        fs::write(month_dir.join("blank-1.md"), "").unwrap();
+        fs::write(month_dir.join("blank-2.md"), "\n  \n").unwrap();
+
+        let path = |name: &str| month_dir.join(name).display().to_string();
+        assert_eq!(
+            find_duplicate_entries_in(journal_dir).unwrap(),
+            vec![vec![
+                path("entry-1 (conflicted copy).md"),
+                path("entry-1.md")
+            ]]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Html,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "html" => Ok(ExportFormat::Html),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            other => Err(format!(
+                "Unsupported export format '{}': use html or md",
+                other
+            )),
+        }
+    }
+}
+
+const DOCUMENT_STYLE: &str = "body { font-family: -apple-system, BlinkMacSystemFont, \"Segoe UI\", sans-serif; max-width: 720px; margin: 2rem auto; padding: 0 1rem; line-height: 1.6; color: #1f2937; } a { color: #6366f1; } ul { padding-left: 1.5rem; }";
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wraps text between pairs of `marker` in `tag`. An unpaired marker is left as-is.
+fn replace_delimited(text: &str, marker: &str, tag: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(marker) {
+        let after = &rest[start + marker.len()..];
+        match after.find(marker) {
+            Some(end) if end > 0 => {
+                result.push_str(&rest[..start]);
+                result.push_str(&format!("<{}>{}</{}>", tag, &after[..end], tag));
+                rest = &after[end + marker.len()..];
+            }
+            _ => break,
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+const LINK_SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+/// Only links with a scheme in `LINK_SCHEMES` become anchors, so an entry
+/// can't smuggle a `javascript:` href into the exported page.
+fn is_safe_href(href: &str) -> bool {
+    let href = href.trim().to_ascii_lowercase();
+    LINK_SCHEMES.iter().any(|scheme| href.starts_with(scheme))
+}
+
+fn render_links(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+            break;
+        };
+        let Some(end) = rest[close + 2..].find(')').map(|i| close + 2 + i) else {
+            break;
+        };
+
+        let href = &rest[close + 2..end];
+        let label = &rest[open + 1..close];
+        result.push_str(&rest[..open]);
+        if is_safe_href(href) {
+            result.push_str(&format!("<a href=\"{}\">{}</a>", href, label));
+        } else {
+            result.push_str(label);
+        }
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Renders the inline formats the editor understands: links, bold,
+/// strikethrough, underline and italic.
+fn render_inline(text: &str) -> String {
+    let html = render_links(&escape_html(text));
+    let html = replace_delimited(&html, "**", "strong");
+    let html = replace_delimited(&html, "~~", "del");
+    let html = replace_delimited(&html, "__", "u");
+    replace_delimited(&html, "*", "em")
+}
+
+/// Strips the editor's scroll (`~S5~ `) and collapse (`^ `) markers and
+/// returns the heading level and text.
+pub(crate) fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let mut rest = line;
+    if let Some(after) = rest.strip_prefix("~S") {
+        let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 {
+            if let Some(after) = after[digits..].strip_prefix("~ ") {
+                rest = after;
+            }
+        }
+    }
+    rest = rest.strip_prefix("^ ").unwrap_or(rest);
+
+    let level = rest.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    rest[level..].strip_prefix(' ').map(|text| (level, text))
+}
+
+fn parse_bullet(line: &str) -> Option<(usize, &str)> {
+    let indent = line.chars().take_while(|c| *c == '\t').count();
+    if indent == 0 {
+        return None;
+    }
+    line[indent..].strip_prefix("- ").map(|text| (indent, text))
+}
+
+pub fn render_entry_html(content: &str) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut bullets: Vec<String> = Vec::new();
+
+    fn flush(blocks: &mut Vec<String>, paragraph: &mut Vec<String>, bullets: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            blocks.push(format!("<p>{}</p>", paragraph.join("<br>\n")));
+            paragraph.clear();
+        }
+        if !bullets.is_empty() {
+            blocks.push(format!("<ul>\n{}\n</ul>", bullets.join("\n")));
+            bullets.clear();
+        }
+    }
+
+    for line in content.lines() {
+        if let Some((level, text)) = parse_heading(line) {
+            flush(&mut blocks, &mut paragraph, &mut bullets);
+            blocks.push(format!("<h{}>{}</h{}>", level, render_inline(text), level));
+        } else if let Some((indent, text)) = parse_bullet(line) {
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut bullets);
+            }
+            bullets.push(format!(
+                "<li style=\"margin-left: {}rem\">{}</li>",
+                (indent - 1) * 2,
+                render_inline(text)
+            ));
+        } else if line.trim().is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut bullets);
+        } else {
+            if !bullets.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut bullets);
+            }
+            paragraph.push(render_inline(line));
+        }
+    }
+    flush(&mut blocks, &mut paragraph, &mut bullets);
+
+    blocks.join("\n")
+}
+
+pub fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        DOCUMENT_STYLE,
+        body
+    )
+}
+
+fn entry_within_journal(journal_dir: &Path, filepath: &Path) -> Result<PathBuf, String> {
+    let source = filepath
+        .canonicalize()
+        .map_err(|_| format!("Entry not found: {}", filepath.display()))?;
+    let journal_dir = journal_dir
+        .canonicalize()
+        .map_err(|_| format!("Journal directory not found: {}", journal_dir.display()))?;
+
+    if !source.starts_with(&journal_dir) || !source.is_file() {
+        return Err(format!(
+            "Refusing to export {}: not an entry in the journal",
+            filepath.display()
+        ));
+    }
+    Ok(source)
+}
+
+pub fn export_entry_in(
+    journal_dir: &Path,
+    filepath: &Path,
+    dest: &Path,
+    format: ExportFormat,
+) -> Result<(), String> {
+    let source = entry_within_journal(journal_dir, filepath)?;
+    let content =
+        fs::read_to_string(&source).map_err(|e| format!("Failed to read entry: {}", e))?;
+
+    let output = match format {
+        ExportFormat::Markdown => content,
+        ExportFormat::Html => {
+            let title = source
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Journal entry");
+            html_document(title, &render_entry_html(&content))
+        }
+    };
+
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(dest, output).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_entry_html() {
+        let content = "~S3~ ^ ## Morning\nWoke up **early** & ran.\nSee [notes](https://example.com)\n\n\t- *one*\n\t\t- ~~two~~\n<Timer />";
+        let html = render_entry_html(content);
+
+        assert_eq!(
+            html,
+            "<h2>Morning</h2>\n\
+             <p>Woke up <strong>early</strong> &amp; ran.<br>\n\
+             See <a href=\"https://example.com\">notes</a></p>\n\
+             <ul>\n\
+             <li style=\"margin-left: 0rem\"><em>one</em></li>\n\
+             <li style=\"margin-left: 2rem\"><del>two</del></li>\n\
+             </ul>\n\
+             <p>&lt;Timer /&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn test_render_links_only_for_safe_schemes() {
+        assert_eq!(
+            render_links("[site](HTTP://example.com) [mail](mailto:me@example.com)"),
+            "<a href=\"HTTP://example.com\">site</a> <a href=\"mailto:me@example.com\">mail</a>"
+        );
+        assert_eq!(
+            render_links("[click]( JavaScript:void) [page](data:text/html,x)"),
+            "click page"
+        );
+    }
+
+    #[test]
+    fn test_export_entry_html_and_md() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let entry = journal_dir.join("2024/01/entry-1.md");
+        fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        fs::write(&entry, "# Title\nSome __underlined__ text").unwrap();
+
+        let html_dest = temp_dir.path().join("out/entry.html");
+        export_entry_in(&journal_dir, &entry, &html_dest, ExportFormat::Html).unwrap();
+        let html = fs::read_to_string(&html_dest).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>entry-1</title>"));
+        assert!(html.contains("<h1>Title</h1>\n<p>Some <u>underlined</u> text</p>"));
+
+        let md_dest = temp_dir.path().join("entry.md");
+        export_entry_in(&journal_dir, &entry, &md_dest, ExportFormat::Markdown).unwrap();
+        assert_eq!(
+            fs::read_to_string(&md_dest).unwrap(),
+            "# Title\nSome __underlined__ text"
+        );
+    }
+
+    #[test]
+    fn test_export_rejects_outside_journal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        fs::create_dir_all(&journal_dir).unwrap();
+        let outside = temp_dir.path().join("secret.md");
+        fs::write(&outside, "secret").unwrap();
+
+        let dest = temp_dir.path().join("out.html");
+        let result = export_entry_in(&journal_dir, &outside, &dest, ExportFormat::Html);
+        assert!(result.unwrap_err().contains("not an entry"));
+        let escaped = journal_dir.join("../secret.md");
+        assert!(export_entry_in(&journal_dir, &escaped, &dest, ExportFormat::Markdown).is_err());
+        assert!(!dest.exists());
+
+        assert!(ExportFormat::parse("pdf").is_err());
+        assert_eq!(ExportFormat::parse("MD").unwrap(), ExportFormat::Markdown);
+    }
+}
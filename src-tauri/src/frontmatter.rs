@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ParsedEntry {
+    /// The entry with its frontmatter block removed.
+    pub body: String,
+    pub frontmatter: HashMap<String, Value>,
+    pub tags: Vec<String>,
+    /// Set when part of the frontmatter couldn't be read; the fields that
+    /// could are still returned.
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// The frontmatter and body of an entry that opens with a `---` line and has
+/// a closing one. `None` when there is no complete block.
+pub fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn unquote(value: &str) -> Option<&str> {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+}
+
+fn parse_scalar(value: &str) -> Value {
+    let value = value.trim();
+    if let Some(text) = unquote(value) {
+        return Value::String(text.to_string());
+    }
+    match value {
+        "" | "~" | "null" => return Value::Null,
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return Value::from(n);
+    }
+    if let Some(n) = value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+    {
+        return Value::Number(n);
+    }
+    Value::String(value.to_string())
+}
+
+fn parse_value(value: &str) -> Value {
+    let value = value.trim();
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) if items.trim().is_empty() => Value::Array(Vec::new()),
+        Some(items) => Value::Array(items.split(',').map(parse_scalar).collect()),
+        None => parse_scalar(value),
+    }
+}
+
+/// Reads the subset of YAML journal frontmatter uses: `key: value` pairs
+/// whose values are scalars, `[inline, lists]` or `- block` lists. Anything
+/// else is skipped and reported in the returned warning.
+fn parse_fields(yaml: &str) -> (HashMap<String, Value>, Option<String>) {
+    let mut fields = HashMap::new();
+    let mut warning = None;
+    // The last key with nothing after its colon, which `- items` extend.
+    let mut list_key: Option<String> = None;
+
+    for (index, line) in yaml.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let item = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""));
+        if let (Some(item), Some(key)) = (item, &list_key) {
+            let field = fields.entry(key.clone()).or_insert(Value::Null);
+            if field.is_null() {
+                *field = Value::Array(Vec::new());
+            }
+            if let Value::Array(items) = field {
+                items.push(parse_scalar(item));
+            }
+            continue;
+        }
+
+        let pair = line.split_once(':').filter(|(key, _)| {
+            item.is_none() && !line.starts_with([' ', '\t']) && !key.trim().is_empty()
+        });
+        if let Some((key, value)) = pair {
+            let key = key.trim().to_string();
+            list_key = value.trim().is_empty().then(|| key.clone());
+            fields.insert(key, parse_value(value));
+            continue;
+        }
+
+        warning.get_or_insert_with(|| {
+            format!("Unsupported frontmatter on line {}: {}", index + 2, trimmed)
+        });
+    }
+
+    (fields, warning)
+}
+
+/// Tags from a `tags` field given as a list or a comma-separated string, with
+/// any leading `#` dropped.
+fn tags_from(fields: &HashMap<String, Value>) -> Vec<String> {
+    let raw: Vec<String> = match fields.get("tags") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        Some(Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+pub fn parse_entry(content: &str) -> ParsedEntry {
+    let Some((yaml, body)) = split_frontmatter(content) else {
+        let opens = content.starts_with("---\n") || content.starts_with("---\r\n");
+        return ParsedEntry {
+            body: content.to_string(),
+            warning: opens.then(|| "Frontmatter block is never closed".to_string()),
+            ..Default::default()
+        };
+    };
+
+    let (frontmatter, warning) = parse_fields(yaml);
+    ParsedEntry {
+        body: body.to_string(),
+        tags: tags_from(&frontmatter),
+        frontmatter,
+        warning,
+    }
+}
+
+/// Every tag used across `contents` and how many entries use it, most used
+/// first.
+pub fn count_tags<'a>(contents: impl IntoIterator<Item = &'a str>) -> Vec<TagCount> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for content in contents {
+        for tag in parse_entry(content).tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    let mut tags: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    tags.sort_by_key(|t| std::cmp::Reverse(t.count));
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry_frontmatter_and_tags() {
+        let content = "---\ntitle: \"Lake day\"\nmood: 7\nrested: true\ntags:\n  - travel\n  - '#family'\npeople: [Sam, Alex]\n---\n# Lake day\nWe swam.\n";
+
+        let parsed = parse_entry(content);
+
+        assert_eq!(parsed.body, "# Lake day\nWe swam.\n");
+        assert_eq!(parsed.tags, vec!["travel", "family"]);
+        assert_eq!(parsed.frontmatter["title"], "Lake day");
+        assert_eq!(parsed.frontmatter["mood"], 7);
+        assert_eq!(parsed.frontmatter["rested"], true);
+        assert_eq!(
+            parsed.frontmatter["people"],
+            serde_json::json!(["Sam", "Alex"])
+        );
+        assert!(parsed.warning.is_none());
+
+        let inline = parse_entry("---\ntags: work, #ideas\nempty:\nnone: []\n---\n");
+        assert_eq!(inline.tags, vec!["work", "ideas"]);
+        assert_eq!(inline.frontmatter["empty"], Value::Null);
+        assert_eq!(inline.frontmatter["none"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_parse_entry_without_or_with_bad_frontmatter() {
+        let plain = parse_entry("# Monday\nNo metadata.\n");
+        assert_eq!(plain.body, "# Monday\nNo metadata.\n");
+        assert!(plain.frontmatter.is_empty() && plain.tags.is_empty());
+        assert!(plain.warning.is_none());
+
+        let malformed =
+            parse_entry("---\nmood: 3\nthis is not yaml\n  nested: value\ntags: [a]\n---\nBody\n");
+        assert_eq!(malformed.body, "Body\n");
+        assert_eq!(malformed.frontmatter["mood"], 3);
+        assert_eq!(malformed.tags, vec!["a"]);
+        assert_eq!(
+            malformed.warning.as_deref(),
+            Some("Unsupported frontmatter on line 3: this is not yaml")
+        );
+
+        let unclosed = parse_entry("---\ntags: [a]\nBody");
+        assert_eq!(unclosed.body, "---\ntags: [a]\nBody");
+        assert!(unclosed.tags.is_empty());
+        assert!(unclosed.warning.is_some());
+    }
+
+    #[test]
+    fn test_count_tags() {
+        let entries = [
+            "---\ntags: [travel, family]\n---\n",
+            "---\ntags: [travel]\n---\n",
+            "no frontmatter",
+            "---\ntags: [work, travel, work]\n---\n",
+        ];
+
+        assert_eq!(
+            count_tags(entries),
+            vec![
+                TagCount {
+                    tag: "travel".to_string(),
+                    count: 3
+                },
+                TagCount {
+                    tag: "family".to_string(),
+                    count: 1
+                },
+                TagCount {
+                    tag: "work".to_string(),
+                    count: 1
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,17 @@
 use std::path::Path;
+use std::process::Command;
 
+use super::cli;
+use super::commit::delete_remote_branch;
+use super::pr::get_branch_pr_states;
 use super::GitOpsError;
 
+pub const DEFAULT_BRANCH_PREFIX: &str = "claude/";
+
 pub fn create_feature_branch(repo_path: &Path, branch_name: &str) -> Result<(), GitOpsError> {
-    let repo = git2::Repository::open(repo_path)?;
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return cli::create_branch(repo_path, branch_name);
+    };
 
     let head = repo.head()?;
     let head_commit = head.peel_to_commit()?;
@@ -22,7 +30,9 @@ pub fn create_feature_branch(repo_path: &Path, branch_name: &str) -> Result<(),
     Ok(())
 }
 
-pub fn generate_branch_name(description: &str) -> String {
+/// `<prefix><slug>-<unix seconds>`; the suffix keeps names from repeated
+/// descriptions apart.
+pub fn generate_branch_name(description: &str, prefix: &str) -> String {
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -41,7 +51,69 @@ pub fn generate_branch_name(description: &str) -> String {
         .take(30)
         .collect();
 
-    format!("claude/{}-{}", slug, timestamp)
+    format!("{}{}-{}", prefix, slug, timestamp)
+}
+
+/// Branch names from `git ls-remote --heads` output that start with `prefix`.
+pub fn parse_remote_heads(output: &str, prefix: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter_map(|refname| refname.trim().strip_prefix("refs/heads/"))
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+pub fn list_remote_branches(repo_path: &Path, prefix: &str) -> Result<Vec<String>, GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["ls-remote", "--heads", "origin"])
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git ls-remote: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!(
+            "git ls-remote failed: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(parse_remote_heads(
+        &String::from_utf8_lossy(&output.stdout),
+        prefix,
+    ))
+}
+
+/// A branch is safe to delete once one of its PRs merged and none is still
+/// open. Branches with no PR at all are left alone.
+pub fn should_delete_branch(pr_states: &[String]) -> bool {
+    pr_states.iter().any(|state| state == "merged")
+        && !pr_states.iter().any(|state| state == "open")
+}
+
+/// Deletes `origin`'s `prefix` branches whose PRs have merged and returns the
+/// names removed. A branch whose PR lookup or delete fails is skipped.
+pub fn sweep_merged_branches(repo_path: &Path, prefix: &str) -> Result<Vec<String>, GitOpsError> {
+    let mut removed = Vec::new();
+    for branch in list_remote_branches(repo_path, prefix)? {
+        let states = match get_branch_pr_states(repo_path, &branch) {
+            Ok(states) => states,
+            Err(e) => {
+                eprintln!("Warning: Failed to look up PRs for {}: {}", branch, e);
+                continue;
+            }
+        };
+        if !should_delete_branch(&states) {
+            continue;
+        }
+        match delete_remote_branch(repo_path, &branch) {
+            Ok(()) => removed.push(branch),
+            Err(e) => eprintln!("Warning: Failed to delete {}: {}", branch, e),
+        }
+    }
+    Ok(removed)
 }
 
 #[cfg(test)]
@@ -105,21 +177,49 @@ mod tests {
 
     #[test]
     fn test_generate_branch_name() {
-        let name = generate_branch_name("Add dark mode toggle");
+        let name = generate_branch_name("Add dark mode toggle", DEFAULT_BRANCH_PREFIX);
         assert!(name.starts_with("claude/add-dark-mode-toggle-"));
     }
 
+    #[test]
+    fn test_generate_branch_name_uses_prefix() {
+        let name = generate_branch_name("Add dark mode toggle", "bot/");
+        assert!(name.starts_with("bot/add-dark-mode-toggle-"));
+    }
+
     #[test]
     fn test_generate_branch_name_special_chars() {
-        let name = generate_branch_name("Fix bug #123: user's profile");
+        let name = generate_branch_name("Fix bug #123: user's profile", DEFAULT_BRANCH_PREFIX);
         assert!(name.starts_with("claude/fix-bug-123-user-s-profile-"));
     }
 
     #[test]
     fn test_generate_branch_name_truncates_long_description() {
-        let name = generate_branch_name("This is a very long description that should be truncated");
+        let name = generate_branch_name(
+            "This is a very long description that should be truncated",
+            DEFAULT_BRANCH_PREFIX,
+        );
         let parts: Vec<&str> = name.rsplitn(2, '-').collect();
         let slug_part = parts[1].strip_prefix("claude/").unwrap();
         assert!(slug_part.len() <= 30);
     }
+
+    #[test]
+    fn test_should_delete_branch() {
+        let states = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(should_delete_branch(&states(&["merged"])));
+        assert!(should_delete_branch(&states(&["closed", "merged"])));
+        assert!(!should_delete_branch(&states(&["merged", "open"])));
+        assert!(!should_delete_branch(&states(&["open"])));
+        assert!(!should_delete_branch(&states(&["closed"])));
+        assert!(!should_delete_branch(&[]));
+    }
+
+    #[test]
+    fn test_parse_remote_heads() {
+        let output = "abc123\trefs/heads/main\ndef456\trefs/heads/claude/fix-1\n\
+                      789abc\trefs/heads/feature/claude/x\n";
+        assert_eq!(parse_remote_heads(output, "claude/"), vec!["claude/fix-1"]);
+    }
 }
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use super::cli;
+use super::GitOpsError;
+
+/// The name `base_branch` resolves to, checked the same way as for patches:
+/// the local branch first, then the remote-tracking one.
+fn base_ref(repo_path: &Path, base_branch: &str) -> Option<String> {
+    [
+        format!("refs/heads/{}", base_branch),
+        format!("refs/remotes/origin/{}", base_branch),
+    ]
+    .into_iter()
+    .find(|name| cli::resolve_commit(repo_path, name).is_some())
+}
+
+pub fn bundle_args(dest: &Path, branch: &str, exclude: Option<&str>) -> Vec<String> {
+    let mut args = vec![
+        "bundle".to_string(),
+        "create".to_string(),
+        dest.to_string_lossy().to_string(),
+        format!("refs/heads/{}", branch),
+    ];
+    if let Some(exclude) = exclude {
+        args.push(format!("^{}", exclude));
+    }
+    args
+}
+
+/// Writes a git bundle of `branch` to `dest` and returns its size. The full
+/// history is included so the bundle can be cloned on its own; with
+/// `since_base`, only the commits since `base_branch` are, and the receiver
+/// needs the base to unbundle it.
+pub fn create_bundle(
+    repo_path: &Path,
+    branch: &str,
+    base_branch: &str,
+    since_base: bool,
+    dest: &Path,
+) -> Result<u64, GitOpsError> {
+    // Only ref lookups are needed, so the git CLI serves for every repo.
+    cli::resolve_commit(repo_path, &format!("refs/heads/{}", branch))
+        .ok_or_else(|| GitOpsError::GitError(format!("Branch not found: {}", branch)))?;
+
+    let exclude = if since_base {
+        Some(base_ref(repo_path, base_branch).ok_or_else(|| {
+            GitOpsError::GitError(format!("Base branch not found: {}", base_branch))
+        })?)
+    } else {
+        None
+    };
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(bundle_args(dest, branch, exclude.as_deref()))
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git bundle: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!(
+            "git bundle failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    Ok(fs::metadata(dest)?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::branch::create_feature_branch;
+    use crate::git_ops::commit::{create_commit, stage_all_changes, CommitIdentity};
+    use crate::test_support::setup_test_repo;
+
+    /// The shared test repo with a `claude/test-feature` commit on top of `base`.
+    fn setup_session_repo() -> (tempfile::TempDir, std::path::PathBuf) {
+        let (temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+        fs::write(repo_path.join("feature.txt"), "feature\n").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        create_commit(&repo_path, "Add feature", &identity).unwrap();
+
+        (temp_dir, repo_path)
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> std::process::Output {
+        Command::new("git")
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_full_bundle_can_be_cloned() {
+        let (_repo_dir, repo_path) = setup_session_repo();
+        let out = tempfile::tempdir().unwrap();
+        let dest = out.path().join("out/session.bundle");
+
+        let size = create_bundle(&repo_path, "claude/test-feature", "base", false, &dest).unwrap();
+        assert_eq!(size, fs::metadata(&dest).unwrap().len());
+
+        // Verifying needs a repo, but an empty one has every prerequisite.
+        let bundle = dest.to_str().unwrap();
+        let empty = tempfile::tempdir().unwrap();
+        git2::Repository::init(empty.path()).unwrap();
+        assert!(git(empty.path(), &["bundle", "verify", bundle])
+            .status
+            .success());
+        let cloned = git(
+            out.path(),
+            &[
+                "clone",
+                "--quiet",
+                "-b",
+                "claude/test-feature",
+                bundle,
+                "clone",
+            ],
+        );
+        assert!(cloned.status.success());
+        assert_eq!(
+            fs::read_to_string(out.path().join("clone/feature.txt")).unwrap(),
+            "feature\n"
+        );
+    }
+
+    #[test]
+    fn test_bundle_since_base_requires_base() {
+        let (_repo_dir, repo_path) = setup_session_repo();
+        let out = tempfile::tempdir().unwrap();
+        let dest = out.path().join("since-base.bundle");
+
+        create_bundle(&repo_path, "claude/test-feature", "base", true, &dest).unwrap();
+
+        let bundle = dest.to_str().unwrap();
+        assert!(git(&repo_path, &["bundle", "verify", bundle])
+            .status
+            .success());
+        let elsewhere = tempfile::tempdir().unwrap();
+        git2::Repository::init(elsewhere.path()).unwrap();
+        assert!(!git(elsewhere.path(), &["bundle", "verify", bundle])
+            .status
+            .success());
+
+        assert!(create_bundle(&repo_path, "claude/test-feature", "nope", true, &dest).is_err());
+        assert!(create_bundle(&repo_path, "missing", "base", false, &dest).is_err());
+    }
+}
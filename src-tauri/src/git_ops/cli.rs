@@ -0,0 +1,530 @@
+//! `git` CLI versions of the repo operations sessions need, for checkouts that
+//! libgit2 can't open but git itself can.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::commit::CommitIdentity;
+use super::GitOpsError;
+
+const UNSUPPORTED_MARKERS: &[&str] = &[
+    "unsupported extension",
+    "unsupported repository version",
+    "unsupported index version",
+    "unknown extension",
+    "invalid index version",
+];
+
+/// Whether libgit2 refused a repo for using a format or extension it doesn't
+/// implement, as opposed to the repo being missing or corrupt.
+pub fn is_unsupported_by_git2(error: &git2::Error) -> bool {
+    let message = error.message().to_lowercase();
+    UNSUPPORTED_MARKERS.iter().any(|m| message.contains(m))
+}
+
+/// Runs git in `repo_path` and returns its raw stdout. `exit_ok` lists exit
+/// codes besides 0 that aren't failures, such as `merge-tree` reporting
+/// conflicts.
+fn git_bytes(
+    repo_path: &Path,
+    args: &[&str],
+    envs: &[(&str, &OsStr)],
+    exit_ok: &[i32],
+) -> Result<(Vec<u8>, i32), GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .envs(envs.iter().copied())
+        .args(args)
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git: {}", e)))?;
+
+    let code = output.status.code().unwrap_or(-1);
+    if !output.status.success() && !exit_ok.contains(&code) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            stderr.trim()
+        )));
+    }
+    Ok((output.stdout, code))
+}
+
+fn git(repo_path: &Path, args: &[&str]) -> Result<String, GitOpsError> {
+    let (stdout, _) = git_bytes(repo_path, args, &[], &[])?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+/// NUL-separated `-z` output split into its non-empty entries.
+fn z_entries(stdout: &[u8]) -> impl Iterator<Item = String> + '_ {
+    stdout
+        .split(|b| *b == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| String::from_utf8_lossy(entry).into_owned())
+}
+
+/// Opens `repo_path` with libgit2. `Ok(None)` means libgit2 doesn't support
+/// the repo but the `git` CLI can read it, so callers should use this
+/// module's functions instead.
+pub fn open_or_fallback(repo_path: &Path) -> Result<Option<git2::Repository>, GitOpsError> {
+    match git2::Repository::open(repo_path) {
+        Ok(repo) => Ok(Some(repo)),
+        Err(e)
+            if is_unsupported_by_git2(&e)
+                && git(repo_path, &["rev-parse", "--git-dir"]).is_ok() =>
+        {
+            eprintln!(
+                "Warning: libgit2 can't open {} ({}), using the git CLI",
+                repo_path.display(),
+                e.message()
+            );
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn clone(source_path: &Path, dest: &Path) -> Result<(), GitOpsError> {
+    let parent = dest.parent().unwrap_or(source_path);
+    git(
+        parent,
+        &[
+            "clone",
+            "--quiet",
+            &source_path.to_string_lossy(),
+            &dest.to_string_lossy(),
+        ],
+    )
+    .map(|_| ())
+}
+
+pub fn origin_url(repo_path: &Path) -> Option<String> {
+    config_value(repo_path, "remote.origin.url")
+}
+
+pub fn set_origin_url(repo_path: &Path, url: &str) -> Result<(), GitOpsError> {
+    git(repo_path, &["remote", "set-url", "origin", url]).map(|_| ())
+}
+
+pub fn create_branch(repo_path: &Path, branch_name: &str) -> Result<(), GitOpsError> {
+    git(repo_path, &["checkout", "-b", branch_name]).map(|_| ())
+}
+
+pub fn stage_all(repo_path: &Path) -> Result<(), GitOpsError> {
+    git(repo_path, &["add", "-A"]).map(|_| ())
+}
+
+fn config_value(repo_path: &Path, key: &str) -> Option<String> {
+    git(repo_path, &["config", key])
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Commits the index like `create_commit`, with the same author fallbacks.
+pub fn commit(
+    repo_path: &Path,
+    message: &str,
+    identity: &CommitIdentity,
+) -> Result<git2::Oid, GitOpsError> {
+    let name = identity
+        .name
+        .clone()
+        .or_else(|| config_value(repo_path, "user.name"))
+        .unwrap_or_else(|| "Claude".to_string());
+    let email = identity
+        .email
+        .clone()
+        .or_else(|| config_value(repo_path, "user.email"))
+        .unwrap_or_else(|| "claude@dreamal.app".to_string());
+
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .env("GIT_AUTHOR_NAME", &name)
+        .env("GIT_AUTHOR_EMAIL", &email)
+        .env("GIT_COMMITTER_NAME", &name)
+        .env("GIT_COMMITTER_EMAIL", &email)
+        .args(["commit", "--allow-empty", "-m", message])
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git commit: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!(
+            "git commit failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    let head = git(repo_path, &["rev-parse", "HEAD"])?;
+    git2::Oid::from_str(&head).map_err(GitOpsError::from)
+}
+
+/// Whether the index differs from HEAD, i.e. there is something to commit.
+pub fn has_staged_changes(repo_path: &Path) -> Result<bool, GitOpsError> {
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .args(["diff", "--cached", "--quiet"])
+        .status()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git diff: {}", e)))?;
+    match status.code() {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => Err(GitOpsError::GitError(format!(
+            "git diff --cached failed: {}",
+            status
+        ))),
+    }
+}
+
+pub fn staged_change_count(repo_path: &Path) -> Result<usize, GitOpsError> {
+    Ok(git(repo_path, &["diff", "--cached", "--name-only"])?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .count())
+}
+
+pub fn is_shallow(repo_path: &Path) -> Result<bool, GitOpsError> {
+    Ok(git(repo_path, &["rev-parse", "--is-shallow-repository"])? == "true")
+}
+
+/// The commit `rev` names, or `None` if it names none.
+pub fn resolve_commit(repo_path: &Path, rev: &str) -> Option<git2::Oid> {
+    let id = git(
+        repo_path,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", rev),
+        ],
+    )
+    .ok()?;
+    git2::Oid::from_str(&id).ok()
+}
+
+pub fn merge_base(
+    repo_path: &Path,
+    one: git2::Oid,
+    two: git2::Oid,
+) -> Result<git2::Oid, GitOpsError> {
+    let id = git(
+        repo_path,
+        &["merge-base", &one.to_string(), &two.to_string()],
+    )?;
+    git2::Oid::from_str(&id).map_err(GitOpsError::from)
+}
+
+/// Paths `git status` reports as changed, untracked files listed one by one
+/// and renames as a deletion and an addition, like libgit2's status.
+pub fn dirty_paths(repo_path: &Path) -> Result<Vec<PathBuf>, GitOpsError> {
+    let (stdout, _) = git_bytes(
+        repo_path,
+        &[
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--no-renames",
+        ],
+        &[],
+        &[],
+    )?;
+    // Each entry is two status letters and a space before the path.
+    Ok(z_entries(&stdout)
+        .filter_map(|entry| entry.get(3..).map(PathBuf::from))
+        .collect())
+}
+
+/// Resets the index to HEAD, then stages `paths` as they are in the working
+/// tree, deletions included.
+pub fn stage_only(repo_path: &Path, paths: &[PathBuf]) -> Result<(), GitOpsError> {
+    git(repo_path, &["reset", "--quiet"])?;
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let mut args = vec!["add", "-A", "--"];
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    args.extend(paths.iter().map(String::as_str));
+    git_bytes(
+        repo_path,
+        &args,
+        &[("GIT_LITERAL_PATHSPECS", OsStr::new("1"))],
+        &[],
+    )
+    .map(|_| ())
+}
+
+/// Paths that would conflict if `head` were merged into `base`, worked out by
+/// `git merge-tree` without touching the working tree or index. Empty when
+/// the merge is clean.
+pub fn merge_conflicts(
+    repo_path: &Path,
+    base: git2::Oid,
+    head: git2::Oid,
+) -> Result<Vec<String>, GitOpsError> {
+    let (stdout, code) = git_bytes(
+        repo_path,
+        &[
+            "merge-tree",
+            "--write-tree",
+            "--name-only",
+            "--no-messages",
+            "-z",
+            &base.to_string(),
+            &head.to_string(),
+        ],
+        &[],
+        &[1],
+    )?;
+    if code == 0 {
+        return Ok(Vec::new());
+    }
+    // The first entry is the id of the merged tree.
+    let mut paths: Vec<String> = z_entries(&stdout).skip(1).collect();
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// `git diff` from `base` to the working tree, untracked files included.
+/// Untracked files are staged into a scratch copy of the index, so the real
+/// one is left as it was.
+pub fn diff_worktree(
+    repo_path: &Path,
+    base: git2::Oid,
+    diff_args: &[&str],
+) -> Result<Vec<u8>, GitOpsError> {
+    let index = PathBuf::from(git(repo_path, &["rev-parse", "--git-path", "index"])?);
+    let index = if index.is_absolute() {
+        index
+    } else {
+        repo_path.join(index)
+    };
+    let scratch = std::env::temp_dir().join(format!("dreamal-index-{}", uuid::Uuid::new_v4()));
+    if index.exists() {
+        fs::copy(&index, &scratch)?;
+    }
+
+    let envs = [("GIT_INDEX_FILE", scratch.as_os_str())];
+    let base = base.to_string();
+    let mut args = vec!["diff", "--cached", "--no-renames"];
+    args.extend_from_slice(diff_args);
+    args.push(&base);
+    let diff = git_bytes(repo_path, &["add", "-A"], &envs, &[])
+        .and_then(|_| git_bytes(repo_path, &args, &envs, &[]));
+    let _ = fs::remove_file(&scratch);
+
+    diff.map(|(stdout, _)| stdout)
+}
+
+/// `git diff` between two commits.
+pub fn diff_commits(
+    repo_path: &Path,
+    from: git2::Oid,
+    to: git2::Oid,
+    diff_args: &[&str],
+) -> Result<Vec<u8>, GitOpsError> {
+    let (from, to) = (from.to_string(), to.to_string());
+    let mut args = vec!["diff", "--no-renames"];
+    args.extend_from_slice(diff_args);
+    args.extend([from.as_str(), to.as_str()]);
+    git_bytes(repo_path, &args, &[], &[]).map(|(stdout, _)| stdout)
+}
+
+/// Per-file `(path, insertions, deletions)` between two commits. Binary files
+/// count no lines.
+pub fn numstat(
+    repo_path: &Path,
+    from: git2::Oid,
+    to: git2::Oid,
+) -> Result<Vec<(String, usize, usize)>, GitOpsError> {
+    let stdout = diff_commits(repo_path, from, to, &["--numstat", "-z"])?;
+    Ok(z_entries(&stdout)
+        .filter_map(|entry| {
+            let mut fields = entry.splitn(3, '\t');
+            let insertions = fields.next()?.parse().unwrap_or(0);
+            let deletions = fields.next()?.parse().unwrap_or(0);
+            Some((fields.next()?.to_string(), insertions, deletions))
+        })
+        .collect())
+}
+
+/// Fetches `refspec` from the repo at `url` into `repo_path`.
+pub fn fetch(repo_path: &Path, url: &str, refspec: &str) -> Result<(), GitOpsError> {
+    git(repo_path, &["fetch", "--quiet", url, refspec]).map(|_| ())
+}
+
+/// Like `fetch`, also deleting local refs under `refspec`'s destination that
+/// `url` doesn't have.
+pub fn fetch_pruned(repo_path: &Path, url: &str, refspec: &str) -> Result<(), GitOpsError> {
+    git(repo_path, &["fetch", "--quiet", "--prune", url, refspec]).map(|_| ())
+}
+
+pub fn delete_ref(repo_path: &Path, name: &str) -> Result<(), GitOpsError> {
+    git(repo_path, &["update-ref", "-d", name]).map(|_| ())
+}
+
+/// Initializes and checks out every submodule, recursively, optionally
+/// shallow.
+pub fn update_submodules(repo_path: &Path, depth: Option<u32>) -> Result<(), GitOpsError> {
+    let depth = depth.map(|depth| format!("--depth={}", depth));
+    let mut args = vec!["submodule", "update", "--init", "--recursive"];
+    args.extend(depth.as_deref());
+    git(repo_path, &args).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_cli_fallback_commits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        let repo = git2::Repository::init(repo_path).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.name", "Test User")
+            .unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "test@test.com")
+            .unwrap();
+        fs::write(repo_path.join("test.txt"), "hello").unwrap();
+
+        stage_all(repo_path).unwrap();
+        commit(repo_path, "Initial commit", &CommitIdentity::default()).unwrap();
+        create_branch(repo_path, "claude/feature").unwrap();
+        fs::write(repo_path.join("test.txt"), "hello world").unwrap();
+        stage_all(repo_path).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Dreamal Bot".to_string()),
+            email: None,
+        };
+        let oid = commit(repo_path, "feat: change", &identity).unwrap();
+
+        let head = repo.head().unwrap();
+        assert_eq!(head.shorthand(), Some("claude/feature"));
+        let commit = head.peel_to_commit().unwrap();
+        assert_eq!(commit.id(), oid);
+        assert_eq!(commit.message(), Some("feat: change\n"));
+        assert_eq!(commit.author().name(), Some("Dreamal Bot"));
+        assert_eq!(commit.author().email(), Some("test@test.com"));
+        assert_eq!(commit.parent_count(), 1);
+        assert!(repo.statuses(None).unwrap().is_empty());
+        assert!(!is_shallow(repo_path).unwrap());
+    }
+
+    #[test]
+    fn test_cli_stages_and_diffs_like_git2() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        let repo = git2::Repository::init(repo_path).unwrap();
+        fs::write(repo_path.join("kept.txt"), "one\n").unwrap();
+        fs::write(repo_path.join("gone.txt"), "two\n").unwrap();
+        stage_all(repo_path).unwrap();
+        let base = commit(repo_path, "Initial commit", &CommitIdentity::default()).unwrap();
+
+        fs::write(repo_path.join("kept.txt"), "one\nmore\n").unwrap();
+        fs::remove_file(repo_path.join("gone.txt")).unwrap();
+        fs::create_dir_all(repo_path.join("new dir")).unwrap();
+        fs::write(repo_path.join("new dir/added.txt"), "three\n").unwrap();
+        let mut dirty = dirty_paths(repo_path).unwrap();
+        dirty.sort();
+        assert_eq!(
+            dirty,
+            vec![
+                PathBuf::from("gone.txt"),
+                PathBuf::from("kept.txt"),
+                PathBuf::from("new dir/added.txt"),
+            ]
+        );
+
+        // The untracked file shows up without being added to the real index.
+        let patch = String::from_utf8(diff_worktree(repo_path, base, &[]).unwrap()).unwrap();
+        assert!(patch.contains("+three") && patch.contains("-two") && patch.contains("+more"));
+        assert!(repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("new dir/added.txt"), 0)
+            .is_none());
+
+        stage_only(
+            repo_path,
+            &[
+                PathBuf::from("gone.txt"),
+                PathBuf::from("new dir/added.txt"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(staged_change_count(repo_path).unwrap(), 2);
+        let head = commit(repo_path, "Change", &CommitIdentity::default()).unwrap();
+        let mut stats = numstat(repo_path, base, head).unwrap();
+        stats.sort();
+        assert_eq!(
+            stats,
+            vec![
+                ("gone.txt".to_string(), 0, 1),
+                ("new dir/added.txt".to_string(), 1, 0),
+            ]
+        );
+        assert_eq!(resolve_commit(repo_path, "HEAD"), Some(head));
+        assert_eq!(merge_base(repo_path, base, head).unwrap(), base);
+        assert!(resolve_commit(repo_path, "refs/heads/none").is_none());
+    }
+
+    #[test]
+    fn test_cli_merge_conflicts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git2::Repository::init(repo_path).unwrap();
+        fs::write(repo_path.join("notes.txt"), "one\n").unwrap();
+        stage_all(repo_path).unwrap();
+        let base = commit(repo_path, "Initial commit", &CommitIdentity::default()).unwrap();
+        create_branch(repo_path, "feature").unwrap();
+        fs::write(repo_path.join("notes.txt"), "feature\n").unwrap();
+        stage_all(repo_path).unwrap();
+        let feature = commit(repo_path, "Feature", &CommitIdentity::default()).unwrap();
+
+        assert!(merge_conflicts(repo_path, base, feature)
+            .unwrap()
+            .is_empty());
+
+        git(
+            repo_path,
+            &["checkout", "--quiet", "-b", "other", &base.to_string()],
+        )
+        .unwrap();
+        fs::write(repo_path.join("notes.txt"), "other\n").unwrap();
+        stage_all(repo_path).unwrap();
+        let other = commit(repo_path, "Other", &CommitIdentity::default()).unwrap();
+        assert_eq!(
+            merge_conflicts(repo_path, other, feature).unwrap(),
+            vec!["notes.txt"]
+        );
+    }
+
+    #[test]
+    fn test_is_unsupported_by_git2() {
+        let unsupported = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Repository,
+            "unsupported extension name extensions.objectformat",
+        );
+        let missing = git2::Error::new(
+            git2::ErrorCode::NotFound,
+            git2::ErrorClass::Repository,
+            "could not find repository at '/tmp/none'",
+        );
+
+        assert!(is_unsupported_by_git2(&unsupported));
+        assert!(!is_unsupported_by_git2(&missing));
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(open_or_fallback(temp_dir.path()).is_err());
+    }
+}
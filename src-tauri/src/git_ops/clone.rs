@@ -1,7 +1,10 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use super::{ensure_temp_checkouts_dir, get_session_dir, GitOpsError};
+use serde::Serialize;
+
+use super::size::{dir_stats, DirStats};
+use super::GitOpsError;
 
 impl From<git2::Error> for GitOpsError {
     fn from(e: git2::Error) -> Self {
@@ -9,7 +12,29 @@ impl From<git2::Error> for GitOpsError {
     }
 }
 
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressUnit {
+    /// Files copied from the source checkout.
+    Files,
+    /// Objects received while fetching a submodule.
+    Objects,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CloneProgress {
+    pub unit: ProgressUnit,
+    pub done: u64,
+    pub total: u64,
+}
+
+pub type ProgressCallback<'a> = &'a dyn Fn(CloneProgress);
+
+fn copy_dir_counting(
+    src: &Path,
+    dst: &Path,
+    on_file: &mut dyn FnMut(),
+) -> Result<(), std::io::Error> {
     fs::create_dir_all(dst)?;
 
     for entry in fs::read_dir(src)? {
@@ -18,29 +43,245 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
         let dst_path = dst.join(entry.file_name());
 
         if src_path.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
+            copy_dir_counting(&src_path, &dst_path, on_file)?;
         } else {
             fs::copy(&src_path, &dst_path)?;
+            on_file();
+        }
+    }
+
+    Ok(())
+}
+
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    copy_dir_counting(src, dst, &mut || {})
+}
+
+/// Calls `progress` whenever the whole-percent value of `done / total`
+/// changes, so large trees don't flood listeners.
+struct ThrottledProgress<'a> {
+    progress: ProgressCallback<'a>,
+    unit: ProgressUnit,
+    last_percent: Option<u64>,
+}
+
+impl<'a> ThrottledProgress<'a> {
+    fn new(progress: ProgressCallback<'a>, unit: ProgressUnit) -> Self {
+        Self {
+            progress,
+            unit,
+            last_percent: None,
+        }
+    }
+
+    fn report(&mut self, done: u64, total: u64) {
+        // Symlinked directories are followed when copying but not when
+        // counting, so `done` can run past the estimate.
+        let total = total.max(done);
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if self.last_percent == Some(percent) {
+            return;
         }
+        self.last_percent = Some(percent);
+        (self.progress)(CloneProgress {
+            unit: self.unit,
+            done,
+            total,
+        });
     }
+}
+
+fn origin_url(repo_path: &Path) -> Option<String> {
+    match git2::Repository::open(repo_path) {
+        Ok(repo) => repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|remote| remote.url().map(str::to_string)),
+        Err(_) => super::cli::origin_url(repo_path),
+    }
+}
 
+fn clone_with_git2(
+    source_path: &Path,
+    session_dir: &Path,
+    progress: Option<ProgressCallback>,
+) -> Result<(), git2::Error> {
+    let mut reporter = progress.map(|p| ThrottledProgress::new(p, ProgressUnit::Files));
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    if let Some(reporter) = reporter.as_mut() {
+        checkout.progress(|_path, done, total| reporter.report(done as u64, total as u64));
+    }
+
+    git2::build::RepoBuilder::new()
+        .with_checkout(checkout)
+        .clone(&source_path.to_string_lossy(), session_dir)?;
     Ok(())
 }
 
-pub fn clone_to_temp(source_path: &Path, session_id: &str) -> Result<PathBuf, GitOpsError> {
-    ensure_temp_checkouts_dir()?;
+/// Where the clone keeps its copy of what the source last saw upstream.
+const UPSTREAM_TRACKING_REFS: &str = "+refs/remotes/origin/*:refs/remotes/origin/*";
+
+/// Replaces the clone's `refs/remotes/origin/*`, which cloning filled from the
+/// source's local branches, with the source's own remote-tracking refs, so
+/// they match the upstream `origin` now points at.
+fn track_source_upstream(source_path: &Path, session_dir: &Path) -> Result<(), GitOpsError> {
+    let source = source_path.to_string_lossy();
+    let Some(repo) = super::cli::open_or_fallback(session_dir)? else {
+        return super::cli::fetch_pruned(session_dir, &source, UPSTREAM_TRACKING_REFS);
+    };
+    let stale: Vec<String> = repo
+        .references_glob("refs/remotes/origin/*")?
+        .filter_map(|reference| reference.ok()?.name().map(str::to_string))
+        .collect();
+    for name in stale {
+        repo.find_reference(&name)?.delete()?;
+    }
+    repo.remote_anonymous(&source)?
+        .fetch(&[UPSTREAM_TRACKING_REFS], None, None)?;
+    Ok(())
+}
+
+/// Clones `source_path` into `session_dir`, which must not exist yet, checked
+/// out at the source's HEAD. The clone's `origin` is pointed at the source's own `origin`
+/// so session branches are pushed upstream rather than back into the source
+/// checkout, and its remote-tracking branches are the source's. `progress`,
+/// if given, receives files-checked-out counts against the total.
+pub fn clone_to_dir(
+    source_path: &Path,
+    session_dir: &Path,
+    progress: Option<ProgressCallback>,
+) -> Result<PathBuf, GitOpsError> {
+    if session_dir.exists() {
+        return Err(GitOpsError::SessionExists(
+            session_dir.display().to_string(),
+        ));
+    }
+    if let Some(parent) = session_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if let Err(e) = clone_with_git2(source_path, session_dir, progress) {
+        if session_dir.exists() {
+            fs::remove_dir_all(session_dir)?;
+        }
+        // Shallow sources and newer repo formats can defeat libgit2 but not git.
+        eprintln!(
+            "Warning: libgit2 can't clone {} ({}), using the git CLI",
+            source_path.display(),
+            e.message()
+        );
+        super::cli::clone(source_path, session_dir)?;
+    }
 
-    let session_dir = get_session_dir(session_id)?;
+    if let Some(url) = origin_url(source_path) {
+        match super::cli::open_or_fallback(session_dir)? {
+            Some(repo) => repo.remote_set_url("origin", &url)?,
+            None => super::cli::set_origin_url(session_dir, &url)?,
+        }
+        track_source_upstream(source_path, session_dir)?;
+    }
 
+    Ok(session_dir.to_path_buf())
+}
+
+/// Like `clone_to_dir`, but first removes what a session that is no longer
+/// active left in `session_dir`.
+pub fn clone_to_dir_reclaiming(
+    source_path: &Path,
+    session_dir: &Path,
+    session_active: bool,
+    progress: Option<ProgressCallback>,
+) -> Result<PathBuf, GitOpsError> {
     if session_dir.exists() {
-        return Err(GitOpsError::SessionExists(session_id.to_string()));
+        if session_active {
+            return Err(GitOpsError::SessionExists(
+                session_dir.display().to_string(),
+            ));
+        }
+        fs::remove_dir_all(session_dir)?;
+    }
+
+    clone_to_dir(source_path, session_dir, progress)
+}
+
+/// A copied submodule without a usable gitdir is emptied so libgit2 re-clones it.
+fn reset_unusable_submodule(submodule_path: &Path) -> Result<(), GitOpsError> {
+    if submodule_path.exists() && git2::Repository::open(submodule_path).is_err() {
+        fs::remove_dir_all(submodule_path)?;
+        fs::create_dir_all(submodule_path)?;
+    }
+    Ok(())
+}
+
+fn fetch_submodule(
+    submodule: &mut git2::Submodule,
+    progress: Option<ProgressCallback>,
+    depth: Option<u32>,
+) -> Result<(), git2::Error> {
+    let mut reporter = progress.map(|p| ThrottledProgress::new(p, ProgressUnit::Objects));
+
+    let mut fetch_options = git2::FetchOptions::new();
+    if let Some(depth) = depth {
+        fetch_options.depth(depth as i32);
+    }
+    if let Some(reporter) = reporter.as_mut() {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.transfer_progress(|stats| {
+            reporter.report(
+                stats.received_objects() as u64,
+                stats.total_objects() as u64,
+            );
+            true
+        });
+        fetch_options.remote_callbacks(callbacks);
     }
 
-    copy_dir_recursive(source_path, &session_dir)?;
+    let mut update_options = git2::SubmoduleUpdateOptions::new();
+    update_options.fetch(fetch_options);
+    submodule.update(true, Some(&mut update_options))
+}
 
-    git2::Repository::open(&session_dir)?;
+/// Initializes and checks out every submodule (recursively) so the working tree is complete.
+/// `progress`, if given, receives each submodule fetch's received-object counts. With
+/// `depth`, submodules are fetched shallowly; one whose remote refuses a shallow fetch
+/// falls back to its full history.
+pub fn update_submodules(
+    repo_path: &Path,
+    progress: Option<ProgressCallback>,
+    depth: Option<u32>,
+) -> Result<(), GitOpsError> {
+    let Some(repo) = super::cli::open_or_fallback(repo_path)? else {
+        return super::cli::update_submodules(repo_path, depth);
+    };
+
+    for mut submodule in repo.submodules()? {
+        let submodule_path = repo_path.join(submodule.path());
+        reset_unusable_submodule(&submodule_path)?;
+
+        if let Err(e) = fetch_submodule(&mut submodule, progress, depth) {
+            if depth.is_none() {
+                return Err(e.into());
+            }
+            eprintln!(
+                "Warning: Shallow fetch of submodule {} failed, fetching full history: {}",
+                submodule.path().display(),
+                e.message()
+            );
+            reset_unusable_submodule(&submodule_path)?;
+            fetch_submodule(&mut submodule, progress, None)?;
+        }
+        update_submodules(&submodule_path, progress, depth)?;
+    }
 
-    Ok(session_dir)
+    Ok(())
+}
+
+pub fn estimate_clone(source_path: &Path) -> Result<DirStats, GitOpsError> {
+    super::cli::open_or_fallback(source_path)?;
+
+    // A clone also leaves out untracked files, but ignored build output is
+    // what usually dominates, so this is close enough.
+    Ok(dir_stats(source_path, true)?)
 }
 
 #[cfg(test)]
@@ -72,11 +313,12 @@ mod tests {
     }
 
     #[test]
-    fn test_clone_to_temp() {
+    fn test_clone_to_dir() {
         let (_temp_dir, source_path) = setup_test_repo();
-        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
 
-        let result = clone_to_temp(&source_path, &session_id);
+        let result = clone_to_dir(&source_path, &session_dir, None);
         assert!(result.is_ok());
 
         let cloned_path = result.unwrap();
@@ -88,36 +330,231 @@ mod tests {
         let test_file = cloned_path.join("test.txt");
         assert!(test_file.exists());
         assert_eq!(fs::read_to_string(&test_file).unwrap(), "hello world");
-
-        fs::remove_dir_all(&cloned_path).unwrap();
     }
 
     #[test]
-    fn test_clone_to_temp_preserves_history() {
+    fn test_clone_to_dir_preserves_history() {
         let (_temp_dir, source_path) = setup_test_repo();
-        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
 
-        let cloned_path = clone_to_temp(&source_path, &session_id).unwrap();
+        let cloned_path = clone_to_dir(&source_path, &session_dir, None).unwrap();
 
         let repo = git2::Repository::open(&cloned_path).unwrap();
         let head = repo.head().unwrap();
         let commit = head.peel_to_commit().unwrap();
 
         assert_eq!(commit.message().unwrap(), "Initial commit");
-
-        fs::remove_dir_all(&cloned_path).unwrap();
     }
 
     #[test]
-    fn test_clone_to_temp_session_exists() {
+    fn test_clone_to_dir_session_exists() {
         let (_temp_dir, source_path) = setup_test_repo();
-        let session_id = format!("test-{}", uuid::Uuid::new_v4());
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
 
-        let cloned_path = clone_to_temp(&source_path, &session_id).unwrap();
+        clone_to_dir(&source_path, &session_dir, None).unwrap();
 
-        let result = clone_to_temp(&source_path, &session_id);
+        let result = clone_to_dir(&source_path, &session_dir, None);
         assert!(result.is_err());
+    }
 
-        fs::remove_dir_all(&cloned_path).unwrap();
+    #[test]
+    fn test_clone_to_dir_tracks_source_upstream() {
+        let (_upstream_dir, upstream_path) = setup_test_repo();
+        let source_dir = tempfile::tempdir().unwrap();
+        let source =
+            git2::Repository::clone(&upstream_path.to_string_lossy(), source_dir.path()).unwrap();
+        let upstream_head = source.refname_to_id("refs/remotes/origin/HEAD").unwrap();
+        // Local work in the source that upstream has never seen.
+        let head = source.head().unwrap().peel_to_commit().unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        let unpushed = source
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                "Unpushed",
+                &head.tree().unwrap(),
+                &[&head],
+            )
+            .unwrap();
+        source
+            .branch("local-only", &source.find_commit(unpushed).unwrap(), false)
+            .unwrap();
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
+
+        let cloned_path = clone_to_dir(source_dir.path(), &session_dir, None).unwrap();
+
+        let repo = git2::Repository::open(&cloned_path).unwrap();
+        let branch = source.head().unwrap().shorthand().unwrap().to_string();
+        assert_eq!(repo.head().unwrap().target(), Some(unpushed));
+        assert_eq!(
+            repo.refname_to_id(&format!("refs/remotes/origin/{}", branch))
+                .unwrap(),
+            upstream_head
+        );
+        assert!(repo
+            .find_reference("refs/remotes/origin/local-only")
+            .is_err());
+    }
+
+    #[test]
+    fn test_clone_to_dir_reclaims_stale_dir() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
+
+        fs::create_dir_all(&session_dir).unwrap();
+        fs::write(session_dir.join("leftover.txt"), "stale").unwrap();
+
+        let cloned_path = clone_to_dir_reclaiming(&source_path, &session_dir, false, None).unwrap();
+
+        assert!(!cloned_path.join("leftover.txt").exists());
+        assert!(cloned_path.join("test.txt").exists());
+        assert!(git2::Repository::open(&cloned_path).is_ok());
+    }
+
+    #[test]
+    fn test_clone_to_dir_protects_active_session_dir() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
+
+        fs::create_dir_all(&session_dir).unwrap();
+        fs::write(session_dir.join("in-progress.txt"), "active").unwrap();
+
+        let result = clone_to_dir_reclaiming(&source_path, &session_dir, true, None);
+
+        assert!(matches!(result, Err(GitOpsError::SessionExists(_))));
+        assert!(session_dir.join("in-progress.txt").exists());
+    }
+
+    #[test]
+    fn test_estimate_clone() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        fs::create_dir_all(source_path.join("src")).unwrap();
+        fs::write(
+            source_path.join("src").join("big.bin"),
+            vec![0u8; 64 * 1024],
+        )
+        .unwrap();
+
+        let estimate = estimate_clone(&source_path).unwrap();
+
+        let content_bytes = (64 * 1024 + "hello world".len()) as u64;
+        assert!(estimate.bytes >= content_bytes);
+        assert!(estimate.bytes < content_bytes + 1024 * 1024);
+        assert!(estimate.files >= 2);
+    }
+
+    #[test]
+    fn test_clone_to_dir_leaves_out_untracked_files() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        fs::write(source_path.join(".gitignore"), "build/\n").unwrap();
+        fs::create_dir_all(source_path.join("build")).unwrap();
+        fs::write(source_path.join("build").join("out.txt"), "generated").unwrap();
+        fs::write(source_path.join("notes.txt"), "uncommitted").unwrap();
+        fs::write(source_path.join("test.txt"), "modified").unwrap();
+
+        let repo = git2::Repository::open(&source_path).unwrap();
+        repo.remote("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
+        let cloned_path = clone_to_dir(&source_path, &session_dir, None).unwrap();
+
+        assert!(!cloned_path.join("build").exists());
+        assert!(!cloned_path.join("notes.txt").exists());
+        assert!(!cloned_path.join(".gitignore").exists());
+        assert_eq!(
+            fs::read_to_string(cloned_path.join("test.txt")).unwrap(),
+            "hello world"
+        );
+        let cloned = git2::Repository::open(&cloned_path).unwrap();
+        assert!(cloned.statuses(None).unwrap().is_empty());
+        assert_eq!(
+            cloned.find_remote("origin").unwrap().url(),
+            Some("https://github.com/owner/repo.git")
+        );
+    }
+
+    #[test]
+    fn test_estimate_clone_requires_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(estimate_clone(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_update_submodules_after_clone() {
+        let (_sub_dir, sub_path) = setup_test_repo();
+        let (_temp_dir, source_path) = setup_test_repo();
+
+        let repo = git2::Repository::open(&source_path).unwrap();
+        let mut submodule = repo
+            .submodule(sub_path.to_str().unwrap(), Path::new("vendor/lib"), true)
+            .unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+
+        let mut index = repo.index().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Add submodule", &tree, &[&parent])
+            .unwrap();
+
+        // Mimic a checkout made without --recursive.
+        fs::remove_dir_all(source_path.join("vendor").join("lib")).unwrap();
+        fs::create_dir_all(source_path.join("vendor").join("lib")).unwrap();
+        fs::remove_dir_all(source_path.join(".git").join("modules")).unwrap();
+
+        // A local-path remote may not serve shallow fetches, so depth must
+        // still end in a complete checkout.
+        for depth in [None, Some(1)] {
+            let checkouts_dir = tempfile::tempdir().unwrap();
+            let session_dir = checkouts_dir.path().join("session");
+            let cloned_path = clone_to_dir(&source_path, &session_dir, None).unwrap();
+
+            update_submodules(&cloned_path, None, depth).unwrap();
+
+            let submodule_file = cloned_path.join("vendor").join("lib").join("test.txt");
+            assert_eq!(fs::read_to_string(&submodule_file).unwrap(), "hello world");
+        }
+    }
+
+    #[test]
+    fn test_clone_progress_is_monotonic() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        let repo = git2::Repository::open(&source_path).unwrap();
+        let mut index = repo.index().unwrap();
+        for i in 0..250 {
+            let name = format!("file-{}.txt", i);
+            fs::write(source_path.join(&name), "x").unwrap();
+            index.add_path(Path::new(&name)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Add files", &tree, &[&parent])
+            .unwrap();
+        let checkouts_dir = tempfile::tempdir().unwrap();
+        let session_dir = checkouts_dir.path().join("session");
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let record = |p: CloneProgress| reports.lock().unwrap().push(p);
+        clone_to_dir(&source_path, &session_dir, Some(&record)).unwrap();
+
+        let reports = reports.into_inner().unwrap();
+        assert!(reports.len() > 2);
+        assert!(reports.iter().all(|p| p.unit == ProgressUnit::Files));
+        assert_eq!(reports[0].done, 0);
+        assert!(reports.windows(2).all(|w| w[0].done <= w[1].done));
+        let last = reports.last().unwrap();
+        assert_eq!(last.done, last.total);
+        assert_eq!(last.total, 251);
     }
 }
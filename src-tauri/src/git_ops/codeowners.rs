@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::GitOpsError;
+
+const CODEOWNERS_DIRS: &[&str] = &[".github", "", "docs"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnerRule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+}
+
+fn find_codeowners(repo_path: &Path) -> Option<PathBuf> {
+    CODEOWNERS_DIRS
+        .iter()
+        .map(|dir| repo_path.join(dir).join("CODEOWNERS"))
+        .find(|path| path.is_file())
+}
+
+/// Parses CODEOWNERS lines into rules. A pattern with no owners is kept so it
+/// can un-own paths matched by an earlier rule.
+pub fn parse_codeowners(content: &str) -> Vec<OwnerRule> {
+    content
+        .lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?.to_string();
+            let owners = parts
+                .filter(|owner| owner.contains('@'))
+                .map(str::to_string)
+                .collect();
+            Some(OwnerRule { pattern, owners })
+        })
+        .collect()
+}
+
+pub fn load_codeowners(repo_path: &Path) -> Result<Vec<OwnerRule>, GitOpsError> {
+    match find_codeowners(repo_path) {
+        Some(path) => Ok(parse_codeowners(&fs::read_to_string(path)?)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn segment_matches(pattern: &[char], text: &[char]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            segment_matches(&pattern[1..], text)
+                || (!text.is_empty() && segment_matches(pattern, &text[1..]))
+        }
+        (Some('?'), Some(_)) => segment_matches(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => segment_matches(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|skip| segments_match(&pattern[1..], &path[skip..])),
+        Some(segment) => {
+            !path.is_empty()
+                && segment_matches(
+                    &segment.chars().collect::<Vec<_>>(),
+                    &path[0].chars().collect::<Vec<_>>(),
+                )
+                && segments_match(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+/// Matches a repo-relative path against a CODEOWNERS pattern using GitHub's
+/// gitignore-style rules: a pattern without a leading or inner `/` matches at
+/// any depth, and a pattern naming a directory owns everything beneath it,
+/// except that a trailing wildcard (`docs/*`) only matches direct children.
+pub fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let body = pattern.trim_matches('/');
+    if body.is_empty() {
+        return false;
+    }
+
+    let anchored = pattern.starts_with('/') || body.contains('/');
+    let mut segments: Vec<&str> = Vec::new();
+    if !anchored {
+        segments.push("**");
+    }
+    segments.extend(body.split('/'));
+
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let last_is_wildcard = segments.last().is_some_and(|s| s.contains(['*', '?']));
+
+    if !dir_only && segments_match(&segments, &path) {
+        return true;
+    }
+    if dir_only || !last_is_wildcard {
+        // At least one segment below the directory.
+        segments.extend(["*", "**"]);
+        return segments_match(&segments, &path);
+    }
+    false
+}
+
+/// Owners of `path`; the last matching rule wins, as on GitHub.
+pub fn owners_for_path<'a>(rules: &'a [OwnerRule], path: &str) -> &'a [String] {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| pattern_matches(&rule.pattern, path))
+        .map(|rule| rule.owners.as_slice())
+        .unwrap_or_default()
+}
+
+/// Deduplicated owners of `changed_paths`, in order of first appearance.
+pub fn suggest_reviewers<S: AsRef<str>>(rules: &[OwnerRule], changed_paths: &[S]) -> Vec<String> {
+    let mut reviewers: Vec<String> = Vec::new();
+    for path in changed_paths {
+        for owner in owners_for_path(rules, path.as_ref()) {
+            if !reviewers.contains(owner) {
+                reviewers.push(owner.clone());
+            }
+        }
+    }
+    reviewers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "\
+# Default owners
+*       @acme/core
+
+*.js    @frontend-dev   # inline comment
+/docs/  @acme/docs docs@example.com
+docs/*.md @writer
+apps/   @apps-owner
+/build/logs/ @ops
+**/migrations @dba
+/vendor/
+";
+
+    #[test]
+    fn test_parse_codeowners() {
+        let rules = parse_codeowners(FIXTURE);
+
+        assert_eq!(rules.len(), 8);
+        assert_eq!(rules[0].pattern, "*");
+        assert_eq!(rules[1].owners, vec!["@frontend-dev"]);
+        assert_eq!(rules[2].owners, vec!["@acme/docs", "docs@example.com"]);
+        assert!(rules[7].owners.is_empty());
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("*", "src/main.rs"));
+        assert!(pattern_matches("*.js", "web/src/app.js"));
+        assert!(!pattern_matches("*.js", "web/src/app.jsx"));
+        assert!(pattern_matches("/docs/", "docs/guide/intro.md"));
+        assert!(!pattern_matches("/docs/", "src/docs/intro.md"));
+        assert!(pattern_matches("docs/*", "docs/intro.md"));
+        assert!(!pattern_matches("docs/*", "docs/guide/intro.md"));
+        assert!(pattern_matches("apps/", "web/apps/main.rs"));
+        assert!(pattern_matches("/build/logs", "build/logs/today.log"));
+        assert!(pattern_matches("**/migrations", "db/migrations/001.sql"));
+        assert!(pattern_matches("src/?ain.rs", "src/main.rs"));
+        assert!(!pattern_matches("/src", "lib/src/main.rs"));
+    }
+
+    #[test]
+    fn test_owners_last_match_wins() {
+        let rules = parse_codeowners(FIXTURE);
+
+        assert_eq!(owners_for_path(&rules, "README"), ["@acme/core"]);
+        assert_eq!(owners_for_path(&rules, "web/app.js"), ["@frontend-dev"]);
+        assert_eq!(owners_for_path(&rules, "docs/intro.md"), ["@writer"]);
+        assert_eq!(
+            owners_for_path(&rules, "docs/img/logo.svg"),
+            ["@acme/docs", "docs@example.com"]
+        );
+        assert!(owners_for_path(&rules, "vendor/lib.rs").is_empty());
+    }
+
+    #[test]
+    fn test_suggest_reviewers_dedupes() {
+        let rules = parse_codeowners(FIXTURE);
+        let changed = [
+            "src/main.rs",
+            "web/app.js",
+            "web/other.js",
+            "db/migrations/001.sql",
+            "vendor/lib.rs",
+            "Cargo.toml",
+        ];
+
+        assert_eq!(
+            suggest_reviewers(&rules, &changed),
+            vec!["@acme/core", "@frontend-dev", "@dba"]
+        );
+    }
+
+    #[test]
+    fn test_load_codeowners_locations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(load_codeowners(temp_dir.path()).unwrap().is_empty());
+
+        fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+        fs::write(temp_dir.path().join("docs/CODEOWNERS"), "* @docs-dir").unwrap();
+        fs::write(temp_dir.path().join("CODEOWNERS"), "* @root").unwrap();
+        assert_eq!(
+            load_codeowners(temp_dir.path()).unwrap()[0].owners,
+            ["@root"]
+        );
+
+        fs::create_dir_all(temp_dir.path().join(".github")).unwrap();
+        fs::write(temp_dir.path().join(".github/CODEOWNERS"), "* @github-dir").unwrap();
+        assert_eq!(
+            load_codeowners(temp_dir.path()).unwrap()[0].owners,
+            ["@github-dir"]
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use super::cli;
 use super::GitOpsError;
 
 pub fn stage_all_changes(repo_path: &Path) -> Result<(), GitOpsError> {
-    let repo = git2::Repository::open(repo_path)?;
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return cli::stage_all(repo_path);
+    };
     let mut index = repo.index()?;
 
     index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
@@ -13,16 +17,225 @@ pub fn stage_all_changes(repo_path: &Path) -> Result<(), GitOpsError> {
     Ok(())
 }
 
-pub fn create_commit(repo_path: &Path, message: &str) -> Result<git2::Oid, GitOpsError> {
-    let repo = git2::Repository::open(repo_path)?;
+/// Dirty paths in a checkout and the content hash of each (`None` if deleted),
+/// taken before Claude runs so its changes can be told apart from files that
+/// were already dirty, such as baseline build output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkTreeSnapshot {
+    paths: HashMap<PathBuf, Option<git2::Oid>>,
+}
+
+fn dirty_paths(
+    repo: &git2::Repository,
+) -> Result<HashMap<PathBuf, Option<git2::Oid>>, GitOpsError> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| GitOpsError::GitError("Repository has no working directory".to_string()))?;
+
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+
+    let mut paths = HashMap::new();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let full_path = workdir.join(path);
+        let hash = if full_path.is_file() {
+            Some(git2::Oid::hash_file(git2::ObjectType::Blob, &full_path)?)
+        } else {
+            None
+        };
+        paths.insert(PathBuf::from(path), hash);
+    }
+
+    Ok(paths)
+}
+
+/// `dirty_paths` for a checkout only the git CLI can read.
+fn dirty_paths_cli(repo_path: &Path) -> Result<HashMap<PathBuf, Option<git2::Oid>>, GitOpsError> {
+    let mut paths = HashMap::new();
+    for path in cli::dirty_paths(repo_path)? {
+        let full_path = repo_path.join(&path);
+        let hash = if full_path.is_file() {
+            Some(git2::Oid::hash_file(git2::ObjectType::Blob, &full_path)?)
+        } else {
+            None
+        };
+        paths.insert(path, hash);
+    }
+    Ok(paths)
+}
+
+pub fn snapshot_worktree(repo_path: &Path) -> Result<WorkTreeSnapshot, GitOpsError> {
+    let paths = match cli::open_or_fallback(repo_path)? {
+        Some(repo) => dirty_paths(&repo)?,
+        None => dirty_paths_cli(repo_path)?,
+    };
+    Ok(WorkTreeSnapshot { paths })
+}
+
+fn changed_since(
+    current: HashMap<PathBuf, Option<git2::Oid>>,
+    snapshot: &WorkTreeSnapshot,
+) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = current
+        .into_iter()
+        .filter(|(path, hash)| snapshot.paths.get(path) != Some(hash))
+        .map(|(path, _)| path)
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Resets the index to HEAD and stages only paths whose content differs from
+/// `snapshot`. A file that was already dirty and that Claude edited further is
+/// staged whole, including the pre-existing edits. Returns the staged paths.
+pub fn stage_changes_since(
+    repo_path: &Path,
+    snapshot: &WorkTreeSnapshot,
+) -> Result<Vec<PathBuf>, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        let staged = changed_since(dirty_paths_cli(repo_path)?, snapshot);
+        cli::stage_only(repo_path, &staged)?;
+        return Ok(staged);
+    };
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let staged = changed_since(dirty_paths(&repo)?, snapshot);
+
+    let mut index = repo.index()?;
+    index.read_tree(&head_tree)?;
+
+    for path in &staged {
+        if repo_path.join(path).is_file() {
+            index.add_path(path)?;
+        } else {
+            index.remove_path(path)?;
+        }
+    }
+    index.write()?;
+
+    Ok(staged)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommitIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// The paths in `changed` outside every one of `editable_paths`, which are
+/// normalized repo-relative paths. Empty `editable_paths` allow everything.
+pub fn out_of_scope_paths(changed: &[PathBuf], editable_paths: &[String]) -> Vec<String> {
+    if editable_paths.is_empty() {
+        return Vec::new();
+    }
+
+    changed
+        .iter()
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .filter(|path| {
+            !editable_paths.iter().any(|allowed| {
+                path == allowed
+                    || path
+                        .strip_prefix(allowed.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+        .collect()
+}
+
+const MAX_SUBJECT_LEN: usize = 72;
+
+/// Checks a commit message supplied in place of the generated one: a
+/// non-empty subject of at most 72 characters, with a blank line before any
+/// body. Returns it with surrounding whitespace trimmed.
+pub fn validate_commit_message(message: &str) -> Result<String, String> {
+    let message = message.trim();
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or("").trim();
+    if subject.is_empty() {
+        return Err("The commit message must not be empty".to_string());
+    }
+    if subject.chars().count() > MAX_SUBJECT_LEN {
+        return Err(format!(
+            "The commit subject must be at most {} characters",
+            MAX_SUBJECT_LEN
+        ));
+    }
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        return Err("Separate the commit subject from its body with a blank line".to_string());
+    }
+    Ok(message.to_string())
+}
+
+/// `message` with `prefix` and a space in front of its subject line.
+pub fn prefixed_message(prefix: Option<&str>, message: &str) -> String {
+    match prefix.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(prefix) => format!("{} {}", prefix, message),
+        None => message.to_string(),
+    }
+}
+
+fn commit_signature(
+    repo: &git2::Repository,
+    identity: &CommitIdentity,
+) -> Result<git2::Signature<'static>, GitOpsError> {
+    let repo_sig = repo.signature().ok();
+    let name = identity
+        .name
+        .clone()
+        .or_else(|| repo_sig.as_ref().and_then(|s| s.name().map(str::to_string)))
+        .unwrap_or_else(|| "Claude".to_string());
+    let email = identity
+        .email
+        .clone()
+        .or_else(|| repo_sig.as_ref().and_then(|s| s.email().map(str::to_string)))
+        .unwrap_or_else(|| "claude@dreamal.app".to_string());
+
+    Ok(git2::Signature::now(&name, &email)?)
+}
+
+/// Whether the staged index differs from HEAD. Run after staging to tell a
+/// session that changed nothing apart from one that has something to commit.
+pub fn has_changes(repo_path: &Path) -> Result<bool, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return cli::has_staged_changes(repo_path);
+    };
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+    Ok(diff.deltas().len() > 0)
+}
+
+/// How many files the staged index changes relative to HEAD.
+pub fn staged_change_count(repo_path: &Path) -> Result<usize, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return cli::staged_change_count(repo_path);
+    };
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+    Ok(diff.deltas().len())
+}
+
+/// Commits the index, with `identity` overriding the repo's configured author.
+/// Repos libgit2 can't open are committed with the git CLI.
+pub fn create_commit(
+    repo_path: &Path,
+    message: &str,
+    identity: &CommitIdentity,
+) -> Result<git2::Oid, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return cli::commit(repo_path, message, identity);
+    };
     let mut index = repo.index()?;
 
     let tree_id = index.write_tree()?;
     let tree = repo.find_tree(tree_id)?;
 
-    let sig = repo
-        .signature()
-        .or_else(|_| git2::Signature::now("Claude", "claude@dreamal.app"))?;
+    let sig = commit_signature(&repo, identity)?;
 
     let parent_commit = repo.head()?.peel_to_commit()?;
 
@@ -31,32 +244,129 @@ pub fn create_commit(repo_path: &Path, message: &str) -> Result<git2::Oid, GitOp
     Ok(commit_id)
 }
 
-pub fn push_to_remote(repo_path: &Path, branch_name: &str) -> Result<(), GitOpsError> {
+fn run_push(repo_path: &Path, branch_name: &str) -> Result<(), String> {
     let output = Command::new("git")
         .current_dir(repo_path)
         .args(["push", "-u", "origin", branch_name])
         .output()
-        .map_err(|e| GitOpsError::GitError(format!("Failed to run git push: {}", e)))?;
+        .map_err(|e| format!("Failed to run git push: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    Ok(())
+}
+
+/// Whether `git push` was refused because the remote lacks history that a
+/// shallow checkout can't send.
+pub fn is_shallow_push_rejection(stderr: &str) -> bool {
+    stderr.to_lowercase().contains("shallow update not allowed")
+}
+
+fn unshallow(repo_path: &Path) -> Result<(), GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(["fetch", "--unshallow", "origin"])
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git fetch: {}", e)))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!("git fetch --unshallow failed: {}", stderr)));
+    }
+
+    Ok(())
+}
+
+/// Pushes `branch_name` to `origin`. A shallow checkout whose push is refused
+/// for missing history fetches the rest of it and retries once.
+pub fn push_to_remote(repo_path: &Path, branch_name: &str) -> Result<(), GitOpsError> {
+    let stderr = match run_push(repo_path, branch_name) {
+        Ok(()) => return Ok(()),
+        Err(stderr) => stderr,
+    };
+
+    let shallow = match cli::open_or_fallback(repo_path)? {
+        Some(repo) => repo.is_shallow(),
+        None => cli::is_shallow(repo_path)?,
+    };
+    if !shallow || !is_shallow_push_rejection(&stderr) {
         return Err(GitOpsError::GitError(format!("git push failed: {}", stderr)));
     }
 
+    unshallow(repo_path)?;
+    run_push(repo_path, branch_name)
+        .map_err(|stderr| GitOpsError::GitError(format!("git push failed: {}", stderr)))
+}
+
+/// Deletes `branch_name` from `origin` by pushing an empty source refspec.
+pub fn delete_remote_branch(repo_path: &Path, branch_name: &str) -> Result<(), GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["push", "origin", &format!(":refs/heads/{}", branch_name)])
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git push: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(classify_push_error(&String::from_utf8_lossy(&output.stderr)));
+    }
+
     Ok(())
 }
 
-pub fn commit_and_push(repo_path: &Path, message: &str) -> Result<(), GitOpsError> {
-    stage_all_changes(repo_path)?;
-    create_commit(repo_path, message)?;
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "authentication failed",
+    "permission denied",
+    "could not read username",
+    "could not read password",
+    "invalid username or password",
+    "repository not found",
+    "returned error: 401",
+    "returned error: 403",
+];
+
+const NETWORK_FAILURE_MARKERS: &[&str] = &[
+    "could not resolve host",
+    "connection refused",
+    "connection timed out",
+    "network is unreachable",
+    "could not read from remote repository",
+];
+
+/// Sorts `git push` stderr into an auth, network or other git error.
+pub fn classify_push_error(stderr: &str) -> GitOpsError {
+    let lower = stderr.to_lowercase();
+    let message = stderr.trim().to_string();
+
+    if AUTH_FAILURE_MARKERS.iter().any(|m| lower.contains(m)) {
+        GitOpsError::AuthError(message)
+    } else if NETWORK_FAILURE_MARKERS.iter().any(|m| lower.contains(m)) {
+        GitOpsError::NetworkError(message)
+    } else {
+        GitOpsError::GitError(format!("git push failed: {}", message))
+    }
+}
 
-    let repo = git2::Repository::open(repo_path)?;
-    let head = repo.head()?;
-    let branch_name = head
-        .shorthand()
-        .ok_or_else(|| GitOpsError::GitError("Could not get branch name".to_string()))?;
+/// Authenticates to `origin` for pushing the same way `push_to_remote` does,
+/// using `git push --dry-run` so no refs are created or updated.
+pub fn test_push_credentials(repo_path: &Path) -> Result<(), GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args([
+            "push",
+            "--dry-run",
+            "origin",
+            "HEAD:refs/heads/dreamal-credential-check",
+        ])
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git push: {}", e)))?;
 
-    push_to_remote(repo_path, branch_name)?;
+    if !output.status.success() {
+        return Err(classify_push_error(&String::from_utf8_lossy(&output.stderr)));
+    }
 
     Ok(())
 }
@@ -117,6 +427,46 @@ mod tests {
         assert!(new_file_entry.is_some());
     }
 
+    #[test]
+    fn test_has_changes() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+
+        stage_all_changes(&repo_path).unwrap();
+        assert!(!has_changes(&repo_path).unwrap());
+        assert!(!cli::has_staged_changes(&repo_path).unwrap());
+
+        fs::write(repo_path.join("test.txt"), "modified content").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        assert!(has_changes(&repo_path).unwrap());
+        assert!(cli::has_staged_changes(&repo_path).unwrap());
+    }
+
+    #[test]
+    fn test_staged_change_count() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        assert_eq!(staged_change_count(&repo_path).unwrap(), 0);
+
+        fs::write(repo_path.join("test.txt"), "modified content").unwrap();
+        fs::write(repo_path.join("new.txt"), "new").unwrap();
+        fs::create_dir_all(repo_path.join("src")).unwrap();
+        fs::write(repo_path.join("src/lib.rs"), "// lib").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+
+        assert_eq!(staged_change_count(&repo_path).unwrap(), 3);
+        assert_eq!(cli::staged_change_count(&repo_path).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_validate_commit_message() {
+        assert_eq!(
+            validate_commit_message("  Fix the build\n\nDetails.\n").unwrap(),
+            "Fix the build\n\nDetails."
+        );
+        assert!(validate_commit_message("   ").is_err());
+        assert!(validate_commit_message(&"x".repeat(73)).is_err());
+        assert!(validate_commit_message("Subject\nbody without a blank line").is_err());
+    }
+
     #[test]
     fn test_create_commit() {
         let (_temp_dir, repo_path) = setup_test_repo();
@@ -124,7 +474,7 @@ mod tests {
         fs::write(repo_path.join("new_file.txt"), "new content").unwrap();
         stage_all_changes(&repo_path).unwrap();
 
-        let result = create_commit(&repo_path, "Add new file");
+        let result = create_commit(&repo_path, "Add new file", &CommitIdentity::default());
         assert!(result.is_ok());
 
         let repo = git2::Repository::open(&repo_path).unwrap();
@@ -139,11 +489,285 @@ mod tests {
 
         fs::write(repo_path.join("another.txt"), "content").unwrap();
         stage_all_changes(&repo_path).unwrap();
-        create_commit(&repo_path, "Test commit").unwrap();
+        create_commit(&repo_path, "Test commit", &CommitIdentity::default()).unwrap();
+
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+
+        assert_eq!(head.author().name().unwrap(), "Test User");
+    }
+
+    #[test]
+    fn test_create_commit_overrides_repo_signature() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+
+        fs::write(repo_path.join("another.txt"), "content").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Dreamal Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        create_commit(&repo_path, "Test commit", &identity).unwrap();
+
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+
+        assert_eq!(head.author().name().unwrap(), "Dreamal Bot");
+        assert_eq!(head.author().email().unwrap(), "bot@example.com");
+        assert_eq!(head.committer().name().unwrap(), "Dreamal Bot");
+    }
+
+    #[test]
+    fn test_stage_changes_since_excludes_preexisting_changes() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+
+        fs::write(repo_path.join("test.txt"), "user's uncommitted edit").unwrap();
+        fs::write(repo_path.join("user_notes.txt"), "untracked by user").unwrap();
+        fs::write(repo_path.join("staged.txt"), "staged by user").unwrap();
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+
+        let snapshot = snapshot_worktree(&repo_path).unwrap();
+
+        fs::write(repo_path.join("claude.txt"), "claude's work").unwrap();
+        fs::write(repo_path.join("user_notes.txt"), "edited by claude").unwrap();
+
+        let staged = stage_changes_since(&repo_path, &snapshot).unwrap();
+        assert_eq!(
+            staged,
+            vec![PathBuf::from("claude.txt"), PathBuf::from("user_notes.txt")]
+        );
+        create_commit(&repo_path, "Claude's change", &CommitIdentity::default()).unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let tree = head.tree().unwrap();
+        assert!(tree.get_name("claude.txt").is_some());
+        assert!(tree.get_name("user_notes.txt").is_some());
+        assert!(tree.get_name("staged.txt").is_none());
+
+        let blob = tree
+            .get_name("test.txt")
+            .unwrap()
+            .to_object(&repo)
+            .unwrap()
+            .peel_to_blob()
+            .unwrap();
+        assert_eq!(blob.content(), b"hello world");
+        assert_eq!(
+            fs::read_to_string(repo_path.join("test.txt")).unwrap(),
+            "user's uncommitted edit"
+        );
+    }
+
+    #[test]
+    fn test_stage_changes_since_includes_deletions() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        let snapshot = snapshot_worktree(&repo_path).unwrap();
+
+        fs::remove_file(repo_path.join("test.txt")).unwrap();
+        let staged = stage_changes_since(&repo_path, &snapshot).unwrap();
+        assert_eq!(staged, vec![PathBuf::from("test.txt")]);
+
+        create_commit(&repo_path, "Remove file", &CommitIdentity::default()).unwrap();
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("test.txt").is_none());
+    }
+
+    #[test]
+    fn test_changes_checked_against_editable_paths() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        let editable = vec!["src".to_string()];
+        fs::create_dir_all(repo_path.join("src")).unwrap();
+        fs::create_dir_all(repo_path.join(".github")).unwrap();
+
+        let snapshot = snapshot_worktree(&repo_path).unwrap();
+        fs::write(repo_path.join("src/lib.rs"), "pub fn f() {}").unwrap();
+        let staged = stage_changes_since(&repo_path, &snapshot).unwrap();
+        assert!(out_of_scope_paths(&staged, &editable).is_empty());
+        create_commit(&repo_path, "In scope", &CommitIdentity::default()).unwrap();
+
+        let snapshot = snapshot_worktree(&repo_path).unwrap();
+        fs::write(repo_path.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(repo_path.join("src-old.rs"), "// not under src/").unwrap();
+        fs::write(repo_path.join(".github/ci.yml"), "on: push").unwrap();
+        fs::remove_file(repo_path.join("test.txt")).unwrap();
+        let staged = stage_changes_since(&repo_path, &snapshot).unwrap();
+        assert_eq!(
+            out_of_scope_paths(&staged, &editable),
+            vec![".github/ci.yml", "src-old.rs", "test.txt"]
+        );
+        assert!(out_of_scope_paths(&staged, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_create_commit_partial_identity() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+
+        fs::write(repo_path.join("another.txt"), "content").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        let identity = CommitIdentity {
+            name: None,
+            email: Some("bot@example.com".to_string()),
+        };
+        create_commit(&repo_path, "Test commit", &identity).unwrap();
 
         let repo = git2::Repository::open(&repo_path).unwrap();
         let head = repo.head().unwrap().peel_to_commit().unwrap();
 
         assert_eq!(head.author().name().unwrap(), "Test User");
+        assert_eq!(head.author().email().unwrap(), "bot@example.com");
+    }
+
+    #[test]
+    fn test_push_credentials_against_local_bare_remote() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote = git2::Repository::init_bare(remote_dir.path()).unwrap();
+
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        assert!(test_push_credentials(&repo_path).is_err());
+
+        repo.remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+        test_push_credentials(&repo_path).unwrap();
+
+        assert!(remote.references().unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_shallow_checkout_commits_and_pushes() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        fs::write(source_path.join("test.txt"), "second").unwrap();
+        stage_all_changes(&source_path).unwrap();
+        create_commit(&source_path, "Second commit", &CommitIdentity::default()).unwrap();
+
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote = git2::Repository::init_bare(remote_dir.path()).unwrap();
+        let source = git2::Repository::open(&source_path).unwrap();
+        source
+            .remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+        run_push(&source_path, "HEAD:refs/heads/main").unwrap();
+
+        let clone_dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1", "--branch", "main"])
+            .arg(format!("file://{}", remote_dir.path().display()))
+            .arg(clone_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let shallow = git2::Repository::open(clone_dir.path()).unwrap();
+        assert!(shallow.is_shallow());
+
+        fs::write(clone_dir.path().join("feature.txt"), "feature").unwrap();
+        stage_all_changes(clone_dir.path()).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        let commit_id = create_commit(clone_dir.path(), "Add feature", &identity).unwrap();
+        shallow.branch("feature", &shallow.find_commit(commit_id).unwrap(), false).unwrap();
+
+        push_to_remote(clone_dir.path(), "feature").unwrap();
+
+        let pushed = remote.find_reference("refs/heads/feature").unwrap();
+        assert_eq!(pushed.target(), Some(commit_id));
+    }
+
+    #[test]
+    fn test_shallow_push_rejection_unshallows_and_retries() {
+        let (_temp_dir, source_path) = setup_test_repo();
+        fs::write(source_path.join("test.txt"), "second").unwrap();
+        stage_all_changes(&source_path).unwrap();
+        create_commit(&source_path, "Second commit", &CommitIdentity::default()).unwrap();
+
+        let clone_dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1"])
+            .arg(format!("file://{}", source_path.display()))
+            .arg(clone_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // Fetches come from the full source, but pushes go to an empty remote
+        // that refuses the shallow history.
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote = git2::Repository::init_bare(remote_dir.path()).unwrap();
+        let status = Command::new("git")
+            .current_dir(clone_dir.path())
+            .args(["remote", "set-url", "--push", "origin"])
+            .arg(remote_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let shallow = git2::Repository::open(clone_dir.path()).unwrap();
+        fs::write(clone_dir.path().join("feature.txt"), "feature").unwrap();
+        stage_all_changes(clone_dir.path()).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        let commit_id = create_commit(clone_dir.path(), "Add feature", &identity).unwrap();
+        shallow.branch("feature", &shallow.find_commit(commit_id).unwrap(), false).unwrap();
+
+        let stderr = run_push(clone_dir.path(), "feature").unwrap_err();
+        assert!(is_shallow_push_rejection(&stderr));
+
+        push_to_remote(clone_dir.path(), "feature").unwrap();
+
+        assert!(!git2::Repository::open(clone_dir.path()).unwrap().is_shallow());
+        let pushed = remote.find_reference("refs/heads/feature").unwrap();
+        assert_eq!(pushed.target(), Some(commit_id));
+    }
+
+    #[test]
+    fn test_is_shallow_push_rejection() {
+        assert!(is_shallow_push_rejection(
+            "! [remote rejected] feature -> feature (shallow update not allowed)"
+        ));
+        assert!(!is_shallow_push_rejection("fatal: Authentication failed"));
+    }
+
+    #[test]
+    fn test_classify_push_error() {
+        assert!(matches!(
+            classify_push_error("remote: Invalid username or password.\nfatal: Authentication failed"),
+            GitOpsError::AuthError(_)
+        ));
+        assert!(matches!(
+            classify_push_error("git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."),
+            GitOpsError::AuthError(_)
+        ));
+        assert!(matches!(
+            classify_push_error("fatal: unable to access 'https://github.com/o/r/': Could not resolve host: github.com"),
+            GitOpsError::NetworkError(_)
+        ));
+        assert!(matches!(
+            classify_push_error("error: src refspec main does not match any"),
+            GitOpsError::GitError(_)
+        ));
+    }
+
+    #[test]
+    fn test_commit_subject_starts_with_prefix() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        fs::write(repo_path.join("test.txt"), "changed").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+
+        let message = prefixed_message(Some("[JIRA-123]"), "feat: Add dark mode");
+        create_commit(&repo_path, &message, &CommitIdentity::default()).unwrap();
+
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.summary().unwrap(), "[JIRA-123] feat: Add dark mode");
+
+        assert_eq!(prefixed_message(None, "feat: x"), "feat: x");
+        assert_eq!(prefixed_message(Some("  "), "feat: x"), "feat: x");
     }
 }
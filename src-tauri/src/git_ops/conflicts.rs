@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::cli;
+use super::GitOpsError;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConflictCheck {
+    pub will_conflict: bool,
+    pub conflicting_paths: Vec<String>,
+}
+
+fn resolve_base<'r>(
+    repo: &'r git2::Repository,
+    base_branch: &str,
+) -> Result<git2::Commit<'r>, GitOpsError> {
+    repo.revparse_single(&format!("refs/remotes/origin/{}", base_branch))
+        .or_else(|_| repo.revparse_single(&format!("refs/heads/{}", base_branch)))
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|_| GitOpsError::GitError(format!("Base branch not found: {}", base_branch)))
+}
+
+fn check_merge_conflicts_cli(
+    repo_path: &Path,
+    base_branch: &str,
+) -> Result<ConflictCheck, GitOpsError> {
+    let head = cli::resolve_commit(repo_path, "HEAD")
+        .ok_or_else(|| GitOpsError::GitError("HEAD has no commit".to_string()))?;
+    let base = [
+        format!("refs/remotes/origin/{}", base_branch),
+        format!("refs/heads/{}", base_branch),
+    ]
+    .iter()
+    .find_map(|name| cli::resolve_commit(repo_path, name))
+    .ok_or_else(|| GitOpsError::GitError(format!("Base branch not found: {}", base_branch)))?;
+
+    let conflicting_paths = cli::merge_conflicts(repo_path, base, head)?;
+    Ok(ConflictCheck {
+        will_conflict: !conflicting_paths.is_empty(),
+        conflicting_paths,
+    })
+}
+
+fn conflict_path(conflict: &git2::IndexConflict) -> Option<String> {
+    [&conflict.our, &conflict.their, &conflict.ancestor]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+}
+
+/// Merges HEAD into `base_branch` in memory and reports the paths that would
+/// conflict. The working tree and index are left untouched. The remote-tracking
+/// branch is preferred, so fetch it first for an up-to-date answer.
+pub fn check_merge_conflicts(
+    repo_path: &Path,
+    base_branch: &str,
+) -> Result<ConflictCheck, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return check_merge_conflicts_cli(repo_path, base_branch);
+    };
+    let head = repo.head()?.peel_to_commit()?;
+    let base = resolve_base(&repo, base_branch)?;
+
+    let (analysis, _) = repo.merge_analysis(&[&repo.find_annotated_commit(base.id())?])?;
+    if analysis.is_up_to_date() || analysis.is_fast_forward() {
+        return Ok(ConflictCheck::default());
+    }
+
+    let index = repo.merge_commits(&base, &head, None)?;
+    if !index.has_conflicts() {
+        return Ok(ConflictCheck::default());
+    }
+
+    let mut conflicting_paths = Vec::new();
+    for conflict in index.conflicts()? {
+        if let Some(path) = conflict_path(&conflict?) {
+            conflicting_paths.push(path);
+        }
+    }
+    conflicting_paths.sort();
+    conflicting_paths.dedup();
+
+    Ok(ConflictCheck {
+        will_conflict: true,
+        conflicting_paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn commit_file(
+        repo: &git2::Repository,
+        parent: Option<&git2::Commit>,
+        name: &str,
+        content: &str,
+    ) -> git2::Oid {
+        fs::write(repo.workdir().unwrap().join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        if let Some(parent) = parent {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        index.add_path(Path::new(name)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        let parents: Vec<_> = parent.into_iter().collect();
+        repo.commit(None, &sig, &sig, "change", &tree, &parents)
+            .unwrap()
+    }
+
+    /// A repo on `feature` whose branch and `main` each changed one file since
+    /// their common ancestor.
+    fn diverged_repo(main_change: (&str, &str), feature_change: (&str, &str)) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+
+        let initial = commit_file(&repo, None, "notes.txt", "one\ntwo\n");
+        let initial = repo.find_commit(initial).unwrap();
+        let main = commit_file(&repo, Some(&initial), main_change.0, main_change.1);
+        repo.reference("refs/heads/main", main, true, "main")
+            .unwrap();
+
+        let feature = commit_file(&repo, Some(&initial), feature_change.0, feature_change.1);
+        repo.reference("refs/heads/feature", feature, true, "feature")
+            .unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_reports_conflicting_paths() {
+        let repo_dir = diverged_repo(
+            ("notes.txt", "one\nmain\n"),
+            ("notes.txt", "one\nfeature\n"),
+        );
+
+        let check = check_merge_conflicts(repo_dir.path(), "main").unwrap();
+
+        assert!(check.will_conflict);
+        assert_eq!(check.conflicting_paths, vec!["notes.txt"]);
+        let repo = git2::Repository::open(repo_dir.path()).unwrap();
+        assert!(repo.statuses(None).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(repo_dir.path().join("notes.txt")).unwrap(),
+            "one\nfeature\n"
+        );
+    }
+
+    #[test]
+    fn test_clean_merge_and_missing_base() {
+        let repo_dir = diverged_repo(("other.txt", "x"), ("notes.txt", "one\nfeature\n"));
+
+        let check = check_merge_conflicts(repo_dir.path(), "main").unwrap();
+        assert_eq!(check, ConflictCheck::default());
+
+        assert!(check_merge_conflicts(repo_dir.path(), "no-such-branch").is_err());
+    }
+}
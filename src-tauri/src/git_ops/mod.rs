@@ -1,11 +1,17 @@
 pub mod branch;
+pub mod bundle;
 pub mod cleanup;
+pub mod cli;
 pub mod clone;
+pub mod codeowners;
 pub mod commit;
+pub mod conflicts;
+pub mod patch;
 pub mod pr;
+pub mod reset;
+pub mod size;
 
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum GitOpsError {
@@ -46,14 +52,12 @@ pub fn get_temp_checkouts_dir() -> Result<PathBuf, GitOpsError> {
     Ok(get_dreamal_dir()?.join("temp-checkouts"))
 }
 
-pub fn ensure_temp_checkouts_dir() -> Result<PathBuf, GitOpsError> {
-    let path = get_temp_checkouts_dir()?;
-    fs::create_dir_all(&path)?;
-    Ok(path)
+pub fn session_dir_in(checkouts_dir: &Path, session_id: &str) -> PathBuf {
+    checkouts_dir.join(format!("session-{}", session_id))
 }
 
 pub fn get_session_dir(session_id: &str) -> Result<PathBuf, GitOpsError> {
-    Ok(get_temp_checkouts_dir()?.join(format!("session-{}", session_id)))
+    Ok(session_dir_in(&get_temp_checkouts_dir()?, session_id))
 }
 
 #[cfg(test)]
@@ -76,14 +80,6 @@ mod tests {
         assert!(path.ends_with("temp-checkouts"));
     }
 
-    #[test]
-    fn test_ensure_temp_checkouts_dir() {
-        let result = ensure_temp_checkouts_dir();
-        assert!(result.is_ok());
-        let path = result.unwrap();
-        assert!(path.exists());
-    }
-
     #[test]
     fn test_get_session_dir() {
         let result = get_session_dir("test-123");
@@ -0,0 +1,512 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::cli;
+use super::GitOpsError;
+
+/// Under which `diff_checkout_branches` fetches the other checkout's branch,
+/// one ref per comparison so concurrent ones don't clobber each other.
+const COMPARE_REF_PREFIX: &str = "refs/dreamal/compare";
+
+fn compare_ref() -> String {
+    format!("{}/{}", COMPARE_REF_PREFIX, uuid::Uuid::new_v4())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileDiffStat {
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchDiff {
+    pub patch: String,
+    pub files: Vec<FileDiffStat>,
+}
+
+fn resolve_base_commit<'r>(
+    repo: &'r git2::Repository,
+    base_branch: &str,
+) -> Result<git2::Commit<'r>, GitOpsError> {
+    let head = repo.head()?.peel_to_commit()?;
+
+    let base = repo
+        .revparse_single(&format!("refs/heads/{}", base_branch))
+        .or_else(|_| repo.revparse_single(&format!("refs/remotes/origin/{}", base_branch)))
+        .and_then(|obj| obj.peel_to_commit());
+
+    match base {
+        Ok(base) => {
+            let merge_base = repo.merge_base(head.id(), base.id())?;
+            Ok(repo.find_commit(merge_base)?)
+        }
+        Err(_) => Ok(head),
+    }
+}
+
+/// `branch`'s tip, local branch first, in a checkout only the git CLI can read.
+fn find_branch_cli(repo_path: &Path, branch: &str) -> Option<git2::Oid> {
+    [
+        format!("refs/heads/{}", branch),
+        format!("refs/remotes/origin/{}", branch),
+    ]
+    .iter()
+    .find_map(|name| cli::resolve_commit(repo_path, name))
+}
+
+fn branch_not_found(branch: &str) -> GitOpsError {
+    GitOpsError::GitError(format!("Branch not found: {}", branch))
+}
+
+fn base_branch_not_found(base_branch: &str) -> GitOpsError {
+    GitOpsError::GitError(format!(
+        "Base branch {} not found, so the session's changes can't be told apart from \
+         its history",
+        base_branch
+    ))
+}
+
+/// `resolve_base_commit` for a checkout only the git CLI can read.
+fn resolve_base_commit_cli(repo_path: &Path, base_branch: &str) -> Result<git2::Oid, GitOpsError> {
+    let head = cli::resolve_commit(repo_path, "HEAD")
+        .ok_or_else(|| GitOpsError::GitError("HEAD has no commit".to_string()))?;
+    match find_branch_cli(repo_path, base_branch) {
+        Some(base) => cli::merge_base(repo_path, head, base),
+        None => Ok(head),
+    }
+}
+
+pub fn generate_patch(repo_path: &Path, base_branch: &str) -> Result<Vec<u8>, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        let base = resolve_base_commit_cli(repo_path, base_branch)?;
+        return cli::diff_worktree(repo_path, base, &["--binary"]);
+    };
+    let base_tree = resolve_base_commit(&repo, base_branch)?.tree()?;
+
+    // Binary files get a `GIT binary patch` section instead of raw bytes.
+    // Non-UTF-8 text is copied byte for byte, which `git apply` accepts.
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .show_binary(true);
+
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut opts))?;
+
+    render_patch(&diff)
+}
+
+fn render_patch(diff: &git2::Diff) -> Result<Vec<u8>, GitOpsError> {
+    let mut patch = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })?;
+
+    Ok(patch)
+}
+
+fn find_branch<'r>(
+    repo: &'r git2::Repository,
+    branch: &str,
+) -> Result<git2::Commit<'r>, git2::Error> {
+    repo.revparse_single(&format!("refs/heads/{}", branch))
+        .or_else(|_| repo.revparse_single(&format!("refs/remotes/origin/{}", branch)))
+        .and_then(|obj| obj.peel_to_commit())
+}
+
+/// Whether `repo_path` has `branch`, locally or as a remote-tracking branch.
+pub fn has_branch(repo_path: &Path, branch: &str) -> bool {
+    match cli::open_or_fallback(repo_path) {
+        Ok(Some(repo)) => find_branch(&repo, branch).is_ok(),
+        Ok(None) => find_branch_cli(repo_path, branch).is_some(),
+        Err(_) => false,
+    }
+}
+
+/// The patch `branch` makes on top of its merge base with `base_branch`,
+/// read from committed history. Used once a session's checkout is gone but
+/// its pushed branch is still known to `repo_path`.
+pub fn generate_branch_patch(
+    repo_path: &Path,
+    base_branch: &str,
+    branch: &str,
+) -> Result<Vec<u8>, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        let tip = find_branch_cli(repo_path, branch).ok_or_else(|| branch_not_found(branch))?;
+        let base = find_branch_cli(repo_path, base_branch)
+            .ok_or_else(|| base_branch_not_found(base_branch))?;
+        let base = cli::merge_base(repo_path, tip, base)?;
+        return cli::diff_commits(repo_path, base, tip, &["--binary"]);
+    };
+    let tip = find_branch(&repo, branch)?;
+    let base = find_branch(&repo, base_branch).map_err(|_| base_branch_not_found(base_branch))?;
+    let base_tree = repo
+        .find_commit(repo.merge_base(tip.id(), base.id())?)?
+        .tree()?;
+
+    let mut opts = git2::DiffOptions::new();
+    opts.show_binary(true);
+    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&tip.tree()?), Some(&mut opts))?;
+
+    render_patch(&diff)
+}
+
+fn diff_commits(
+    repo: &git2::Repository,
+    from: &git2::Commit,
+    to: &git2::Commit,
+) -> Result<BranchDiff, GitOpsError> {
+    let mut opts = git2::DiffOptions::new();
+    opts.show_binary(true);
+    let diff = repo.diff_tree_to_tree(Some(&from.tree()?), Some(&to.tree()?), Some(&mut opts))?;
+
+    let mut files = Vec::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        // Binary files have no lines to count.
+        let (_, insertions, deletions) = match git2::Patch::from_diff(&diff, i)? {
+            Some(patch) => patch.line_stats()?,
+            None => (0, 0, 0),
+        };
+        files.push(FileDiffStat {
+            path,
+            insertions,
+            deletions,
+        });
+    }
+
+    Ok(BranchDiff {
+        patch: String::from_utf8_lossy(&render_patch(&diff)?).into_owned(),
+        files,
+    })
+}
+
+/// Line counts per file of the commit at HEAD in `repo_path`.
+pub fn head_commit_stats(repo_path: &Path) -> Result<Vec<FileDiffStat>, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        let (Some(parent), Some(head)) = (
+            cli::resolve_commit(repo_path, "HEAD^"),
+            cli::resolve_commit(repo_path, "HEAD"),
+        ) else {
+            return Err(GitOpsError::GitError(
+                "HEAD has no parent commit".to_string(),
+            ));
+        };
+        return Ok(cli::numstat(repo_path, parent, head)?
+            .into_iter()
+            .map(|(path, insertions, deletions)| FileDiffStat {
+                path,
+                insertions,
+                deletions,
+            })
+            .collect());
+    };
+    let head = repo.head()?.peel_to_commit()?;
+    let diff = diff_commits(&repo, &head.parent(0)?, &head)?;
+    Ok(diff.files)
+}
+
+fn diff_checkout_branches_cli(
+    repo_path: &Path,
+    from: &str,
+    other_path: &Path,
+    to: &str,
+) -> Result<BranchDiff, GitOpsError> {
+    let from = find_branch_cli(repo_path, from).ok_or_else(|| branch_not_found(from))?;
+    let compare_ref = compare_ref();
+    cli::fetch(
+        repo_path,
+        &other_path.to_string_lossy(),
+        &format!("+refs/heads/{}:{}", to, compare_ref),
+    )?;
+    let diff = cli::resolve_commit(repo_path, &compare_ref)
+        .ok_or_else(|| branch_not_found(to))
+        .and_then(|to| {
+            let files = cli::numstat(repo_path, from, to)?
+                .into_iter()
+                .map(|(path, insertions, deletions)| FileDiffStat {
+                    path,
+                    insertions,
+                    deletions,
+                })
+                .collect();
+            let patch = cli::diff_commits(repo_path, from, to, &["--binary"])?;
+            Ok(BranchDiff {
+                patch: String::from_utf8_lossy(&patch).into_owned(),
+                files,
+            })
+        });
+    if let Err(e) = cli::delete_ref(repo_path, &compare_ref) {
+        eprintln!("Warning: Failed to delete {}: {}", compare_ref, e);
+    }
+
+    diff
+}
+
+/// The changes from the tip of `from` in `repo_path` to the tip of `to` in
+/// the checkout at `other_path`, which may be the same one. `to` is fetched
+/// into a scratch ref of `repo_path` for the comparison.
+pub fn diff_checkout_branches(
+    repo_path: &Path,
+    from: &str,
+    other_path: &Path,
+    to: &str,
+) -> Result<BranchDiff, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        return diff_checkout_branches_cli(repo_path, from, other_path, to);
+    };
+    let from = find_branch(&repo, from)?;
+
+    let compare_ref = compare_ref();
+    let mut remote = repo.remote_anonymous(&other_path.to_string_lossy())?;
+    remote.fetch(
+        &[&format!("+refs/heads/{}:{}", to, compare_ref)],
+        None,
+        None,
+    )?;
+    let diff = repo
+        .find_reference(&compare_ref)
+        .and_then(|reference| reference.peel_to_commit())
+        .map_err(GitOpsError::from)
+        .and_then(|to| diff_commits(&repo, &from, &to));
+    if let Err(e) = repo
+        .find_reference(&compare_ref)
+        .and_then(|mut reference| reference.delete())
+    {
+        eprintln!("Warning: Failed to delete {}: {}", compare_ref, e);
+    }
+
+    diff
+}
+
+/// Paths that differ between the merge base with `base_branch` and the
+/// working tree, untracked files included.
+pub fn changed_paths(repo_path: &Path, base_branch: &str) -> Result<Vec<String>, GitOpsError> {
+    let Some(repo) = cli::open_or_fallback(repo_path)? else {
+        let base = resolve_base_commit_cli(repo_path, base_branch)?;
+        let names = cli::diff_worktree(repo_path, base, &["--name-only", "-z"])?;
+        return Ok(names
+            .split(|b| *b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect());
+    };
+    let base_tree = resolve_base_commit(&repo, base_branch)?.tree()?;
+
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut opts))?;
+
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .collect())
+}
+
+pub fn write_patch(repo_path: &Path, base_branch: &str, dest: &Path) -> Result<usize, GitOpsError> {
+    let patch = generate_patch(repo_path, base_branch)?;
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, &patch)?;
+
+    Ok(patch.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::branch::create_feature_branch;
+    use crate::git_ops::commit::{create_commit, stage_all_changes, CommitIdentity};
+    use crate::test_support::setup_test_repo;
+
+    #[test]
+    fn test_generate_patch_includes_uncommitted_changes() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+
+        fs::write(repo_path.join("test.txt"), "hello patch\n").unwrap();
+        fs::write(repo_path.join("new_file.txt"), "new content\n").unwrap();
+
+        let patch = String::from_utf8(generate_patch(&repo_path, "base").unwrap()).unwrap();
+
+        assert!(patch.contains("-hello world"));
+        assert!(patch.contains("+hello patch"));
+        assert!(patch.contains("+new content"));
+    }
+
+    #[test]
+    fn test_generate_patch_includes_committed_changes() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+
+        fs::write(repo_path.join("test.txt"), "committed change\n").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        create_commit(&repo_path, "Change test file", &CommitIdentity::default()).unwrap();
+
+        let patch = String::from_utf8(generate_patch(&repo_path, "base").unwrap()).unwrap();
+
+        assert!(patch.contains("+committed change"));
+    }
+
+    #[test]
+    fn test_generate_patch_with_binary_and_latin1_files() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+
+        fs::write(
+            repo_path.join("image.png"),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR",
+        )
+        .unwrap();
+        fs::write(repo_path.join("latin1.txt"), b"caf\xe9\n").unwrap();
+
+        let patch = generate_patch(&repo_path, "base").unwrap();
+        let text = String::from_utf8_lossy(&patch);
+
+        assert!(text.contains("diff --git a/image.png b/image.png"));
+        assert!(text.contains("GIT binary patch"));
+        assert!(!patch.windows(4).any(|w| w == b"IHDR"));
+        assert!(patch.windows(5).any(|w| w == b"+caf\xe9"));
+    }
+
+    #[test]
+    fn test_write_patch_produces_non_empty_file() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+        fs::write(repo_path.join("test.txt"), "hello patch\n").unwrap();
+
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest = dest_dir.path().join("session.patch");
+
+        let written = write_patch(&repo_path, "base", &dest).unwrap();
+
+        assert!(written > 0);
+        assert!(fs::metadata(&dest).unwrap().len() > 0);
+    }
+
+    #[test]
+    fn test_generate_patch_empty_without_changes() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+
+        let patch = generate_patch(&repo_path, "base").unwrap();
+
+        assert!(patch.is_empty());
+    }
+
+    #[test]
+    fn test_generate_branch_patch() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        create_feature_branch(&repo_path, "claude/test-feature").unwrap();
+        fs::write(repo_path.join("test.txt"), "committed change\n").unwrap();
+        stage_all_changes(&repo_path).unwrap();
+        create_commit(&repo_path, "Change test file", &CommitIdentity::default()).unwrap();
+        fs::write(repo_path.join("test.txt"), "uncommitted\n").unwrap();
+
+        let patch = generate_branch_patch(&repo_path, "base", "claude/test-feature").unwrap();
+        let patch = String::from_utf8(patch).unwrap();
+
+        assert!(patch.contains("+committed change"));
+        assert!(!patch.contains("uncommitted"));
+        assert!(generate_branch_patch(&repo_path, "base", "claude/missing").is_err());
+        let error = generate_branch_patch(&repo_path, "release", "claude/test-feature")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Base branch release not found"), "{}", error);
+    }
+
+    fn commit_branch(repo_path: &Path, branch: &str, files: &[(&str, &str)]) {
+        create_feature_branch(repo_path, branch).unwrap();
+        for (path, content) in files {
+            fs::write(repo_path.join(path), content).unwrap();
+        }
+        stage_all_changes(repo_path).unwrap();
+        create_commit(repo_path, branch, &CommitIdentity::default()).unwrap();
+    }
+
+    #[test]
+    fn test_diff_local_branches() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        commit_branch(
+            &repo_path,
+            "claude/first",
+            &[("test.txt", "hello first\n"), ("only_first.txt", "a\nb\n")],
+        );
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        repo.set_head("refs/heads/base").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        commit_branch(
+            &repo_path,
+            "claude/second",
+            &[("test.txt", "hello second\nand more\n")],
+        );
+
+        let diff = diff_checkout_branches(&repo_path, "claude/first", &repo_path, "claude/second")
+            .unwrap();
+
+        assert!(diff.patch.contains("-hello first"));
+        assert!(diff.patch.contains("+hello second"));
+        assert_eq!(
+            diff.files,
+            vec![
+                FileDiffStat {
+                    path: "only_first.txt".to_string(),
+                    insertions: 0,
+                    deletions: 2,
+                },
+                FileDiffStat {
+                    path: "test.txt".to_string(),
+                    insertions: 2,
+                    deletions: 1,
+                },
+            ]
+        );
+        assert!(
+            diff_checkout_branches(&repo_path, "claude/first", &repo_path, "claude/missing")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_diff_branches_across_checkouts() {
+        let (_temp_dir, repo_path) = setup_test_repo();
+        let other_dir = tempfile::tempdir().unwrap();
+        git2::Repository::clone(&repo_path.to_string_lossy(), other_dir.path()).unwrap();
+        commit_branch(&repo_path, "claude/first", &[("test.txt", "hello first\n")]);
+        commit_branch(
+            other_dir.path(),
+            "claude/second",
+            &[("test.txt", "hello second\n")],
+        );
+
+        let diff = diff_checkout_branches(
+            &repo_path,
+            "claude/first",
+            other_dir.path(),
+            "claude/second",
+        )
+        .unwrap();
+
+        assert!(diff.patch.contains("+hello second"));
+        assert_eq!(diff.files.len(), 1);
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let compare_glob = format!("{}/*", COMPARE_REF_PREFIX);
+        assert_eq!(repo.references_glob(&compare_glob).unwrap().count(), 0);
+    }
+}
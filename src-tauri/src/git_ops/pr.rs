@@ -2,20 +2,23 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+use serde::{Deserialize, Serialize};
+
 use super::{get_dreamal_dir, GitOpsError};
 
+fn credential(key: &str) -> Option<String> {
+    let dreamal_dir = get_dreamal_dir().ok()?;
+    let content = fs::read_to_string(dreamal_dir.join("credentials.json")).ok()?;
+    let json = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+    json.get(key)
+        .and_then(|v| v.as_str())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+}
+
 fn get_github_token() -> Result<String, GitOpsError> {
-    if let Ok(dreamal_dir) = get_dreamal_dir() {
-        let creds_path = dreamal_dir.join("credentials.json");
-        if let Ok(content) = fs::read_to_string(&creds_path) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                if let Some(token) = json.get("github_token").and_then(|v| v.as_str()) {
-                    if !token.is_empty() {
-                        return Ok(token.to_string());
-                    }
-                }
-            }
-        }
+    if let Some(token) = credential("github_token") {
+        return Ok(token);
     }
 
     if let Ok(output) = Command::new("gh").args(["auth", "token"]).output() {
@@ -34,13 +37,42 @@ fn get_github_token() -> Result<String, GitOpsError> {
     })
 }
 
+fn get_gitlab_token() -> Result<String, GitOpsError> {
+    if let Some(token) = credential("gitlab_token") {
+        return Ok(token);
+    }
+
+    std::env::var("GITLAB_TOKEN").map_err(|_| {
+        GitOpsError::AuthError(
+            "No GitLab token found. Add gitlab_token to ~/.dreamal/credentials.json".to_string(),
+        )
+    })
+}
+
 #[derive(Debug)]
 pub struct RepoInfo {
     pub owner: String,
     pub repo: String,
 }
 
-pub fn parse_github_remote(remote_url: &str) -> Result<RepoInfo, GitOpsError> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteHost {
+    GitHub,
+    GitLab,
+}
+
+fn split_repo_path(path: &str) -> Option<(String, String)> {
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.rsplit_once('/')?;
+    if owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// Works out which forge hosts `remote_url`. GitLab projects can sit in
+/// nested groups, so their `owner` is the whole namespace path.
+pub fn parse_remote(remote_url: &str) -> Result<(RemoteHost, RepoInfo), GitOpsError> {
     let url = remote_url.trim();
 
     // SSH format: git@github.com:owner/repo.git or git@alias.github.com:owner/repo.git
@@ -50,10 +82,11 @@ pub fn parse_github_remote(remote_url: &str) -> Result<RepoInfo, GitOpsError> {
             let path = path.strip_suffix(".git").unwrap_or(path);
             let parts: Vec<&str> = path.split('/').collect();
             if parts.len() == 2 {
-                return Ok(RepoInfo {
+                let info = RepoInfo {
                     owner: parts[0].to_string(),
                     repo: parts[1].to_string(),
-                });
+                };
+                return Ok((RemoteHost::GitHub, info));
             }
         }
     }
@@ -64,21 +97,43 @@ pub fn parse_github_remote(remote_url: &str) -> Result<RepoInfo, GitOpsError> {
         let path = path.strip_suffix(".git").unwrap_or(path);
         let parts: Vec<&str> = path.split('/').collect();
         if parts.len() >= 2 {
-            return Ok(RepoInfo {
+            let info = RepoInfo {
                 owner: parts[0].to_string(),
                 repo: parts[1].to_string(),
-            });
+            };
+            return Ok((RemoteHost::GitHub, info));
         }
     }
 
+    // git@gitlab.com:group/subgroup/repo.git or https://gitlab.com/group/repo.git
+    let gitlab_path = url
+        .strip_prefix("git@gitlab.com:")
+        .or_else(|| url.strip_prefix("https://gitlab.com/"));
+    if let Some((owner, repo)) = gitlab_path.and_then(split_repo_path) {
+        return Ok((RemoteHost::GitLab, RepoInfo { owner, repo }));
+    }
+
     Err(GitOpsError::GitError(format!(
-        "Could not parse GitHub remote URL: {}",
+        "Could not parse remote URL: {}",
         url
     )))
 }
 
+pub fn parse_github_remote(remote_url: &str) -> Result<RepoInfo, GitOpsError> {
+    match parse_remote(remote_url) {
+        Ok((RemoteHost::GitHub, info)) => Ok(info),
+        _ => Err(GitOpsError::GitError(format!(
+            "Could not parse GitHub remote URL: {}",
+            remote_url.trim()
+        ))),
+    }
+}
+
 pub fn get_remote_url(repo_path: &Path) -> Result<String, GitOpsError> {
-    let repo = git2::Repository::open(repo_path)?;
+    let Some(repo) = super::cli::open_or_fallback(repo_path)? else {
+        return super::cli::origin_url(repo_path)
+            .ok_or_else(|| GitOpsError::GitError("Remote 'origin' has no URL".to_string()));
+    };
     let remote = repo.find_remote("origin")?;
     let url = remote
         .url()
@@ -86,55 +141,919 @@ pub fn get_remote_url(repo_path: &Path) -> Result<String, GitOpsError> {
     Ok(url.to_string())
 }
 
+const PR_TITLE_MAX_LEN: usize = 72;
+const DEFAULT_PR_TITLE: &str = "Update from Claude";
+
+const POLITE_PREFIXES: &[&str] = &[
+    "i would like you to",
+    "i'd like you to",
+    "i want you to",
+    "please",
+    "can you",
+    "could you",
+    "would you",
+    "we need to",
+    "you should",
+    "let's",
+    "to",
+];
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    let rest = &text[prefix.len()..];
+    let at_word_boundary =
+        rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == ',');
+    if head.eq_ignore_ascii_case(prefix) && at_word_boundary {
+        Some(rest.trim_start_matches(|c: char| c.is_whitespace() || c == ','))
+    } else {
+        None
+    }
+}
+
+fn trim_title_punctuation(text: &str) -> &str {
+    text.trim_end_matches(|c: char| c.is_whitespace() || ".!?,;:".contains(c))
+}
+
+pub fn suggest_pr_title(instructions: &str) -> String {
+    let first_line = instructions
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    let mut title = trim_title_punctuation(first_line);
+
+    while let Some(rest) = POLITE_PREFIXES
+        .iter()
+        .find_map(|prefix| strip_prefix_ignore_case(title, prefix))
+    {
+        title = rest;
+    }
+
+    let suffix_start = title.len().saturating_sub("please".len());
+    if title
+        .get(suffix_start..)
+        .is_some_and(|tail| tail.eq_ignore_ascii_case("please"))
+        && title[..suffix_start].ends_with([' ', ','])
+    {
+        title = trim_title_punctuation(&title[..suffix_start]);
+    }
+
+    let mut title = title.to_string();
+    if title.chars().count() > PR_TITLE_MAX_LEN {
+        let cut: String = title.chars().take(PR_TITLE_MAX_LEN).collect();
+        let cut = match cut.rfind(char::is_whitespace) {
+            Some(idx) => cut[..idx].to_string(),
+            None => cut,
+        };
+        title = trim_title_punctuation(&cut).to_string();
+    }
+
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => DEFAULT_PR_TITLE.to_string(),
+    }
+}
+
+const PR_TEMPLATE_DIRS: &[&str] = &[".github", "", "docs"];
+const PR_TEMPLATE_NAMES: &[&str] = &["pull_request_template.md", "PULL_REQUEST_TEMPLATE.md"];
+
+fn find_pr_template(repo_path: &Path) -> Option<std::path::PathBuf> {
+    PR_TEMPLATE_DIRS.iter().find_map(|dir| {
+        PR_TEMPLATE_NAMES
+            .iter()
+            .map(|name| repo_path.join(dir).join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Returns the repo's pull request template, or an empty string if it has none.
+pub fn read_pr_template(repo_path: &Path) -> Result<String, GitOpsError> {
+    match find_pr_template(repo_path) {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => Ok(String::new()),
+    }
+}
+
+pub fn compose_pr_body(template: &str, instructions: &str) -> String {
+    let generated = format!(
+        "This PR was generated by Claude via the Dreamal `/claude` macro.\n\n## Instructions\n\n{}\n",
+        instructions
+    );
+
+    if template.trim().is_empty() {
+        format!("## Summary\n\n{}", generated)
+    } else {
+        format!("{}\n\n---\n\n{}", template.trim_end(), generated)
+    }
+}
+
+pub fn parse_pr_url(pr_url: &str) -> Option<(RepoInfo, u64)> {
+    let path = pr_url
+        .trim_end_matches('/')
+        .strip_prefix("https://github.com/")?;
+    let mut parts = path.split('/');
+
+    let owner = parts.next()?.to_string();
+    let repo = parts.next()?.to_string();
+    if parts.next()? != "pull" {
+        return None;
+    }
+    let number = parts.next()?.parse().ok()?;
+
+    Some((RepoInfo { owner, repo }, number))
+}
+
+/// "merged", or the PR's `state`. Single-PR responses carry `merged`; items
+/// of the list endpoint only have `merged_at`.
+pub fn parse_pr_state(json: &serde_json::Value) -> Option<String> {
+    if json["merged"].as_bool() == Some(true) || !json["merged_at"].is_null() {
+        return Some("merged".to_string());
+    }
+    json["state"].as_str().map(|s| s.to_string())
+}
+
+pub fn get_pull_request_state(pr_url: &str) -> Result<String, GitOpsError> {
+    let (repo_info, number) = parse_pr_url(pr_url)
+        .ok_or_else(|| GitOpsError::GitError(format!("Not a GitHub PR URL: {}", pr_url)))?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        &github_api_url(&[
+            "repos",
+            &repo_info.owner,
+            &repo_info.repo,
+            "pulls",
+            &number.to_string(),
+        ]),
+        &token,
+    )
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let json = parse_json_response(response, "pull-request read")?;
+
+    parse_pr_state(&json)
+        .ok_or_else(|| GitOpsError::GitError("No PR state in response".to_string()))
+}
+
+/// States of every PR, open or closed, whose head is `branch` on the repo's
+/// `origin`.
+pub fn get_branch_pr_states(repo_path: &Path, branch: &str) -> Result<Vec<String>, GitOpsError> {
+    let repo_info = get_repo_info(repo_path)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        &github_api_url(&["repos", &repo_info.owner, &repo_info.repo, "pulls"]),
+        &token,
+    )
+    .query(&[
+        ("head", format!("{}:{}", repo_info.owner, branch)),
+        ("state", "all".to_string()),
+        ("per_page", "100".to_string()),
+    ])
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let json = parse_json_response(response, "pull-request read")?;
+
+    Ok(parse_pr_states(&json))
+}
+
+fn parse_pr_states(json: &serde_json::Value) -> Vec<String> {
+    json.as_array()
+        .map(|prs| prs.iter().filter_map(parse_pr_state).collect())
+        .unwrap_or_default()
+}
+
+/// The API URL of `pr_url` under `collection`, either `pulls` or `issues`
+/// (every PR is also an issue), followed by `rest`.
+fn pr_api_url(pr_url: &str, collection: &str, rest: &[&str]) -> Result<String, GitOpsError> {
+    let (repo_info, number) = parse_pr_url(pr_url)
+        .ok_or_else(|| GitOpsError::GitError(format!("Not a GitHub PR URL: {}", pr_url)))?;
+    let number = number.to_string();
+
+    let mut segments = vec![
+        "repos",
+        repo_info.owner.as_str(),
+        repo_info.repo.as_str(),
+        collection,
+        number.as_str(),
+    ];
+    segments.extend_from_slice(rest);
+    Ok(github_api_url(&segments))
+}
+
+fn pull_request_endpoint(pr_url: &str) -> Result<String, GitOpsError> {
+    pr_api_url(pr_url, "pulls", &[])
+}
+
+/// The endpoint and body of the request that reopens `pr_url`.
+pub fn reopen_request(pr_url: &str) -> Result<(String, serde_json::Value), GitOpsError> {
+    Ok((
+        pull_request_endpoint(pr_url)?,
+        serde_json::json!({ "state": "open" }),
+    ))
+}
+
+/// The endpoint and body of a partial update to `pr_url`. Only the given
+/// fields are sent, so GitHub leaves the others unchanged.
+pub fn update_request(
+    pr_url: &str,
+    title: Option<&str>,
+    body: Option<&str>,
+) -> Result<(String, serde_json::Value), GitOpsError> {
+    let mut fields = serde_json::Map::new();
+    if let Some(title) = title {
+        fields.insert("title".to_string(), title.into());
+    }
+    if let Some(body) = body {
+        fields.insert("body".to_string(), body.into());
+    }
+    if fields.is_empty() {
+        return Err(GitOpsError::GitError(
+            "Nothing to update: give a title or a body".to_string(),
+        ));
+    }
+
+    Ok((pull_request_endpoint(pr_url)?, fields.into()))
+}
+
+/// Updates the title and/or body of `pr_url` and returns the PR's URL.
+pub fn update_pull_request(
+    pr_url: &str,
+    title: Option<&str>,
+    body: Option<&str>,
+) -> Result<String, GitOpsError> {
+    let (url, fields) = update_request(pr_url, title, body)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(&client, reqwest::Method::PATCH, &url, &token)
+        .json(&fields)
+        .send()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let json = parse_json_response(response, "pull-request write")?;
+
+    let pr_url = json["html_url"]
+        .as_str()
+        .ok_or_else(|| GitOpsError::GitError("No PR URL in response".to_string()))?;
+
+    Ok(pr_url.to_string())
+}
+
+/// GitHub answers 422 "state cannot be changed" when the PR's head branch is
+/// gone or was rewritten, which only a fresh push can fix.
+pub fn reopen_error(status: u16, body: &str) -> GitOpsError {
+    if status == 422 && body.to_lowercase().contains("state cannot be changed") {
+        return GitOpsError::GitError(
+            "The PR can't be reopened because its branch was deleted or rewritten. Push the branch again (for example by re-running the session) and retry.".to_string(),
+        );
+    }
+    github_api_error(status, body, "pull-request write")
+}
+
+/// Reopens a closed PR and returns its new state.
+pub fn reopen_pull_request(pr_url: &str) -> Result<String, GitOpsError> {
+    let (url, body) = reopen_request(pr_url)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(&client, reqwest::Method::PATCH, &url, &token)
+        .json(&body)
+        .send()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        return Err(reopen_error(status, &response.text().unwrap_or_default()));
+    }
+    let json: serde_json::Value = response
+        .json()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    parse_pr_state(&json)
+        .ok_or_else(|| GitOpsError::GitError("No PR state in response".to_string()))
+}
+
+pub fn get_repo_info(repo_path: &Path) -> Result<RepoInfo, GitOpsError> {
+    let remote_url = get_remote_url(repo_path)?;
+    parse_github_remote(&remote_url)
+}
+
+pub fn get_remote_host(repo_path: &Path) -> Result<RemoteHost, GitOpsError> {
+    let remote_url = get_remote_url(repo_path)?;
+    Ok(parse_remote(&remote_url)?.0)
+}
+
+fn github_request(
+    client: &reqwest::blocking::Client,
+    method: reqwest::Method,
+    url: &str,
+    token: &str,
+) -> reqwest::blocking::RequestBuilder {
+    client
+        .request(method, url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "dreamal-app")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+}
+
+const INSUFFICIENT_PERMISSION_MARKERS: &[&str] = &[
+    "resource not accessible by personal access token",
+    "resource not accessible by integration",
+    "must have push access",
+    "must have admin rights",
+    "insufficient",
+];
+
+/// Maps an unsuccessful GitHub response to an error. A 403 caused by missing
+/// token permissions becomes an `AuthError` naming the `permission` the request
+/// needed; other 403s (rate limits, abuse detection) stay generic.
+pub fn github_api_error(status: u16, body: &str, permission: &str) -> GitOpsError {
+    let lowered = body.to_lowercase();
+    if status == 403
+        && INSUFFICIENT_PERMISSION_MARKERS
+            .iter()
+            .any(|marker| lowered.contains(marker))
+    {
+        return GitOpsError::AuthError(format!(
+            "Your GitHub token lacks {} permission for this repo. Grant it to the token (or use a classic token with the repo scope) and try again.",
+            permission
+        ));
+    }
+
+    GitOpsError::GitError(format!("GitHub API error ({}): {}", status, body))
+}
+
+fn parse_json_response(
+    response: reqwest::blocking::Response,
+    permission: &str,
+) -> Result<serde_json::Value, GitOpsError> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_body = response.text().unwrap_or_default();
+        return Err(github_api_error(status.as_u16(), &error_body, permission));
+    }
+
+    response
+        .json()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct BranchProtection {
+    pub protected: bool,
+    /// False when the branch is protected but the token can't read the
+    /// protection settings (that needs administration access), so the fields
+    /// below are unknown rather than empty.
+    pub details_known: bool,
+    pub requires_pull_request: bool,
+    pub required_approving_reviews: u64,
+    pub required_status_checks: Vec<String>,
+    /// Whether branches must be up to date with the base before merging.
+    pub strict_status_checks: bool,
+    pub require_code_owner_reviews: bool,
+    pub enforce_admins: bool,
+    pub allows_force_pushes: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchPermissions {
+    pub can_push: bool,
+    pub protection: BranchProtection,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForcePushCheck {
+    pub allowed: bool,
+    /// False when the branch is protected but the token can't read whether
+    /// that protection allows force pushes. `allowed` is then only a guess.
+    pub protection_known: bool,
+    pub message: Option<String>,
+}
+
+fn enabled_flag(json: &serde_json::Value, key: &str) -> bool {
+    json[key]["enabled"].as_bool().unwrap_or(false)
+}
+
+pub fn parse_branch_protection(json: &serde_json::Value) -> BranchProtection {
+    let reviews = &json["required_pull_request_reviews"];
+    let checks = &json["required_status_checks"];
+
+    let mut required_status_checks: Vec<String> = checks["contexts"]
+        .as_array()
+        .map(|contexts| {
+            contexts
+                .iter()
+                .filter_map(|c| c.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some(check_runs) = checks["checks"].as_array() {
+        for check in check_runs {
+            if let Some(context) = check["context"].as_str() {
+                if !required_status_checks.iter().any(|c| c == context) {
+                    required_status_checks.push(context.to_string());
+                }
+            }
+        }
+    }
+
+    BranchProtection {
+        protected: true,
+        details_known: true,
+        requires_pull_request: reviews.is_object(),
+        required_approving_reviews: reviews["required_approving_review_count"]
+            .as_u64()
+            .unwrap_or(0),
+        required_status_checks,
+        strict_status_checks: checks["strict"].as_bool().unwrap_or(false),
+        require_code_owner_reviews: reviews["require_code_owner_reviews"]
+            .as_bool()
+            .unwrap_or(false),
+        enforce_admins: enabled_flag(json, "enforce_admins"),
+        allows_force_pushes: enabled_flag(json, "allow_force_pushes"),
+    }
+}
+
+pub fn parse_push_permission(json: &serde_json::Value) -> bool {
+    json["permissions"]["push"].as_bool().unwrap_or(false)
+}
+
+/// A GitHub API URL with each path segment percent-encoded, so a branch name
+/// containing `/`, `#` or `?` can't address a different route.
+pub fn github_api_url(segments: &[&str]) -> String {
+    let mut url = reqwest::Url::parse("https://api.github.com").expect("valid base URL");
+    url.path_segments_mut()
+        .expect("base URL has a path")
+        .extend(segments);
+    url.to_string()
+}
+
+/// Whether `branch` is protected comes from the branch itself, which any token
+/// that can read the repo may see. The protection details need administration
+/// access; when GitHub refuses them (403, or 404 for tokens without it), the
+/// branch is reported protected with unknown details.
+pub fn get_branch_protection(
+    repo_path: &Path,
+    branch: &str,
+) -> Result<BranchProtection, GitOpsError> {
+    let repo_info = get_repo_info(repo_path)?;
+    let token = get_github_token()?;
+    let branch_url = github_api_url(&[
+        "repos",
+        &repo_info.owner,
+        &repo_info.repo,
+        "branches",
+        branch,
+    ]);
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(&client, reqwest::Method::GET, &branch_url, &token)
+        .send()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let unprotected = BranchProtection {
+        details_known: true,
+        ..BranchProtection::default()
+    };
+    // A branch that doesn't exist yet has no protection.
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(unprotected);
+    }
+    let json = parse_json_response(response, "metadata read")?;
+    if !json["protected"].as_bool().unwrap_or(false) {
+        return Ok(unprotected);
+    }
+
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        &format!("{}/protection", branch_url),
+        &token,
+    )
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND
+    ) {
+        return Ok(BranchProtection {
+            protected: true,
+            ..BranchProtection::default()
+        });
+    }
+
+    let json = parse_json_response(response, "administration read")?;
+
+    Ok(parse_branch_protection(&json))
+}
+
+/// `protection` as a list of merge requirements for `branch`. Protection
+/// whose details couldn't be read is an error rather than an empty list, so
+/// callers don't mistake "unknown" for "none".
+pub fn branch_requirements(
+    branch: &str,
+    protection: BranchProtection,
+) -> Result<BranchProtection, GitOpsError> {
+    if protection.protected && !protection.details_known {
+        return Err(GitOpsError::AuthError(format!(
+            "Branch '{}' is protected, but your GitHub token lacks administration read permission, so its requirements are unknown.",
+            branch
+        )));
+    }
+    Ok(protection)
+}
+
+pub fn parse_rules_forbid_force_push(json: &serde_json::Value) -> bool {
+    json.as_array().is_some_and(|rules| {
+        rules
+            .iter()
+            .any(|rule| rule["type"].as_str() == Some("non_fast_forward"))
+    })
+}
+
+/// Protection whose details the token can't read is reported as unknown and
+/// left allowed, since it may well permit force pushes.
+pub fn force_push_check(
+    branch: &str,
+    protection: &BranchProtection,
+    rules_forbid: bool,
+) -> ForcePushCheck {
+    let protection_known = !protection.protected || protection.details_known;
+    if rules_forbid || (protection_known && protection.protected && !protection.allows_force_pushes)
+    {
+        ForcePushCheck {
+            allowed: false,
+            protection_known: true,
+            message: Some(format!(
+                "Branch '{}' is protected; force push will be rejected",
+                branch
+            )),
+        }
+    } else if !protection_known {
+        ForcePushCheck {
+            allowed: true,
+            protection_known: false,
+            message: Some(format!(
+                "Branch '{}' is protected, but its protection is unknown: your GitHub token lacks administration read permission",
+                branch
+            )),
+        }
+    } else {
+        ForcePushCheck {
+            allowed: true,
+            protection_known: true,
+            message: None,
+        }
+    }
+}
+
+/// Checks both classic branch protection and repository rulesets, since either can
+/// match the branch by pattern.
+pub fn check_force_push(repo_path: &Path, branch: &str) -> Result<ForcePushCheck, GitOpsError> {
+    let protection = get_branch_protection(repo_path, branch)?;
+
+    let repo_info = get_repo_info(repo_path)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        &github_api_url(&[
+            "repos",
+            &repo_info.owner,
+            &repo_info.repo,
+            "rules",
+            "branches",
+            branch,
+        ]),
+        &token,
+    )
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let rules_forbid = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        false
+    } else {
+        parse_rules_forbid_force_push(&parse_json_response(response, "metadata read")?)
+    };
+
+    Ok(force_push_check(branch, &protection, rules_forbid))
+}
+
+pub fn check_branch_permissions(
+    repo_path: &Path,
+    base_branch: &str,
+) -> Result<BranchPermissions, GitOpsError> {
+    let repo_info = get_repo_info(repo_path)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        &github_api_url(&["repos", &repo_info.owner, &repo_info.repo]),
+        &token,
+    )
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let json = parse_json_response(response, "metadata read")?;
+
+    Ok(BranchPermissions {
+        can_push: parse_push_permission(&json),
+        protection: get_branch_protection(repo_path, base_branch)?,
+    })
+}
+
+/// A repo the token's user can access, as `GET /user/repos` lists it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserRepo {
+    pub name: String,
+    pub full_name: String,
+    pub ssh_url: String,
+    pub clone_url: String,
+    pub default_branch: String,
+    pub private: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserRepoPage {
+    pub repos: Vec<UserRepo>,
+    pub page: u32,
+    pub has_next_page: bool,
+}
+
+/// Entries that don't parse as repos are skipped.
+pub fn parse_user_repos(json: &serde_json::Value) -> Vec<UserRepo> {
+    json.as_array()
+        .map(|repos| {
+            repos
+                .iter()
+                .filter_map(|repo| serde_json::from_value(repo.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether a `Link` header points at a further page.
+pub fn link_has_next(link: &str) -> bool {
+    link.split(',')
+        .any(|part| part.split(';').any(|param| param.trim() == r#"rel="next""#))
+}
+
+/// One page of the authenticated user's repos, most recently updated first.
+pub fn list_user_repos(page: u32, per_page: u32) -> Result<UserRepoPage, GitOpsError> {
+    let token = get_github_token()?;
+    let page = page.max(1);
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::GET,
+        "https://api.github.com/user/repos",
+        &token,
+    )
+    .query(&[
+        ("sort", "updated".to_string()),
+        ("page", page.to_string()),
+        ("per_page", per_page.clamp(1, 100).to_string()),
+    ])
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let has_next_page = response
+        .headers()
+        .get("link")
+        .and_then(|link| link.to_str().ok())
+        .is_some_and(link_has_next);
+    let json = parse_json_response(response, "metadata read")?;
+
+    Ok(UserRepoPage {
+        repos: parse_user_repos(&json),
+        page,
+        has_next_page,
+    })
+}
+
 pub fn create_pull_request(
     repo_path: &Path,
     title: &str,
     body: &str,
     head_branch: &str,
     base_branch: &str,
+    draft: bool,
 ) -> Result<String, GitOpsError> {
-    let remote_url = get_remote_url(repo_path)?;
-    let repo_info = parse_github_remote(&remote_url)?;
+    let repo_info = get_repo_info(repo_path)?;
     let token = get_github_token()?;
 
     let client = reqwest::blocking::Client::new();
 
+    let response = github_request(
+        &client,
+        reqwest::Method::POST,
+        &github_api_url(&["repos", &repo_info.owner, &repo_info.repo, "pulls"]),
+        &token,
+    )
+    .json(&serde_json::json!({
+        "title": title,
+        "body": body,
+        "head": head_branch,
+        "base": base_branch,
+        "draft": draft
+    }))
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    let json = parse_json_response(response, "pull-request write")?;
+
+    let pr_url = json["html_url"]
+        .as_str()
+        .ok_or_else(|| GitOpsError::GitError("No PR URL in response".to_string()))?;
+
+    Ok(pr_url.to_string())
+}
+
+/// The merge requests endpoint for a gitlab.com project, addressed by its
+/// URL-encoded path rather than its numeric id.
+pub fn merge_request_endpoint(info: &RepoInfo) -> String {
+    let project = format!("{}/{}", info.owner, info.repo).replace('/', "%2F");
+    format!(
+        "https://gitlab.com/api/v4/projects/{}/merge_requests",
+        project
+    )
+}
+
+pub fn merge_request_payload(
+    title: &str,
+    body: &str,
+    source_branch: &str,
+    target_branch: &str,
+    draft: bool,
+    labels: &[String],
+) -> serde_json::Value {
+    // GitLab has no draft flag on create; the title prefix is what marks one.
+    let title = if draft {
+        format!("Draft: {}", title)
+    } else {
+        title.to_string()
+    };
+    serde_json::json!({
+        "source_branch": source_branch,
+        "target_branch": target_branch,
+        "title": title,
+        "description": body,
+        "labels": labels.join(","),
+    })
+}
+
+/// Opens a GitLab merge request, the GitLab counterpart of
+/// `create_pull_request`. Labels go in the same request since GitLab accepts
+/// them on create.
+pub fn create_merge_request(
+    repo_path: &Path,
+    title: &str,
+    body: &str,
+    source_branch: &str,
+    target_branch: &str,
+    draft: bool,
+    labels: &[String],
+) -> Result<String, GitOpsError> {
+    let remote_url = get_remote_url(repo_path)?;
+    let (_, repo_info) = parse_remote(&remote_url)?;
+    let token = get_gitlab_token()?;
+
+    let client = reqwest::blocking::Client::new();
     let response = client
-        .post(format!(
-            "https://api.github.com/repos/{}/{}/pulls",
-            repo_info.owner, repo_info.repo
-        ))
-        .header("Authorization", format!("Bearer {}", token))
-        .header("Accept", "application/vnd.github+json")
+        .post(merge_request_endpoint(&repo_info))
+        .header("PRIVATE-TOKEN", token)
         .header("User-Agent", "dreamal-app")
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .json(&serde_json::json!({
-            "title": title,
-            "body": body,
-            "head": head_branch,
-            "base": base_branch
-        }))
+        .json(&merge_request_payload(
+            title,
+            body,
+            source_branch,
+            target_branch,
+            draft,
+            labels,
+        ))
         .send()
         .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
 
     if !response.status().is_success() {
-        let status = response.status();
+        let status = response.status().as_u16();
         let error_body = response.text().unwrap_or_default();
         return Err(GitOpsError::GitError(format!(
-            "GitHub API error ({}): {}",
+            "GitLab API error ({}): {}",
             status, error_body
         )));
     }
-
     let json: serde_json::Value = response
         .json()
         .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
 
-    let pr_url = json["html_url"]
+    json["web_url"]
         .as_str()
-        .ok_or_else(|| GitOpsError::GitError("No PR URL in response".to_string()))?;
+        .map(|url| url.to_string())
+        .ok_or_else(|| GitOpsError::GitError("No merge request URL in response".to_string()))
+}
 
-    Ok(pr_url.to_string())
+/// Splits CODEOWNERS handles into user logins and team slugs for the
+/// requested-reviewers API. Email owners can't be requested and are dropped.
+pub fn split_reviewers(handles: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut users = Vec::new();
+    let mut teams = Vec::new();
+
+    for handle in handles {
+        let Some(name) = handle.strip_prefix('@') else {
+            continue;
+        };
+        match name.split_once('/') {
+            Some((_, team)) => teams.push(team.to_string()),
+            None => users.push(name.to_string()),
+        }
+    }
+
+    (users, teams)
+}
+
+/// GitHub doesn't accept reviewers when opening a PR, so they are requested
+/// on the created PR instead.
+pub fn request_reviewers(pr_url: &str, handles: &[String]) -> Result<(), GitOpsError> {
+    let (users, teams) = split_reviewers(handles);
+    if users.is_empty() && teams.is_empty() {
+        return Ok(());
+    }
+
+    let (repo_info, number) = parse_pr_url(pr_url)
+        .ok_or_else(|| GitOpsError::GitError(format!("Not a GitHub PR URL: {}", pr_url)))?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(
+        &client,
+        reqwest::Method::POST,
+        &github_api_url(&[
+            "repos",
+            &repo_info.owner,
+            &repo_info.repo,
+            "pulls",
+            &number.to_string(),
+            "requested_reviewers",
+        ]),
+        &token,
+    )
+    .json(&serde_json::json!({
+        "reviewers": users,
+        "team_reviewers": teams
+    }))
+    .send()
+    .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    parse_json_response(response, "pull-request write")?;
+    Ok(())
+}
+
+/// The endpoint and body of the request that adds `labels` to `pr_url`.
+pub fn labels_request(
+    pr_url: &str,
+    labels: &[String],
+) -> Result<(String, serde_json::Value), GitOpsError> {
+    Ok((
+        pr_api_url(pr_url, "issues", &["labels"])?,
+        serde_json::json!({ "labels": labels }),
+    ))
+}
+
+/// Adds `labels` to the PR; labels the repo doesn't have yet are created.
+pub fn add_labels(pr_url: &str, labels: &[String]) -> Result<(), GitOpsError> {
+    if labels.is_empty() {
+        return Ok(());
+    }
+
+    let (url, body) = labels_request(pr_url, labels)?;
+    let token = get_github_token()?;
+
+    let client = reqwest::blocking::Client::new();
+    let response = github_request(&client, reqwest::Method::POST, &url, &token)
+        .json(&body)
+        .send()
+        .map_err(|e| GitOpsError::NetworkError(e.to_string()))?;
+
+    parse_json_response(response, "issues write")?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -195,9 +1114,511 @@ mod tests {
         assert_eq!(info.repo, "myrepo");
     }
 
+    #[test]
+    fn test_suggest_pr_title_capitalizes_and_trims_punctuation() {
+        assert_eq!(
+            suggest_pr_title("add dark mode toggle."),
+            "Add dark mode toggle"
+        );
+    }
+
+    #[test]
+    fn test_suggest_pr_title_strips_polite_prefixes() {
+        assert_eq!(
+            suggest_pr_title("Please fix the login bug!"),
+            "Fix the login bug"
+        );
+        assert_eq!(
+            suggest_pr_title("can you update the README?"),
+            "Update the README"
+        );
+        assert_eq!(
+            suggest_pr_title("I'd like you to refactor the parser"),
+            "Refactor the parser"
+        );
+        assert_eq!(
+            suggest_pr_title("Could you please add tests for the cache"),
+            "Add tests for the cache"
+        );
+        assert_eq!(
+            suggest_pr_title("Bump the version, please."),
+            "Bump the version"
+        );
+    }
+
+    #[test]
+    fn test_suggest_pr_title_uses_first_line() {
+        assert_eq!(
+            suggest_pr_title("\n  migrate settings to json\nMore details follow here"),
+            "Migrate settings to json"
+        );
+    }
+
+    #[test]
+    fn test_suggest_pr_title_caps_length_at_word_boundary() {
+        let title = suggest_pr_title(
+            "rewrite the entire synchronization layer so that offline edits are merged correctly across devices",
+        );
+
+        assert!(title.chars().count() <= PR_TITLE_MAX_LEN);
+        assert_eq!(
+            title,
+            "Rewrite the entire synchronization layer so that offline edits are"
+        );
+    }
+
+    #[test]
+    fn test_suggest_pr_title_empty_instructions() {
+        assert_eq!(suggest_pr_title("   "), DEFAULT_PR_TITLE);
+        assert_eq!(suggest_pr_title("please."), DEFAULT_PR_TITLE);
+    }
+
+    #[test]
+    fn test_parse_user_repos() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"[
+                {
+                    "id": 1,
+                    "name": "dreamal",
+                    "full_name": "rvpaul1/dreamal",
+                    "private": false,
+                    "ssh_url": "git@github.com:rvpaul1/dreamal.git",
+                    "clone_url": "https://github.com/rvpaul1/dreamal.git",
+                    "default_branch": "main",
+                    "owner": {"login": "rvpaul1"}
+                },
+                {
+                    "name": "notes",
+                    "full_name": "rvpaul1/notes",
+                    "private": true,
+                    "ssh_url": "git@github.com:rvpaul1/notes.git",
+                    "clone_url": "https://github.com/rvpaul1/notes.git",
+                    "default_branch": "trunk"
+                },
+                {"name": "broken"}
+            ]"#,
+        )
+        .unwrap();
+
+        let repos = parse_user_repos(&json);
+
+        assert_eq!(repos.len(), 2);
+        assert_eq!(
+            repos[0],
+            UserRepo {
+                name: "dreamal".to_string(),
+                full_name: "rvpaul1/dreamal".to_string(),
+                ssh_url: "git@github.com:rvpaul1/dreamal.git".to_string(),
+                clone_url: "https://github.com/rvpaul1/dreamal.git".to_string(),
+                default_branch: "main".to_string(),
+                private: false,
+            }
+        );
+        assert!(repos[1].private);
+        assert_eq!(repos[1].default_branch, "trunk");
+        assert!(parse_user_repos(&serde_json::json!({"message": "Bad credentials"})).is_empty());
+    }
+
+    #[test]
+    fn test_link_has_next() {
+        let middle = r#"<https://api.github.com/user/repos?page=1>; rel="prev", <https://api.github.com/user/repos?page=3>; rel="next""#;
+        let last = r#"<https://api.github.com/user/repos?page=1>; rel="first", <https://api.github.com/user/repos?page=2>; rel="prev""#;
+
+        assert!(link_has_next(middle));
+        assert!(!link_has_next(last));
+        assert!(!link_has_next(""));
+    }
+
+    #[test]
+    fn test_parse_branch_protection() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{
+                "required_status_checks": {
+                    "strict": true,
+                    "contexts": ["ci/build"],
+                    "checks": [{"context": "ci/build", "app_id": 1}, {"context": "lint", "app_id": 2}]
+                },
+                "required_pull_request_reviews": {
+                    "dismiss_stale_reviews": false,
+                    "require_code_owner_reviews": true,
+                    "required_approving_review_count": 2
+                },
+                "enforce_admins": {"enabled": true},
+                "allow_force_pushes": {"enabled": false},
+                "allow_deletions": {"enabled": false}
+            }"#,
+        )
+        .unwrap();
+
+        let protection = parse_branch_protection(&json);
+
+        assert!(protection.protected);
+        assert!(protection.details_known);
+        assert!(protection.requires_pull_request);
+        assert_eq!(protection.required_approving_reviews, 2);
+        assert_eq!(protection.required_status_checks, vec!["ci/build", "lint"]);
+        assert!(protection.strict_status_checks);
+        assert!(protection.require_code_owner_reviews);
+        assert!(protection.enforce_admins);
+        assert!(!protection.allows_force_pushes);
+    }
+
+    #[test]
+    fn test_parse_branch_protection_checks_only() {
+        let json = serde_json::json!({
+            "required_status_checks": {
+                "strict": false,
+                "checks": [{"context": "test", "app_id": null}, {"context": "deploy-preview"}]
+            },
+            "enforce_admins": {"enabled": false}
+        });
+
+        let protection = parse_branch_protection(&json);
+
+        assert_eq!(
+            protection.required_status_checks,
+            vec!["test", "deploy-preview"]
+        );
+        assert!(!protection.strict_status_checks);
+        assert_eq!(protection.required_approving_reviews, 0);
+        assert!(!protection.require_code_owner_reviews);
+        assert!(!protection.enforce_admins);
+    }
+
+    #[test]
+    fn test_parse_branch_protection_without_reviews() {
+        let json = serde_json::json!({
+            "enforce_admins": {"enabled": false},
+            "allow_force_pushes": {"enabled": true}
+        });
+
+        let protection = parse_branch_protection(&json);
+
+        assert!(protection.protected);
+        assert!(!protection.requires_pull_request);
+        assert!(protection.required_status_checks.is_empty());
+        assert!(protection.allows_force_pushes);
+    }
+
+    #[test]
+    fn test_branch_requirements_reports_unknown_protection() {
+        let unknown = BranchProtection {
+            protected: true,
+            ..BranchProtection::default()
+        };
+        let error = branch_requirements("main", unknown).unwrap_err();
+        assert!(matches!(error, GitOpsError::AuthError(ref msg) if msg.contains("'main'")));
+
+        let unprotected = BranchProtection {
+            details_known: true,
+            ..BranchProtection::default()
+        };
+        assert_eq!(
+            branch_requirements("main", unprotected.clone()).unwrap(),
+            unprotected
+        );
+    }
+
+    #[test]
+    fn test_github_api_url_encodes_segments() {
+        assert_eq!(
+            github_api_url(&["repos", "owner", "repo", "branches", "claude/fix#2?x"]),
+            "https://api.github.com/repos/owner/repo/branches/claude%2Ffix%232%3Fx"
+        );
+    }
+
+    #[test]
+    fn test_force_push_check_rejects_protected_branch() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{
+                "url": "https://api.github.com/repos/owner/repo/branches/claude/fix/protection",
+                "enforce_admins": {"enabled": false},
+                "allow_force_pushes": {"enabled": false},
+                "allow_deletions": {"enabled": false}
+            }"#,
+        )
+        .unwrap();
+        let protection = parse_branch_protection(&json);
+
+        let check = force_push_check("claude/fix", &protection, false);
+
+        assert!(!check.allowed);
+        assert_eq!(
+            check.message.as_deref(),
+            Some("Branch 'claude/fix' is protected; force push will be rejected")
+        );
+    }
+
+    #[test]
+    fn test_force_push_check_rulesets() {
+        let rules = serde_json::json!([
+            {"type": "deletion", "ruleset_id": 1},
+            {"type": "non_fast_forward", "ruleset_id": 1}
+        ]);
+        assert!(parse_rules_forbid_force_push(&rules));
+        assert!(!parse_rules_forbid_force_push(&serde_json::json!([])));
+
+        let check = force_push_check("claude/fix", &BranchProtection::default(), true);
+        assert!(!check.allowed);
+
+        let check = force_push_check("claude/fix", &BranchProtection::default(), false);
+        assert!(check.allowed);
+        assert!(check.message.is_none());
+    }
+
+    #[test]
+    fn test_force_push_check_unknown_protection() {
+        let protection = BranchProtection {
+            protected: true,
+            ..BranchProtection::default()
+        };
+
+        let check = force_push_check("claude/fix", &protection, false);
+        assert!(check.allowed);
+        assert!(!check.protection_known);
+        assert!(check.message.unwrap().contains("protection is unknown"));
+
+        let check = force_push_check("claude/fix", &protection, true);
+        assert!(!check.allowed);
+        assert!(check.protection_known);
+    }
+
+    #[test]
+    fn test_parse_push_permission() {
+        let json = serde_json::json!({"permissions": {"admin": false, "push": true, "pull": true}});
+        assert!(parse_push_permission(&json));
+
+        let json = serde_json::json!({"permissions": {"push": false, "pull": true}});
+        assert!(!parse_push_permission(&json));
+    }
+
+    #[test]
+    fn test_parse_pr_url() {
+        let (info, number) = parse_pr_url("https://github.com/owner/repo/pull/42").unwrap();
+        assert_eq!(info.owner, "owner");
+        assert_eq!(info.repo, "repo");
+        assert_eq!(number, 42);
+
+        assert!(parse_pr_url("https://github.com/owner/repo/issues/42").is_none());
+        assert!(parse_pr_url("https://gitlab.com/owner/repo/pull/42").is_none());
+    }
+
+    #[test]
+    fn test_parse_pr_states_from_list() {
+        // The list endpoint's items have `merged_at` but no `merged` field.
+        let json = serde_json::json!([
+            {"number": 3, "state": "closed", "merged_at": "2024-05-01T12:00:00Z"},
+            {"number": 2, "state": "closed", "merged_at": null},
+            {"number": 1, "state": "open", "merged_at": null},
+        ]);
+        assert_eq!(parse_pr_states(&json), vec!["merged", "closed", "open"]);
+        assert!(parse_pr_states(&serde_json::json!({"message": "Not Found"})).is_empty());
+    }
+
+    #[test]
+    fn test_parse_pr_state() {
+        let json = serde_json::json!({"state": "closed", "merged": true});
+        assert_eq!(parse_pr_state(&json).as_deref(), Some("merged"));
+
+        let json = serde_json::json!({"state": "open", "merged": false});
+        assert_eq!(parse_pr_state(&json).as_deref(), Some("open"));
+    }
+
+    #[test]
+    fn test_reopen_request_and_response() {
+        let (url, body) = reopen_request("https://github.com/owner/repo/pull/42/").unwrap();
+        assert_eq!(url, "https://api.github.com/repos/owner/repo/pulls/42");
+        assert_eq!(body, serde_json::json!({"state": "open"}));
+        assert!(reopen_request("https://github.com/owner/repo/issues/42").is_err());
+
+        let response = serde_json::json!({"number": 42, "state": "open", "merged": false});
+        assert_eq!(parse_pr_state(&response).as_deref(), Some("open"));
+
+        let deleted = r#"{"message":"Validation Failed","errors":[{"resource":"PullRequest","code":"custom","message":"state cannot be changed. The claude/add-dark-mode-1 branch has been deleted."}]}"#;
+        match reopen_error(422, deleted) {
+            GitOpsError::GitError(msg) => assert!(msg.contains("Push the branch again")),
+            other => panic!("unexpected error: {}", other),
+        }
+        match reopen_error(404, r#"{"message":"Not Found"}"#) {
+            GitOpsError::GitError(msg) => assert!(msg.contains("GitHub API error (404)")),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_update_request_only_sends_given_fields() {
+        let (url, fields) = update_request(
+            "https://github.com/owner/repo/pull/7",
+            None,
+            Some("New body"),
+        )
+        .unwrap();
+        assert_eq!(url, "https://api.github.com/repos/owner/repo/pulls/7");
+        assert_eq!(fields, serde_json::json!({"body": "New body"}));
+
+        let (_, fields) =
+            update_request("https://github.com/owner/repo/pull/7", Some("Title"), None).unwrap();
+        assert_eq!(fields, serde_json::json!({"title": "Title"}));
+
+        assert!(update_request("https://github.com/owner/repo/pull/7", None, None).is_err());
+    }
+
+    #[test]
+    fn test_labels_request() {
+        let labels = vec!["automated".to_string(), "frontend".to_string()];
+        let (url, body) = labels_request("https://github.com/owner/repo/pull/9", &labels).unwrap();
+
+        assert_eq!(
+            url,
+            "https://api.github.com/repos/owner/repo/issues/9/labels"
+        );
+        assert_eq!(
+            body,
+            serde_json::json!({"labels": ["automated", "frontend"]})
+        );
+    }
+
+    #[test]
+    fn test_github_api_error_insufficient_permissions() {
+        let body = r#"{"message":"Resource not accessible by personal access token","documentation_url":"https://docs.github.com/rest/pulls/pulls#create-a-pull-request","status":"403"}"#;
+
+        match github_api_error(403, body, "pull-request write") {
+            GitOpsError::AuthError(msg) => {
+                assert!(msg.contains("lacks pull-request write permission for this repo"));
+            }
+            other => panic!("expected AuthError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_github_api_error_generic_403() {
+        let body = r#"{"message":"API rate limit exceeded for user ID 1."}"#;
+
+        match github_api_error(403, body, "pull-request write") {
+            GitOpsError::GitError(msg) => assert!(msg.contains("rate limit")),
+            other => panic!("expected GitError, got {:?}", other),
+        }
+        assert!(matches!(
+            github_api_error(422, "Resource not accessible by integration", "x"),
+            GitOpsError::GitError(_)
+        ));
+    }
+
+    #[test]
+    fn test_split_reviewers() {
+        let handles = vec![
+            "@octocat".to_string(),
+            "@acme/core".to_string(),
+            "docs@example.com".to_string(),
+        ];
+
+        let (users, teams) = split_reviewers(&handles);
+        assert_eq!(users, vec!["octocat"]);
+        assert_eq!(teams, vec!["core"]);
+    }
+
+    #[test]
+    fn test_parse_remote_gitlab() {
+        let (host, info) = parse_remote("git@gitlab.com:group/subgroup/repo.git").unwrap();
+        assert_eq!(host, RemoteHost::GitLab);
+        assert_eq!(info.owner, "group/subgroup");
+        assert_eq!(info.repo, "repo");
+
+        let (host, info) = parse_remote("https://gitlab.com/group/repo").unwrap();
+        assert_eq!(host, RemoteHost::GitLab);
+        assert_eq!(info.owner, "group");
+        assert_eq!(info.repo, "repo");
+
+        let (host, _) = parse_remote("https://github.com/owner/repo.git").unwrap();
+        assert_eq!(host, RemoteHost::GitHub);
+        assert!(parse_github_remote("git@gitlab.com:group/repo.git").is_err());
+        assert!(parse_remote("https://gitlab.com/repo").is_err());
+    }
+
+    #[test]
+    fn test_merge_request_endpoint_and_payload() {
+        let info = RepoInfo {
+            owner: "group/subgroup".to_string(),
+            repo: "repo".to_string(),
+        };
+        assert_eq!(
+            merge_request_endpoint(&info),
+            "https://gitlab.com/api/v4/projects/group%2Fsubgroup%2Frepo/merge_requests"
+        );
+
+        let labels = vec!["dreamal".to_string(), "automated".to_string()];
+        let payload = merge_request_payload("Fix it", "Body", "claude/fix", "main", true, &labels);
+        assert_eq!(payload["title"], "Draft: Fix it");
+        assert_eq!(payload["description"], "Body");
+        assert_eq!(payload["source_branch"], "claude/fix");
+        assert_eq!(payload["target_branch"], "main");
+        assert_eq!(payload["labels"], "dreamal,automated");
+
+        let payload = merge_request_payload("Fix it", "Body", "claude/fix", "main", false, &[]);
+        assert_eq!(payload["title"], "Fix it");
+    }
+
     #[test]
     fn test_parse_github_remote_invalid() {
         let result = parse_github_remote("https://gitlab.com/owner/repo");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_read_pr_template_locations() {
+        let locations = [
+            ".github/pull_request_template.md",
+            ".github/PULL_REQUEST_TEMPLATE.md",
+            "pull_request_template.md",
+            "docs/pull_request_template.md",
+            "docs/PULL_REQUEST_TEMPLATE.md",
+        ];
+
+        for location in locations {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let path = temp_dir.path().join(location);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("template at {}", location)).unwrap();
+
+            let template = read_pr_template(temp_dir.path()).unwrap();
+            assert_eq!(template, format!("template at {}", location));
+        }
+    }
+
+    #[test]
+    fn test_read_pr_template_prefers_github_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".github")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+        fs::write(
+            temp_dir.path().join("docs/pull_request_template.md"),
+            "docs",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("pull_request_template.md"), "root").unwrap();
+        fs::write(
+            temp_dir.path().join(".github/pull_request_template.md"),
+            "github",
+        )
+        .unwrap();
+
+        assert_eq!(read_pr_template(temp_dir.path()).unwrap(), "github");
+    }
+
+    #[test]
+    fn test_read_pr_template_missing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_pr_template(temp_dir.path()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_compose_pr_body() {
+        let body = compose_pr_body("", "fix the bug");
+        assert!(body.starts_with("## Summary"));
+        assert!(body.contains("fix the bug"));
+
+        let body = compose_pr_body("## Checklist\n- [ ] Tests\n", "fix the bug");
+        assert!(body.starts_with("## Checklist\n- [ ] Tests\n\n---"));
+        assert!(body.contains("fix the bug"));
+    }
 }
@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::process::Command;
+
+use super::cli;
+use super::GitOpsError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResetTarget {
+    Base,
+    Commit(String),
+}
+
+impl ResetTarget {
+    pub fn parse(target: &str) -> Self {
+        match target.trim() {
+            "base" => ResetTarget::Base,
+            rev => ResetTarget::Commit(rev.to_string()),
+        }
+    }
+}
+
+pub fn reset_push_args(branch: &str, target: git2::Oid, expected: git2::Oid) -> Vec<String> {
+    vec![
+        "push".to_string(),
+        format!("--force-with-lease=refs/heads/{}:{}", branch, expected),
+        "origin".to_string(),
+        format!("{}:refs/heads/{}", target, branch),
+    ]
+}
+
+fn run_git(repo_path: &Path, args: &[String]) -> Result<(), GitOpsError> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(args)
+        .output()
+        .map_err(|e| GitOpsError::GitError(format!("Failed to run git {}: {}", args[0], e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitOpsError::GitError(format!(
+            "git {} failed: {}",
+            args[0], stderr
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn fetch_remote_branch(repo_path: &Path, branch: &str) -> Result<(), GitOpsError> {
+    run_git(
+        repo_path,
+        &[
+            "fetch".to_string(),
+            "origin".to_string(),
+            format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch),
+        ],
+    )
+}
+
+pub fn reset_remote_branch(
+    repo_path: &Path,
+    branch: &str,
+    base_branch: &str,
+    target: &ResetTarget,
+) -> Result<git2::Oid, GitOpsError> {
+    fetch_remote_branch(repo_path, base_branch)?;
+    fetch_remote_branch(repo_path, branch)?;
+
+    // The push itself goes through git, so the lookups do too and work for
+    // checkouts libgit2 can't open.
+    let resolve = |rev: &str| {
+        cli::resolve_commit(repo_path, rev)
+            .ok_or_else(|| GitOpsError::GitError(format!("Not a commit: {}", rev)))
+    };
+    let target_oid = match target {
+        ResetTarget::Base => resolve(&format!("refs/remotes/origin/{}", base_branch))?,
+        ResetTarget::Commit(rev) => resolve(rev)?,
+    };
+    let expected_oid = resolve(&format!("refs/remotes/origin/{}", branch))?;
+
+    run_git(
+        repo_path,
+        &reset_push_args(branch, target_oid, expected_oid),
+    )?;
+
+    Ok(target_oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn commit_file(repo: &git2::Repository, name: &str, content: &str, message: &str) -> git2::Oid {
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        fs::write(workdir.join(name), content).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parent_refs)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reset_target_parse() {
+        assert_eq!(ResetTarget::parse("base"), ResetTarget::Base);
+        assert_eq!(
+            ResetTarget::parse(" abc123 "),
+            ResetTarget::Commit("abc123".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reset_push_args() {
+        let target = git2::Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let expected = git2::Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+
+        let args = reset_push_args("claude/fix-bug-123", target, expected);
+
+        assert_eq!(
+            args,
+            vec![
+                "push",
+                "--force-with-lease=refs/heads/claude/fix-bug-123:2222222222222222222222222222222222222222",
+                "origin",
+                "1111111111111111111111111111111111111111:refs/heads/claude/fix-bug-123",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reset_remote_branch_to_base() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(remote_dir.path()).unwrap();
+
+        let local_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(local_dir.path()).unwrap();
+        repo.remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+
+        let base_oid = commit_file(&repo, "a.txt", "base", "Base commit");
+        let base_commit = repo.find_commit(base_oid).unwrap();
+        repo.branch("main", &base_commit, true).unwrap();
+        repo.branch("claude/feature", &base_commit, false).unwrap();
+        repo.set_head("refs/heads/claude/feature").unwrap();
+        commit_file(&repo, "b.txt", "feature", "Feature commit");
+
+        let push = |refspec: &str| {
+            let status = Command::new("git")
+                .current_dir(local_dir.path())
+                .args(["push", "origin", refspec])
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success());
+        };
+        push("refs/heads/main:refs/heads/main");
+        push("refs/heads/claude/feature:refs/heads/claude/feature");
+
+        let new_oid = reset_remote_branch(
+            local_dir.path(),
+            "claude/feature",
+            "main",
+            &ResetTarget::Base,
+        )
+        .unwrap();
+
+        let remote = git2::Repository::open_bare(remote_dir.path()).unwrap();
+        assert_eq!(new_oid, base_oid);
+        assert_eq!(
+            remote.refname_to_id("refs/heads/claude/feature").unwrap(),
+            base_oid
+        );
+    }
+}
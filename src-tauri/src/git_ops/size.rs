@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct DirStats {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+fn is_ignored(repo: &git2::Repository, root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .ok()
+        .and_then(|relative| repo.is_path_ignored(relative).ok())
+        .unwrap_or(false)
+}
+
+fn accumulate(
+    root: &Path,
+    dir: &Path,
+    repo: Option<&git2::Repository>,
+    stats: &mut DirStats,
+) -> Result<(), std::io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // libgit2 treats everything under .git as ignored, but it still takes space.
+        let repo = repo.filter(|_| entry.file_name() != ".git");
+
+        if repo.is_some_and(|repo| is_ignored(repo, root, &path)) {
+            continue;
+        }
+
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            accumulate(root, &path, repo, stats)?;
+        } else {
+            stats.bytes += metadata.len();
+            stats.files += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sums file sizes under `path`. When `respect_gitignore` is set and `path` is a
+/// git work tree, paths matched by its ignore rules are skipped.
+pub fn dir_stats(path: &Path, respect_gitignore: bool) -> Result<DirStats, std::io::Error> {
+    let repo = if respect_gitignore {
+        git2::Repository::open(path).ok()
+    } else {
+        None
+    };
+
+    let mut stats = DirStats::default();
+    accumulate(path, path, repo.as_ref(), &mut stats)?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_stats_counts_nested_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), vec![b'a'; 100]).unwrap();
+        fs::create_dir_all(temp_dir.path().join("sub").join("deeper")).unwrap();
+        fs::write(temp_dir.path().join("sub").join("b.txt"), vec![b'b'; 250]).unwrap();
+        fs::write(
+            temp_dir.path().join("sub").join("deeper").join("c.txt"),
+            vec![b'c'; 50],
+        )
+        .unwrap();
+
+        let stats = dir_stats(temp_dir.path(), false).unwrap();
+
+        assert_eq!(
+            stats,
+            DirStats {
+                bytes: 400,
+                files: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_dir_stats_respects_gitignore() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "node_modules/\n").unwrap();
+        fs::write(temp_dir.path().join("index.js"), vec![b'x'; 100]).unwrap();
+        fs::create_dir_all(temp_dir.path().join("node_modules").join("dep")).unwrap();
+        fs::write(
+            temp_dir
+                .path()
+                .join("node_modules")
+                .join("dep")
+                .join("index.js"),
+            vec![b'y'; 5000],
+        )
+        .unwrap();
+
+        let all = dir_stats(temp_dir.path(), false).unwrap();
+        let filtered = dir_stats(temp_dir.path(), true).unwrap();
+
+        assert_eq!(all.bytes - filtered.bytes, 5000);
+        assert_eq!(all.files - filtered.files, 1);
+    }
+}
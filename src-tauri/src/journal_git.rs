@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Settings key holding the journal directory versioning was turned on for.
+pub const VERSIONING_KEY: &str = "journal_versioning";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JournalCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// Seconds since the epoch.
+    pub time: i64,
+}
+
+/// Pending changes in the journal's git repo, counted in files. `versioned` is
+/// false, and everything else empty, when the journal isn't a git repo.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JournalGitStatus {
+    pub versioned: bool,
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub last_commit: Option<JournalCommit>,
+}
+
+/// The repo rooted at the journal directory. A repo further up the tree
+/// doesn't count: the journal would then be part of something else.
+fn open_journal_repo(journal_dir: &Path) -> Option<git2::Repository> {
+    git2::Repository::open(journal_dir).ok()
+}
+
+fn last_commit(repo: &git2::Repository) -> Option<JournalCommit> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    let author = commit.author().name().unwrap_or_default().to_string();
+    Some(JournalCommit {
+        id: commit.id().to_string()[..7].to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        author,
+        time: commit.time().seconds(),
+    })
+}
+
+pub fn journal_git_status_in(journal_dir: &Path) -> Result<JournalGitStatus, String> {
+    let Some(repo) = open_journal_repo(journal_dir) else {
+        return Ok(JournalGitStatus::default());
+    };
+
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to read journal status: {}", e.message()))?;
+
+    let staged_flags = git2::Status::INDEX_NEW
+        | git2::Status::INDEX_MODIFIED
+        | git2::Status::INDEX_DELETED
+        | git2::Status::INDEX_RENAMED
+        | git2::Status::INDEX_TYPECHANGE;
+    let unstaged_flags = git2::Status::WT_MODIFIED
+        | git2::Status::WT_DELETED
+        | git2::Status::WT_RENAMED
+        | git2::Status::WT_TYPECHANGE;
+
+    let mut status = JournalGitStatus {
+        versioned: true,
+        last_commit: last_commit(&repo),
+        ..Default::default()
+    };
+    for entry in statuses.iter() {
+        let flags = entry.status();
+        if flags.intersects(staged_flags) {
+            status.staged += 1;
+        }
+        if flags.intersects(unstaged_flags) {
+            status.unstaged += 1;
+        }
+        if flags.contains(git2::Status::WT_NEW) {
+            status.untracked += 1;
+        }
+    }
+
+    Ok(status)
+}
+
+/// Stages every change in the journal, deletions included, and commits it.
+/// The author is the repo's configured identity, falling back to Dreamal.
+pub fn commit_journal_in(journal_dir: &Path, message: &str) -> Result<JournalCommit, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("Commit message is empty".to_string());
+    }
+    let repo =
+        open_journal_repo(journal_dir).ok_or_else(|| "The journal is not versioned".to_string())?;
+    commit_all(&repo, message)?.ok_or_else(|| "Nothing to commit".to_string())
+}
+
+/// Commits every change in `repo`. `None` when the tree matches HEAD, or is
+/// empty in a repo without commits.
+fn commit_all(repo: &git2::Repository, message: &str) -> Result<Option<JournalCommit>, String> {
+    let git_error = |e: git2::Error| format!("Failed to commit journal: {}", e.message());
+
+    let mut index = repo.index().map_err(git_error)?;
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .map_err(git_error)?;
+    index.update_all(["*"].iter(), None).map_err(git_error)?;
+    index.write().map_err(git_error)?;
+
+    let tree = repo
+        .find_tree(index.write_tree().map_err(git_error)?)
+        .map_err(git_error)?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let unchanged = match &parent {
+        Some(parent) => parent.tree_id() == tree.id(),
+        None => tree.is_empty(),
+    };
+    if unchanged {
+        return Ok(None);
+    }
+
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("Dreamal", "journal@dreamal.app"))
+        .map_err(git_error)?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .map_err(git_error)?;
+
+    last_commit(repo)
+        .map(Some)
+        .ok_or_else(|| "Failed to read the new journal commit".to_string())
+}
+
+fn journal_key(journal_dir: &Path) -> String {
+    journal_dir
+        .canonicalize()
+        .unwrap_or_else(|_| journal_dir.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Whether versioning was turned on for this journal. Switching to another
+/// journal directory needs its own opt-in, since that one may be a repo
+/// Dreamal hasn't been allowed to commit to.
+pub fn versioning_enabled(
+    settings: &HashMap<String, serde_json::Value>,
+    journal_dir: &Path,
+) -> bool {
+    settings.get(VERSIONING_KEY).and_then(|v| v.as_str()) == Some(journal_key(journal_dir).as_str())
+}
+
+pub fn record_versioning(settings: &mut HashMap<String, serde_json::Value>, journal_dir: &Path) {
+    settings.insert(
+        VERSIONING_KEY.to_string(),
+        serde_json::json!(journal_key(journal_dir)),
+    );
+}
+
+/// Commits the journal, refusing until versioning has been turned on for it.
+pub fn commit_versioned_journal_in(
+    settings: &HashMap<String, serde_json::Value>,
+    journal_dir: &Path,
+    message: &str,
+) -> Result<JournalCommit, String> {
+    if !versioning_enabled(settings, journal_dir) {
+        return Err("Journal versioning is off; enable it before committing".to_string());
+    }
+    commit_journal_in(journal_dir, message)
+}
+
+/// The outcome of turning on versioning. `enabled` is false while the
+/// journal is an existing repo with `remotes` that Dreamal hasn't been allowed
+/// to commit to; `warning` then says why.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VersioningSetup {
+    pub enabled: bool,
+    /// Whether the journal had to be made a git repo.
+    pub initialized: bool,
+    pub remotes: Vec<String>,
+    pub warning: Option<String>,
+}
+
+/// Prepares the journal for versioning. A journal that isn't a repo yet is
+/// initialized with its current entries as the first commit. An existing repo
+/// with remotes is someone else's history too, so it is left alone unless
+/// `allow_tracked_repo` confirms Dreamal may commit to it.
+pub fn setup_versioning_in(
+    journal_dir: &Path,
+    allow_tracked_repo: bool,
+) -> Result<VersioningSetup, String> {
+    let Some(repo) = open_journal_repo(journal_dir) else {
+        let repo = git2::Repository::init(journal_dir)
+            .map_err(|e| format!("Failed to initialize journal repo: {}", e.message()))?;
+        commit_all(&repo, "Start versioning the journal")?;
+        return Ok(VersioningSetup {
+            enabled: true,
+            initialized: true,
+            ..Default::default()
+        });
+    };
+
+    let remotes: Vec<String> = repo
+        .remotes()
+        .map_err(|e| format!("Failed to read journal remotes: {}", e.message()))?
+        .iter()
+        .flatten()
+        .map(str::to_string)
+        .collect();
+    if !remotes.is_empty() && !allow_tracked_repo {
+        let warning = format!(
+            "The journal is already a git repo with remote {}. Dreamal's commits would end up in its history; confirm to version it anyway.",
+            remotes.join(", ")
+        );
+        return Ok(VersioningSetup {
+            remotes,
+            warning: Some(warning),
+            ..Default::default()
+        });
+    }
+
+    Ok(VersioningSetup {
+        enabled: true,
+        remotes,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup_versioned_journal() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Journal Keeper").unwrap();
+        config.set_str("user.email", "keeper@example.com").unwrap();
+
+        let month_dir = temp_dir.path().join("2024/05");
+        fs::create_dir_all(&month_dir).unwrap();
+        fs::write(month_dir.join("entry-1.md"), "# One").unwrap();
+        fs::write(month_dir.join("entry-2.md"), "# Two").unwrap();
+        commit_journal_in(temp_dir.path(), "Initial entries").unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_journal_git_status_counts_pending_changes() {
+        let temp_dir = setup_versioned_journal();
+        let journal_dir = temp_dir.path();
+        let month_dir = journal_dir.join("2024/05");
+
+        fs::write(month_dir.join("entry-1.md"), "# One, edited").unwrap();
+        fs::write(month_dir.join("entry-3.md"), "# Three").unwrap();
+        fs::write(month_dir.join("entry-4.md"), "# Four").unwrap();
+        fs::remove_file(month_dir.join("entry-2.md")).unwrap();
+        let repo = git2::Repository::open(journal_dir).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("2024/05/entry-3.md")).unwrap();
+        index.write().unwrap();
+
+        let status = journal_git_status_in(journal_dir).unwrap();
+
+        assert!(status.versioned);
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.unstaged, 2);
+        assert_eq!(status.untracked, 1);
+        let last = status.last_commit.unwrap();
+        assert_eq!(last.summary, "Initial entries");
+        assert_eq!(last.author, "Journal Keeper");
+
+        let commit = commit_journal_in(journal_dir, "Edit entries").unwrap();
+        assert_eq!(commit.summary, "Edit entries");
+        let status = journal_git_status_in(journal_dir).unwrap();
+        assert_eq!(
+            (status.staged, status.unstaged, status.untracked),
+            (0, 0, 0)
+        );
+        assert_eq!(status.last_commit, Some(commit));
+        assert_eq!(
+            commit_journal_in(journal_dir, "Again").unwrap_err(),
+            "Nothing to commit"
+        );
+    }
+
+    #[test]
+    fn test_unversioned_journal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            journal_git_status_in(temp_dir.path()).unwrap(),
+            JournalGitStatus::default()
+        );
+        assert!(commit_journal_in(temp_dir.path(), "Save")
+            .unwrap_err()
+            .contains("not versioned"));
+    }
+
+    #[test]
+    fn test_setup_versioning_warns_for_remote_tracked_repo() {
+        let temp_dir = setup_versioned_journal();
+        let journal_dir = temp_dir.path();
+        let repo = git2::Repository::open(journal_dir).unwrap();
+        repo.remote("origin", "https://github.com/keeper/notes.git")
+            .unwrap();
+        let head = repo.head().unwrap().target();
+        fs::write(journal_dir.join("2024/05/entry-3.md"), "# Three").unwrap();
+
+        let setup = setup_versioning_in(journal_dir, false).unwrap();
+        assert!(!setup.enabled);
+        assert_eq!(setup.remotes, vec!["origin"]);
+        assert!(setup.warning.unwrap().contains("origin"));
+        assert_eq!(repo.head().unwrap().target(), head);
+
+        let setup = setup_versioning_in(journal_dir, true).unwrap();
+        assert!(setup.enabled);
+        assert!(!setup.initialized);
+        assert!(setup.warning.is_none());
+        assert_eq!(repo.head().unwrap().target(), head);
+    }
+
+    #[test]
+    fn test_commit_requires_versioning_opt_in() {
+        let temp_dir = setup_versioned_journal();
+        let journal_dir = temp_dir.path();
+        let repo = git2::Repository::open(journal_dir).unwrap();
+        let head = repo.head().unwrap().target();
+        fs::write(journal_dir.join("2024/05/entry-3.md"), "# Three").unwrap();
+
+        let mut settings = HashMap::new();
+        assert!(commit_versioned_journal_in(&settings, journal_dir, "Save")
+            .unwrap_err()
+            .contains("versioning is off"));
+        let other_dir = tempfile::tempdir().unwrap();
+        record_versioning(&mut settings, other_dir.path());
+        assert!(commit_versioned_journal_in(&settings, journal_dir, "Save").is_err());
+        assert_eq!(repo.head().unwrap().target(), head);
+
+        record_versioning(&mut settings, journal_dir);
+        let commit = commit_versioned_journal_in(&settings, journal_dir, "Save").unwrap();
+        assert_eq!(commit.summary, "Save");
+    }
+
+    #[test]
+    fn test_setup_versioning_initializes_plain_journal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("entry.md"), "# Entry").unwrap();
+
+        let setup = setup_versioning_in(temp_dir.path(), false).unwrap();
+
+        assert!(setup.enabled);
+        assert!(setup.initialized);
+        let status = journal_git_status_in(temp_dir.path()).unwrap();
+        assert!(status.versioned);
+        assert_eq!(status.untracked, 0);
+        assert!(status.last_commit.is_some());
+
+        let empty_dir = tempfile::tempdir().unwrap();
+        let setup = setup_versioning_in(empty_dir.path(), false).unwrap();
+        assert!(setup.enabled && setup.initialized);
+        assert!(journal_git_status_in(empty_dir.path())
+            .unwrap()
+            .last_commit
+            .is_none());
+    }
+}
@@ -1,30 +1,263 @@
 mod claude_session;
+mod duplicates;
+mod export;
+mod frontmatter;
 mod git_ops;
+mod journal_git;
+mod links;
+mod paths;
+mod search;
+mod settings;
+mod snapshot;
+mod stats;
+#[cfg(test)]
+mod test_support;
+mod trash;
 
-use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use serde::Serialize;
+use tauri::{Emitter, State};
+
 use claude_session::commands::{
-    cancel_session, get_session_status, list_claude_sessions, spawn_claude_session, AppState,
+    archive_session, cancel_session, check_branch_permissions, check_force_push,
+    check_instructions_length, check_merge_conflicts, cleanup_orphaned_artifacts, dedupe_sessions,
+    detect_project_type, diff_sessions, estimate_clone, export_all_session_patches,
+    export_session_bundle, export_session_patch, get_base_branch_requirements, get_capacity,
+    get_effective_session_config, get_queue_status, get_repo_allowed_commands, get_repo_pr_template,
+    get_session_status, get_session_timeline, list_archived_sessions, list_claude_sessions,
+    list_live_checkouts, list_persisted_sessions, list_recent_prs, list_sessions_for_repo,
+    list_user_repos, pause_scheduler, peek_session_output, preview_branch_name, preview_pr_payload,
+    preview_pr_title, reap_stale_sessions, reclone_session, reconcile_sessions, reopen_pr,
+    reset_session_branch, restore_persisted_sessions, resume_persisted_queue,
+    resume_queued_sessions, resume_scheduler, retry_failed_in_batch, run_command_in_session,
+    search_sessions, session_duration, session_summary_markdown, session_timing, set_clone_depth,
+    set_guidelines_file, set_max_changed_files, set_max_memory_mb, set_repo_allowed_commands,
+    set_repo_default_base, set_session_locked, spawn_batch_sessions, spawn_claude_session,
+    spawn_stale_session_reaper, suggest_reviewers, sweep_merged_branches, test_push_credentials,
+    unarchive_session, update_pr, validate_instructions, AppState,
 };
+use claude_session::process::kill_process;
 use claude_session::SessionManager;
+use settings::{read_settings, repair_settings_file, write_settings, SettingsRepair};
+
+const NOTEBOOKS_KEY: &str = "notebooks";
+const CURRENT_JOURNAL_KEY: &str = "current_journal";
+const DEFAULT_NOTEBOOK_NAME: &str = "default";
+const JOURNAL_DIR_KEY: &str = "journal_dir";
+/// What `journal_dir` was stored as before it was validated; still read when
+/// `journal_dir` is unset.
+const LEGACY_JOURNAL_DIR_KEY: &str = "journalDir";
+
+type Settings = HashMap<String, serde_json::Value>;
+
+/// Checks that `path` can hold the journal: it must be absolute, and either a
+/// directory already or creatable under its nearest existing ancestor.
+fn validate_journal_dir(path: &Path) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err(format!("Journal directory must be an absolute path: {}", path.display()));
+    }
+
+    let existing = path.ancestors().find(|dir| dir.exists()).unwrap_or(path);
+    let metadata = fs::metadata(existing)
+        .map_err(|e| format!("Cannot use {} as the journal: {}", path.display(), e))?;
+    if !metadata.is_dir() {
+        return Err(format!("Not a directory: {}", existing.display()));
+    }
+    if metadata.permissions().readonly() {
+        return Err(format!("Permission denied: cannot create {}", path.display()));
+    }
+    Ok(())
+}
+
+/// The `journal_dir` setting if it names a usable directory, then the legacy
+/// `journalDir` one, otherwise `~/Documents/Journal` (or `~/Journal`).
+fn get_default_journal_dir(settings: &Settings) -> Result<PathBuf, String> {
+    if let Some(dir) = settings.get(JOURNAL_DIR_KEY).and_then(|v| v.as_str()) {
+        let path = PathBuf::from(dir);
+        match validate_journal_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) => eprintln!("Warning: Ignoring {} setting: {}", JOURNAL_DIR_KEY, e),
+        }
+    }
+    if let Some(dir) = settings.get(LEGACY_JOURNAL_DIR_KEY).and_then(|v| v.as_str()) {
+        return Ok(PathBuf::from(dir));
+    }
 
-fn get_default_journal_dir() -> Result<PathBuf, String> {
     let home = dirs::document_dir()
         .or_else(|| dirs::home_dir())
         .ok_or("Could not determine home directory")?;
     Ok(home.join("Journal"))
 }
 
+fn configured_notebooks(settings: &Settings) -> BTreeMap<String, PathBuf> {
+    settings
+        .get(NOTEBOOKS_KEY)
+        .and_then(|v| v.as_object())
+        .map(|notebooks| {
+            notebooks
+                .iter()
+                .filter_map(|(name, path)| path.as_str().map(|p| (name.clone(), PathBuf::from(p))))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn current_notebook_name(settings: &Settings) -> Option<String> {
+    let notebooks = configured_notebooks(settings);
+
+    if let Some(current) = settings.get(CURRENT_JOURNAL_KEY).and_then(|v| v.as_str()) {
+        if notebooks.contains_key(current) {
+            return Some(current.to_string());
+        }
+    }
+
+    if notebooks.len() == 1 {
+        return notebooks.keys().next().cloned();
+    }
+
+    None
+}
+
+fn journal_dir_from_settings(settings: &Settings) -> Result<PathBuf, String> {
+    if let Some(name) = current_notebook_name(settings) {
+        if let Some(path) = configured_notebooks(settings).remove(&name) {
+            return Ok(path);
+        }
+    }
+
+    get_default_journal_dir(settings)
+}
+
 fn get_effective_journal_dir() -> Result<PathBuf, String> {
-    let settings = read_settings()?;
-    if let Some(serde_json::Value::String(dir)) = settings.get("journalDir") {
-        return Ok(PathBuf::from(dir));
+    journal_dir_from_settings(&read_settings()?)
+}
+
+#[derive(Debug, Serialize)]
+struct Notebook {
+    name: String,
+    path: String,
+    current: bool,
+}
+
+fn notebooks_from_settings(settings: &Settings) -> Result<Vec<Notebook>, String> {
+    let notebooks = configured_notebooks(settings);
+
+    if notebooks.is_empty() {
+        return Ok(vec![Notebook {
+            name: DEFAULT_NOTEBOOK_NAME.to_string(),
+            path: journal_dir_from_settings(settings)?.display().to_string(),
+            current: true,
+        }]);
+    }
+
+    let current = current_notebook_name(settings);
+    Ok(notebooks
+        .into_iter()
+        .map(|(name, path)| Notebook {
+            current: current.as_deref() == Some(name.as_str()),
+            path: path.display().to_string(),
+            name,
+        })
+        .collect())
+}
+
+fn add_notebook_to_settings(settings: &mut Settings, name: &str, path: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Notebook name cannot be empty".to_string());
+    }
+    if !Path::new(path).is_absolute() {
+        return Err(format!("Notebook path must be absolute: {}", path));
+    }
+
+    let mut notebooks = configured_notebooks(settings);
+    if notebooks.contains_key(name) {
+        return Err(format!("Notebook already exists: {}", name));
+    }
+
+    // Keep the journal that was in use before notebooks were configured.
+    if notebooks.is_empty() {
+        notebooks.insert(
+            DEFAULT_NOTEBOOK_NAME.to_string(),
+            journal_dir_from_settings(settings)?,
+        );
+        settings.insert(
+            CURRENT_JOURNAL_KEY.to_string(),
+            serde_json::Value::String(DEFAULT_NOTEBOOK_NAME.to_string()),
+        );
+    }
+    notebooks.insert(name.to_string(), PathBuf::from(path));
+
+    store_notebooks(settings, notebooks);
+    Ok(())
+}
+
+fn remove_notebook_from_settings(settings: &mut Settings, name: &str) -> Result<(), String> {
+    let mut notebooks = configured_notebooks(settings);
+    if !notebooks.contains_key(name) {
+        return Err(format!("Notebook not found: {}", name));
+    }
+    if current_notebook_name(settings).as_deref() == Some(name) {
+        return Err(format!("Cannot remove the active notebook: {}", name));
+    }
+
+    notebooks.remove(name);
+    store_notebooks(settings, notebooks);
+    Ok(())
+}
+
+fn switch_notebook_in_settings(settings: &mut Settings, name: &str) -> Result<(), String> {
+    if !configured_notebooks(settings).contains_key(name) {
+        return Err(format!("Notebook not found: {}", name));
     }
-    get_default_journal_dir()
+
+    settings.insert(
+        CURRENT_JOURNAL_KEY.to_string(),
+        serde_json::Value::String(name.to_string()),
+    );
+    Ok(())
+}
+
+fn store_notebooks(settings: &mut Settings, notebooks: BTreeMap<String, PathBuf>) {
+    let map: serde_json::Map<String, serde_json::Value> = notebooks
+        .into_iter()
+        .map(|(name, path)| (name, serde_json::Value::String(path.display().to_string())))
+        .collect();
+    settings.insert(NOTEBOOKS_KEY.to_string(), serde_json::Value::Object(map));
+}
+
+#[tauri::command]
+fn list_notebooks() -> Result<Vec<Notebook>, String> {
+    notebooks_from_settings(&read_settings()?)
+}
+
+#[tauri::command]
+fn add_notebook(name: String, path: String) -> Result<Vec<Notebook>, String> {
+    let mut settings = read_settings()?;
+    add_notebook_to_settings(&mut settings, &name, &path)?;
+    write_settings(&settings)?;
+    notebooks_from_settings(&settings)
+}
+
+#[tauri::command]
+fn remove_notebook(name: String) -> Result<Vec<Notebook>, String> {
+    let mut settings = read_settings()?;
+    remove_notebook_from_settings(&mut settings, &name)?;
+    write_settings(&settings)?;
+    notebooks_from_settings(&settings)
+}
+
+#[tauri::command]
+fn switch_notebook(name: String) -> Result<String, String> {
+    let mut settings = read_settings()?;
+    switch_notebook_in_settings(&mut settings, &name)?;
+    write_settings(&settings)?;
+    journal_dir_from_settings(&settings).map(|p| p.display().to_string())
 }
 
 #[tauri::command]
@@ -42,9 +275,58 @@ fn get_journal_path() -> Result<String, String> {
         .ok_or("Invalid path encoding".to_string())
 }
 
+const NORMALIZE_ON_SAVE_KEY: &str = "normalize_on_save";
+
+/// Converts CRLF/CR line endings to LF and strips trailing whitespace, ending
+/// non-empty content with exactly one newline.
+fn normalize_entry_content(content: &str) -> String {
+    let unified = content.replace("\r\n", "\n").replace('\r', "\n");
+    let normalized = unified
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let normalized = normalized.trim_end_matches('\n');
+
+    if normalized.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", normalized)
+    }
+}
+
+fn prepare_entry_content(content: String, settings: &Settings) -> String {
+    let normalize = settings
+        .get(NORMALIZE_ON_SAVE_KEY)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    if normalize {
+        normalize_entry_content(&content)
+    } else {
+        content
+    }
+}
+
+fn resolve_entry(filepath: &str) -> Result<PathBuf, String> {
+    paths::resolve_entry_path(&get_effective_journal_dir()?, filepath)
+}
+
+/// The journal path `filepath` refers to, spelled as `list_entries` lists it.
+#[tauri::command]
+fn normalize_entry_path(filepath: String) -> Result<String, String> {
+    Ok(resolve_entry(&filepath)?.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 fn write_entry(filepath: String, content: String) -> Result<(), String> {
-    let path = PathBuf::from(&filepath);
+    let path = resolve_entry(&filepath)?;
+    let content = prepare_entry_content(content, &read_settings()?);
+    write_entry_atomic(&path.to_string_lossy(), &content)
+}
+
+fn write_entry_atomic(filepath: &str, content: &str) -> Result<(), String> {
+    let path = PathBuf::from(filepath);
 
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| {
@@ -109,21 +391,69 @@ fn ensure_journal_dir() -> Result<String, String> {
 
 #[tauri::command]
 fn list_entries() -> Result<Vec<String>, String> {
-    let journal_dir = get_effective_journal_dir()?;
+    list_entries_in(&get_effective_journal_dir()?)
+}
+
+/// Entry paths in ascending order, oldest first.
+fn list_entries_in(journal_dir: &Path) -> Result<Vec<String>, String> {
+    let mut entries: Vec<String> = list_entries_detailed_in(journal_dir)?
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+#[derive(Debug, Serialize)]
+struct EntryMeta {
+    path: String,
+    /// Seconds since the epoch; `None` where the filesystem doesn't record it.
+    created_at: Option<u64>,
+    modified_at: Option<u64>,
+    size_bytes: u64,
+    /// `YYYY-MM` from the year/month directories, or `YYYY-MM-DD` when the
+    /// file name starts with a date in that month.
+    date: String,
+}
+
+fn epoch_secs(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    let elapsed = time.ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(elapsed.as_secs())
+}
+
+fn entry_date(year: &str, month: &str, file_stem: &str) -> String {
+    let month_prefix = format!("{}-{}-", year, month);
+    let day = file_stem
+        .strip_prefix(&month_prefix)
+        .and_then(|rest| rest.get(..2))
+        .filter(|day| day.bytes().all(|b| b.is_ascii_digit()));
+    match day {
+        Some(day) => format!("{}{}", month_prefix, day),
+        None => format!("{}-{}", year, month),
+    }
+}
+
+#[tauri::command]
+fn list_entries_detailed() -> Result<Vec<EntryMeta>, String> {
+    list_entries_detailed_in(&get_effective_journal_dir()?)
+}
 
+/// Entries under `journal_dir/<year>/<month>/`, newest date first.
+fn list_entries_detailed_in(journal_dir: &Path) -> Result<Vec<EntryMeta>, String> {
     if !journal_dir.exists() {
         return Ok(vec![]);
     }
 
-    let mut entries: Vec<String> = Vec::new();
+    let mut entries: Vec<EntryMeta> = Vec::new();
 
-    let years = fs::read_dir(&journal_dir).map_err(|e| format!("Failed to read journal: {}", e))?;
+    let years = fs::read_dir(journal_dir).map_err(|e| format!("Failed to read journal: {}", e))?;
 
     for year_entry in years.flatten() {
         let year_path = year_entry.path();
         if !year_path.is_dir() {
             continue;
         }
+        let year = year_entry.file_name().to_string_lossy().to_string();
 
         let months = match fs::read_dir(&year_path) {
             Ok(m) => m,
@@ -135,6 +465,7 @@ fn list_entries() -> Result<Vec<String>, String> {
             if !month_path.is_dir() {
                 continue;
             }
+            let month = month_entry.file_name().to_string_lossy().to_string();
 
             let files = match fs::read_dir(&month_path) {
                 Ok(f) => f,
@@ -143,22 +474,34 @@ fn list_entries() -> Result<Vec<String>, String> {
 
             for file_entry in files.flatten() {
                 let file_path = file_entry.path();
-                if file_path.extension().is_some_and(|ext| ext == "md") {
-                    if let Some(path_str) = file_path.to_str() {
-                        entries.push(path_str.to_string());
-                    }
+                if file_path.extension().is_none_or(|ext| ext != "md") {
+                    continue;
                 }
+                let Some(path_str) = file_path.to_str() else {
+                    continue;
+                };
+                let metadata = file_entry.metadata().ok();
+                let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+                entries.push(EntryMeta {
+                    path: path_str.to_string(),
+                    created_at: metadata.as_ref().and_then(|m| epoch_secs(m.created())),
+                    modified_at: metadata.as_ref().and_then(|m| epoch_secs(m.modified())),
+                    size_bytes: metadata.map_or(0, |m| m.len()),
+                    date: entry_date(&year, &month, stem),
+                });
             }
         }
     }
 
-    entries.sort();
+    entries.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.path.cmp(&a.path)));
     Ok(entries)
 }
 
 #[tauri::command]
 fn read_entry(filepath: String) -> Result<String, String> {
-    fs::read_to_string(&filepath).map_err(|e| {
+    let path = resolve_entry(&filepath)?;
+    fs::read_to_string(&path).map_err(|e| {
         if e.kind() == std::io::ErrorKind::NotFound {
             format!("Entry not found: {}", filepath)
         } else if e.kind() == std::io::ErrorKind::PermissionDenied {
@@ -169,26 +512,133 @@ fn read_entry(filepath: String) -> Result<String, String> {
     })
 }
 
-fn get_settings_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    let dreamal_dir = home.join(".dreamal");
-    fs::create_dir_all(&dreamal_dir).map_err(|e| format!("Failed to create .dreamal directory: {}", e))?;
-    Ok(dreamal_dir.join("settings.json"))
+#[tauri::command]
+fn entry_stats(filepath: String) -> Result<stats::EntryStats, String> {
+    Ok(stats::entry_stats(&read_entry(filepath)?))
+}
+
+#[tauri::command]
+fn read_entry_parsed(filepath: String) -> Result<frontmatter::ParsedEntry, String> {
+    Ok(frontmatter::parse_entry(&read_entry(filepath)?))
+}
+
+#[tauri::command]
+fn list_tags() -> Result<Vec<frontmatter::TagCount>, String> {
+    // Entries that can't be read as text have no tags to count.
+    let contents: Vec<String> = list_entries_in(&get_effective_journal_dir()?)?
+        .iter()
+        .filter_map(|entry| fs::read_to_string(entry).ok())
+        .collect();
+    Ok(frontmatter::count_tags(contents.iter().map(String::as_str)))
+}
+
+#[tauri::command]
+fn get_journal_stats() -> Result<stats::JournalStats, String> {
+    let today = stats::local_today();
+    journal_stats_in(&get_effective_journal_dir()?, today)
+}
+
+fn journal_stats_in(
+    journal_dir: &Path,
+    today: chrono::NaiveDate,
+) -> Result<stats::JournalStats, String> {
+    // An entry that can't be read as text still counts, just with no words.
+    let entries: Vec<(String, String)> = list_entries_detailed_in(journal_dir)?
+        .into_iter()
+        .map(|entry| {
+            let content = fs::read_to_string(&entry.path).unwrap_or_default();
+            (entry.date, content)
+        })
+        .collect();
+    Ok(stats::journal_stats(
+        entries.iter().map(|(date, content)| (date.as_str(), content.as_str())),
+        today,
+    ))
+}
+
+#[tauri::command]
+fn find_duplicate_entries() -> Result<Vec<Vec<String>>, String> {
+    duplicates::find_duplicate_entries_in(&get_effective_journal_dir()?)
+}
+
+#[tauri::command]
+fn find_broken_links() -> Result<Vec<links::BrokenLink>, String> {
+    links::find_broken_links_in(&get_effective_journal_dir()?)
+}
+
+#[tauri::command]
+fn search_entries(query: String, case_sensitive: bool) -> Result<Vec<search::SearchHit>, String> {
+    search::search_entries_in(&get_effective_journal_dir()?, &query, case_sensitive)
+}
+
+#[tauri::command]
+fn journal_git_status() -> Result<journal_git::JournalGitStatus, String> {
+    journal_git::journal_git_status_in(&get_effective_journal_dir()?)
+}
+
+#[tauri::command]
+fn commit_journal(message: String) -> Result<journal_git::JournalCommit, String> {
+    journal_git::commit_versioned_journal_in(
+        &read_settings()?,
+        &get_effective_journal_dir()?,
+        &message,
+    )
 }
 
-fn read_settings() -> Result<HashMap<String, serde_json::Value>, String> {
-    let path = get_settings_path()?;
-    if !path.exists() {
-        return Ok(HashMap::new());
+/// Turns on journal versioning. Returns a warning, leaving it off, when the
+/// journal is already a repo with a remote, until `allow_tracked_repo` is set.
+#[tauri::command]
+fn enable_journal_versioning(
+    allow_tracked_repo: Option<bool>,
+) -> Result<journal_git::VersioningSetup, String> {
+    let journal_dir = get_effective_journal_dir()?;
+    let setup =
+        journal_git::setup_versioning_in(&journal_dir, allow_tracked_repo.unwrap_or(false))?;
+    if setup.enabled {
+        let mut settings = read_settings()?;
+        journal_git::record_versioning(&mut settings, &journal_dir);
+        write_settings(&settings)?;
     }
-    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read settings: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("Failed to parse settings: {}", e))
+    Ok(setup)
 }
 
-fn write_settings(settings: &HashMap<String, serde_json::Value>) -> Result<(), String> {
-    let path = get_settings_path()?;
-    let content = serde_json::to_string_pretty(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    fs::write(&path, content).map_err(|e| format!("Failed to write settings: {}", e))
+#[tauri::command]
+fn export_entry(filepath: String, dest: String, format: String) -> Result<(), String> {
+    let format = export::ExportFormat::parse(&format)?;
+    export::export_entry_in(
+        &get_effective_journal_dir()?,
+        &resolve_entry(&filepath)?,
+        Path::new(&dest),
+        format,
+    )
+}
+
+fn get_trash_dir() -> Result<PathBuf, String> {
+    Ok(git_ops::get_dreamal_dir().map_err(|e| e.to_string())?.join("trash"))
+}
+
+/// Moves an entry to the trash rather than deleting it. Returns its path there.
+#[tauri::command]
+fn delete_entry(filepath: String) -> Result<String, String> {
+    let entry = resolve_entry(&filepath)?;
+    let trashed = trash::trash_entry_in(&get_effective_journal_dir()?, &get_trash_dir()?, &entry)?;
+    Ok(trashed.display().to_string())
+}
+
+/// Restores an entry to the notebook it was deleted from.
+#[tauri::command]
+fn restore_entry(trash_path: String) -> Result<String, String> {
+    let restored = trash::restore_entry_in(
+        &get_effective_journal_dir()?,
+        &get_trash_dir()?,
+        Path::new(&trash_path),
+    )?;
+    Ok(restored.display().to_string())
+}
+
+#[tauri::command]
+fn empty_trash() -> Result<u64, String> {
+    trash::empty_trash_in(&get_trash_dir()?)
 }
 
 #[tauri::command]
@@ -197,13 +647,172 @@ fn get_setting(key: String) -> Result<Option<serde_json::Value>, String> {
     Ok(settings.get(&key).cloned())
 }
 
+/// Validates and stores one setting. The legacy journal dir key is stored as
+/// `journal_dir`, so the two can't disagree.
+fn apply_setting(
+    settings: &mut Settings,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let key = if key == LEGACY_JOURNAL_DIR_KEY {
+        JOURNAL_DIR_KEY.to_string()
+    } else {
+        key
+    };
+    if key == JOURNAL_DIR_KEY {
+        if let Some(dir) = value.as_str() {
+            validate_journal_dir(Path::new(dir))?;
+        }
+        settings.remove(LEGACY_JOURNAL_DIR_KEY);
+    }
+    if key == "session_timeout_secs" {
+        claude_session::config::validate_timeout_setting(&value)?;
+    }
+    settings.insert(key, value);
+    Ok(())
+}
+
 #[tauri::command]
 fn set_setting(key: String, value: serde_json::Value) -> Result<(), String> {
     let mut settings = read_settings()?;
-    settings.insert(key, value);
+    apply_setting(&mut settings, key, value)?;
     write_settings(&settings)
 }
 
+#[tauri::command]
+fn repair_settings() -> Result<SettingsRepair, String> {
+    repair_settings_file()
+}
+
+const RESET_DIRS: &[&str] = &["temp-checkouts", "sessions", "trash"];
+
+#[derive(Debug, Serialize)]
+struct FactoryResetSummary {
+    removed: Vec<String>,
+    sessions_cleared: usize,
+}
+
+fn canonical_or_self(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn factory_reset_targets(
+    dreamal_dir: &Path,
+    journal_dir: &Path,
+    clear_settings: bool,
+    clear_credentials: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let mut targets: Vec<PathBuf> = RESET_DIRS.iter().map(|d| dreamal_dir.join(d)).collect();
+    if clear_settings {
+        targets.push(dreamal_dir.join("settings.json"));
+    }
+    if clear_credentials {
+        targets.push(dreamal_dir.join("credentials.json"));
+    }
+
+    let journal_dir = canonical_or_self(journal_dir);
+    for target in &targets {
+        if journal_dir.starts_with(canonical_or_self(target)) {
+            return Err(format!(
+                "Refusing to reset: journal directory {} is inside {}",
+                journal_dir.display(),
+                target.display()
+            ));
+        }
+    }
+
+    Ok(targets)
+}
+
+fn remove_reset_targets(targets: &[PathBuf]) -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+
+    for target in targets {
+        if !target.exists() {
+            continue;
+        }
+
+        let result = if target.is_dir() {
+            fs::remove_dir_all(target)
+        } else {
+            fs::remove_file(target)
+        };
+        result.map_err(|e| format!("Failed to remove {}: {}", target.display(), e))?;
+
+        removed.push(target.display().to_string());
+    }
+
+    Ok(removed)
+}
+
+#[tauri::command]
+fn factory_reset(
+    state: State<'_, AppState>,
+    confirm: bool,
+    clear_settings: bool,
+    clear_credentials: bool,
+) -> Result<FactoryResetSummary, String> {
+    if !confirm {
+        return Err("Factory reset requires confirmation".to_string());
+    }
+
+    let journal_dir = get_effective_journal_dir()?;
+    let dreamal_dir = git_ops::get_dreamal_dir().map_err(|e| e.to_string())?;
+    let targets = factory_reset_targets(&dreamal_dir, &journal_dir, clear_settings, clear_credentials)?;
+
+    let cleared = state
+        .session_manager
+        .clear_sessions()
+        .map_err(|e| e.to_string())?;
+    for session in &cleared {
+        if let Some(pid) = session.process_id {
+            let _ = kill_process(pid);
+        }
+    }
+
+    let removed = remove_reset_targets(&targets)?;
+
+    Ok(FactoryResetSummary {
+        removed,
+        sessions_cleared: cleared.len(),
+    })
+}
+
+/// The snapshots dir and where restoring one backs the journal up to. The
+/// backups stay out of the trash so emptying it cannot lose them.
+fn get_snapshot_dirs() -> Result<(PathBuf, PathBuf), String> {
+    let dreamal_dir = git_ops::get_dreamal_dir().map_err(|e| e.to_string())?;
+    Ok((
+        dreamal_dir.join("snapshots"),
+        dreamal_dir.join("snapshot-backups"),
+    ))
+}
+
+#[tauri::command]
+fn create_snapshot(name: String) -> Result<snapshot::SnapshotInfo, String> {
+    let (snapshots_dir, _) = get_snapshot_dirs()?;
+    snapshot::create_snapshot_in(&snapshots_dir, &get_effective_journal_dir()?, &name)
+}
+
+#[tauri::command]
+fn list_snapshots() -> Result<Vec<snapshot::SnapshotInfo>, String> {
+    let (snapshots_dir, _) = get_snapshot_dirs()?;
+    snapshot::list_snapshots_in(&snapshots_dir)
+}
+
+#[tauri::command]
+fn restore_snapshot(id: String) -> Result<String, String> {
+    let (snapshots_dir, backups_dir) = get_snapshot_dirs()?;
+    let backup = snapshot::restore_snapshot_in(
+        &snapshots_dir,
+        &get_effective_journal_dir()?,
+        &backups_dir,
+        &id,
+        snapshot::EDIT_QUIET_PERIOD,
+    )?;
+    Ok(backup.to_string_lossy().to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let session_manager = Arc::new(SessionManager::new());
@@ -214,10 +823,27 @@ pub fn run() {
         .manage(AppState {
             session_manager: session_manager.clone(),
         })
-        .setup(|_app| {
+        .setup(move |app| {
+            let handle = app.handle().clone();
+            session_manager.set_event_sink(Arc::new(move |event, payload| {
+                if let Err(e) = handle.emit(event, payload) {
+                    eprintln!("Warning: Failed to emit {}: {}", event, e);
+                }
+            }));
+
             if let Err(e) = git_ops::cleanup::cleanup_orphaned_sessions() {
                 eprintln!("Warning: Failed to cleanup orphaned sessions: {}", e);
             }
+
+            match claude_session::persistence::get_sessions_dir() {
+                Ok(dir) => {
+                    session_manager.set_queue_dir(dir);
+                    restore_persisted_sessions(&session_manager);
+                    resume_persisted_queue(&session_manager);
+                }
+                Err(e) => eprintln!("Warning: Queued sessions won't survive a restart: {}", e),
+            }
+            spawn_stale_session_reaper(session_manager.clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -226,14 +852,393 @@ pub fn run() {
             write_entry,
             ensure_journal_dir,
             list_entries,
+            list_entries_detailed,
             read_entry,
+            delete_entry,
+            restore_entry,
+            empty_trash,
+            normalize_entry_path,
             spawn_claude_session,
             get_session_status,
             cancel_session,
             list_claude_sessions,
             get_setting,
-            set_setting
+            set_setting,
+            factory_reset,
+            list_notebooks,
+            add_notebook,
+            remove_notebook,
+            switch_notebook,
+            export_session_patch,
+            check_branch_permissions,
+            preview_pr_title,
+            reset_session_branch,
+            check_force_push,
+            list_sessions_for_repo,
+            run_command_in_session,
+            dedupe_sessions,
+            estimate_clone,
+            get_repo_pr_template,
+            session_summary_markdown,
+            get_effective_session_config,
+            validate_instructions,
+            session_timing,
+            reclone_session,
+            list_recent_prs,
+            create_snapshot,
+            list_snapshots,
+            restore_snapshot,
+            detect_project_type,
+            pause_scheduler,
+            resume_scheduler,
+            get_queue_status,
+            export_entry,
+            search_sessions,
+            reconcile_sessions,
+            suggest_reviewers,
+            find_broken_links,
+            repair_settings,
+            find_duplicate_entries,
+            get_journal_stats,
+            read_entry_parsed,
+            list_tags,
+            search_entries,
+            journal_git_status,
+            commit_journal,
+            enable_journal_versioning,
+            spawn_batch_sessions,
+            peek_session_output,
+            check_merge_conflicts,
+            archive_session,
+            list_archived_sessions,
+            unarchive_session,
+            set_repo_default_base,
+            set_guidelines_file,
+            test_push_credentials,
+            cleanup_orphaned_artifacts,
+            preview_branch_name,
+            reopen_pr,
+            entry_stats,
+            set_session_locked,
+            update_pr,
+            list_live_checkouts,
+            set_clone_depth,
+            check_instructions_length,
+            resume_queued_sessions,
+            export_session_bundle,
+            reap_stale_sessions,
+            set_max_memory_mb,
+            retry_failed_in_batch,
+            preview_pr_payload,
+            get_base_branch_requirements,
+            get_repo_allowed_commands,
+            set_repo_allowed_commands,
+            get_session_timeline,
+            sweep_merged_branches,
+            export_all_session_patches,
+            list_persisted_sessions,
+            list_user_repos,
+            get_capacity,
+            set_max_changed_files,
+            diff_sessions,
+            session_duration
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_dreamal_dir() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dreamal_dir = temp_dir.path().join(".dreamal");
+        let journal_dir = temp_dir.path().join("Journal");
+
+        for dir in RESET_DIRS {
+            fs::create_dir_all(dreamal_dir.join(dir)).unwrap();
+        }
+        fs::write(dreamal_dir.join("sessions").join("abc.json"), "{}").unwrap();
+        fs::write(dreamal_dir.join("settings.json"), "{}").unwrap();
+        fs::write(dreamal_dir.join("credentials.json"), "{}").unwrap();
+
+        fs::create_dir_all(journal_dir.join("2026").join("01")).unwrap();
+        fs::write(journal_dir.join("2026").join("01").join("entry.md"), "# Entry").unwrap();
+
+        (temp_dir, dreamal_dir, journal_dir)
+    }
+
+    #[test]
+    fn test_write_entry_normalizes_when_enabled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let filepath = temp_dir.path().join("entry.md");
+        let filepath = filepath.to_str().unwrap();
+        let mut settings = Settings::new();
+        settings.insert(NORMALIZE_ON_SAVE_KEY.to_string(), serde_json::json!(true));
+
+        let raw = "# Title  \r\nline one\t\r\nline two\n\n\n";
+
+        let content = prepare_entry_content(raw.to_string(), &settings);
+        write_entry_atomic(filepath, &content).unwrap();
+
+        assert_eq!(fs::read_to_string(filepath).unwrap(), "# Title\nline one\nline two\n");
+    }
+
+    #[test]
+    fn test_write_entry_untouched_when_disabled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let filepath = temp_dir.path().join("entry.md");
+        let filepath = filepath.to_str().unwrap();
+        let raw = "# Title  \r\nline one\t\r\n";
+
+        let content = prepare_entry_content(raw.to_string(), &Settings::new());
+        write_entry_atomic(filepath, &content).unwrap();
+
+        assert_eq!(fs::read_to_string(filepath).unwrap(), raw);
+        assert!(!temp_dir.path().join("entry.md.tmp").exists());
+    }
+
+    #[test]
+    fn test_normalize_entry_content_edge_cases() {
+        assert_eq!(normalize_entry_content(""), "");
+        assert_eq!(normalize_entry_content("  \r\n\r\n"), "");
+        assert_eq!(normalize_entry_content("a\rb"), "a\nb\n");
+        assert_eq!(normalize_entry_content("no newline"), "no newline\n");
+    }
+
+    #[test]
+    fn test_factory_reset_preserves_journal_and_credentials() {
+        let (_temp_dir, dreamal_dir, journal_dir) = setup_dreamal_dir();
+
+        let targets = factory_reset_targets(&dreamal_dir, &journal_dir, true, false).unwrap();
+        let removed = remove_reset_targets(&targets).unwrap();
+
+        assert_eq!(removed.len(), RESET_DIRS.len() + 1);
+        for dir in RESET_DIRS {
+            assert!(!dreamal_dir.join(dir).exists());
+        }
+        assert!(!dreamal_dir.join("settings.json").exists());
+        assert!(dreamal_dir.join("credentials.json").exists());
+        assert!(journal_dir.join("2026").join("01").join("entry.md").exists());
+    }
+
+    #[test]
+    fn test_factory_reset_clears_credentials_when_requested() {
+        let (_temp_dir, dreamal_dir, journal_dir) = setup_dreamal_dir();
+
+        let targets = factory_reset_targets(&dreamal_dir, &journal_dir, false, true).unwrap();
+        remove_reset_targets(&targets).unwrap();
+
+        assert!(dreamal_dir.join("settings.json").exists());
+        assert!(!dreamal_dir.join("credentials.json").exists());
+    }
+
+    #[test]
+    fn test_factory_reset_refuses_journal_inside_target() {
+        let (_temp_dir, dreamal_dir, _journal_dir) = setup_dreamal_dir();
+        let journal_dir = dreamal_dir.join("sessions").join("Journal");
+        fs::create_dir_all(&journal_dir).unwrap();
+
+        let result = factory_reset_targets(&dreamal_dir, &journal_dir, false, false);
+
+        assert!(result.is_err());
+        assert!(journal_dir.exists());
+    }
+
+    fn write_entry_file(journal_dir: &Path, name: &str) {
+        let month_dir = journal_dir.join("2026").join("03");
+        fs::create_dir_all(&month_dir).unwrap();
+        fs::write(month_dir.join(name), "# Entry").unwrap();
+    }
+
+    #[test]
+    fn test_list_entries_detailed_newest_first() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        for (month, name, content) in [
+            ("01", "2024-01-15-103045.md", "# Older"),
+            ("02", "2024-02-03-090000.md", "# Newer"),
+            ("02", "2024-02-03-180000.md", "# Newest"),
+            ("02", "notes.md", "# Undated"),
+        ] {
+            let month_dir = journal_dir.join("2024").join(month);
+            fs::create_dir_all(&month_dir).unwrap();
+            fs::write(month_dir.join(name), content).unwrap();
+        }
+
+        let detailed = list_entries_detailed_in(journal_dir).unwrap();
+
+        let dates: Vec<&str> = detailed.iter().map(|e| e.date.as_str()).collect();
+        assert_eq!(dates, ["2024-02-03", "2024-02-03", "2024-02", "2024-01-15"]);
+        assert!(detailed[0].path.ends_with("2024-02-03-180000.md"));
+        assert_eq!(detailed[0].size_bytes, "# Newest".len() as u64);
+        assert!(detailed.iter().all(|e| e.modified_at.is_some()));
+
+        // list_entries keeps its oldest-first order.
+        let paths = list_entries_in(journal_dir).unwrap();
+        assert_eq!(paths.len(), 4);
+        assert!(paths[0].ends_with("2024-01-15-103045.md"));
+        assert!(paths[3].ends_with("notes.md"));
+    }
+
+    #[test]
+    fn test_journal_stats_from_entry_layout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        for (month, name) in [
+            ("02", "2024-02-28-210000.md"),
+            ("02", "2024-02-29-080000.md"),
+            ("03", "2024-03-01-080000.md"),
+            ("03", "2024-03-01-200000.md"),
+            ("03", "notes.md"),
+        ] {
+            let month_dir = journal_dir.join("2024").join(month);
+            fs::create_dir_all(&month_dir).unwrap();
+            fs::write(month_dir.join(name), "---\ntitle: x\n---\nSome words here").unwrap();
+        }
+
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let stats = journal_stats_in(journal_dir, today).unwrap();
+
+        assert_eq!(stats.total_entries, 5);
+        assert_eq!(stats.total_words, 15);
+        assert_eq!(stats.entries_this_month, 3);
+        assert_eq!(stats.current_streak_days, 3);
+        assert_eq!(stats.longest_streak_days, 3);
+    }
+
+    #[test]
+    fn test_journal_dir_setting_overrides_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let synced = temp_dir.path().join("Dropbox").join("Journal");
+        let mut settings = Settings::new();
+        let builtin = get_default_journal_dir(&settings).unwrap();
+
+        settings.insert(JOURNAL_DIR_KEY.to_string(), serde_json::json!(synced.to_str().unwrap()));
+        assert_eq!(journal_dir_from_settings(&settings).unwrap(), synced);
+        assert!(!synced.exists());
+
+        let file = temp_dir.path().join("file.txt");
+        fs::write(&file, "").unwrap();
+        for invalid in [
+            "relative/Journal".to_string(),
+            file.join("Journal").display().to_string(),
+        ] {
+            assert!(validate_journal_dir(Path::new(&invalid)).is_err(), "{}", invalid);
+            settings.insert(JOURNAL_DIR_KEY.to_string(), serde_json::json!(invalid));
+            assert_eq!(journal_dir_from_settings(&settings).unwrap(), builtin);
+        }
+    }
+
+    #[test]
+    fn test_journal_dir_setting_replaces_legacy_key() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old = temp_dir.path().join("old");
+        let new = temp_dir.path().join("new");
+        let mut settings = Settings::new();
+        settings.insert(LEGACY_JOURNAL_DIR_KEY.to_string(), serde_json::json!(old));
+        settings.insert(JOURNAL_DIR_KEY.to_string(), serde_json::json!(new));
+        assert_eq!(journal_dir_from_settings(&settings).unwrap(), new);
+
+        let mut settings = Settings::new();
+        settings.insert(JOURNAL_DIR_KEY.to_string(), serde_json::json!(new));
+        apply_setting(
+            &mut settings,
+            LEGACY_JOURNAL_DIR_KEY.to_string(),
+            serde_json::json!(old),
+        )
+        .unwrap();
+        assert_eq!(journal_dir_from_settings(&settings).unwrap(), old);
+        assert!(!settings.contains_key(LEGACY_JOURNAL_DIR_KEY));
+        assert!(apply_setting(
+            &mut settings,
+            LEGACY_JOURNAL_DIR_KEY.to_string(),
+            serde_json::json!("relative/Journal"),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_journal_dir_without_notebooks_uses_journal_dir_setting() {
+        let mut settings = Settings::new();
+        settings.insert(LEGACY_JOURNAL_DIR_KEY.to_string(), serde_json::json!("/custom/Journal"));
+
+        assert_eq!(
+            journal_dir_from_settings(&settings).unwrap(),
+            PathBuf::from("/custom/Journal")
+        );
+
+        let notebooks = notebooks_from_settings(&settings).unwrap();
+        assert_eq!(notebooks.len(), 1);
+        assert_eq!(notebooks[0].name, DEFAULT_NOTEBOOK_NAME);
+        assert!(notebooks[0].current);
+    }
+
+    #[test]
+    fn test_switching_notebooks_changes_listed_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let personal = temp_dir.path().join("personal");
+        let work = temp_dir.path().join("work");
+        write_entry_file(&personal, "personal.md");
+        write_entry_file(&work, "work.md");
+
+        let mut settings = Settings::new();
+        settings.insert("journalDir".to_string(), serde_json::json!(personal.to_str().unwrap()));
+        add_notebook_to_settings(&mut settings, "work", work.to_str().unwrap()).unwrap();
+
+        let entries = list_entries_in(&journal_dir_from_settings(&settings).unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].ends_with("personal.md"));
+
+        switch_notebook_in_settings(&mut settings, "work").unwrap();
+
+        let entries = list_entries_in(&journal_dir_from_settings(&settings).unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].ends_with("work.md"));
+    }
+
+    #[test]
+    fn test_notebook_management_validation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let work = temp_dir.path().join("work");
+        let work = work.to_str().unwrap();
+
+        let mut settings = Settings::new();
+        assert!(add_notebook_to_settings(&mut settings, "", work).is_err());
+        assert!(add_notebook_to_settings(&mut settings, "rel", "relative/path").is_err());
+
+        add_notebook_to_settings(&mut settings, "work", work).unwrap();
+        assert!(add_notebook_to_settings(&mut settings, "work", work).is_err());
+        assert!(switch_notebook_in_settings(&mut settings, "missing").is_err());
+
+        assert!(remove_notebook_from_settings(&mut settings, DEFAULT_NOTEBOOK_NAME).is_err());
+
+        switch_notebook_in_settings(&mut settings, "work").unwrap();
+        remove_notebook_from_settings(&mut settings, DEFAULT_NOTEBOOK_NAME).unwrap();
+
+        let notebooks = notebooks_from_settings(&settings).unwrap();
+        assert_eq!(notebooks.len(), 1);
+        assert_eq!(notebooks[0].name, "work");
+        assert!(notebooks[0].current);
+    }
+
+    #[test]
+    fn test_factory_reset_clears_session_manager() {
+        let manager = SessionManager::new();
+        manager
+            .create_session(
+                "test-1".to_string(),
+                "/path/to/repo".to_string(),
+                "Add feature".to_string(),
+                PathBuf::from("/tmp/session-test-1"),
+                "claude/feature-123".to_string(),
+            )
+            .unwrap();
+
+        let cleared = manager.clear_sessions().unwrap();
+
+        assert_eq!(cleared.len(), 1);
+        assert!(manager.list_sessions().unwrap().is_empty());
+    }
+}
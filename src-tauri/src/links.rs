@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokenLink {
+    pub source: String,
+    pub target: String,
+    pub line: usize,
+    pub context: String,
+}
+
+/// Targets of the `[[...]]` links in `line`, with any `|alias` or `#anchor`
+/// removed. Links to an anchor in the same entry (`[[#heading]]`) are skipped.
+pub fn parse_wiki_links(line: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find("[[") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("]]") else {
+            break;
+        };
+
+        let link = &after[..end];
+        let target = link.split(['|', '#']).next().unwrap_or_default().trim();
+        if !target.is_empty() {
+            targets.push(target.to_string());
+        }
+        rest = &after[end + 2..];
+    }
+
+    targets
+}
+
+/// Names an entry can be linked by: its file stem and its path relative to the
+/// journal root, with and without the `.md` extension. Lowercased.
+fn link_names(journal_dir: &Path, entry: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(stem) = entry.file_stem().and_then(|s| s.to_str()) {
+        names.push(stem.to_lowercase());
+    }
+    if let Some(relative) = entry
+        .strip_prefix(journal_dir)
+        .ok()
+        .and_then(|p| p.to_str())
+    {
+        let relative = relative.replace('\\', "/").to_lowercase();
+        names.push(relative.trim_end_matches(".md").to_string());
+        names.push(relative);
+    }
+    names
+}
+
+/// How far into a file to look for a NUL byte, matching git's binary check.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Text content of `path` for scanning. Binary files are skipped; other
+/// non-UTF-8 text (latin-1, say) is decoded lossily rather than dropped, so
+/// its ASCII link syntax is still found.
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn find_broken_links_in(journal_dir: &Path) -> Result<Vec<BrokenLink>, String> {
+    let entries = crate::list_entries_in(journal_dir)?;
+    let known: HashSet<String> = entries
+        .iter()
+        .flat_map(|entry| link_names(journal_dir, Path::new(entry)))
+        .collect();
+
+    let mut broken = Vec::new();
+    for entry in &entries {
+        let Some(content) = read_text(Path::new(entry)) else {
+            continue;
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            for target in parse_wiki_links(line) {
+                if !known.contains(&target.to_lowercase()) {
+                    broken.push(BrokenLink {
+                        source: entry.clone(),
+                        target,
+                        line: index + 1,
+                        context: line.trim().to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(broken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wiki_links() {
+        assert_eq!(
+            parse_wiki_links("See [[entry-1]] and [[Trip Notes|the trip]] or [[plans#June]]"),
+            vec!["entry-1", "Trip Notes", "plans"]
+        );
+        assert!(parse_wiki_links("Jump to [[#Summary]] or [[unclosed").is_empty());
+    }
+
+    #[test]
+    fn test_find_broken_links_reports_only_dangling() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        let month_dir = journal_dir.join("2024/01");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        fs::write(
+            month_dir.join("entry-1.md"),
+            "# Monday\nMet up, see [[Entry-2#Evening|later]].\nAlso [[2024/01/entry-2.md]].\n",
+        )
+        .unwrap();
+        fs::write(
+            month_dir.join("entry-2.md"),
+            "# Tuesday\nFollow-up on [[missing-entry]] soon\n",
+        )
+        .unwrap();
+
+        let broken = find_broken_links_in(journal_dir).unwrap();
+
+        assert_eq!(
+            broken,
+            vec![BrokenLink {
+                source: month_dir.join("entry-2.md").display().to_string(),
+                target: "missing-entry".to_string(),
+                line: 2,
+                context: "Follow-up on [[missing-entry]] soon".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_broken_links_handles_non_utf8_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        let month_dir = journal_dir.join("2024/02");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        // "Café" in latin-1, which isn't valid UTF-8.
+        fs::write(
+            month_dir.join("latin1.md"),
+            b"# Caf\xe9\nSee [[nowhere]]\n".as_slice(),
+        )
+        .unwrap();
+        fs::write(
+            month_dir.join("binary.md"),
+            b"\x89PNG\r\n\x1a\n\0\0[[ignored]]".as_slice(),
+        )
+        .unwrap();
+
+        let broken = find_broken_links_in(journal_dir).unwrap();
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target, "nowhere");
+        assert_eq!(broken[0].line, 2);
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+/// `path` with both `/` and `\` as separators and no trailing separator, for
+/// comparing paths written in either style.
+fn slashed(path: &str) -> String {
+    path.replace('\\', "/").trim_end_matches('/').to_string()
+}
+
+fn same_prefix(path: &str, prefix: &str) -> bool {
+    // Windows paths are case-insensitive.
+    if cfg!(windows) {
+        path.to_lowercase().starts_with(&prefix.to_lowercase())
+    } else {
+        path.starts_with(prefix)
+    }
+}
+
+/// The part of `path` below `root`, if `path` starts with it.
+fn strip_root<'a>(path: &'a str, root: &str) -> Option<&'a str> {
+    if root.is_empty() || !same_prefix(path, root) {
+        return None;
+    }
+    let rest = &path[root.len()..];
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix('/')
+    }
+}
+
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    path.starts_with('/') || drive || Path::new(path).is_absolute()
+}
+
+/// `<stem><ext>` in `dir`, numbered `<stem>-1<ext>`, `<stem>-2<ext>` and so on
+/// when an earlier file, such as a backup made in the same second, already
+/// took the name.
+pub fn first_unused_path(dir: &Path, stem: &str, ext: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}{}", stem, ext));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}{}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// Resolves `filepath` from the frontend to the path of a journal entry, the
+/// same way `list_entries` spells it. `/` and `\` are both separators, `.` and
+/// `..` are resolved, and relative paths are taken from the journal root.
+/// Paths that end up outside the journal are refused.
+pub fn resolve_entry_path(journal_dir: &Path, filepath: &str) -> Result<PathBuf, String> {
+    let path = slashed(filepath.trim());
+    if path.is_empty() {
+        return Err("Entry path is empty".to_string());
+    }
+
+    let root = slashed(&journal_dir.to_string_lossy());
+    let canonical_root = journal_dir
+        .canonicalize()
+        .map(|dir| slashed(&dir.to_string_lossy()))
+        .unwrap_or_default();
+
+    let relative = match strip_root(&path, &root).or_else(|| strip_root(&path, &canonical_root)) {
+        Some(relative) => relative,
+        None if is_absolute(&path) => {
+            return Err(format!("Refusing {}: outside the journal", filepath));
+        }
+        None => path.as_str(),
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in relative.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(format!("Refusing {}: outside the journal", filepath));
+                }
+            }
+            name => components.push(name),
+        }
+    }
+
+    if components.is_empty() {
+        return Err(format!("Refusing {}: not an entry", filepath));
+    }
+
+    Ok(components
+        .into_iter()
+        .fold(journal_dir.to_path_buf(), |path, name| path.join(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_first_unused_path_numbers_taken_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+
+        let first = first_unused_path(dir, "journal-100", "");
+        assert_eq!(first, dir.join("journal-100"));
+        fs::create_dir_all(&first).unwrap();
+        assert_eq!(
+            first_unused_path(dir, "journal-100", ""),
+            dir.join("journal-100-1")
+        );
+
+        fs::write(dir.join("abc.patch"), "").unwrap();
+        fs::write(dir.join("abc-1.patch"), "").unwrap();
+        assert_eq!(
+            first_unused_path(dir, "abc", ".patch"),
+            dir.join("abc-2.patch")
+        );
+    }
+
+    #[test]
+    fn test_separator_styles_resolve_to_same_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let entry = journal_dir.join("2024").join("01").join("entry-1.md");
+        fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        fs::write(&entry, "# Entry").unwrap();
+
+        let root = journal_dir.to_string_lossy().to_string();
+        let inputs = [
+            entry.to_string_lossy().to_string(),
+            format!("{}/2024/01/entry-1.md", root),
+            format!("{}\\2024\\01\\entry-1.md", root.replace('/', "\\")),
+            "2024/01/entry-1.md".to_string(),
+            "2024\\01\\entry-1.md".to_string(),
+            "./2024/02/../01//entry-1.md".to_string(),
+        ];
+
+        for input in inputs {
+            assert_eq!(
+                resolve_entry_path(&journal_dir, &input).unwrap(),
+                entry,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_paths_outside_journal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        fs::create_dir_all(&journal_dir).unwrap();
+        let outside = temp_dir.path().join("secrets.md");
+
+        for input in [
+            "../secrets.md".to_string(),
+            "2024\\..\\..\\secrets.md".to_string(),
+            outside.to_string_lossy().to_string(),
+            format!("{}-other/entry.md", journal_dir.display()),
+            "".to_string(),
+            ".".to_string(),
+        ] {
+            assert!(
+                resolve_entry_path(&journal_dir, &input).is_err(),
+                "{}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Characters of context kept on each side of a match in a snippet.
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    pub line: usize,
+    pub snippet: String,
+}
+
+/// Byte range in `line` of the first occurrence of `query`. Case-insensitive
+/// matching compares lowercased characters but returns the range in `line`.
+fn find_match(line: &str, query: &str, case_sensitive: bool) -> Option<Range<usize>> {
+    if case_sensitive {
+        return line.find(query).map(|start| start..start + query.len());
+    }
+
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    line.char_indices().find_map(|(start, _)| {
+        let mut wanted = query.iter();
+        let mut next = wanted.next();
+        for (offset, c) in line[start..].char_indices() {
+            for lower in c.to_lowercase() {
+                if next != Some(&lower) {
+                    return None;
+                }
+                next = wanted.next();
+            }
+            if next.is_none() {
+                return Some(start..start + offset + c.len_utf8());
+            }
+        }
+        None
+    })
+}
+
+/// `line` cut down to `found` with some context on either side, marking the
+/// cuts with an ellipsis.
+fn snippet(line: &str, found: Range<usize>) -> String {
+    let from = line[..found.start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let to = line[found.end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(line.len(), |(i, _)| found.end + i);
+
+    let mut snippet = line[from..to].trim().to_string();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < line.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Entries containing `query`, in `list_entries` order, each with the first
+/// matching line. Entries that aren't valid UTF-8 are skipped.
+pub fn search_entries_in(
+    journal_dir: &Path,
+    query: &str,
+    case_sensitive: bool,
+) -> Result<Vec<SearchHit>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut hits = Vec::new();
+    for entry in crate::list_entries_in(journal_dir)? {
+        let Ok(content) = fs::read_to_string(&entry) else {
+            continue;
+        };
+
+        let found = content.lines().enumerate().find_map(|(index, line)| {
+            find_match(line, query, case_sensitive).map(|found| (index, snippet(line, found)))
+        });
+        if let Some((index, snippet)) = found {
+            hits.push(SearchHit {
+                snippet,
+                path: entry,
+                line: index + 1,
+            });
+        }
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_entries_matches_case() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path();
+        let month_dir = journal_dir.join("2024/03");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        fs::write(
+            month_dir.join("entry-1.md"),
+            "# Monday\nWent to the Lake.\nThe lake was cold.\n",
+        )
+        .unwrap();
+        fs::write(month_dir.join("entry-2.md"), "# Tuesday\nStayed home.\n").unwrap();
+        // "Lake café" in latin-1, which isn't valid UTF-8.
+        fs::write(month_dir.join("latin1.md"), b"Lake caf\xe9\n".as_slice()).unwrap();
+
+        let entry = month_dir.join("entry-1.md").display().to_string();
+
+        assert_eq!(
+            search_entries_in(journal_dir, "lake", true).unwrap(),
+            vec![SearchHit {
+                path: entry.clone(),
+                line: 3,
+                snippet: "The lake was cold.".to_string(),
+            }]
+        );
+        assert_eq!(
+            search_entries_in(journal_dir, "LAKE", false).unwrap(),
+            vec![SearchHit {
+                path: entry,
+                line: 2,
+                snippet: "Went to the Lake.".to_string(),
+            }]
+        );
+        assert!(search_entries_in(journal_dir, "beach", false)
+            .unwrap()
+            .is_empty());
+        assert!(search_entries_in(journal_dir, "", false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_snippet_trims_long_lines() {
+        let line = format!("{}Ünïcode needle{}", "a".repeat(100), "b".repeat(100));
+        let found = find_match(&line, "ÜNÏCODE NEEDLE", false).unwrap();
+        assert_eq!(&line[found.clone()], "Ünïcode needle");
+
+        let snippet = snippet(&line, found);
+
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(
+            snippet,
+            format!("…{}Ünïcode needle{}…", "a".repeat(40), "b".repeat(40))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::paths::first_unused_path;
+
+pub fn get_settings_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let dreamal_dir = home.join(".dreamal");
+    fs::create_dir_all(&dreamal_dir).map_err(|e| format!("Failed to create .dreamal directory: {}", e))?;
+    Ok(dreamal_dir.join("settings.json"))
+}
+
+pub fn read_settings() -> Result<HashMap<String, serde_json::Value>, String> {
+    read_settings_at(&get_settings_path()?)
+}
+
+fn read_settings_at(path: &Path) -> Result<HashMap<String, serde_json::Value>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is corrupt ({}). Run repair_settings to back it up and reset it.",
+            path.display(),
+            e
+        )
+    })
+}
+
+pub fn write_settings(settings: &HashMap<String, serde_json::Value>) -> Result<(), String> {
+    write_settings_at(&get_settings_path()?, settings)
+}
+
+/// Writes to `<path>.tmp` and renames it into place, so a crash mid-write
+/// leaves the old settings rather than a truncated file.
+fn write_settings_at(path: &Path, settings: &HashMap<String, serde_json::Value>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, content).map_err(|e| format!("Failed to write settings: {}", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+pub fn get_string(key: &str) -> Option<String> {
+    read_settings()
+        .ok()
+        .and_then(|settings| settings.get(key).and_then(|v| v.as_str().map(|s| s.to_string())))
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SettingsRepair {
+    pub repaired: bool,
+    pub backup_path: Option<String>,
+    /// Top-level keys that could still be read from the corrupt file.
+    pub salvaged: HashMap<String, serde_json::Value>,
+}
+
+fn next_value<T: DeserializeOwned>(text: &str) -> Option<(T, &str)> {
+    let mut stream = serde_json::Deserializer::from_str(text).into_iter::<T>();
+    let value = stream.next()?.ok()?;
+    Some((value, &text[stream.byte_offset()..]))
+}
+
+/// Reads `"key": value` pairs from the top-level object until the first one
+/// that doesn't parse. A value must be followed by `,` or `}` so a number cut
+/// off mid-write isn't kept.
+fn salvage_settings(content: &str) -> HashMap<String, serde_json::Value> {
+    let mut salvaged = HashMap::new();
+    let Some(start) = content.find('{') else {
+        return salvaged;
+    };
+    let mut rest = &content[start + 1..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let Some((key, after_key)) = next_value::<String>(rest) else {
+            break;
+        };
+        let Some(after_colon) = after_key.trim_start().strip_prefix(':') else {
+            break;
+        };
+        let Some((value, after_value)) = next_value::<serde_json::Value>(after_colon) else {
+            break;
+        };
+        let after_value = after_value.trim_start();
+        if !after_value.starts_with([',', '}']) {
+            break;
+        }
+        salvaged.insert(key, value);
+        rest = after_value;
+    }
+    salvaged
+}
+
+fn repair_settings_at(path: &Path, timestamp: u64) -> Result<SettingsRepair, String> {
+    if !path.exists() {
+        return Ok(SettingsRepair::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    if serde_json::from_str::<HashMap<String, serde_json::Value>>(&content).is_ok() {
+        return Ok(SettingsRepair::default());
+    }
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup = first_unused_path(
+        path.parent().unwrap_or(Path::new("")),
+        &format!("{}.corrupt-{}", file_name, timestamp),
+        "",
+    );
+    fs::rename(path, &backup).map_err(|e| format!("Failed to back up settings: {}", e))?;
+    write_settings_at(path, &HashMap::new())?;
+
+    Ok(SettingsRepair {
+        repaired: true,
+        backup_path: Some(backup.display().to_string()),
+        salvaged: salvage_settings(&content),
+    })
+}
+
+/// Backs up an unparseable settings file and resets it to defaults. A file
+/// that parses is left alone.
+pub fn repair_settings_file() -> Result<SettingsRepair, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    repair_settings_at(&get_settings_path()?, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_truncated_settings() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("settings.json");
+        let truncated = r#"{"journal_dir": "/tmp/journal", "theme": "dark", "font_size": 1"#;
+        fs::write(&path, truncated).unwrap();
+
+        let error = read_settings_at(&path).unwrap_err();
+        assert!(error.contains("repair_settings"), "{}", error);
+
+        let repair = repair_settings_at(&path, 42).unwrap();
+        assert!(repair.repaired);
+        assert_eq!(repair.salvaged.len(), 2);
+        assert_eq!(repair.salvaged["journal_dir"], "/tmp/journal");
+        assert_eq!(repair.salvaged["theme"], "dark");
+
+        let backup = temp_dir.path().join("settings.json.corrupt-42");
+        assert_eq!(repair.backup_path, Some(backup.display().to_string()));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), truncated);
+        assert!(read_settings_at(&path).unwrap().is_empty());
+
+        let again = repair_settings_at(&path, 43).unwrap();
+        assert!(!again.repaired);
+        assert!(again.backup_path.is_none());
+        assert!(!temp_dir.path().join("settings.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_repairs_in_the_same_second_keep_both_backups() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("settings.json");
+
+        fs::write(&path, "{\"first\": ").unwrap();
+        repair_settings_at(&path, 42).unwrap();
+        fs::write(&path, "{\"second\": ").unwrap();
+        let repair = repair_settings_at(&path, 42).unwrap();
+
+        let second = temp_dir.path().join("settings.json.corrupt-42-1");
+        assert_eq!(repair.backup_path, Some(second.display().to_string()));
+        assert_eq!(fs::read_to_string(&second).unwrap(), "{\"second\": ");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("settings.json.corrupt-42")).unwrap(),
+            "{\"first\": "
+        );
+    }
+
+    #[test]
+    fn test_salvage_settings_invalid() {
+        assert!(salvage_settings("not json").is_empty());
+        assert!(salvage_settings(r#"{"a": tru"#).is_empty());
+        let salvaged = salvage_settings(r#"{"a": [1, 2], "b": {"c": null}}"#);
+        assert_eq!(salvaged.len(), 2);
+    }
+}
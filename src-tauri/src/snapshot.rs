@@ -0,0 +1,320 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::git_ops::clone::copy_dir_recursive;
+use crate::git_ops::size::dir_stats;
+use crate::paths::first_unused_path;
+
+/// How recently an entry may have been written before a restore is refused as
+/// possibly clobbering an in-progress edit.
+pub const EDIT_QUIET_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Snapshot name cannot be empty".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid snapshot name '{}': use letters, numbers, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Splits a snapshot id into its name and timestamp. Ids are
+/// `<name>-<timestamp>`, or `<name>-<timestamp>-<n>` when a snapshot with the
+/// same name was taken in the same second. The counter is told apart from a
+/// name ending in a number by being smaller than the timestamp before it.
+fn parse_snapshot_id(id: &str) -> Option<(&str, u64)> {
+    let (rest, last) = id.rsplit_once('-')?;
+    let last: u64 = last.parse().ok()?;
+    if let Some((name, timestamp)) = rest.rsplit_once('-') {
+        if let Ok(timestamp) = timestamp.parse::<u64>() {
+            if last < timestamp {
+                return Some((name, timestamp));
+            }
+        }
+    }
+    Some((rest, last))
+}
+
+fn snapshot_info(path: &Path) -> Option<SnapshotInfo> {
+    let id = path.file_name()?.to_str()?.to_string();
+    let (name, created_at) = parse_snapshot_id(&id)?;
+    let name = name.to_string();
+    let stats = dir_stats(path, false).ok()?;
+
+    Some(SnapshotInfo {
+        name,
+        id,
+        created_at,
+        bytes: stats.bytes,
+        files: stats.files,
+    })
+}
+
+pub fn create_snapshot_in(
+    snapshots_dir: &Path,
+    journal_dir: &Path,
+    name: &str,
+) -> Result<SnapshotInfo, String> {
+    validate_name(name)?;
+    if !journal_dir.is_dir() {
+        return Err(format!(
+            "Journal directory not found: {}",
+            journal_dir.display()
+        ));
+    }
+
+    let snapshot_dir = first_unused_path(snapshots_dir, &format!("{}-{}", name, now_secs()), "");
+    copy_dir_recursive(journal_dir, &snapshot_dir)
+        .map_err(|e| format!("Failed to copy journal: {}", e))?;
+
+    snapshot_info(&snapshot_dir).ok_or_else(|| "Failed to read snapshot".to_string())
+}
+
+pub fn list_snapshots_in(snapshots_dir: &Path) -> Result<Vec<SnapshotInfo>, String> {
+    if !snapshots_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots: Vec<SnapshotInfo> = fs::read_dir(snapshots_dir)
+        .map_err(|e| format!("Failed to read snapshots: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| snapshot_info(&entry.path()))
+        .collect();
+
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+    Ok(snapshots)
+}
+
+fn find_in_progress_edit(dir: &Path, quiet_period: Duration) -> Option<PathBuf> {
+    let entries = fs::read_dir(dir).ok()?;
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_in_progress_edit(&path, quiet_period) {
+                return Some(found);
+            }
+            continue;
+        }
+
+        // write_entry writes to `<file>.tmp` and renames it into place.
+        let is_temp = path.extension().is_some_and(|ext| ext == "tmp");
+        let recently_modified = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < quiet_period);
+
+        if is_temp || recently_modified {
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+/// Replaces the journal with a snapshot, moving the current journal into
+/// `backups_dir` first. Returns where the previous journal was moved.
+pub fn restore_snapshot_in(
+    snapshots_dir: &Path,
+    journal_dir: &Path,
+    backups_dir: &Path,
+    id: &str,
+    quiet_period: Duration,
+) -> Result<PathBuf, String> {
+    let snapshot_dir = snapshots_dir.join(id);
+    if id.contains(['/', '\\']) || id.starts_with('.') || !snapshot_dir.is_dir() {
+        return Err(format!("Snapshot not found: {}", id));
+    }
+
+    if let Some(path) = find_in_progress_edit(journal_dir, quiet_period) {
+        return Err(format!(
+            "Refusing to restore: {} is being edited. Wait for it to save and try again.",
+            path.display()
+        ));
+    }
+
+    fs::create_dir_all(backups_dir).map_err(|e| format!("Failed to create backups: {}", e))?;
+    let backup_dir = first_unused_path(backups_dir, &format!("journal-{}", now_secs()), "");
+
+    // rename fails across filesystems; fall back to copy + delete.
+    if journal_dir.exists() && fs::rename(journal_dir, &backup_dir).is_err() {
+        copy_dir_recursive(journal_dir, &backup_dir)
+            .map_err(|e| format!("Failed to back up journal: {}", e))?;
+        fs::remove_dir_all(journal_dir).map_err(|e| format!("Failed to clear journal: {}", e))?;
+    }
+
+    copy_dir_recursive(&snapshot_dir, journal_dir)
+        .map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+
+    Ok(backup_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(journal_dir: &Path, relative: &str, content: &str) {
+        let path = journal_dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_modify_restore_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let snapshots_dir = temp_dir.path().join("snapshots");
+        let backups_dir = temp_dir.path().join("snapshot-backups");
+
+        write_entry(&journal_dir, "2024/01/entry-1.md", "original");
+        let snapshot = create_snapshot_in(&snapshots_dir, &journal_dir, "before-edit").unwrap();
+        assert_eq!(snapshot.name, "before-edit");
+        assert_eq!(snapshot.files, 1);
+        assert_eq!(snapshot.bytes, "original".len() as u64);
+
+        write_entry(&journal_dir, "2024/01/entry-1.md", "modified");
+        write_entry(&journal_dir, "2024/02/entry-2.md", "new");
+
+        let backup = restore_snapshot_in(
+            &snapshots_dir,
+            &journal_dir,
+            &backups_dir,
+            &snapshot.id,
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(journal_dir.join("2024/01/entry-1.md")).unwrap(),
+            "original"
+        );
+        assert!(!journal_dir.join("2024/02/entry-2.md").exists());
+        assert_eq!(
+            fs::read_to_string(backup.join("2024/01/entry-1.md")).unwrap(),
+            "modified"
+        );
+        assert!(backup.join("2024/02/entry-2.md").exists());
+    }
+
+    #[test]
+    fn test_list_snapshots() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let snapshots_dir = temp_dir.path().join("snapshots");
+        assert!(list_snapshots_in(&snapshots_dir).unwrap().is_empty());
+
+        fs::create_dir_all(snapshots_dir.join("older-100")).unwrap();
+        fs::create_dir_all(snapshots_dir.join("newer-200")).unwrap();
+        fs::create_dir_all(snapshots_dir.join("not-a-snapshot")).unwrap();
+
+        let ids: Vec<_> = list_snapshots_in(&snapshots_dir)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec!["newer-200", "older-100"]);
+    }
+
+    #[test]
+    fn test_snapshots_in_the_same_second_are_numbered() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let snapshots_dir = temp_dir.path().join("snapshots");
+        write_entry(&journal_dir, "2024/01/entry-1.md", "original");
+
+        fs::create_dir_all(snapshots_dir.join(format!("daily-{}", now_secs()))).unwrap();
+        fs::create_dir_all(snapshots_dir.join(format!("daily-{}", now_secs() + 1))).unwrap();
+        let snapshot = create_snapshot_in(&snapshots_dir, &journal_dir, "daily").unwrap();
+        assert!(snapshot.id.ends_with("-1"));
+        assert_eq!(snapshot.name, "daily");
+
+        assert_eq!(
+            parse_snapshot_id("daily-1700000000-2"),
+            Some(("daily", 1700000000))
+        );
+        assert_eq!(
+            parse_snapshot_id("v-2024-1700000000"),
+            Some(("v-2024", 1700000000))
+        );
+        assert_eq!(parse_snapshot_id("older-100"), Some(("older", 100)));
+        assert_eq!(parse_snapshot_id("not-a-snapshot"), None);
+    }
+
+    #[test]
+    fn test_restore_refuses_in_progress_edit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let snapshots_dir = temp_dir.path().join("snapshots");
+        let backups_dir = temp_dir.path().join("snapshot-backups");
+
+        write_entry(&journal_dir, "2024/01/entry-1.md", "original");
+        let snapshot = create_snapshot_in(&snapshots_dir, &journal_dir, "snap").unwrap();
+        write_entry(&journal_dir, "2024/01/entry-1.md.tmp", "half written");
+
+        let result = restore_snapshot_in(
+            &snapshots_dir,
+            &journal_dir,
+            &backups_dir,
+            &snapshot.id,
+            Duration::ZERO,
+        );
+        assert!(result.unwrap_err().contains("being edited"));
+
+        fs::remove_file(journal_dir.join("2024/01/entry-1.md.tmp")).unwrap();
+        let result = restore_snapshot_in(
+            &snapshots_dir,
+            &journal_dir,
+            &backups_dir,
+            &snapshot.id,
+            EDIT_QUIET_PERIOD,
+        );
+        assert!(result.is_err());
+        assert!(journal_dir.join("2024/01/entry-1.md").exists());
+    }
+
+    #[test]
+    fn test_snapshot_name_validation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        fs::create_dir_all(&journal_dir).unwrap();
+
+        assert!(create_snapshot_in(temp_dir.path(), &journal_dir, "").is_err());
+        assert!(create_snapshot_in(temp_dir.path(), &journal_dir, "../escape").is_err());
+        assert!(restore_snapshot_in(
+            temp_dir.path(),
+            &journal_dir,
+            temp_dir.path(),
+            "../Journal",
+            Duration::ZERO
+        )
+        .is_err());
+    }
+}
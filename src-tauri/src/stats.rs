@@ -0,0 +1,191 @@
+use std::collections::BTreeSet;
+
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+
+use crate::export::parse_heading;
+use crate::frontmatter::split_frontmatter;
+
+pub const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct EntryStats {
+    pub words: usize,
+    pub characters: usize,
+    /// Rounded up, so any non-empty entry takes at least a minute.
+    pub reading_minutes: usize,
+    pub headings: usize,
+}
+
+/// The entry body after a leading `---` frontmatter block, if it has one.
+fn strip_frontmatter(content: &str) -> &str {
+    split_frontmatter(content).map_or(content, |(_, body)| body)
+}
+
+fn is_word(token: &str) -> bool {
+    token.chars().any(char::is_alphanumeric)
+}
+
+pub fn entry_stats(content: &str) -> EntryStats {
+    let mut stats = EntryStats::default();
+
+    for line in strip_frontmatter(content).lines() {
+        stats.characters += line.chars().count();
+        match parse_heading(line) {
+            Some((_, text)) => {
+                stats.headings += 1;
+                stats.words += text.split_whitespace().filter(|t| is_word(t)).count();
+            }
+            None => stats.words += line.split_whitespace().filter(|t| is_word(t)).count(),
+        }
+    }
+
+    stats.reading_minutes = stats.words.div_ceil(WORDS_PER_MINUTE);
+    stats
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct JournalStats {
+    pub total_entries: usize,
+    pub total_words: usize,
+    /// Consecutive active days ending today, or yesterday when nothing has
+    /// been written yet today.
+    pub current_streak_days: usize,
+    pub longest_streak_days: usize,
+    pub entries_this_month: usize,
+}
+
+/// Today in local time, which is what entry file names use.
+pub fn local_today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// The date of a `YYYY-MM-DD` entry; `None` for `YYYY-MM` ones.
+fn parse_day(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Longest run of consecutive days in `days`.
+fn longest_run(days: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = if previous.and_then(|previous| previous.succ_opt()) == Some(day) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+    longest
+}
+
+/// Statistics over `(date, content)` pairs, with dates as in `EntryMeta`.
+/// Entries dated only by month count toward the month but not toward
+/// streaks. Words are counted as `entry_stats` counts them.
+pub fn journal_stats<'a>(
+    entries: impl IntoIterator<Item = (&'a str, &'a str)>,
+    today: NaiveDate,
+) -> JournalStats {
+    let this_month = today.format("%Y-%m").to_string();
+    let mut stats = JournalStats::default();
+    let mut active_days = BTreeSet::new();
+
+    for (date, content) in entries {
+        stats.total_entries += 1;
+        stats.total_words += entry_stats(content).words;
+        if date.starts_with(&this_month) {
+            stats.entries_this_month += 1;
+        }
+        active_days.extend(parse_day(date));
+    }
+
+    let streak_end = if active_days.contains(&today) {
+        Some(today)
+    } else {
+        today.pred_opt()
+    };
+    stats.current_streak_days = std::iter::successors(streak_end, |day| day.pred_opt())
+        .take_while(|day| active_days.contains(day))
+        .count();
+    stats.longest_streak_days = longest_run(&active_days);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_stats_reading_time() {
+        let body = "lorem ".repeat(449);
+        let content = format!(
+            "---\ntitle: Tuesday\ntags: [work]\n---\n~S2~ ^ # Tuesday\n{}\n\t- done -\n## Notes\n",
+            body.trim_end()
+        );
+
+        let stats = entry_stats(&content);
+
+        assert_eq!(stats.words, 452);
+        assert_eq!(stats.headings, 2);
+        assert_eq!(stats.reading_minutes, 3);
+        assert_eq!(
+            stats.characters,
+            "~S2~ ^ # Tuesday".len()
+                + body.trim_end().len()
+                + "\t- done -".len()
+                + "## Notes".len()
+        );
+    }
+
+    #[test]
+    fn test_entry_stats_empty_and_unclosed_frontmatter() {
+        assert_eq!(entry_stats(""), EntryStats::default());
+        assert_eq!(entry_stats("---\ntitle: x\n---\n").words, 0);
+
+        let stats = entry_stats("---\nnot frontmatter");
+        assert_eq!(stats.words, 2);
+        assert_eq!(stats.reading_minutes, 1);
+    }
+
+    #[test]
+    fn test_journal_stats_streaks() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        let entries = [
+            ("2024-02-10", "one two three"),
+            ("2024-02-11", "---\ntags: [a]\n---\nfour five"),
+            ("2024-02-12", "six"),
+            ("2024-02-12", "seven"),
+            ("2024-02-28", "# eight -"),
+            ("2024-02-29", "nine"),
+            ("2024-03-01", "ten"),
+            ("2024-03", "undated in March"),
+        ];
+
+        let stats = journal_stats(entries, today);
+
+        assert_eq!(stats.total_entries, 8);
+        assert_eq!(stats.total_words, 13);
+        assert_eq!(
+            stats.total_words,
+            entries
+                .iter()
+                .map(|(_, content)| entry_stats(content).words)
+                .sum::<usize>()
+        );
+        assert_eq!(stats.entries_this_month, 2);
+        // Nothing yet on the 2nd, so the streak runs through yesterday.
+        assert_eq!(stats.current_streak_days, 3);
+        assert_eq!(stats.longest_streak_days, 3);
+
+        let later = journal_stats(entries, today + chrono::Days::new(2));
+        assert_eq!(later.current_streak_days, 0);
+        assert_eq!(later.longest_streak_days, 3);
+
+        assert_eq!(parse_day("2024-02-30"), None);
+        assert_eq!(parse_day("2024-03"), None);
+        assert_eq!(journal_stats([], today), JournalStats::default());
+    }
+}
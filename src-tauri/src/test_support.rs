@@ -0,0 +1,71 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::claude_session::commands::BatchSessionRequest;
+use crate::claude_session::manager::SessionManager;
+
+/// A session manager that keeps its session records and checkouts in the
+/// returned tempdir instead of `~/.dreamal`.
+pub fn test_manager() -> (tempfile::TempDir, Arc<SessionManager>) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let manager = Arc::new(SessionManager::new());
+    manager.set_queue_dir(temp_dir.path().join("sessions"));
+    manager.set_checkouts_dir(temp_dir.path().join("temp-checkouts"));
+    (temp_dir, manager)
+}
+
+/// An empty repo in a fresh tempdir.
+pub fn init_repo() -> tempfile::TempDir {
+    let temp_dir = tempfile::tempdir().unwrap();
+    git2::Repository::init(temp_dir.path()).unwrap();
+    temp_dir
+}
+
+/// A request to apply a fix in `git_directory`, with nothing else set.
+pub fn request(git_directory: &Path) -> BatchSessionRequest {
+    BatchSessionRequest {
+        git_directory: git_directory.to_string_lossy().to_string(),
+        instructions: "Apply the fix".to_string(),
+        additional_instructions: None,
+        instructions_file_content: None,
+        base_branch: None,
+        timeout_secs: None,
+        branch_name: None,
+        ticket_id: None,
+        labels: None,
+        editable_paths: None,
+        commit_message: None,
+        max_changed_files: None,
+    }
+}
+
+/// A repo in a fresh tempdir with `test.txt` committed on its default
+/// branch, which is also named `base`.
+pub fn setup_test_repo() -> (tempfile::TempDir, PathBuf) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo_path = temp_dir.path().to_path_buf();
+
+    let repo = git2::Repository::init(&repo_path).unwrap();
+
+    fs::write(repo_path.join("test.txt"), "hello world\n").unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("test.txt")).unwrap();
+    index.write().unwrap();
+
+    let tree_id = index.write_tree().unwrap();
+    let tree = repo.find_tree(tree_id).unwrap();
+
+    let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+    let commit_id = repo
+        .commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+        .unwrap();
+
+    let commit = repo.find_commit(commit_id).unwrap();
+    repo.branch("base", &commit, false).unwrap();
+
+    (temp_dir, repo_path)
+}
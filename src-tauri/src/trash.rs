@@ -0,0 +1,276 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Separates an entry's file stem from the deletion timestamp in the trash.
+const TIMESTAMP_SEPARATOR: char = '~';
+/// Added to a trashed entry's name for the file recording which notebook it
+/// was deleted from.
+const ORIGIN_SUFFIX: &str = ".origin";
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+fn origin_path(trashed: &Path) -> PathBuf {
+    let mut name = trashed.as_os_str().to_owned();
+    name.push(ORIGIN_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Renames `from` to `to`, falling back to copy and delete when they are on
+/// different filesystems, as the journal and the trash may be.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    fs::remove_file(from).map_err(|e| {
+        let _ = fs::remove_file(to);
+        format!("Failed to move {}: {}", from.display(), e)
+    })
+}
+
+/// Moves `entry` into `trash_dir` under the same path it had below
+/// `journal_dir`, with the time of deletion added to its name so deleting the
+/// same entry twice keeps both copies. `journal_dir` is recorded beside it so
+/// restoring puts it back in the same notebook. Returns the path in the trash.
+pub fn trash_entry_in(
+    journal_dir: &Path,
+    trash_dir: &Path,
+    entry: &Path,
+) -> Result<PathBuf, String> {
+    if !entry.is_file() {
+        return Err(format!("Entry not found: {}", entry.display()));
+    }
+    let relative = entry
+        .strip_prefix(journal_dir)
+        .map_err(|_| format!("Refusing {}: outside the journal", entry.display()))?;
+    let stem = relative
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| format!("Invalid entry name: {}", entry.display()))?;
+    let extension = relative
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e))
+        .unwrap_or_default();
+    let parent = trash_dir.join(relative.parent().unwrap_or(Path::new("")));
+
+    let trash_name = |timestamp: u128| {
+        parent.join(format!(
+            "{}{}{}{}",
+            stem, TIMESTAMP_SEPARATOR, timestamp, extension
+        ))
+    };
+
+    let mut timestamp = now_ms();
+    while trash_name(timestamp).exists() {
+        timestamp += 1;
+    }
+    let trashed = trash_name(timestamp);
+
+    fs::create_dir_all(&parent)
+        .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    let origin = origin_path(&trashed);
+    fs::write(&origin, journal_dir.to_string_lossy().as_bytes())
+        .map_err(|e| format!("Failed to record {}: {}", origin.display(), e))?;
+    move_file(entry, &trashed).inspect_err(|_| {
+        let _ = fs::remove_file(&origin);
+    })?;
+    Ok(trashed)
+}
+
+/// Moves an entry from the trash back to where it was deleted from, in the
+/// notebook recorded when it was trashed, or `journal_dir` if none was.
+/// Refuses to overwrite an entry that has since taken its place.
+pub fn restore_entry_in(
+    journal_dir: &Path,
+    trash_dir: &Path,
+    trash_path: &Path,
+) -> Result<PathBuf, String> {
+    let relative = trash_path
+        .strip_prefix(trash_dir)
+        .map_err(|_| format!("Not in the trash: {}", trash_path.display()))?;
+    if relative
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(format!("Not in the trash: {}", trash_path.display()));
+    }
+    if !trash_path.is_file() {
+        return Err(format!("Not in the trash: {}", trash_path.display()));
+    }
+    if trash_path.to_string_lossy().ends_with(ORIGIN_SUFFIX) {
+        return Err(format!("Not a deleted entry: {}", trash_path.display()));
+    }
+
+    let name = relative
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid trash entry: {}", trash_path.display()))?;
+    let (stem, rest) = name
+        .rsplit_once(TIMESTAMP_SEPARATOR)
+        .ok_or_else(|| format!("Not a deleted entry: {}", trash_path.display()))?;
+    let (timestamp, extension) = rest.split_once('.').map_or((rest, ""), |(t, e)| (t, e));
+    if timestamp.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Not a deleted entry: {}", trash_path.display()));
+    }
+    let original_name = if extension.is_empty() {
+        stem.to_string()
+    } else {
+        format!("{}.{}", stem, extension)
+    };
+
+    let origin = origin_path(trash_path);
+    let notebook = fs::read_to_string(&origin)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| journal_dir.to_path_buf());
+    let restored = notebook
+        .join(relative.parent().unwrap_or(Path::new("")))
+        .join(original_name);
+    if restored.exists() {
+        return Err(format!("An entry already exists at {}", restored.display()));
+    }
+
+    move_file(trash_path, &restored)?;
+    if origin.exists() {
+        if let Err(e) = fs::remove_file(&origin) {
+            eprintln!("Warning: Failed to remove {}: {}", origin.display(), e);
+        }
+    }
+    Ok(restored)
+}
+
+/// Counts the trashed entries below `dir`, leaving out their origin files.
+fn count_entries(dir: &Path) -> u64 {
+    let Ok(children) = fs::read_dir(dir) else {
+        return 0;
+    };
+    children
+        .flatten()
+        .map(|child| child.path())
+        .map(|path| {
+            if path.is_dir() {
+                count_entries(&path)
+            } else {
+                u64::from(!path.to_string_lossy().ends_with(ORIGIN_SUFFIX))
+            }
+        })
+        .sum()
+}
+
+/// Permanently deletes everything in the trash. Returns how many entries were
+/// removed.
+pub fn empty_trash_in(trash_dir: &Path) -> Result<u64, String> {
+    if !trash_dir.exists() {
+        return Ok(0);
+    }
+    let files = count_entries(trash_dir);
+    fs::remove_dir_all(trash_dir).map_err(|e| format!("Failed to empty trash: {}", e))?;
+    fs::create_dir_all(trash_dir).map_err(|e| format!("Failed to recreate trash: {}", e))?;
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir = temp_dir.path().join("Journal");
+        let trash_dir = temp_dir.path().join(".dreamal/trash");
+        let entry = journal_dir.join("2024/06/2024-06-01-080000.md");
+        fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        fs::write(&entry, "# Saturday").unwrap();
+        (temp_dir, journal_dir, trash_dir, entry)
+    }
+
+    #[test]
+    fn test_trash_and_restore_entry() {
+        let (_temp_dir, journal_dir, trash_dir, entry) = setup();
+
+        let trashed = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        assert!(!entry.exists());
+        assert!(trashed.starts_with(trash_dir.join("2024/06")));
+        let name = trashed.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("2024-06-01-080000~") && name.ends_with(".md"));
+
+        // Deleting a new entry at the same path keeps both in the trash.
+        fs::write(&entry, "# Saturday, again").unwrap();
+        let second = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        assert_ne!(trashed, second);
+
+        assert_eq!(
+            restore_entry_in(&journal_dir, &trash_dir, &trashed).unwrap(),
+            entry
+        );
+        assert_eq!(fs::read_to_string(&entry).unwrap(), "# Saturday");
+        assert!(!trashed.exists());
+
+        let clobber = restore_entry_in(&journal_dir, &trash_dir, &second).unwrap_err();
+        assert!(clobber.contains("already exists"));
+        assert!(second.exists());
+    }
+
+    #[test]
+    fn test_restore_entry_to_its_notebook() {
+        let (temp_dir, journal_dir, trash_dir, entry) = setup();
+        let trashed = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        assert!(origin_path(&trashed).exists());
+
+        // Another notebook is open by the time the entry is restored.
+        let other_dir = temp_dir.path().join("Other");
+        let restored = restore_entry_in(&other_dir, &trash_dir, &trashed).unwrap();
+        assert_eq!(restored, entry);
+        assert_eq!(fs::read_to_string(&entry).unwrap(), "# Saturday");
+        assert!(!other_dir.exists());
+        assert!(!origin_path(&trashed).exists());
+
+        // Entries trashed without a recorded notebook go to the open one.
+        let trashed = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        fs::remove_file(origin_path(&trashed)).unwrap();
+        assert_eq!(
+            restore_entry_in(&other_dir, &trash_dir, &trashed).unwrap(),
+            other_dir.join("2024/06/2024-06-01-080000.md")
+        );
+    }
+
+    #[test]
+    fn test_trash_rejects_paths_outside() {
+        let (temp_dir, journal_dir, trash_dir, entry) = setup();
+        let outside = temp_dir.path().join("notes.md");
+        fs::write(&outside, "# Notes").unwrap();
+
+        assert!(trash_entry_in(&journal_dir, &trash_dir, &outside).is_err());
+        assert!(trash_entry_in(&journal_dir, &trash_dir, &journal_dir.join("missing.md")).is_err());
+        assert!(restore_entry_in(&journal_dir, &trash_dir, &outside).is_err());
+        assert!(
+            restore_entry_in(&journal_dir, &trash_dir, &trash_dir.join("../../notes.md")).is_err()
+        );
+        assert!(restore_entry_in(&journal_dir, &trash_dir, &entry).is_err());
+        assert!(outside.exists());
+
+        let trashed = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        assert!(restore_entry_in(&journal_dir, &trash_dir, &origin_path(&trashed)).is_err());
+    }
+
+    #[test]
+    fn test_empty_trash() {
+        let (_temp_dir, journal_dir, trash_dir, entry) = setup();
+        assert_eq!(empty_trash_in(&trash_dir).unwrap(), 0);
+
+        let trashed = trash_entry_in(&journal_dir, &trash_dir, &entry).unwrap();
+        assert_eq!(empty_trash_in(&trash_dir).unwrap(), 1);
+        assert!(!trashed.exists());
+        assert!(trash_dir.is_dir());
+    }
+}
@@ -2,7 +2,13 @@ import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { openUrl } from "@tauri-apps/plugin-opener";
 
-type SessionStatus = "initializing" | "working" | "completed" | "error";
+type SessionStatus =
+  | "queued"
+  | "initializing"
+  | "working"
+  | "completed"
+  | "nochanges"
+  | "error";
 
 interface SessionInfo {
   id: string;
@@ -12,6 +18,7 @@ interface SessionInfo {
   git_directory: string;
   instructions: string;
   created_at: number;
+  completed_at: number | null;
 }
 
 interface ClaudeStatusProps {
@@ -21,6 +28,12 @@ interface ClaudeStatusProps {
 }
 
 const statusConfig = {
+  queued: {
+    label: "Queued",
+    backgroundColor: "rgba(156, 163, 175, 0.2)",
+    color: "#9ca3af",
+    borderColor: "rgba(156, 163, 175, 0.4)",
+  },
   initializing: {
     label: "Initializing...",
     backgroundColor: "rgba(99, 102, 241, 0.2)",
@@ -39,6 +52,12 @@ const statusConfig = {
     color: "#22c55e",
     borderColor: "rgba(34, 197, 94, 0.4)",
   },
+  nochanges: {
+    label: "Claude made no changes",
+    backgroundColor: "rgba(156, 163, 175, 0.2)",
+    color: "#9ca3af",
+    borderColor: "rgba(156, 163, 175, 0.4)",
+  },
   error: {
     label: "Error",
     backgroundColor: "rgba(239, 68, 68, 0.2)",
@@ -75,6 +94,7 @@ export function ClaudeStatus({ sessionId, prUrl, __onStateChange }: ClaudeStatus
 
           if (
             info.status === "completed" ||
+            info.status === "nochanges" ||
             info.status === "error"
           ) {
             if (pollInterval) {
@@ -119,7 +119,7 @@ export function usePersistence(
 
     flushSave();
 
-    await invoke("set_setting", { key: "journalDir", value: selected });
+    await invoke("set_setting", { key: "journal_dir", value: selected });
     const newDir = await invoke<string>("ensure_journal_dir");
     if (isMountedRef.current) {
       setJournalDir(newDir);